@@ -24,7 +24,7 @@ use std::num::NonZeroUsize;
 use std::path::PathBuf;
 
 use firewood::db::{BatchOp, Db, DbConfig};
-use firewood::manager::{CacheReadStrategy, RevisionManagerConfig};
+use firewood::manager::{CacheReadStrategy, NodeCacheBudget, RevisionManagerConfig};
 
 use fastrace::collector::Config;
 
@@ -54,8 +54,10 @@ struct GlobalOpts {
     batch_size: u64,
     #[arg(short, long, default_value_t = 1000)]
     number_of_batches: u64,
-    #[arg(short, long, default_value_t = NonZeroUsize::new(1500000).expect("is non-zero"))]
-    cache_size: NonZeroUsize,
+    #[arg(long, default_value_t = NodeCacheBudget::DEFAULT_BRANCH_BYTES)]
+    branch_cache_bytes: usize,
+    #[arg(long, default_value_t = NodeCacheBudget::DEFAULT_LEAF_BYTES)]
+    leaf_cache_bytes: usize,
     #[arg(short, long, default_value_t = 128)]
     revisions: usize,
     #[cfg(feature = "prometheus")]
@@ -228,7 +230,8 @@ fn main() -> Result<(), Box<dyn Error>> {
         .expect("failed to spawn prometheus listener");
 
     let mgrcfg = RevisionManagerConfig::builder()
-        .node_cache_size(args.global_opts.cache_size)
+        .branch_cache_bytes(args.global_opts.branch_cache_bytes)
+        .leaf_cache_bytes(args.global_opts.leaf_cache_bytes)
         .free_list_cache_size(
             NonZeroUsize::new(4 * args.global_opts.batch_size as usize).expect("batch size > 0"),
         )
@@ -59,7 +59,8 @@ type Database struct {
 // Config configures the opening of a [Database].
 type Config struct {
 	Truncate             bool
-	NodeCacheEntries     uint
+	BranchCacheBytes     uint
+	LeafCacheBytes       uint
 	FreeListCacheEntries uint
 	Revisions            uint
 	ReadCacheStrategy    CacheStrategy
@@ -68,7 +69,8 @@ type Config struct {
 // DefaultConfig returns a sensible default Config.
 func DefaultConfig() *Config {
 	return &Config{
-		NodeCacheEntries:     1_000_000,
+		BranchCacheBytes:     512 << 20,
+		LeafCacheBytes:       256 << 20,
 		FreeListCacheEntries: 40_000,
 		Revisions:            100,
 		ReadCacheStrategy:    OnlyCacheWrites,
@@ -100,9 +102,6 @@ func New(filePath string, conf *Config) (*Database, error) {
 	if conf.Revisions < 2 {
 		return nil, fmt.Errorf("%T.Revisions must be >= 2", conf)
 	}
-	if conf.NodeCacheEntries < 1 {
-		return nil, fmt.Errorf("%T.NodeCacheEntries must be >= 1", conf)
-	}
 	if conf.FreeListCacheEntries < 1 {
 		return nil, fmt.Errorf("%T.FreeListCacheEntries must be >= 1", conf)
 	}
@@ -112,7 +111,8 @@ func New(filePath string, conf *Config) (*Database, error) {
 
 	args := C.struct_DatabaseHandleArgs{
 		path:                 newBorrowedBytes([]byte(filePath), &pinner),
-		cache_size:           C.size_t(conf.NodeCacheEntries),
+		branch_cache_bytes:   C.size_t(conf.BranchCacheBytes),
+		leaf_cache_bytes:     C.size_t(conf.LeafCacheBytes),
 		free_list_cache_size: C.size_t(conf.FreeListCacheEntries),
 		revisions:            C.size_t(conf.Revisions),
 		strategy:             C.uint8_t(conf.ReadCacheStrategy),
@@ -148,6 +148,127 @@ func (db *Database) Update(keys, vals [][]byte) ([]byte, error) {
 	return getHashKeyFromHashResult(C.fwd_batch(db.handle, kvp))
 }
 
+// BatchOp is a single operation of a batch passed to [Database.UpdateOps],
+// [Database.ProposeOps] or [Proposal.ProposeOps].
+type BatchOp struct {
+	kind  C.enum_BatchOpKind
+	key   []byte
+	value []byte
+}
+
+// PutOp inserts or updates key with value. A nil value is stored as an empty
+// value.
+func PutOp(key, value []byte) BatchOp {
+	if value == nil {
+		value = []byte{}
+	}
+	return BatchOp{kind: C.BatchOpKind_PutOrDeletePrefix, key: key, value: value}
+}
+
+// DeletePrefixOp removes every key starting with prefix.
+func DeletePrefixOp(prefix []byte) BatchOp {
+	return BatchOp{kind: C.BatchOpKind_PutOrDeletePrefix, key: prefix}
+}
+
+// DeleteRangeOp removes the keys in the half-open range [start, end). If end
+// is nil, every key from start onwards is removed.
+func DeleteRangeOp(start, end []byte) BatchOp {
+	return BatchOp{kind: C.BatchOpKind_DeleteRange, key: start, value: end}
+}
+
+// UpdateOps is like [Database.Update], taking the batch as a list of
+// operations, so that it can also remove ranges of keys.
+func (db *Database) UpdateOps(ops []BatchOp) ([]byte, error) {
+	if db.handle == nil {
+		return nil, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	return getHashKeyFromHashResult(C.fwd_batch(db.handle, newBatchOpPairs(ops, &pinner)))
+}
+
+// ComputeRoot returns the hash of the root node that applying the given keys
+// and values on top of parent would produce, without creating a proposal. If
+// parent is nil, they are applied on top of the latest revision. The parent may
+// be a committed revision or an uncommitted proposal.
+//
+// The keys and values have the same semantics as in [Database.Update].
+func (db *Database) ComputeRoot(parent []byte, keys, vals [][]byte) ([]byte, error) {
+	if db.handle == nil {
+		return nil, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	kvp, err := newKeyValuePairs(keys, vals, &pinner)
+	if err != nil {
+		return nil, err
+	}
+
+	return getHashKeyFromHashResult(C.fwd_compute_root(
+		db.handle,
+		newBorrowedBytes(parent, &pinner),
+		kvp,
+	))
+}
+
+// DeleteRange removes the keys in the half-open range [start, end) from the
+// database and commits the result, returning the hash of the root node and
+// the number of keys removed. If end is nil, every key from start onwards is
+// removed. The root hash is zeroed if the database is now empty.
+func (db *Database) DeleteRange(start, end []byte) ([]byte, uint64, error) {
+	if db.handle == nil {
+		return nil, 0, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	return getDeleteRangeResult(C.fwd_delete_range(
+		db.handle,
+		newBorrowedBytes(start, &pinner),
+		newBorrowedBytes(end, &pinner),
+	))
+}
+
+// ProposeDeleteRange creates a new proposal removing the keys in the
+// half-open range [start, end), returning it with the number of keys it
+// removes. If end is nil, every key from start onwards is removed. The
+// proposal is not committed until [Proposal.Commit] is called.
+func (db *Database) ProposeDeleteRange(start, end []byte) (*Proposal, uint64, error) {
+	if db.handle == nil {
+		return nil, 0, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	return getProposalFromProposeDeleteRangeResult(C.fwd_propose_delete_range(
+		db.handle,
+		newBorrowedBytes(start, &pinner),
+		newBorrowedBytes(end, &pinner),
+	), &db.outstandingHandles)
+}
+
+// ProposeOps is like [Database.Propose], taking the batch as a list of
+// operations, so that it can also remove ranges of keys.
+func (db *Database) ProposeOps(ops []BatchOp) (*Proposal, error) {
+	if db.handle == nil {
+		return nil, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	return getProposalFromProposalResult(
+		C.fwd_propose_on_db(db.handle, newBatchOpPairs(ops, &pinner)),
+		&db.outstandingHandles,
+	)
+}
+
 // Propose creates a new proposal with the given keys and values. The proposal
 // is not committed until [Proposal.Commit] is called. See [Database.Close] re
 // freeing proposals.
@@ -230,6 +351,42 @@ func (db *Database) Root() ([]byte, error) {
 	return bytes, err
 }
 
+// Flush waits until every revision committed so far has been written to disk.
+//
+// Commits return as soon as the new revision is readable, and revisions are
+// written to disk in the background, in commit order. If a revision could not
+// be written, Flush returns that error, and every later Flush and commit fails
+// until the database is reopened.
+func (db *Database) Flush() error {
+	if db.handle == nil {
+		return errDBClosed
+	}
+
+	return getErrorFromVoidResult(C.fwd_flush_db(db.handle))
+}
+
+// RollbackTo makes the retained revision with the given root the latest one,
+// discarding every revision committed after it and reclaiming their space.
+//
+// It fails if the root is not a retained revision, or if a discarded revision
+// or any proposal is still in use. Drop them before rolling back.
+func (db *Database) RollbackTo(root []byte) error {
+	if db.handle == nil {
+		return errDBClosed
+	}
+	if len(root) != RootLength {
+		return errInvalidRootLength
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	return getErrorFromVoidResult(C.fwd_rollback_to(
+		db.handle,
+		newBorrowedBytes(root, &pinner),
+	))
+}
+
 func (db *Database) LatestRevision() (*Revision, error) {
 	root, err := db.Root()
 	if err != nil {
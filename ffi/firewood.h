@@ -11,6 +11,22 @@
 #include <stdlib.h>
 
 
+/**
+ * How a [`KeyValuePair`] is applied as part of a batch.
+ */
+typedef enum BatchOpKind {
+  /**
+   * Puts `value` at `key`. If `value` is null, every key starting with
+   * `key` is deleted instead.
+   */
+  BatchOpKind_PutOrDeletePrefix,
+  /**
+   * Deletes the keys in the half-open range [`key`, `value`). If `value`
+   * is null, every key from `key` onwards is deleted.
+   */
+  BatchOpKind_DeleteRange,
+} BatchOpKind;
+
 /**
  * FFI context for a parsed or generated change proof.
  */
@@ -160,6 +176,7 @@ typedef struct BorrowedSlice_u8 BorrowedBytes;
 typedef struct KeyValuePair {
   BorrowedBytes key;
   BorrowedBytes value;
+  enum BatchOpKind kind;
 } KeyValuePair;
 
 /**
@@ -652,6 +669,84 @@ typedef struct VerifyRangeProofArgs {
   uint32_t max_length;
 } VerifyRangeProofArgs;
 
+/**
+ * A result type returned from FFI functions that remove a range of keys and
+ * commit the result.
+ */
+typedef enum DeleteRangeResult_Tag {
+  /**
+   * The caller provided a null pointer to a database handle.
+   */
+  DeleteRangeResult_NullHandlePointer,
+  /**
+   * The keys were removed and the result was committed.
+   */
+  DeleteRangeResult_Ok,
+  /**
+   * An error occurred and the message is returned as an [`OwnedBytes`]. If
+   * value is guaranteed to contain only valid UTF-8.
+   *
+   * The caller must call [`fwd_free_owned_bytes`] to free the memory
+   * associated with this error.
+   *
+   * [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+   */
+  DeleteRangeResult_Err,
+} DeleteRangeResult_Tag;
+
+typedef struct DeleteRangeResult_Ok_Body {
+  /**
+   * The new root hash of the database. Zeroed if the database is now
+   * empty.
+   */
+  struct HashKey root_hash;
+  /**
+   * The number of keys removed.
+   */
+  uint64_t removed;
+} DeleteRangeResult_Ok_Body;
+
+typedef struct DeleteRangeResult {
+  DeleteRangeResult_Tag tag;
+  union {
+    DeleteRangeResult_Ok_Body ok;
+    struct {
+      OwnedBytes err;
+    };
+  };
+} DeleteRangeResult;
+
+/**
+ * A change to the value of one key, returned to ffi callers.
+ *
+ * A key that was inserted has no old value, and a key that was deleted has no
+ * new value.
+ *
+ * C callers must free a list of these using [`crate::fwd_free_owned_key_changes`],
+ * not the C standard library's `free` function.
+ */
+typedef struct OwnedKeyChange {
+  OwnedBytes key;
+  struct Maybe_OwnedBytes old_value;
+  struct Maybe_OwnedBytes new_value;
+} OwnedKeyChange;
+
+/**
+ * A Rust-owned vector of bytes that can be passed to C code.
+ *
+ * C callers must free this memory using the respective FFI function for the
+ * concrete type (but not using the `free` function from the C standard library).
+ */
+typedef struct OwnedSlice_OwnedKeyChange {
+  struct OwnedKeyChange *ptr;
+  size_t len;
+} OwnedSlice_OwnedKeyChange;
+
+/**
+ * A rust-owned list of key changes, in ascending key order.
+ */
+typedef struct OwnedSlice_OwnedKeyChange OwnedKeyChanges;
+
 /**
  * Owned version of `KeyValuePair`, returned to ffi callers.
  *
@@ -679,6 +774,90 @@ typedef struct OwnedSlice_OwnedKeyValuePair {
  */
 typedef struct OwnedSlice_OwnedKeyValuePair OwnedKeyValueBatch;
 
+/**
+ * A Rust-owned vector of bytes that can be passed to C code.
+ *
+ * C callers must free this memory using the respective FFI function for the
+ * concrete type (but not using the `free` function from the C standard library).
+ */
+typedef struct OwnedSlice_Maybe_OwnedBytes {
+  struct Maybe_OwnedBytes *ptr;
+  size_t len;
+} OwnedSlice_Maybe_OwnedBytes;
+
+/**
+ * A rust-owned list of optional values, one for each key looked up.
+ */
+typedef struct OwnedSlice_Maybe_OwnedBytes OwnedValues;
+
+/**
+ * A result type returned from FFI functions that retrieve many values.
+ */
+typedef enum ValuesResult_Tag {
+  /**
+   * The caller provided a null pointer to a revision handle.
+   */
+  ValuesResult_NullHandlePointer,
+  /**
+   * The value of each key, in the order the keys were given. Keys that were
+   * not found are [`Maybe::None`].
+   *
+   * The caller must call [`fwd_free_owned_values`] to free the memory
+   * associated with these values.
+   *
+   * [`fwd_free_owned_values`]: crate::fwd_free_owned_values
+   */
+  ValuesResult_Some,
+  /**
+   * An error occurred and the message is returned as an [`OwnedBytes`]. If
+   * value is guaranteed to contain only valid UTF-8.
+   *
+   * The caller must call [`fwd_free_owned_bytes`] to free the memory
+   * associated with this error.
+   *
+   * [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+   */
+  ValuesResult_Err,
+} ValuesResult_Tag;
+
+typedef struct ValuesResult {
+  ValuesResult_Tag tag;
+  union {
+    struct {
+      OwnedValues some;
+    };
+    struct {
+      OwnedBytes err;
+    };
+  };
+} ValuesResult;
+
+/**
+ * A borrowed byte slice. Used to represent data that was passed in from C
+ * callers and will not be freed or retained by Rust code.
+ */
+typedef struct BorrowedSlice_BorrowedBytes {
+  /**
+   * A pointer to the slice of bytes. This can be null if the slice is empty.
+   *
+   * If the pointer is not null, it must point to a valid slice of `len`
+   * elements sized and aligned for `T`.
+   *
+   * As a note, [`NonNull`] is not appropriate here because [`NonNull`] pointer
+   * provenance requires mutable access to the pointer, which is not an invariant
+   * we want to enforce here. We want (and require) the pointer to be immutable.
+   *
+   * [`NonNull`]: std::ptr::NonNull
+   */
+  const BorrowedBytes *ptr;
+  /**
+   * The length of the slice. It is ignored if the pointer is null; however,
+   * if the pointer is not null, it must be equal to the number of elements
+   * pointed to by `ptr`.
+   */
+  size_t len;
+} BorrowedSlice_BorrowedBytes;
+
 /**
  * A result type returned from FFI functions that get a revision
  */
@@ -912,11 +1091,17 @@ typedef struct DatabaseHandleArgs {
    */
   BorrowedBytes path;
   /**
-   * The size of the node cache.
+   * The number of bytes the node cache may use for branch nodes.
    *
-   * Opening returns an error if this is zero.
+   * Zero disables caching of branch nodes.
    */
-  size_t cache_size;
+  size_t branch_cache_bytes;
+  /**
+   * The number of bytes the node cache may use for leaf nodes.
+   *
+   * Zero disables caching of leaf nodes.
+   */
+  size_t leaf_cache_bytes;
   /**
    * The size of the free list cache.
    *
@@ -945,6 +1130,104 @@ typedef struct DatabaseHandleArgs {
   bool truncate;
 } DatabaseHandleArgs;
 
+/**
+ * A result type returned from FFI functions that list the changes made by a
+ * proposal.
+ */
+typedef enum KeyChangesResult_Tag {
+  /**
+   * The caller provided a null pointer to a proposal handle.
+   */
+  KeyChangesResult_NullHandlePointer,
+  /**
+   * The keys that changed, in ascending key order.
+   *
+   * The caller must call [`fwd_free_owned_key_changes`] to free the memory
+   * associated with these changes.
+   *
+   * [`fwd_free_owned_key_changes`]: crate::fwd_free_owned_key_changes
+   */
+  KeyChangesResult_Some,
+  /**
+   * An error occurred and the message is returned as an [`OwnedBytes`]. If
+   * value is guaranteed to contain only valid UTF-8.
+   *
+   * The caller must call [`fwd_free_owned_bytes`] to free the memory
+   * associated with this error.
+   *
+   * [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+   */
+  KeyChangesResult_Err,
+} KeyChangesResult_Tag;
+
+typedef struct KeyChangesResult {
+  KeyChangesResult_Tag tag;
+  union {
+    struct {
+      OwnedKeyChanges some;
+    };
+    struct {
+      OwnedBytes err;
+    };
+  };
+} KeyChangesResult;
+
+/**
+ * A result type returned from FFI functions that propose removing a range of
+ * keys.
+ */
+typedef enum ProposeDeleteRangeResult_Tag {
+  /**
+   * The caller provided a null pointer to a database handle.
+   */
+  ProposeDeleteRangeResult_NullHandlePointer,
+  /**
+   * Building the proposal was successful and the proposal handle, root
+   * hash and number of keys removed are returned.
+   */
+  ProposeDeleteRangeResult_Ok,
+  /**
+   * An error occurred and the message is returned as an [`OwnedBytes`]. If
+   * value is guaranteed to contain only valid UTF-8.
+   *
+   * The caller must call [`fwd_free_owned_bytes`] to free the memory
+   * associated with this error.
+   *
+   * [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+   */
+  ProposeDeleteRangeResult_Err,
+} ProposeDeleteRangeResult_Tag;
+
+typedef struct ProposeDeleteRangeResult_Ok_Body {
+  /**
+   * An opaque pointer to the [`ProposalHandle`] that can be use to create
+   * an additional proposal or later commit. The caller must ensure that this
+   * pointer is freed with [`fwd_free_proposal`] if it is not committed.
+   *
+   * [`fwd_free_proposal`]: crate::fwd_free_proposal
+   */
+  struct ProposalHandle *handle;
+  /**
+   * The root hash of the proposal. Zeroed if the proposal resulted in an
+   * empty database.
+   */
+  struct HashKey root_hash;
+  /**
+   * The number of keys the proposal removes.
+   */
+  uint64_t removed;
+} ProposeDeleteRangeResult_Ok_Body;
+
+typedef struct ProposeDeleteRangeResult {
+  ProposeDeleteRangeResult_Tag tag;
+  union {
+    ProposeDeleteRangeResult_Ok_Body ok;
+    struct {
+      OwnedBytes err;
+    };
+  };
+} ProposeDeleteRangeResult;
+
 /**
  * A result type returned from FFI functions that create a proposal but do not
  * commit it to the database.
@@ -1171,6 +1454,40 @@ struct VoidResult fwd_close_db(struct DatabaseHandle *db);
  */
 struct HashResult fwd_commit_proposal(struct ProposalHandle *proposal);
 
+/**
+ * Computes the root hash that a batch of operations would produce, without
+ * creating a proposal.
+ *
+ * # Arguments
+ *
+ * * `db` - The database handle returned by [`fwd_open_db`]
+ * * `parent` - The root hash of the revision or proposal to apply the batch
+ *   to, as a [`BorrowedBytes`]. If it is null, the batch is applied to the
+ *   latest revision.
+ * * `values` - A [`BorrowedKeyValuePairs`] containing the key-value pairs to put.
+ *
+ * # Returns
+ *
+ * - [`HashResult::NullHandlePointer`] if the provided database handle is null.
+ * - [`HashResult::None`] if the batch would result in an empty database.
+ * - [`HashResult::Some`] containing the root hash the batch would produce.
+ * - [`HashResult::Err`] if an error occurred, for example if the parent was
+ *   not found.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+ * * ensure that `parent` is valid for [`BorrowedBytes`]
+ * * ensure that `values` is valid for [`BorrowedKeyValuePairs`]
+ * * call [`fwd_free_owned_bytes`] to free the memory associated with the
+ *   returned error ([`HashKey`] does not need to be freed as it is returned by
+ *   value).
+ */
+struct HashResult fwd_compute_root(const struct DatabaseHandle *db,
+                                   BorrowedBytes parent,
+                                   BorrowedKeyValuePairs values);
+
 /**
  * Create a change proof for the given range of keys between two roots.
  *
@@ -1334,6 +1651,66 @@ struct VoidResult fwd_db_verify_change_proof(const struct DatabaseHandle *_db,
 struct VoidResult fwd_db_verify_range_proof(const struct DatabaseHandle *_db,
                                             struct VerifyRangeProofArgs _args);
 
+/**
+ * Removes the keys in a half-open range from the database and commits the
+ * result.
+ *
+ * To remove a range as part of a batch, pass a [`KeyValuePair`] of kind
+ * [`BatchOpKind::DeleteRange`] to [`fwd_batch`] instead.
+ *
+ * # Arguments
+ *
+ * * `db` - The database handle returned by [`fwd_open_db`]
+ * * `start` - The first key to remove, as a [`BorrowedBytes`].
+ * * `end` - The first key after the range, as a [`BorrowedBytes`]. If it is
+ *   null, every key from `start` onwards is removed.
+ *
+ * # Returns
+ *
+ * - [`DeleteRangeResult::NullHandlePointer`] if the provided database handle is null.
+ * - [`DeleteRangeResult::Ok`] if the commit was successful, containing the new
+ *   root hash and the number of keys removed.
+ * - [`DeleteRangeResult::Err`] if an error occurred while removing the keys.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+ * * ensure that `start` and `end` are valid for [`BorrowedBytes`]
+ * * call [`fwd_free_owned_bytes`] to free the memory associated with the
+ *   returned error.
+ */
+struct DeleteRangeResult fwd_delete_range(const struct DatabaseHandle *db,
+                                          BorrowedBytes start,
+                                          BorrowedBytes end);
+
+/**
+ * Wait until every committed revision has been written to disk.
+ *
+ * Commits return as soon as the new revision is available in memory, and
+ * revisions are written to disk in the background, in commit order. Once this
+ * returns successfully, every revision committed before the call is on disk.
+ *
+ * # Argument
+ *
+ * * `db` - The database handle returned by [`fwd_open_db`]
+ *
+ * # Returns
+ *
+ * - [`VoidResult::NullHandlePointer`] if the provided database handle is null.
+ * - [`VoidResult::Ok`] if every committed revision has been written.
+ * - [`VoidResult::Err`] if a revision could not be written. After such a
+ *   failure no later revision is written, and subsequent flushes and commits
+ *   fail until the database is reopened.
+ *
+ * # Safety
+ *
+ * * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+ * * call [`fwd_free_owned_bytes`] to free the memory associated with the
+ *   returned error (if any).
+ */
+struct VoidResult fwd_flush_db(const struct DatabaseHandle *db);
+
 /**
  * Frees the memory associated with a `ChangeProofContext`.
  *
@@ -1391,6 +1768,27 @@ struct VoidResult fwd_free_iterator(struct IteratorHandle *iterator);
  */
 struct VoidResult fwd_free_owned_bytes(OwnedBytes bytes);
 
+/**
+ * Consumes the [`OwnedKeyChanges`] and frees the memory associated with it.
+ *
+ * # Arguments
+ *
+ * * `changes` - The [`OwnedKeyChanges`] struct to free, previously returned from
+ *   [`fwd_proposal_diff`].
+ *
+ * # Returns
+ *
+ * - [`VoidResult::Ok`] if the memory was successfully freed.
+ * - [`VoidResult::Err`] if the process panics while freeing the memory.
+ *
+ * # Safety
+ *
+ * The caller must ensure that the `changes` struct is valid and that the memory
+ * it points to is uniquely owned by this object. However, if `changes.ptr` is
+ * null, this function does nothing.
+ */
+struct VoidResult fwd_free_owned_key_changes(OwnedKeyChanges changes);
+
 /**
  * Consumes the [`OwnedKeyValueBatch`] and frees the memory associated with it.
  *
@@ -1431,6 +1829,27 @@ struct VoidResult fwd_free_owned_key_value_batch(OwnedKeyValueBatch batch);
  */
 struct VoidResult fwd_free_owned_kv_pair(struct OwnedKeyValuePair kv);
 
+/**
+ * Consumes the [`OwnedValues`] and frees the memory associated with it.
+ *
+ * # Arguments
+ *
+ * * `values` - The [`OwnedValues`] struct to free, previously returned from
+ *   [`fwd_get_many_from_revision`].
+ *
+ * # Returns
+ *
+ * - [`VoidResult::Ok`] if the memory was successfully freed.
+ * - [`VoidResult::Err`] if the process panics while freeing the memory.
+ *
+ * # Safety
+ *
+ * The caller must ensure that the `values` struct is valid and that the memory
+ * it points to is uniquely owned by this object. However, if `values.ptr` is
+ * null, this function does nothing.
+ */
+struct VoidResult fwd_free_owned_values(OwnedValues values);
+
 /**
  * Consumes the [`ProposalHandle`], cancels the proposal, and frees the memory.
  *
@@ -1621,6 +2040,36 @@ struct ValueResult fwd_get_from_root(const struct DatabaseHandle *db,
  */
 struct ValueResult fwd_get_latest(const struct DatabaseHandle *db, BorrowedBytes key);
 
+/**
+ * Gets the values associated with many keys from the provided revision handle.
+ *
+ * The keys are looked up together, reading each node shared by several keys
+ * once and searching independent subtries on the database's thread pool.
+ *
+ * # Arguments
+ *
+ * * `revision` - The revision handle returned by [`fwd_get_revision`].
+ * * `keys` - The keys to look up, as a [`BorrowedSlice`] of [`BorrowedBytes`].
+ *
+ * # Returns
+ *
+ * - [`ValuesResult::NullHandlePointer`] if the provided revision handle is null.
+ * - [`ValuesResult::Some`] with one entry per key, in the order of `keys`.
+ *   Keys that were not found are [`Maybe::None`].
+ * - [`ValuesResult::Err`] if an error occurred while retrieving the values.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `revision` is a valid pointer to a [`RevisionHandle`].
+ * * ensure that `keys` is valid for [`BorrowedSlice`] and each key is valid
+ *   for [`BorrowedBytes`].
+ * * call [`fwd_free_owned_values`] to free the memory associated with the
+ *   [`OwnedValues`] returned in the result.
+ */
+struct ValuesResult fwd_get_many_from_revision(const struct RevisionHandle *revision,
+                                               struct BorrowedSlice_BorrowedBytes keys);
+
 /**
  * Gets a handle to the revision identified by the provided root hash.
  *
@@ -1662,7 +2111,8 @@ struct RevisionResult fwd_get_revision(const struct DatabaseHandle *db, Borrowed
  *   subsequent calls will continue returning [`KeyValueResult::None`]. You may still call this
  *   safely, but freeing the iterator with [`fwd_free_iterator`] is recommended.
  * - [`KeyValueResult::Some`] if the next item on iterator was retrieved, with the associated
- *   key value pair.
+ *   key value pair. The value is always returned, whatever mode an earlier call to
+ *   [`fwd_iter_next_n`] used.
  * - [`KeyValueResult::Err`] if an I/O error occurred while retrieving the next item. Most
  *   iterator errors are non-reentrant. Once returned, the iterator should be considered
  *   invalid and must be freed with [`fwd_free_iterator`].
@@ -1684,10 +2134,19 @@ struct KeyValueResult fwd_iter_next(struct IteratorHandle *handle);
  *
  * * `handle` - The iterator handle returned by [`fwd_iter_on_revision`] or
  *   [`fwd_iter_on_proposal`].
+ * * `n` - The maximum number of items to retrieve.
+ * * `mode` - What to return alongside each key. This must be one of the following:
+ *
+ *   - `0`: The key's value.
+ *   - `1`: Nothing; every value is empty, and values are not read.
+ *   - `2`: The 32 byte SHA-256 digest of the key's value.
+ *
+ *   The mode applies to the items of this batch only.
  *
  * # Returns
  *
  * - [`KeyValueBatchResult::NullHandlePointer`] if the provided iterator handle is null.
+ * - [`KeyValueBatchResult::Err`] if `mode` is not one of the above values.
  * - [`KeyValueBatchResult::Some`] with up to `n` key/value pairs. If the iterator is
  *   exhausted, this may be fewer than `n`, including zero items.
  * - [`KeyValueBatchResult::Err`] if an I/O error occurred while retrieving items. Most
@@ -1705,7 +2164,7 @@ struct KeyValueResult fwd_iter_next(struct IteratorHandle *handle);
  * * call [`fwd_free_owned_key_value_batch`] on the returned batch to free any allocated memory.
  *
  */
-struct KeyValueBatchResult fwd_iter_next_n(struct IteratorHandle *handle, size_t n);
+struct KeyValueBatchResult fwd_iter_next_n(struct IteratorHandle *handle, size_t n, uint8_t mode);
 
 /**
  * Returns an iterator on the provided proposal optionally starting from a key
@@ -1715,6 +2174,9 @@ struct KeyValueBatchResult fwd_iter_next_n(struct IteratorHandle *handle, size_t
  * * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
  *   [`fwd_propose_on_proposal`].
  * * `key` - The key to look up as a [`BorrowedBytes`]
+ * * `descending` - Whether to iterate in descending order. The iterator then
+ *   starts at the highest key less than or equal to `key`, or at the highest
+ *   key if `key` is empty.
  *
  * # Returns
  *
@@ -1730,7 +2192,9 @@ struct KeyValueBatchResult fwd_iter_next_n(struct IteratorHandle *handle, size_t
  * * call [`fwd_free_iterator`] to free the memory associated with the iterator.
  *
  */
-struct IteratorResult fwd_iter_on_proposal(const struct ProposalHandle *handle, BorrowedBytes key);
+struct IteratorResult fwd_iter_on_proposal(const struct ProposalHandle *handle,
+                                           BorrowedBytes key,
+                                           bool descending);
 
 /**
  * Returns an iterator optionally starting from a key in the provided revision.
@@ -1739,6 +2203,9 @@ struct IteratorResult fwd_iter_on_proposal(const struct ProposalHandle *handle,
  *
  * * `revision` - The revision handle returned by [`fwd_get_revision`].
  * * `key` - The key to look up as a [`BorrowedBytes`]
+ * * `descending` - Whether to iterate in descending order. The iterator then
+ *   starts at the highest key less than or equal to `key`, or at the highest
+ *   key if `key` is empty.
  *
  * # Returns
  *
@@ -1755,7 +2222,120 @@ struct IteratorResult fwd_iter_on_proposal(const struct ProposalHandle *handle,
  *
  */
 struct IteratorResult fwd_iter_on_revision(const struct RevisionHandle *revision,
-                                           BorrowedBytes key);
+                                           BorrowedBytes key,
+                                           bool descending);
+
+/**
+ * Returns an iterator over the keys starting with `prefix` in the provided proposal.
+ *
+ * # Arguments
+ *
+ * * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
+ *   [`fwd_propose_on_proposal`].
+ * * `prefix` - The prefix of the keys to return, as a [`BorrowedBytes`]. If
+ *   empty, the iterator returns every key.
+ *
+ * # Returns
+ *
+ * - [`IteratorResult::NullHandlePointer`] if the provided proposal handle is null.
+ * - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+ * - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `handle` is a valid pointer to a [`ProposalHandle`]
+ * * ensure that `prefix` is a valid [`BorrowedBytes`]
+ * * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+ *
+ */
+struct IteratorResult fwd_iter_prefix_on_proposal(const struct ProposalHandle *handle,
+                                                  BorrowedBytes prefix);
+
+/**
+ * Returns an iterator over the keys starting with `prefix` in the provided revision.
+ *
+ * # Arguments
+ *
+ * * `revision` - The revision handle returned by [`fwd_get_revision`].
+ * * `prefix` - The prefix of the keys to return, as a [`BorrowedBytes`]. If
+ *   empty, the iterator returns every key.
+ *
+ * # Returns
+ *
+ * - [`IteratorResult::NullHandlePointer`] if the provided revision handle is null.
+ * - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+ * - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `revision` is a valid pointer to a [`RevisionHandle`]
+ * * ensure that `prefix` is a valid [`BorrowedBytes`]
+ * * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+ *
+ */
+struct IteratorResult fwd_iter_prefix_on_revision(const struct RevisionHandle *revision,
+                                                  BorrowedBytes prefix);
+
+/**
+ * Returns an iterator over the keys in [`start`, `end`) in the provided proposal.
+ *
+ * # Arguments
+ *
+ * * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
+ *   [`fwd_propose_on_proposal`].
+ * * `start` - The first key to return, as a [`BorrowedBytes`]. If empty, the
+ *   iterator starts at the lowest key.
+ * * `end` - The key to stop before, as a [`BorrowedBytes`]. If empty, the
+ *   iterator continues to the highest key.
+ *
+ * # Returns
+ *
+ * - [`IteratorResult::NullHandlePointer`] if the provided proposal handle is null.
+ * - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+ * - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `handle` is a valid pointer to a [`ProposalHandle`]
+ * * ensure that `start` and `end` are valid [`BorrowedBytes`]
+ * * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+ *
+ */
+struct IteratorResult fwd_iter_range_on_proposal(const struct ProposalHandle *handle,
+                                                 BorrowedBytes start,
+                                                 BorrowedBytes end);
+
+/**
+ * Returns an iterator over the keys in [`start`, `end`) in the provided revision.
+ *
+ * # Arguments
+ *
+ * * `revision` - The revision handle returned by [`fwd_get_revision`].
+ * * `start` - The first key to return, as a [`BorrowedBytes`]. If empty, the
+ *   iterator starts at the lowest key.
+ * * `end` - The key to stop before, as a [`BorrowedBytes`]. If empty, the
+ *   iterator continues to the highest key.
+ *
+ * # Returns
+ *
+ * - [`IteratorResult::NullHandlePointer`] if the provided revision handle is null.
+ * - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+ * - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `revision` is a valid pointer to a [`RevisionHandle`]
+ * * ensure that `start` and `end` are valid [`BorrowedBytes`]
+ * * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+ *
+ */
+struct IteratorResult fwd_iter_range_on_revision(const struct RevisionHandle *revision,
+                                                 BorrowedBytes start,
+                                                 BorrowedBytes end);
 
 /**
  * Open a database with the given arguments.
@@ -1778,6 +2358,69 @@ struct IteratorResult fwd_iter_on_revision(const struct RevisionHandle *revision
  */
 struct HandleResult fwd_open_db(struct DatabaseHandleArgs args);
 
+/**
+ * Lists the keys changed by the provided proposal relative to the revision
+ * or proposal it was proposed on top of.
+ *
+ * Only the subtries that differ from the parent are read, so the cost depends
+ * on the size of the proposal rather than the size of the database.
+ *
+ * # Arguments
+ *
+ * * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
+ *   [`fwd_propose_on_proposal`].
+ *
+ * # Returns
+ *
+ * - [`KeyChangesResult::NullHandlePointer`] if the provided proposal handle is null.
+ * - [`KeyChangesResult::Some`] with one entry per changed key, in ascending key
+ *   order. Inserted keys have no old value and deleted keys have no new value.
+ * - [`KeyChangesResult::Err`] if the parent is no longer available or an error
+ *   occurred while reading the changes.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `handle` is a valid pointer to a [`ProposalHandle`]
+ * * call [`fwd_free_owned_key_changes`] to free the memory associated with the
+ *   [`OwnedKeyChanges`] returned in the result.
+ */
+struct KeyChangesResult fwd_proposal_diff(const struct ProposalHandle *handle);
+
+/**
+ * Proposes removing the keys in a half-open range from the database.
+ *
+ * Unlike [`fwd_delete_range`], the result is not committed, and unlike a
+ * range delete in [`fwd_propose_on_db`], the number of keys removed is
+ * returned.
+ *
+ * # Arguments
+ *
+ * * `db` - The database handle returned by [`fwd_open_db`]
+ * * `start` - The first key to remove, as a [`BorrowedBytes`].
+ * * `end` - The first key after the range, as a [`BorrowedBytes`]. If it is
+ *   null, every key from `start` onwards is removed.
+ *
+ * # Returns
+ *
+ * - [`ProposeDeleteRangeResult::NullHandlePointer`] if the provided database handle is null.
+ * - [`ProposeDeleteRangeResult::Ok`] if the proposal was created, with the
+ *   proposal handle, calculated root hash and number of keys removed.
+ * - [`ProposeDeleteRangeResult::Err`] if an error occurred while creating the proposal.
+ *
+ * # Safety
+ *
+ * The caller must:
+ * * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+ * * ensure that `start` and `end` are valid for [`BorrowedBytes`]
+ * * call [`fwd_commit_proposal`] or [`fwd_free_proposal`] to free the memory
+ *   associated with the proposal. And, the caller must ensure this is done
+ *   before calling [`fwd_close_db`] to avoid memory leaks or undefined behavior.
+ */
+struct ProposeDeleteRangeResult fwd_propose_delete_range(const struct DatabaseHandle *db,
+                                                         BorrowedBytes start,
+                                                         BorrowedBytes end);
+
 /**
  * Proposes a batch of operations to the database.
  *
@@ -1923,6 +2566,36 @@ struct ValueResult fwd_range_proof_to_bytes(const struct RangeProofContext *proo
  */
 struct VoidResult fwd_range_proof_verify(struct VerifyRangeProofArgs _args);
 
+/**
+ * Make an earlier revision the latest one, discarding every revision
+ * committed after it.
+ *
+ * The areas written by the discarded revisions are returned to the free
+ * lists. Only revisions that are still retained can be rolled back to.
+ *
+ * # Arguments
+ *
+ * * `db` - The database handle returned by [`fwd_open_db`]
+ * * `root` - The root hash of the revision as a [`BorrowedBytes`].
+ *
+ * # Returns
+ *
+ * - [`VoidResult::NullHandlePointer`] if the provided database handle is null.
+ * - [`VoidResult::Ok`] if the revision is now the latest one.
+ * - [`VoidResult::Err`] if the revision is not retained, if a discarded
+ *   revision or any proposal is still in use, or if an i/o error occurred.
+ *
+ * # Safety
+ *
+ * * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+ * * ensure that `root` is valid for [`BorrowedBytes`].
+ * * call [`fwd_free_owned_bytes`] to free the memory associated with the
+ *   returned error (if any).
+ *
+ * [`BorrowedBytes`]: crate::value::BorrowedBytes
+ */
+struct VoidResult fwd_rollback_to(const struct DatabaseHandle *db, BorrowedBytes root);
+
 /**
  * Get the root hash of the latest version of the database
  *
@@ -6,6 +6,7 @@ package ffi
 import (
 	"bytes"
 	"context"
+	"crypto/sha256"
 	"encoding/hex"
 	"errors"
 	"fmt"
@@ -182,6 +183,105 @@ func TestUpdateMultiKV(t *testing.T) {
 	}
 }
 
+func TestComputeRoot(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+	keys, vals := kvForTest(10)
+	committed, err := db.Update(keys[:5], vals[:5])
+	r.NoError(err)
+
+	computed, err := db.ComputeRoot(nil, keys[5:], vals[5:])
+	r.NoError(err)
+	proposal, err := db.Propose(keys[5:], vals[5:])
+	r.NoError(err)
+	root, err := proposal.Root()
+	r.NoError(err)
+	r.Equal(root, computed)
+
+	// An empty batch leaves the parent unchanged.
+	computed, err = db.ComputeRoot(committed, nil, nil)
+	r.NoError(err)
+	r.Equal(committed, computed)
+
+	r.NoError(proposal.Drop())
+}
+
+func TestDeleteRange(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+	keys := [][]byte{[]byte("a"), []byte("ab"), []byte("b"), []byte("c")}
+	vals := [][]byte{[]byte("1"), []byte("2"), []byte("3"), []byte("4")}
+	_, err := db.Update(keys, vals)
+	r.NoError(err)
+
+	root, removed, err := db.DeleteRange([]byte("ab"), []byte("c"))
+	r.NoError(err)
+	r.Equal(uint64(2), removed)
+	got, err := db.Root()
+	r.NoError(err)
+	r.Equal(got, root)
+
+	for i, key := range keys {
+		got, err := db.Get(key)
+		r.NoError(err)
+		if i == 1 || i == 2 {
+			r.Nil(got)
+		} else {
+			r.Equal(vals[i], got)
+		}
+	}
+
+	// A nil end removes every key from start onwards.
+	_, removed, err = db.DeleteRange([]byte("b"), nil)
+	r.NoError(err)
+	r.Equal(uint64(1), removed)
+}
+
+func TestDeleteRangeInBatch(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+	keys := [][]byte{[]byte("a"), []byte("ab"), []byte("b"), []byte("c")}
+	vals := [][]byte{[]byte("1"), []byte("2"), []byte("3"), []byte("4")}
+	_, err := db.Update(keys, vals)
+	r.NoError(err)
+
+	p, removed, err := db.ProposeDeleteRange([]byte("ab"), []byte("c"))
+	r.NoError(err)
+	r.Equal(uint64(2), removed)
+	got, err := p.Get([]byte("b"))
+	r.NoError(err)
+	r.Nil(got)
+	r.NoError(p.Drop())
+
+	p, err = db.ProposeOps([]BatchOp{
+		DeleteRangeOp([]byte("ab"), []byte("c")),
+		PutOp([]byte("d"), nil),
+	})
+	r.NoError(err)
+	r.NoError(p.Commit())
+
+	for i, key := range keys {
+		got, err := db.Get(key)
+		r.NoError(err)
+		if i == 1 || i == 2 {
+			r.Nil(got)
+		} else {
+			r.Equal(vals[i], got)
+		}
+	}
+	got, err = db.Get([]byte("d"))
+	r.NoError(err)
+	r.NotNil(got)
+	r.Empty(got)
+
+	// A nil end removes every key from start onwards.
+	_, err = db.UpdateOps([]BatchOp{DeleteRangeOp([]byte("b"), nil)})
+	r.NoError(err)
+	got, err = db.Get([]byte("c"))
+	r.NoError(err)
+	r.Nil(got)
+}
+
 func TestTruncateDatabase(t *testing.T) {
 	r := require.New(t)
 	dbFile := filepath.Join(t.TempDir(), "test.db")
@@ -232,9 +332,41 @@ func TestClosedDatabase(t *testing.T) {
 	r.Empty(root)
 	r.ErrorIs(err, errDBClosed)
 
+	r.ErrorIs(db.Flush(), errDBClosed)
+
 	r.NoError(db.Close(t.Context()))
 }
 
+func TestFlush(t *testing.T) {
+	r := require.New(t)
+	dbFile := filepath.Join(t.TempDir(), "test.db")
+	db, err := newDatabase(dbFile)
+	r.NoError(err)
+
+	keys, vals := kvForTest(10)
+	root, err := db.Update(keys, vals)
+	r.NoError(err)
+	r.NoError(db.Flush())
+	// nothing is pending, so flushing again returns immediately
+	r.NoError(db.Flush())
+	r.NoError(db.Close(t.Context()))
+
+	// everything flushed is there after reopening
+	db, err = New(dbFile, DefaultConfig())
+	r.NoError(err)
+	t.Cleanup(func() {
+		r.NoError(db.Close(context.Background())) //nolint:usetesting // t.Context() will already be cancelled
+	})
+	got, err := db.Root()
+	r.NoError(err)
+	r.Equal(root, got)
+	for i, key := range keys {
+		got, err := db.Get(key)
+		r.NoError(err)
+		r.Equal(vals[i], got)
+	}
+}
+
 func keyForTest(i int) []byte {
 	return []byte("key" + strconv.Itoa(i))
 }
@@ -860,6 +992,80 @@ func TestRevision(t *testing.T) {
 	}
 }
 
+func TestRevisionGetMany(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+
+	keys, vals := kvForTest(100)
+	_, err := db.Update(keys, vals)
+	r.NoError(err)
+
+	root, err := db.Root()
+	r.NoError(err)
+	revision, err := db.Revision(root)
+	r.NoError(err)
+	t.Cleanup(func() {
+		r.NoError(revision.Drop())
+	})
+
+	// Look the keys up in reverse order, with a missing key in the middle.
+	lookup := make([][]byte, 0, len(keys)+1)
+	for i := len(keys) - 1; i >= 0; i-- {
+		lookup = append(lookup, keys[i])
+		if i == len(keys)/2 {
+			lookup = append(lookup, []byte("missing"))
+		}
+	}
+
+	got, err := revision.GetMany(lookup)
+	r.NoError(err)
+	r.Len(got, len(lookup))
+	for i, key := range lookup {
+		want, err := revision.Get(key)
+		r.NoError(err)
+		r.Equal(want, got[i], "GetMany(%x)", key)
+	}
+
+	got, err = revision.GetMany(nil)
+	r.NoError(err)
+	r.Empty(got)
+}
+
+func TestProposalDiff(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+
+	keys, vals := kvForTest(10)
+	_, err := db.Update(keys, vals)
+	r.NoError(err)
+
+	// Update the first key, delete the second and insert a new one.
+	proposal, err := db.Propose(
+		[][]byte{keys[0], keys[1], []byte("new")},
+		[][]byte{[]byte("updated"), nil, []byte("inserted")},
+	)
+	r.NoError(err)
+
+	changes, err := proposal.Diff()
+	r.NoError(err)
+	want := []KeyChange{
+		{Key: keys[0], OldValue: vals[0], NewValue: []byte("updated")},
+		{Key: keys[1], OldValue: vals[1]},
+		{Key: []byte("new"), NewValue: []byte("inserted")},
+	}
+	r.ElementsMatch(want, changes)
+
+	// A proposal on top of another only lists its own changes.
+	child, err := proposal.Propose([][]byte{[]byte("new")}, [][]byte{nil})
+	r.NoError(err)
+	changes, err = child.Diff()
+	r.NoError(err)
+	r.Equal([]KeyChange{{Key: []byte("new"), OldValue: []byte("inserted")}}, changes)
+
+	r.NoError(child.Drop())
+	r.NoError(proposal.Drop())
+}
+
 // Tests that even if a proposal is committed, the corresponding revision will not go away
 // as we're holding on to it
 func TestRevisionOutlivesProposal(t *testing.T) {
@@ -933,6 +1139,40 @@ func TestRevisionOutlivesReaping(t *testing.T) {
 	r.Error(err)
 }
 
+func TestRollbackTo(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+
+	keys, vals := kvForTest(30)
+	firstRoot, err := db.Update(keys[:10], vals[:10])
+	r.NoError(err)
+	secondRoot, err := db.Update(keys[10:20], vals[10:20])
+	r.NoError(err)
+
+	// a held revision that would be discarded prevents the rollback
+	rev, err := db.Revision(secondRoot)
+	r.NoError(err)
+	r.Error(db.RollbackTo(firstRoot))
+	r.NoError(rev.Drop())
+
+	r.ErrorIs(db.RollbackTo(nil), errInvalidRootLength)
+	r.NoError(db.RollbackTo(firstRoot))
+
+	got, err := db.Root()
+	r.NoError(err)
+	r.Equal(firstRoot, got)
+	_, err = db.Revision(secondRoot)
+	r.ErrorIs(err, errRevisionNotFound)
+	val, err := db.Get(keys[10])
+	r.NoError(err)
+	r.Nil(val)
+
+	// committing the same changes again gives the same root
+	root, err := db.Update(keys[10:20], vals[10:20])
+	r.NoError(err)
+	r.Equal(secondRoot, root)
+}
+
 func TestInvalidRevision(t *testing.T) {
 	r := require.New(t)
 	db := newTestDatabase(t)
@@ -1352,6 +1592,115 @@ func TestIter(t *testing.T) {
 	})
 }
 
+// Tests that iterating in descending order yields keys from the end, or from
+// a seek key
+func TestIterReverse(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+	keys, vals := kvForTest(100)
+	_, err := db.Update(keys, vals)
+	r.NoError(err)
+
+	reversed := func(in [][]byte) [][]byte {
+		out := make([][]byte, len(in))
+		for i, v := range in {
+			out[len(in)-1-i] = v
+		}
+		return out
+	}
+
+	runIteratorTestForAllModes(t, func(t *testing.T, cfn iteratorConfigFn) {
+		r := require.New(t)
+		rev, err := db.LatestRevision()
+		r.NoError(err)
+		all, err := rev.IterReverse(nil)
+		r.NoError(err)
+		fromKey, err := rev.IterReverse(keys[49])
+		r.NoError(err)
+		t.Cleanup(func() {
+			r.NoError(all.Drop())
+			r.NoError(fromKey.Drop())
+			r.NoError(rev.Drop())
+		})
+
+		assertIteratorYields(r, cfn(all), reversed(keys), reversed(vals))
+		assertIteratorYields(r, cfn(fromKey), reversed(keys[:50]), reversed(vals[:50]))
+	})
+}
+
+// Tests that range and prefix iterators stop at their end key
+func TestIterRangeAndPrefix(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+	keys, vals := kvForTest(100)
+	_, err := db.Update(keys, vals)
+	r.NoError(err)
+
+	runIteratorTestForAllModes(t, func(t *testing.T, cfn iteratorConfigFn) {
+		r := require.New(t)
+		rev, err := db.LatestRevision()
+		r.NoError(err)
+		rangeIt, err := rev.IterRange(keys[10], keys[20])
+		r.NoError(err)
+		prefixIt, err := rev.IterPrefix([]byte("key1"))
+		r.NoError(err)
+		t.Cleanup(func() {
+			r.NoError(rangeIt.Drop())
+			r.NoError(prefixIt.Drop())
+			r.NoError(rev.Drop())
+		})
+
+		// "key1" is a prefix of "key1" and "key10" to "key19"
+		prefixKeys, prefixVals := [][]byte{}, [][]byte{}
+		for i, k := range keys {
+			if bytes.HasPrefix(k, []byte("key1")) {
+				prefixKeys = append(prefixKeys, k)
+				prefixVals = append(prefixVals, vals[i])
+			}
+		}
+		r.Len(prefixKeys, 11)
+
+		assertIteratorYields(r, cfn(rangeIt), keys[10:20], vals[10:20])
+		assertIteratorYields(r, cfn(prefixIt), prefixKeys, prefixVals)
+	})
+}
+
+// Tests that keys-only and value digest modes replace each value
+func TestIterModes(t *testing.T) {
+	r := require.New(t)
+	db := newTestDatabase(t)
+	keys, vals := kvForTest(100)
+	_, err := db.Update(keys, vals)
+	r.NoError(err)
+
+	empty := make([][]byte, len(keys))
+	digests := make([][]byte, len(keys))
+	for i, v := range vals {
+		empty[i] = []byte{}
+		sum := sha256.Sum256(v)
+		digests[i] = sum[:]
+	}
+
+	for _, batchSize := range []int{1, 100} {
+		rev, err := db.LatestRevision()
+		r.NoError(err)
+		keysOnly, err := rev.Iter(nil)
+		r.NoError(err)
+		keysOnly.SetBatchSize(batchSize)
+		keysOnly.SetMode(IterKeysOnly)
+		withDigests, err := rev.Iter(nil)
+		r.NoError(err)
+		withDigests.SetBatchSize(batchSize)
+		withDigests.SetMode(IterValueDigests)
+
+		assertIteratorYields(r, keysOnly, keys, empty)
+		assertIteratorYields(r, withDigests, keys, digests)
+		r.NoError(keysOnly.Drop())
+		r.NoError(withDigests.Drop())
+		r.NoError(rev.Drop())
+	}
+}
+
 func TestIterOnRoot(t *testing.T) {
 	r := require.New(t)
 	db := newTestDatabase(t)
@@ -13,6 +13,18 @@ import (
 	"unsafe"
 )
 
+// IterMode selects what an [Iterator] returns alongside each key.
+type IterMode uint8
+
+const (
+	// IterKeyValue returns each key's value.
+	IterKeyValue IterMode = iota
+	// IterKeysOnly returns an empty value for each key.
+	IterKeysOnly
+	// IterValueDigests returns the SHA-256 digest of each key's value.
+	IterValueDigests
+)
+
 type Iterator struct {
 	// handle is an opaque pointer to the iterator within Firewood. It should be
 	// passed to the C FFI functions that operate on iterators
@@ -24,6 +36,9 @@ type Iterator struct {
 	// to reduce ffi call overheads
 	batchSize int
 
+	// mode is what is returned alongside each key
+	mode IterMode
+
 	// loadedPairs is the latest loaded key value pairs retrieved
 	// from the iterator, not yet consumed by user
 	loadedPairs []*ownedKeyValue
@@ -60,7 +75,7 @@ func (it *Iterator) nextInternal() error {
 	if e := it.freeCurrentAllocation(); e != nil {
 		return e
 	}
-	if it.batchSize <= 1 {
+	if it.batchSize <= 1 && it.mode == IterKeyValue {
 		kv, e := getKeyValueFromResult(C.fwd_iter_next(it.handle))
 		if e != nil {
 			return e
@@ -68,7 +83,7 @@ func (it *Iterator) nextInternal() error {
 		it.currentPair = kv
 		it.currentResource = kv
 	} else {
-		batch, e := getKeyValueBatchFromResult(C.fwd_iter_next_n(it.handle, C.size_t(it.batchSize)))
+		batch, e := getKeyValueBatchFromResult(C.fwd_iter_next_n(it.handle, C.size_t(max(it.batchSize, 1)), C.uint8_t(it.mode)))
 		if e != nil {
 			return e
 		}
@@ -89,6 +104,11 @@ func (it *Iterator) SetBatchSize(batchSize int) {
 	it.batchSize = batchSize
 }
 
+// SetMode sets what Value returns for each key from the next batch on.
+func (it *Iterator) SetMode(mode IterMode) {
+	it.mode = mode
+}
+
 // Next proceeds to the next item on the iterator, and returns true
 // if succeeded and there is a pair available.
 // The new pair could be retrieved with Key and Value methods.
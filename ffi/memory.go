@@ -94,6 +94,20 @@ func newKeyValuePair(key, value []byte, pinner Pinner) C.KeyValuePair {
 	}
 }
 
+// newBatchOpPairs creates a new BorrowedKeyValuePairs from a slice of BatchOp.
+//
+// Provide a Pinner to ensure the memory is pinned while the BorrowedKeyValuePairs is
+// in use.
+func newBatchOpPairs(ops []BatchOp, pinner Pinner) C.BorrowedKeyValuePairs {
+	pairs := make([]C.KeyValuePair, len(ops))
+	for i, op := range ops {
+		pairs[i] = newKeyValuePair(op.key, op.value, pinner)
+		pairs[i].kind = op.kind
+	}
+
+	return newBorrowedKeyValuePairs(pairs, pinner)
+}
+
 // newBorrowedKeyValuePairs creates a new BorrowedKeyValuePairs from a slice of KeyValuePair.
 //
 // Provide a Pinner to ensure the memory is pinned while the BorrowedKeyValuePairs is
@@ -136,6 +150,29 @@ func newKeyValuePairs(keys, vals [][]byte, pinner Pinner) (C.BorrowedKeyValuePai
 	return newBorrowedKeyValuePairs(pairs, pinner), nil
 }
 
+// newBorrowedKeys creates a new BorrowedSlice of BorrowedBytes from a slice
+// of keys.
+//
+// Provide a Pinner to ensure the memory is pinned while the slice is in use.
+func newBorrowedKeys(keys [][]byte, pinner Pinner) C.BorrowedSlice_BorrowedBytes {
+	if len(keys) == 0 {
+		return C.BorrowedSlice_BorrowedBytes{ptr: nil, len: 0}
+	}
+
+	borrowed := make([]C.BorrowedBytes, len(keys))
+	for i := range keys {
+		borrowed[i] = newBorrowedBytes(keys[i], pinner)
+	}
+
+	ptr := unsafe.SliceData(borrowed)
+	pinner.Pin(ptr)
+
+	return C.BorrowedSlice_BorrowedBytes{
+		ptr: ptr,
+		len: C.size_t(len(borrowed)),
+	}
+}
+
 // ownedBytes is a wrapper around C.OwnedBytes that provides a Go interface
 // for Rust-owned byte slices.
 //
@@ -265,6 +302,24 @@ func getHashKeyFromHashResult(result C.HashResult) ([]byte, error) {
 	}
 }
 
+// getDeleteRangeResult converts a C.DeleteRangeResult to the root hash and
+// the number of keys removed, or an error.
+func getDeleteRangeResult(result C.DeleteRangeResult) ([]byte, uint64, error) {
+	switch result.tag {
+	case C.DeleteRangeResult_NullHandlePointer:
+		return nil, 0, errDBClosed
+	case C.DeleteRangeResult_Ok:
+		body := (*C.DeleteRangeResult_Ok_Body)(unsafe.Pointer(&result.anon0))
+		hashKey := *(*[32]byte)(unsafe.Pointer(&body.root_hash._0))
+		return hashKey[:], uint64(body.removed), nil
+	case C.DeleteRangeResult_Err:
+		ownedBytes := newOwnedBytes(*(*C.OwnedBytes)(unsafe.Pointer(&result.anon0)))
+		return nil, 0, ownedBytes.intoError()
+	default:
+		return nil, 0, fmt.Errorf("unknown C.DeleteRangeResult tag: %d", result.tag)
+	}
+}
+
 // getErrorgetErrorFromVoidResult converts a C.VoidResult to an error.
 //
 // It will return nil if the result is Ok, otherwise it returns an error.
@@ -312,6 +367,75 @@ func getValueFromValueResult(result C.ValueResult) ([]byte, error) {
 	}
 }
 
+// getValuesFromValuesResult converts a C.ValuesResult to a slice of values or
+// an error.
+//
+// Keys that were not found have a nil value.
+func getValuesFromValuesResult(result C.ValuesResult) ([][]byte, error) {
+	switch result.tag {
+	case C.ValuesResult_NullHandlePointer:
+		return nil, errDBClosed
+	case C.ValuesResult_Some:
+		owned := *(*C.OwnedValues)(unsafe.Pointer(&result.anon0))
+		values := make([][]byte, owned.len)
+		if owned.ptr != nil {
+			for i, value := range unsafe.Slice(owned.ptr, owned.len) {
+				if value.tag == C.Maybe_OwnedBytes_Some_OwnedBytes {
+					values[i] = newOwnedBytes(*(*C.OwnedBytes)(unsafe.Pointer(&value.anon0))).CopiedBytes()
+				}
+			}
+		}
+		if err := getErrorFromVoidResult(C.fwd_free_owned_values(owned)); err != nil {
+			return nil, fmt.Errorf("%w: %w", errFreeingValue, err)
+		}
+		return values, nil
+	case C.ValuesResult_Err:
+		err := newOwnedBytes(*(*C.OwnedBytes)(unsafe.Pointer(&result.anon0))).intoError()
+		return nil, err
+	default:
+		return nil, fmt.Errorf("unknown C.ValuesResult tag: %d", result.tag)
+	}
+}
+
+// getKeyChangesFromKeyChangesResult converts a C.KeyChangesResult to a slice
+// of key changes or an error.
+func getKeyChangesFromKeyChangesResult(result C.KeyChangesResult) ([]KeyChange, error) {
+	switch result.tag {
+	case C.KeyChangesResult_NullHandlePointer:
+		return nil, errDBClosed
+	case C.KeyChangesResult_Some:
+		owned := *(*C.OwnedKeyChanges)(unsafe.Pointer(&result.anon0))
+		changes := make([]KeyChange, owned.len)
+		if owned.ptr != nil {
+			for i, change := range unsafe.Slice(owned.ptr, owned.len) {
+				changes[i] = KeyChange{
+					Key:      newOwnedBytes(change.key).CopiedBytes(),
+					OldValue: copiedMaybeBytes(change.old_value),
+					NewValue: copiedMaybeBytes(change.new_value),
+				}
+			}
+		}
+		if err := getErrorFromVoidResult(C.fwd_free_owned_key_changes(owned)); err != nil {
+			return nil, fmt.Errorf("%w: %w", errFreeingValue, err)
+		}
+		return changes, nil
+	case C.KeyChangesResult_Err:
+		err := newOwnedBytes(*(*C.OwnedBytes)(unsafe.Pointer(&result.anon0))).intoError()
+		return nil, err
+	default:
+		return nil, fmt.Errorf("unknown C.KeyChangesResult tag: %d", result.tag)
+	}
+}
+
+// copiedMaybeBytes copies the bytes out of a C.Maybe_OwnedBytes, returning nil
+// if there are none. The bytes are not freed.
+func copiedMaybeBytes(value C.Maybe_OwnedBytes) []byte {
+	if value.tag != C.Maybe_OwnedBytes_Some_OwnedBytes {
+		return nil
+	}
+	return newOwnedBytes(*(*C.OwnedBytes)(unsafe.Pointer(&value.anon0))).CopiedBytes()
+}
+
 type ownedKeyValueBatch struct {
 	owned C.OwnedKeyValueBatch
 }
@@ -69,7 +69,76 @@ func (p *Proposal) Iter(key []byte) (*Iterator, error) {
 	var pinner runtime.Pinner
 	defer pinner.Unpin()
 
-	itResult := C.fwd_iter_on_proposal(p.handle, newBorrowedBytes(key, &pinner))
+	itResult := C.fwd_iter_on_proposal(p.handle, newBorrowedBytes(key, &pinner), false)
+
+	return getIteratorFromIteratorResult(itResult)
+}
+
+// IterRange creates an iterator over the keys in [start, end) on proposal.
+// pass empty start to start from the beginning, and empty end to continue to the end
+func (p *Proposal) IterRange(start, end []byte) (*Iterator, error) {
+	if p.handle == nil {
+		return nil, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	itResult := C.fwd_iter_range_on_proposal(
+		p.handle,
+		newBorrowedBytes(start, &pinner),
+		newBorrowedBytes(end, &pinner),
+	)
+
+	return getIteratorFromIteratorResult(itResult)
+}
+
+// IterPrefix creates an iterator over the keys starting with prefix on proposal.
+// pass empty prefix to iterate over every key
+func (p *Proposal) IterPrefix(prefix []byte) (*Iterator, error) {
+	if p.handle == nil {
+		return nil, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	itResult := C.fwd_iter_prefix_on_proposal(p.handle, newBorrowedBytes(prefix, &pinner))
+
+	return getIteratorFromIteratorResult(itResult)
+}
+
+// KeyChange is a change the proposal makes to the value of one key.
+// OldValue is nil for a key the proposal inserts, and NewValue is nil for a
+// key the proposal deletes.
+type KeyChange struct {
+	Key      []byte
+	OldValue []byte
+	NewValue []byte
+}
+
+// Diff lists the keys the proposal changes relative to the revision or
+// proposal it was created on, in ascending key order.
+func (p *Proposal) Diff() ([]KeyChange, error) {
+	if p.handle == nil {
+		return nil, errDroppedProposal
+	}
+
+	return getKeyChangesFromKeyChangesResult(C.fwd_proposal_diff(p.handle))
+}
+
+// IterReverse creates an iterator over the proposal in descending key order,
+// starting from the highest key less than or equal to the provided key.
+// pass empty slice to start from the end
+func (p *Proposal) IterReverse(key []byte) (*Iterator, error) {
+	if p.handle == nil {
+		return nil, errDBClosed
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	itResult := C.fwd_iter_on_proposal(p.handle, newBorrowedBytes(key, &pinner), true)
 
 	return getIteratorFromIteratorResult(itResult)
 }
@@ -96,6 +165,22 @@ func (p *Proposal) Propose(keys, vals [][]byte) (*Proposal, error) {
 	return getProposalFromProposalResult(C.fwd_propose_on_proposal(p.handle, kvp), p.outstandingHandles)
 }
 
+// ProposeOps is equivalent to [Database.ProposeOps] except that the new
+// proposal is based on `p`.
+func (p *Proposal) ProposeOps(ops []BatchOp) (*Proposal, error) {
+	if p.handle == nil {
+		return nil, errDroppedProposal
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	return getProposalFromProposalResult(
+		C.fwd_propose_on_proposal(p.handle, newBatchOpPairs(ops, &pinner)),
+		p.outstandingHandles,
+	)
+}
+
 // disownHandle is the common path of [Proposal.Commit] and [Proposal.Drop], the
 // `fn` argument defining the method-specific behaviour.
 func (p *Proposal) disownHandle(fn func(*C.ProposalHandle) error, disownEvenOnErr bool) error {
@@ -168,3 +253,28 @@ func getProposalFromProposalResult(result C.ProposalResult, outstandingHandles *
 		return nil, fmt.Errorf("unknown C.ProposalResult tag: %d", result.tag)
 	}
 }
+
+// getProposalFromProposeDeleteRangeResult converts a C.ProposeDeleteRangeResult
+// to a Proposal and the number of keys it removes.
+func getProposalFromProposeDeleteRangeResult(result C.ProposeDeleteRangeResult, outstandingHandles *sync.WaitGroup) (*Proposal, uint64, error) {
+	switch result.tag {
+	case C.ProposeDeleteRangeResult_NullHandlePointer:
+		return nil, 0, errDBClosed
+	case C.ProposeDeleteRangeResult_Ok:
+		body := (*C.ProposeDeleteRangeResult_Ok_Body)(unsafe.Pointer(&result.anon0))
+		hashKey := *(*[32]byte)(unsafe.Pointer(&body.root_hash._0))
+		proposal := &Proposal{
+			handle:             body.handle,
+			root:               hashKey[:],
+			outstandingHandles: outstandingHandles,
+		}
+		outstandingHandles.Add(1)
+		runtime.SetFinalizer(proposal, (*Proposal).Drop)
+		return proposal, uint64(body.removed), nil
+	case C.ProposeDeleteRangeResult_Err:
+		err := newOwnedBytes(*(*C.OwnedBytes)(unsafe.Pointer(&result.anon0))).intoError()
+		return nil, 0, err
+	default:
+		return nil, 0, fmt.Errorf("unknown C.ProposeDeleteRangeResult tag: %d", result.tag)
+	}
+}
@@ -57,6 +57,25 @@ func (r *Revision) Get(key []byte) ([]byte, error) {
 	))
 }
 
+// GetMany reads the values of many keys from the revision at once.
+//
+// The values are returned in the order of keys. A key that was not found has
+// a nil value. Looking keys up together reads the nodes they share only once
+// and searches independent parts of the trie in parallel.
+func (r *Revision) GetMany(keys [][]byte) ([][]byte, error) {
+	if r.handle == nil {
+		return nil, errDroppedRevision
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	return getValuesFromValuesResult(C.fwd_get_many_from_revision(
+		r.handle,
+		newBorrowedKeys(keys, &pinner),
+	))
+}
+
 // Iter creates an iterator starting from the provided key on revision.
 // pass empty slice to start from beginning
 func (r *Revision) Iter(key []byte) (*Iterator, error) {
@@ -67,7 +86,57 @@ func (r *Revision) Iter(key []byte) (*Iterator, error) {
 	var pinner runtime.Pinner
 	defer pinner.Unpin()
 
-	itResult := C.fwd_iter_on_revision(r.handle, newBorrowedBytes(key, &pinner))
+	itResult := C.fwd_iter_on_revision(r.handle, newBorrowedBytes(key, &pinner), false)
+
+	return getIteratorFromIteratorResult(itResult)
+}
+
+// IterRange creates an iterator over the keys in [start, end) on revision.
+// pass empty start to start from the beginning, and empty end to continue to the end
+func (r *Revision) IterRange(start, end []byte) (*Iterator, error) {
+	if r.handle == nil {
+		return nil, errDroppedRevision
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	itResult := C.fwd_iter_range_on_revision(
+		r.handle,
+		newBorrowedBytes(start, &pinner),
+		newBorrowedBytes(end, &pinner),
+	)
+
+	return getIteratorFromIteratorResult(itResult)
+}
+
+// IterPrefix creates an iterator over the keys starting with prefix on revision.
+// pass empty prefix to iterate over every key
+func (r *Revision) IterPrefix(prefix []byte) (*Iterator, error) {
+	if r.handle == nil {
+		return nil, errDroppedRevision
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	itResult := C.fwd_iter_prefix_on_revision(r.handle, newBorrowedBytes(prefix, &pinner))
+
+	return getIteratorFromIteratorResult(itResult)
+}
+
+// IterReverse creates an iterator over the revision in descending key order,
+// starting from the highest key less than or equal to the provided key.
+// pass empty slice to start from the end
+func (r *Revision) IterReverse(key []byte) (*Iterator, error) {
+	if r.handle == nil {
+		return nil, errDroppedRevision
+	}
+
+	var pinner runtime.Pinner
+	defer pinner.Unpin()
+
+	itResult := C.fwd_iter_on_revision(r.handle, newBorrowedBytes(key, &pinner), true)
 
 	return getIteratorFromIteratorResult(itResult)
 }
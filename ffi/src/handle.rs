@@ -7,7 +7,9 @@ use firewood::{
     v2::api::{self, ArcDynDbView, Db as _, DbView, HashKey, HashKeyExt, KeyType},
 };
 
-use crate::{BorrowedBytes, CView, CreateProposalResult, KeyValuePair, arc_cache::ArcCache};
+use crate::{
+    BorrowedBytes, CView, CreateProposalResult, KeyValuePair, ProposalHandle, arc_cache::ArcCache,
+};
 
 use crate::revision::{GetRevisionResult, RevisionHandle};
 use metrics::counter;
@@ -25,10 +27,15 @@ pub struct DatabaseHandleArgs<'a> {
     /// If this is empty, an error will be returned.
     pub path: BorrowedBytes<'a>,
 
-    /// The size of the node cache.
+    /// The number of bytes the node cache may use for branch nodes.
     ///
-    /// Opening returns an error if this is zero.
-    pub cache_size: usize,
+    /// Zero disables caching of branch nodes.
+    pub branch_cache_bytes: usize,
+
+    /// The number of bytes the node cache may use for leaf nodes.
+    ///
+    /// Zero disables caching of leaf nodes.
+    pub leaf_cache_bytes: usize,
 
     /// The size of the free list cache.
     ///
@@ -62,11 +69,8 @@ impl DatabaseHandleArgs<'_> {
             _ => return Err(invalid_data("invalid cache strategy")),
         };
         let config = RevisionManagerConfig::builder()
-            .node_cache_size(
-                self.cache_size
-                    .try_into()
-                    .map_err(|_| invalid_data("cache size should be non-zero"))?,
-            )
+            .branch_cache_bytes(self.branch_cache_bytes)
+            .leaf_cache_bytes(self.leaf_cache_bytes)
             .max_revisions(self.revisions)
             .cache_read_strategy(cache_read_strategy)
             .free_list_cache_size(
@@ -126,6 +130,28 @@ impl DatabaseHandle {
         self.db.root_hash()
     }
 
+    /// Waits until every committed revision has been written to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from the first revision that could not be written.
+    pub fn flush(&self) -> Result<(), api::Error> {
+        self.db.flush()
+    }
+
+    /// Makes the retained revision with the given root hash the latest one,
+    /// discarding every revision committed after it.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the revision is not retained, if a discarded
+    /// revision or a proposal is still in use, or if there was an i/o error.
+    pub fn rollback_to(&self, root: HashKey) -> Result<(), api::Error> {
+        // the cached view may hold one of the discarded revisions
+        self.clear_cached_view();
+        self.db.rollback_to(root)
+    }
+
     /// Returns a value from the database for the given key from the latest root hash.
     ///
     /// # Errors
@@ -177,6 +203,82 @@ impl DatabaseHandle {
         Ok(root_hash)
     }
 
+    /// Computes the root hash the given values would produce on top of
+    /// `parent`, or on top of the latest revision if `parent` is `None`,
+    /// without creating a proposal.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the parent was not found or if the batch could
+    /// not be applied.
+    pub fn compute_root<'kvp>(
+        &self,
+        parent: Option<HashKey>,
+        values: impl AsRef<[KeyValuePair<'kvp>]> + 'kvp,
+    ) -> Result<Option<HashKey>, api::Error> {
+        let start_time = coarsetime::Instant::now();
+        let root_hash = self.db.compute_root(parent, values.as_ref())?;
+
+        counter!("firewood.ffi.compute_root_ms").increment(start_time.elapsed().as_millis());
+        counter!("firewood.ffi.compute_root").increment(1);
+
+        Ok(root_hash)
+    }
+
+    /// Removes the keys in the half-open range `[start, end)` from the latest
+    /// revision, or every key from `start` onwards if `end` is `None`, and
+    /// commits the result.
+    ///
+    /// Returns the new root hash and the number of keys removed.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the proposal could not be created or committed.
+    pub fn delete_range(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<(Option<HashKey>, usize), api::Error> {
+        let start_time = coarsetime::Instant::now();
+        let (proposal, removed) = self.db.propose_delete_range(start, end)?;
+        let root_hash = proposal.root_hash()?;
+        api::Proposal::commit(proposal)?;
+
+        counter!("firewood.ffi.delete_range_ms").increment(start_time.elapsed().as_millis());
+        counter!("firewood.ffi.delete_range").increment(1);
+
+        Ok((root_hash, removed))
+    }
+
+    /// Proposes removing the keys in the half-open range `[start, end)` from
+    /// the latest revision, or every key from `start` onwards if `end` is
+    /// `None`.
+    ///
+    /// Returns the proposal and the number of keys it removes.
+    ///
+    /// # Errors
+    ///
+    /// An error is returned if the proposal could not be created.
+    pub fn propose_delete_range(
+        &self,
+        start: &[u8],
+        end: Option<&[u8]>,
+    ) -> Result<(CreateProposalResult<'_>, usize), api::Error> {
+        let start_time = coarsetime::Instant::now();
+        let (proposal, removed) = self.db.propose_delete_range(start, end)?;
+
+        counter!("firewood.ffi.propose_ms").increment(start_time.elapsed().as_millis());
+        counter!("firewood.ffi.propose").increment(1);
+
+        Ok((
+            CreateProposalResult {
+                handle: ProposalHandle::new(self, proposal)?,
+                start_time,
+            },
+            removed,
+        ))
+    }
+
     /// Returns an owned handle to the revision corresponding to the provided root hash.
     ///
     /// # Errors
@@ -187,7 +289,7 @@ impl DatabaseHandle {
     pub fn get_revision(&self, root: HashKey) -> Result<GetRevisionResult, api::Error> {
         let view = self.db.view(root.clone())?;
         Ok(GetRevisionResult {
-            handle: RevisionHandle::new(view),
+            handle: RevisionHandle::new(view, self.db.threadpool()),
             root_hash: root,
         })
     }
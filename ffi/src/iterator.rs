@@ -3,7 +3,7 @@
 
 use derive_where::derive_where;
 use firewood::merkle;
-use firewood::v2::api::{self, BoxKeyValueIter};
+use firewood::v2::api::{self, BoxKeyValueIter, IterationMode, KeyValueIter as _};
 use std::iter::FusedIterator;
 
 type KeyValueItem = (merkle::Key, merkle::Value);
@@ -37,9 +37,42 @@ impl FusedIterator for IteratorHandle<'_> {}
 
 #[expect(clippy::missing_errors_doc)]
 impl IteratorHandle<'_> {
-    pub fn iter_next_n(&mut self, n: usize) -> Result<Vec<KeyValueItem>, api::Error> {
+    /// Returns the next item with its value.
+    pub fn iter_next(&mut self) -> Option<Result<KeyValueItem, api::Error>> {
+        self.set_mode(IterationMode::KeyValue);
+        self.next()
+    }
+
+    /// Returns up to `n` items, with what `mode` asks for alongside each key.
+    pub fn iter_next_n(
+        &mut self,
+        n: usize,
+        mode: IterationMode,
+    ) -> Result<Vec<KeyValueItem>, api::Error> {
+        self.set_mode(mode);
         self.by_ref().take(n).collect()
     }
+
+    /// Applies `mode` to the underlying iterator, so values are never copied
+    /// out of the trie unless they are returned
+    fn set_mode(&mut self, mode: IterationMode) {
+        if let Some(iter) = self.0.as_mut() {
+            iter.set_mode(mode);
+        }
+    }
+}
+
+/// Converts the `mode` argument of [`crate::fwd_iter_next_n`] to an [`IterationMode`].
+pub(crate) fn iteration_mode(mode: u8) -> Result<IterationMode, api::Error> {
+    match mode {
+        0 => Ok(IterationMode::KeyValue),
+        1 => Ok(IterationMode::KeysOnly),
+        2 => Ok(IterationMode::ValueDigests),
+        _ => Err(api::Error::IO(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "invalid iteration mode",
+        ))),
+    }
 }
 
 #[derive(Debug, Default)]
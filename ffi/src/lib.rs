@@ -117,6 +117,9 @@ pub unsafe extern "C" fn fwd_get_latest(
 ///
 /// * `revision` - The revision handle returned by [`fwd_get_revision`].
 /// * `key` - The key to look up as a [`BorrowedBytes`]
+/// * `descending` - Whether to iterate in descending order. The iterator then
+///   starts at the highest key less than or equal to `key`, or at the highest
+///   key if `key` is empty.
 ///
 /// # Returns
 ///
@@ -135,8 +138,15 @@ pub unsafe extern "C" fn fwd_get_latest(
 pub unsafe extern "C" fn fwd_iter_on_revision<'view>(
     revision: Option<&'view RevisionHandle>,
     key: BorrowedBytes,
+    descending: bool,
 ) -> IteratorResult<'view> {
-    invoke_with_handle(revision, move |rev| rev.iter_from(Some(key.as_slice())))
+    invoke_with_handle(revision, move |rev| {
+        if descending {
+            rev.iter_rev_from(Some(key.as_slice()).filter(|key| !key.is_empty()))
+        } else {
+            rev.iter_from(Some(key.as_slice()))
+        }
+    })
 }
 
 /// Returns an iterator on the provided proposal optionally starting from a key
@@ -146,6 +156,9 @@ pub unsafe extern "C" fn fwd_iter_on_revision<'view>(
 /// * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
 ///   [`fwd_propose_on_proposal`].
 /// * `key` - The key to look up as a [`BorrowedBytes`]
+/// * `descending` - Whether to iterate in descending order. The iterator then
+///   starts at the highest key less than or equal to `key`, or at the highest
+///   key if `key` is empty.
 ///
 /// # Returns
 ///
@@ -164,8 +177,149 @@ pub unsafe extern "C" fn fwd_iter_on_revision<'view>(
 pub unsafe extern "C" fn fwd_iter_on_proposal<'p>(
     handle: Option<&'p ProposalHandle<'_>>,
     key: BorrowedBytes,
+    descending: bool,
+) -> IteratorResult<'p> {
+    invoke_with_handle(handle, move |p| {
+        if descending {
+            p.iter_rev_from(Some(key.as_slice()).filter(|key| !key.is_empty()))
+        } else {
+            p.iter_from(Some(key.as_slice()))
+        }
+    })
+}
+
+/// Returns an iterator over the keys in [`start`, `end`) in the provided revision.
+///
+/// # Arguments
+///
+/// * `revision` - The revision handle returned by [`fwd_get_revision`].
+/// * `start` - The first key to return, as a [`BorrowedBytes`]. If empty, the
+///   iterator starts at the lowest key.
+/// * `end` - The key to stop before, as a [`BorrowedBytes`]. If empty, the
+///   iterator continues to the highest key.
+///
+/// # Returns
+///
+/// - [`IteratorResult::NullHandlePointer`] if the provided revision handle is null.
+/// - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+/// - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `revision` is a valid pointer to a [`RevisionHandle`]
+/// * ensure that `start` and `end` are valid [`BorrowedBytes`]
+/// * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+///
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_iter_range_on_revision<'view>(
+    revision: Option<&'view RevisionHandle>,
+    start: BorrowedBytes,
+    end: BorrowedBytes,
+) -> IteratorResult<'view> {
+    invoke_with_handle(revision, move |rev| {
+        rev.iter_range(
+            Some(start.as_slice()),
+            Some(end.as_slice()).filter(|end| !end.is_empty()),
+        )
+    })
+}
+
+/// Returns an iterator over the keys in [`start`, `end`) in the provided proposal.
+///
+/// # Arguments
+///
+/// * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
+///   [`fwd_propose_on_proposal`].
+/// * `start` - The first key to return, as a [`BorrowedBytes`]. If empty, the
+///   iterator starts at the lowest key.
+/// * `end` - The key to stop before, as a [`BorrowedBytes`]. If empty, the
+///   iterator continues to the highest key.
+///
+/// # Returns
+///
+/// - [`IteratorResult::NullHandlePointer`] if the provided proposal handle is null.
+/// - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+/// - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `handle` is a valid pointer to a [`ProposalHandle`]
+/// * ensure that `start` and `end` are valid [`BorrowedBytes`]
+/// * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+///
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_iter_range_on_proposal<'p>(
+    handle: Option<&'p ProposalHandle<'_>>,
+    start: BorrowedBytes,
+    end: BorrowedBytes,
 ) -> IteratorResult<'p> {
-    invoke_with_handle(handle, move |p| p.iter_from(Some(key.as_slice())))
+    invoke_with_handle(handle, move |p| {
+        p.iter_range(
+            Some(start.as_slice()),
+            Some(end.as_slice()).filter(|end| !end.is_empty()),
+        )
+    })
+}
+
+/// Returns an iterator over the keys starting with `prefix` in the provided revision.
+///
+/// # Arguments
+///
+/// * `revision` - The revision handle returned by [`fwd_get_revision`].
+/// * `prefix` - The prefix of the keys to return, as a [`BorrowedBytes`]. If
+///   empty, the iterator returns every key.
+///
+/// # Returns
+///
+/// - [`IteratorResult::NullHandlePointer`] if the provided revision handle is null.
+/// - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+/// - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `revision` is a valid pointer to a [`RevisionHandle`]
+/// * ensure that `prefix` is a valid [`BorrowedBytes`]
+/// * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+///
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_iter_prefix_on_revision<'view>(
+    revision: Option<&'view RevisionHandle>,
+    prefix: BorrowedBytes,
+) -> IteratorResult<'view> {
+    invoke_with_handle(revision, move |rev| rev.iter_prefix(prefix.as_slice()))
+}
+
+/// Returns an iterator over the keys starting with `prefix` in the provided proposal.
+///
+/// # Arguments
+///
+/// * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
+///   [`fwd_propose_on_proposal`].
+/// * `prefix` - The prefix of the keys to return, as a [`BorrowedBytes`]. If
+///   empty, the iterator returns every key.
+///
+/// # Returns
+///
+/// - [`IteratorResult::NullHandlePointer`] if the provided proposal handle is null.
+/// - [`IteratorResult::Ok`] if the iterator was created, with the iterator handle.
+/// - [`IteratorResult::Err`] if an error occurred while creating the iterator.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `handle` is a valid pointer to a [`ProposalHandle`]
+/// * ensure that `prefix` is a valid [`BorrowedBytes`]
+/// * call [`fwd_free_iterator`] to free the memory associated with the iterator.
+///
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_iter_prefix_on_proposal<'p>(
+    handle: Option<&'p ProposalHandle<'_>>,
+    prefix: BorrowedBytes,
+) -> IteratorResult<'p> {
+    invoke_with_handle(handle, move |p| p.iter_prefix(prefix.as_slice()))
 }
 
 /// Retrieves the next item from the iterator.
@@ -182,7 +336,8 @@ pub unsafe extern "C" fn fwd_iter_on_proposal<'p>(
 ///   subsequent calls will continue returning [`KeyValueResult::None`]. You may still call this
 ///   safely, but freeing the iterator with [`fwd_free_iterator`] is recommended.
 /// - [`KeyValueResult::Some`] if the next item on iterator was retrieved, with the associated
-///   key value pair.
+///   key value pair. The value is always returned, whatever mode an earlier call to
+///   [`fwd_iter_next_n`] used.
 /// - [`KeyValueResult::Err`] if an I/O error occurred while retrieving the next item. Most
 ///   iterator errors are non-reentrant. Once returned, the iterator should be considered
 ///   invalid and must be freed with [`fwd_free_iterator`].
@@ -196,7 +351,7 @@ pub unsafe extern "C" fn fwd_iter_on_proposal<'p>(
 ///
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn fwd_iter_next(handle: Option<&mut IteratorHandle<'_>>) -> KeyValueResult {
-    invoke_with_handle(handle, Iterator::next)
+    invoke_with_handle(handle, IteratorHandle::iter_next)
 }
 
 /// Retrieves the next batch of items from the iterator.
@@ -205,10 +360,19 @@ pub unsafe extern "C" fn fwd_iter_next(handle: Option<&mut IteratorHandle<'_>>)
 ///
 /// * `handle` - The iterator handle returned by [`fwd_iter_on_revision`] or
 ///   [`fwd_iter_on_proposal`].
+/// * `n` - The maximum number of items to retrieve.
+/// * `mode` - What to return alongside each key. This must be one of the following:
+///
+///   - `0`: The key's value.
+///   - `1`: Nothing; every value is empty, and values are not read.
+///   - `2`: The 32 byte SHA-256 digest of the key's value.
+///
+///   The mode applies to the items of this batch only.
 ///
 /// # Returns
 ///
 /// - [`KeyValueBatchResult::NullHandlePointer`] if the provided iterator handle is null.
+/// - [`KeyValueBatchResult::Err`] if `mode` is not one of the above values.
 /// - [`KeyValueBatchResult::Some`] with up to `n` key/value pairs. If the iterator is
 ///   exhausted, this may be fewer than `n`, including zero items.
 /// - [`KeyValueBatchResult::Err`] if an I/O error occurred while retrieving items. Most
@@ -229,8 +393,9 @@ pub unsafe extern "C" fn fwd_iter_next(handle: Option<&mut IteratorHandle<'_>>)
 pub unsafe extern "C" fn fwd_iter_next_n(
     handle: Option<&mut IteratorHandle<'_>>,
     n: usize,
+    mode: u8,
 ) -> KeyValueBatchResult {
-    invoke_with_handle(handle, |it| it.iter_next_n(n))
+    invoke_with_handle(handle, |it| it.iter_next_n(n, iteration_mode(mode)?))
 }
 
 /// Consumes the [`IteratorHandle`], destroys the iterator, and frees the memory.
@@ -317,6 +482,39 @@ pub unsafe extern "C" fn fwd_get_from_revision(
     invoke_with_handle(revision, move |rev| rev.val(key))
 }
 
+/// Gets the values associated with many keys from the provided revision handle.
+///
+/// The keys are looked up together, reading each node shared by several keys
+/// once and searching independent subtries on the database's thread pool.
+///
+/// # Arguments
+///
+/// * `revision` - The revision handle returned by [`fwd_get_revision`].
+/// * `keys` - The keys to look up, as a [`BorrowedSlice`] of [`BorrowedBytes`].
+///
+/// # Returns
+///
+/// - [`ValuesResult::NullHandlePointer`] if the provided revision handle is null.
+/// - [`ValuesResult::Some`] with one entry per key, in the order of `keys`.
+///   Keys that were not found are [`Maybe::None`].
+/// - [`ValuesResult::Err`] if an error occurred while retrieving the values.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `revision` is a valid pointer to a [`RevisionHandle`].
+/// * ensure that `keys` is valid for [`BorrowedSlice`] and each key is valid
+///   for [`BorrowedBytes`].
+/// * call [`fwd_free_owned_values`] to free the memory associated with the
+///   [`OwnedValues`] returned in the result.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_get_many_from_revision(
+    revision: Option<&RevisionHandle>,
+    keys: BorrowedSlice<BorrowedBytes>,
+) -> ValuesResult {
+    invoke_with_handle(revision, move |rev| rev.val_many(keys.as_slice()))
+}
+
 /// Consumes the [`RevisionHandle`] and frees the memory associated with it.
 ///
 /// # Arguments
@@ -369,6 +567,38 @@ pub unsafe extern "C" fn fwd_get_from_proposal(
     invoke_with_handle(handle, move |handle| handle.val(key))
 }
 
+/// Lists the keys changed by the provided proposal relative to the revision
+/// or proposal it was proposed on top of.
+///
+/// Only the subtries that differ from the parent are read, so the cost depends
+/// on the size of the proposal rather than the size of the database.
+///
+/// # Arguments
+///
+/// * `handle` - The proposal handle returned by [`fwd_propose_on_db`] or
+///   [`fwd_propose_on_proposal`].
+///
+/// # Returns
+///
+/// - [`KeyChangesResult::NullHandlePointer`] if the provided proposal handle is null.
+/// - [`KeyChangesResult::Some`] with one entry per changed key, in ascending key
+///   order. Inserted keys have no old value and deleted keys have no new value.
+/// - [`KeyChangesResult::Err`] if the parent is no longer available or an error
+///   occurred while reading the changes.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `handle` is a valid pointer to a [`ProposalHandle`]
+/// * call [`fwd_free_owned_key_changes`] to free the memory associated with the
+///   [`OwnedKeyChanges`] returned in the result.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_proposal_diff(
+    handle: Option<&ProposalHandle<'_>>,
+) -> KeyChangesResult {
+    invoke_with_handle(handle, ProposalHandle::diff)
+}
+
 /// Gets a value assoicated with the given root hash and key.
 ///
 /// The hash may refer to a historical revision or an existing proposal.
@@ -436,6 +666,129 @@ pub unsafe extern "C" fn fwd_batch(
     invoke_with_handle(db, move |db| db.create_batch(values))
 }
 
+/// Computes the root hash that a batch of operations would produce, without
+/// creating a proposal.
+///
+/// # Arguments
+///
+/// * `db` - The database handle returned by [`fwd_open_db`]
+/// * `parent` - The root hash of the revision or proposal to apply the batch
+///   to, as a [`BorrowedBytes`]. If it is null, the batch is applied to the
+///   latest revision.
+/// * `values` - A [`BorrowedKeyValuePairs`] containing the key-value pairs to put.
+///
+/// # Returns
+///
+/// - [`HashResult::NullHandlePointer`] if the provided database handle is null.
+/// - [`HashResult::None`] if the batch would result in an empty database.
+/// - [`HashResult::Some`] containing the root hash the batch would produce.
+/// - [`HashResult::Err`] if an error occurred, for example if the parent was
+///   not found.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+/// * ensure that `parent` is valid for [`BorrowedBytes`]
+/// * ensure that `values` is valid for [`BorrowedKeyValuePairs`]
+/// * call [`fwd_free_owned_bytes`] to free the memory associated with the
+///   returned error ([`HashKey`] does not need to be freed as it is returned by
+///   value).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_compute_root(
+    db: Option<&DatabaseHandle>,
+    parent: BorrowedBytes,
+    values: BorrowedKeyValuePairs<'_>,
+) -> HashResult {
+    invoke_with_handle(db, move |db| {
+        let parent = if parent.is_null() {
+            None
+        } else {
+            Some(parent.as_ref().try_into()?)
+        };
+        db.compute_root(parent, values)
+    })
+}
+
+/// Removes the keys in a half-open range from the database and commits the
+/// result.
+///
+/// To remove a range as part of a batch, pass a [`KeyValuePair`] of kind
+/// [`BatchOpKind::DeleteRange`] to [`fwd_batch`] instead.
+///
+/// # Arguments
+///
+/// * `db` - The database handle returned by [`fwd_open_db`]
+/// * `start` - The first key to remove, as a [`BorrowedBytes`].
+/// * `end` - The first key after the range, as a [`BorrowedBytes`]. If it is
+///   null, every key from `start` onwards is removed.
+///
+/// # Returns
+///
+/// - [`DeleteRangeResult::NullHandlePointer`] if the provided database handle is null.
+/// - [`DeleteRangeResult::Ok`] if the commit was successful, containing the new
+///   root hash and the number of keys removed.
+/// - [`DeleteRangeResult::Err`] if an error occurred while removing the keys.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+/// * ensure that `start` and `end` are valid for [`BorrowedBytes`]
+/// * call [`fwd_free_owned_bytes`] to free the memory associated with the
+///   returned error.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_delete_range(
+    db: Option<&DatabaseHandle>,
+    start: BorrowedBytes,
+    end: BorrowedBytes,
+) -> DeleteRangeResult {
+    invoke_with_handle(db, move |db| {
+        let end = (!end.is_null()).then(|| end.as_slice());
+        db.delete_range(start.as_slice(), end)
+    })
+}
+
+/// Proposes removing the keys in a half-open range from the database.
+///
+/// Unlike [`fwd_delete_range`], the result is not committed, and unlike a
+/// range delete in [`fwd_propose_on_db`], the number of keys removed is
+/// returned.
+///
+/// # Arguments
+///
+/// * `db` - The database handle returned by [`fwd_open_db`]
+/// * `start` - The first key to remove, as a [`BorrowedBytes`].
+/// * `end` - The first key after the range, as a [`BorrowedBytes`]. If it is
+///   null, every key from `start` onwards is removed.
+///
+/// # Returns
+///
+/// - [`ProposeDeleteRangeResult::NullHandlePointer`] if the provided database handle is null.
+/// - [`ProposeDeleteRangeResult::Ok`] if the proposal was created, with the
+///   proposal handle, calculated root hash and number of keys removed.
+/// - [`ProposeDeleteRangeResult::Err`] if an error occurred while creating the proposal.
+///
+/// # Safety
+///
+/// The caller must:
+/// * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+/// * ensure that `start` and `end` are valid for [`BorrowedBytes`]
+/// * call [`fwd_commit_proposal`] or [`fwd_free_proposal`] to free the memory
+///   associated with the proposal. And, the caller must ensure this is done
+///   before calling [`fwd_close_db`] to avoid memory leaks or undefined behavior.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_propose_delete_range<'db>(
+    db: Option<&'db DatabaseHandle>,
+    start: BorrowedBytes,
+    end: BorrowedBytes,
+) -> ProposeDeleteRangeResult<'db> {
+    invoke_with_handle(db, move |db| {
+        let end = (!end.is_null()).then(|| end.as_slice());
+        db.propose_delete_range(start.as_slice(), end)
+    })
+}
+
 /// Proposes a batch of operations to the database.
 ///
 /// # Arguments
@@ -587,6 +940,68 @@ pub unsafe extern "C" fn fwd_root_hash(db: Option<&DatabaseHandle>) -> HashResul
     invoke_with_handle(db, DatabaseHandle::current_root_hash)
 }
 
+/// Wait until every committed revision has been written to disk.
+///
+/// Commits return as soon as the new revision is available in memory, and
+/// revisions are written to disk in the background, in commit order. Once this
+/// returns successfully, every revision committed before the call is on disk.
+///
+/// # Argument
+///
+/// * `db` - The database handle returned by [`fwd_open_db`]
+///
+/// # Returns
+///
+/// - [`VoidResult::NullHandlePointer`] if the provided database handle is null.
+/// - [`VoidResult::Ok`] if every committed revision has been written.
+/// - [`VoidResult::Err`] if a revision could not be written. After such a
+///   failure no later revision is written, and subsequent flushes and commits
+///   fail until the database is reopened.
+///
+/// # Safety
+///
+/// * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+/// * call [`fwd_free_owned_bytes`] to free the memory associated with the
+///   returned error (if any).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_flush_db(db: Option<&DatabaseHandle>) -> VoidResult {
+    invoke_with_handle(db, DatabaseHandle::flush)
+}
+
+/// Make an earlier revision the latest one, discarding every revision
+/// committed after it.
+///
+/// The areas written by the discarded revisions are returned to the free
+/// lists. Only revisions that are still retained can be rolled back to.
+///
+/// # Arguments
+///
+/// * `db` - The database handle returned by [`fwd_open_db`]
+/// * `root` - The root hash of the revision as a [`BorrowedBytes`].
+///
+/// # Returns
+///
+/// - [`VoidResult::NullHandlePointer`] if the provided database handle is null.
+/// - [`VoidResult::Ok`] if the revision is now the latest one.
+/// - [`VoidResult::Err`] if the revision is not retained, if a discarded
+///   revision or any proposal is still in use, or if an i/o error occurred.
+///
+/// # Safety
+///
+/// * ensure that `db` is a valid pointer to a [`DatabaseHandle`]
+/// * ensure that `root` is valid for [`BorrowedBytes`].
+/// * call [`fwd_free_owned_bytes`] to free the memory associated with the
+///   returned error (if any).
+///
+/// [`BorrowedBytes`]: crate::value::BorrowedBytes
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_rollback_to(
+    db: Option<&DatabaseHandle>,
+    root: BorrowedBytes,
+) -> VoidResult {
+    invoke_with_handle(db, move |db| db.rollback_to(root.as_ref().try_into()?))
+}
+
 /// Start metrics recorder for this process.
 ///
 /// # Returns
@@ -753,6 +1168,50 @@ pub unsafe extern "C" fn fwd_free_owned_key_value_batch(batch: OwnedKeyValueBatc
     invoke(move || drop(batch))
 }
 
+/// Consumes the [`OwnedValues`] and frees the memory associated with it.
+///
+/// # Arguments
+///
+/// * `values` - The [`OwnedValues`] struct to free, previously returned from
+///   [`fwd_get_many_from_revision`].
+///
+/// # Returns
+///
+/// - [`VoidResult::Ok`] if the memory was successfully freed.
+/// - [`VoidResult::Err`] if the process panics while freeing the memory.
+///
+/// # Safety
+///
+/// The caller must ensure that the `values` struct is valid and that the memory
+/// it points to is uniquely owned by this object. However, if `values.ptr` is
+/// null, this function does nothing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_free_owned_values(values: OwnedValues) -> VoidResult {
+    invoke(move || drop(values))
+}
+
+/// Consumes the [`OwnedKeyChanges`] and frees the memory associated with it.
+///
+/// # Arguments
+///
+/// * `changes` - The [`OwnedKeyChanges`] struct to free, previously returned from
+///   [`fwd_proposal_diff`].
+///
+/// # Returns
+///
+/// - [`VoidResult::Ok`] if the memory was successfully freed.
+/// - [`VoidResult::Err`] if the process panics while freeing the memory.
+///
+/// # Safety
+///
+/// The caller must ensure that the `changes` struct is valid and that the memory
+/// it points to is uniquely owned by this object. However, if `changes.ptr` is
+/// null, this function does nothing.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fwd_free_owned_key_changes(changes: OwnedKeyChanges) -> VoidResult {
+    invoke(move || drop(changes))
+}
+
 /// Consumes the [`OwnedKeyValuePair`] and frees the memory associated with it.
 ///
 /// # Arguments
@@ -67,7 +67,7 @@ pub fn setup_metrics_with_exporter(metrics_port: u16) -> Result<(), Box<dyn Erro
     })
     .bind((Ipv4Addr::LOCALHOST, metrics_port))
     .bind((Ipv6Addr::LOCALHOST, metrics_port))
-    .with_global_timeout(Duration::from_secs(60 * 60))
+    .with_global_timeout(Duration::from_hours(1))
     .with_max_concurrent_connections(2)
     .spawn()?;
     Ok(())
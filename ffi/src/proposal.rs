@@ -31,6 +31,13 @@ impl<'db> DbView for ProposalHandle<'db> {
         self.proposal.val(key)
     }
 
+    fn val_many<K: api::KeyType>(
+        &self,
+        keys: &[K],
+    ) -> Result<Vec<Option<firewood::merkle::Value>>, api::Error> {
+        self.proposal.val_many(keys)
+    }
+
     fn single_key_proof<K: api::KeyType>(&self, key: K) -> Result<api::FrozenProof, api::Error> {
         self.proposal.single_key_proof(key)
     }
@@ -50,6 +57,39 @@ impl<'db> DbView for ProposalHandle<'db> {
     ) -> Result<Self::Iter<'_>, api::Error> {
         self.proposal.iter_option(first_key)
     }
+
+    fn iter_rev_option<K: api::KeyType>(
+        &self,
+        last_key: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        self.proposal.iter_rev_option(last_key)
+    }
+
+    fn iter_range<K: api::KeyType>(
+        &self,
+        start: Option<K>,
+        end: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        self.proposal.iter_range(start, end)
+    }
+}
+
+impl<'db> ProposalHandle<'db> {
+    /// Wraps a proposal created on the database of `handle`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the root hash of the proposal cannot be read.
+    pub(crate) fn new(
+        handle: &'db crate::DatabaseHandle,
+        proposal: firewood::db::Proposal<'db>,
+    ) -> Result<Self, api::Error> {
+        Ok(Self {
+            hash_key: proposal.root_hash()?,
+            proposal,
+            handle,
+        })
+    }
 }
 
 impl ProposalHandle<'_> {
@@ -99,6 +139,16 @@ impl ProposalHandle<'_> {
         Ok(hash_key)
     }
 
+    /// Lists the keys this proposal changes relative to its parent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the parent revision is no longer available or if a
+    /// node could not be read.
+    pub fn diff(&self) -> Result<Vec<api::KeyChange>, api::Error> {
+        self.proposal.diff()?.collect()
+    }
+
     /// Creates an iterator on the proposal starting from the given key.
     #[must_use]
     #[allow(clippy::missing_panics_doc)]
@@ -108,6 +158,33 @@ impl ProposalHandle<'_> {
             .expect("infallible; see issue #1329");
         CreateIteratorResult((Box::new(it) as BoxKeyValueIter<'_>).into())
     }
+
+    /// Creates an iterator on the proposal in descending order, starting from
+    /// the given key or from the end.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn iter_rev_from(&self, last_key: Option<&[u8]>) -> CreateIteratorResult<'_> {
+        let it = self
+            .iter_rev_option(last_key)
+            .expect("infallible; see issue #1329");
+        CreateIteratorResult((Box::new(it) as BoxKeyValueIter<'_>).into())
+    }
+
+    /// Creates an iterator on the proposal over the keys in [`start`, `end`).
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn iter_range(&self, start: Option<&[u8]>, end: Option<&[u8]>) -> CreateIteratorResult<'_> {
+        let it = DbView::iter_range(self, start, end).expect("infallible; see issue #1329");
+        CreateIteratorResult((Box::new(it) as BoxKeyValueIter<'_>).into())
+    }
+
+    /// Creates an iterator on the proposal over the keys starting with `prefix`.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn iter_prefix(&self, prefix: &[u8]) -> CreateIteratorResult<'_> {
+        let it = DbView::iter_prefix(self, prefix).expect("infallible; see issue #1329");
+        CreateIteratorResult((Box::new(it) as BoxKeyValueIter<'_>).into())
+    }
 }
 #[derive(Debug)]
 pub struct CreateProposalResult<'db> {
@@ -167,14 +244,8 @@ pub trait CView<'db> {
         counter!("firewood.ffi.propose_ms").increment(propose_time.as_millis());
         counter!("firewood.ffi.propose").increment(1);
 
-        let hash_key = proposal.root_hash()?;
-
         Ok(CreateProposalResult {
-            handle: ProposalHandle {
-                hash_key,
-                proposal,
-                handle,
-            },
+            handle: ProposalHandle::new(handle, proposal)?,
             start_time,
         })
     }
@@ -4,16 +4,20 @@
 use crate::CreateIteratorResult;
 use firewood::v2::api;
 use firewood::v2::api::{ArcDynDbView, BoxKeyValueIter, DbView, HashKey};
+use rayon::ThreadPool;
+use std::sync::Arc;
 
 #[derive(Debug)]
 pub struct RevisionHandle {
     view: ArcDynDbView,
+    /// The database's thread pool, used to look up many keys at once.
+    threadpool: Arc<ThreadPool>,
 }
 
 impl RevisionHandle {
     /// Creates a new revision handle for the provided database view.
-    pub(crate) fn new(view: ArcDynDbView) -> RevisionHandle {
-        RevisionHandle { view }
+    pub(crate) fn new(view: ArcDynDbView, threadpool: Arc<ThreadPool>) -> RevisionHandle {
+        RevisionHandle { view, threadpool }
     }
 
     /// Creates an iterator on the revision starting from the given key.
@@ -26,6 +30,40 @@ impl RevisionHandle {
             .expect("infallible; see issue #1329");
         CreateIteratorResult(it.into())
     }
+
+    /// Creates an iterator on the revision in descending order, starting from
+    /// the given key or from the end.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn iter_rev_from(&self, last_key: Option<&[u8]>) -> CreateIteratorResult<'_> {
+        let it = self
+            .view
+            .iter_rev_option(last_key)
+            .expect("infallible; see issue #1329");
+        CreateIteratorResult(it.into())
+    }
+
+    /// Creates an iterator on the revision over the keys in [`start`, `end`).
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn iter_range(&self, start: Option<&[u8]>, end: Option<&[u8]>) -> CreateIteratorResult<'_> {
+        let it = self
+            .view
+            .iter_range(start, end)
+            .expect("infallible; see issue #1329");
+        CreateIteratorResult(it.into())
+    }
+
+    /// Creates an iterator on the revision over the keys starting with `prefix`.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn iter_prefix(&self, prefix: &[u8]) -> CreateIteratorResult<'_> {
+        let it = self
+            .view
+            .iter_prefix(prefix)
+            .expect("infallible; see issue #1329");
+        CreateIteratorResult(it.into())
+    }
 }
 
 impl DbView for RevisionHandle {
@@ -42,6 +80,14 @@ impl DbView for RevisionHandle {
         self.view.val(key.as_ref())
     }
 
+    fn val_many<K: api::KeyType>(
+        &self,
+        keys: &[K],
+    ) -> Result<Vec<Option<firewood::merkle::Value>>, api::Error> {
+        let keys: Vec<&[u8]> = keys.iter().map(AsRef::as_ref).collect();
+        self.threadpool.install(|| self.view.val_many(&keys))
+    }
+
     fn single_key_proof<K: api::KeyType>(&self, key: K) -> Result<api::FrozenProof, api::Error> {
         self.view.single_key_proof(key.as_ref())
     }
@@ -65,6 +111,25 @@ impl DbView for RevisionHandle {
     ) -> Result<Self::Iter<'_>, api::Error> {
         self.view.iter_option(first_key.as_ref().map(AsRef::as_ref))
     }
+
+    fn iter_rev_option<K: api::KeyType>(
+        &self,
+        last_key: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        self.view
+            .iter_rev_option(last_key.as_ref().map(AsRef::as_ref))
+    }
+
+    fn iter_range<K: api::KeyType>(
+        &self,
+        start: Option<K>,
+        end: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        self.view.iter_range(
+            start.as_ref().map(AsRef::as_ref),
+            end.as_ref().map(AsRef::as_ref),
+        )
+    }
 }
 
 #[derive(Debug)]
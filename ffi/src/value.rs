@@ -2,6 +2,7 @@
 // See the file LICENSE.md for licensing terms.
 
 mod borrowed;
+mod change;
 mod display_hex;
 mod hash_key;
 mod kvp;
@@ -9,17 +10,22 @@ mod owned;
 mod results;
 
 pub use self::borrowed::{BorrowedBytes, BorrowedKeyValuePairs, BorrowedSlice};
+pub use self::change::{OwnedKeyChange, OwnedKeyChanges};
 use self::display_hex::DisplayHex;
 pub use self::hash_key::HashKey;
-pub use self::kvp::{KeyValuePair, OwnedKeyValueBatch, OwnedKeyValuePair};
+pub use self::kvp::{BatchOpKind, KeyValuePair, OwnedKeyValueBatch, OwnedKeyValuePair};
 pub use self::owned::{OwnedBytes, OwnedSlice};
 pub(crate) use self::results::{CResult, NullHandleResult};
 pub use self::results::{
-    ChangeProofResult, HandleResult, HashResult, IteratorResult, KeyValueBatchResult,
-    KeyValueResult, NextKeyRangeResult, ProposalResult, RangeProofResult, RevisionResult,
-    ValueResult, VoidResult,
+    ChangeProofResult, DeleteRangeResult, HandleResult, HashResult, IteratorResult,
+    KeyChangesResult, KeyValueBatchResult, KeyValueResult, NextKeyRangeResult, ProposalResult,
+    ProposeDeleteRangeResult, RangeProofResult, RevisionResult, ValueResult, ValuesResult,
+    VoidResult,
 };
 
+/// A rust-owned list of optional values, one for each key looked up.
+pub type OwnedValues = OwnedSlice<Maybe<OwnedBytes>>;
+
 /// Maybe is a C-compatible optional type using a tagged union pattern.
 ///
 /// FFI methods and types can use this to represent optional values where `Optional<T>`
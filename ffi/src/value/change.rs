@@ -0,0 +1,39 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use firewood::v2::api::KeyChange;
+
+use crate::{Maybe, OwnedBytes, OwnedSlice};
+
+/// A rust-owned list of key changes, in ascending key order.
+pub type OwnedKeyChanges = OwnedSlice<OwnedKeyChange>;
+
+/// A change to the value of one key, returned to ffi callers.
+///
+/// A key that was inserted has no old value, and a key that was deleted has no
+/// new value.
+///
+/// C callers must free a list of these using [`crate::fwd_free_owned_key_changes`],
+/// not the C standard library's `free` function.
+#[repr(C)]
+#[derive(Debug)]
+pub struct OwnedKeyChange {
+    pub key: OwnedBytes,
+    pub old_value: Maybe<OwnedBytes>,
+    pub new_value: Maybe<OwnedBytes>,
+}
+
+impl From<KeyChange> for OwnedKeyChange {
+    fn from(change: KeyChange) -> Self {
+        let (key, old_value, new_value) = match change {
+            KeyChange::Inserted { key, value } => (key, None, Some(value)),
+            KeyChange::Updated { key, old, new } => (key, Some(old), Some(new)),
+            KeyChange::Deleted { key, old } => (key, Some(old), None),
+        };
+        OwnedKeyChange {
+            key: key.into(),
+            old_value: Maybe::from(old_value).map(Into::into),
+            new_value: Maybe::from(new_value).map(Into::into),
+        }
+    }
+}
@@ -10,12 +10,26 @@ use firewood::v2::api;
 /// A type alias for a rust-owned byte slice.
 pub type OwnedKeyValueBatch = OwnedSlice<OwnedKeyValuePair>;
 
+/// How a [`KeyValuePair`] is applied as part of a batch.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchOpKind {
+    /// Puts `value` at `key`. If `value` is null, every key starting with
+    /// `key` is deleted instead.
+    #[default]
+    PutOrDeletePrefix,
+    /// Deletes the keys in the half-open range [`key`, `value`). If `value`
+    /// is null, every key from `key` onwards is deleted.
+    DeleteRange,
+}
+
 /// A `KeyValue` represents a key-value pair, passed to the FFI.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct KeyValuePair<'a> {
     pub key: BorrowedBytes<'a>,
     pub value: BorrowedBytes<'a>,
+    pub kind: BatchOpKind,
 }
 
 impl<'a> KeyValuePair<'a> {
@@ -23,6 +37,7 @@ impl<'a> KeyValuePair<'a> {
         Self {
             key: BorrowedBytes::from_slice(key.as_ref()),
             value: BorrowedBytes::from_slice(value.as_ref()),
+            kind: BatchOpKind::PutOrDeletePrefix,
         }
     }
 }
@@ -46,13 +61,18 @@ impl<'a> api::KeyValuePair for KeyValuePair<'a> {
     fn into_batch(self) -> api::BatchOp<Self::Key, Self::Value> {
         // Check if the value pointer is null (nil slice in Go)
         // vs non-null but empty (empty slice []byte{} in Go)
-        if self.value.is_null() {
-            api::BatchOp::DeleteRange { prefix: self.key }
-        } else {
-            api::BatchOp::Put {
+        match self.kind {
+            BatchOpKind::PutOrDeletePrefix if self.value.is_null() => {
+                api::BatchOp::DeleteRange { prefix: self.key }
+            }
+            BatchOpKind::PutOrDeletePrefix => api::BatchOp::Put {
                 key: self.key,
                 value: self.value,
-            }
+            },
+            BatchOpKind::DeleteRange => api::BatchOp::DeleteKeyRange {
+                start: self.key,
+                end: (!self.value.is_null()).then_some(self.value),
+            },
         }
     }
 }
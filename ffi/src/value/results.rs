@@ -7,9 +7,9 @@ use std::fmt;
 
 use crate::revision::{GetRevisionResult, RevisionHandle};
 use crate::{
-    ChangeProofContext, CreateIteratorResult, CreateProposalResult, HashKey, IteratorHandle,
-    NextKeyRange, OwnedBytes, OwnedKeyValueBatch, OwnedKeyValuePair, ProposalHandle,
-    RangeProofContext,
+    ChangeProofContext, CreateIteratorResult, CreateProposalResult, HashKey, IteratorHandle, Maybe,
+    NextKeyRange, OwnedBytes, OwnedKeyChanges, OwnedKeyValueBatch, OwnedKeyValuePair, OwnedValues,
+    ProposalHandle, RangeProofContext,
 };
 
 /// The result type returned from an FFI function that returns no value but may
@@ -181,6 +181,90 @@ impl<E: fmt::Display> From<Result<Option<api::HashKey>, E>> for HashResult {
     }
 }
 
+/// A result type returned from FFI functions that remove a range of keys and
+/// commit the result.
+#[derive(Debug)]
+#[repr(C)]
+pub enum DeleteRangeResult {
+    /// The caller provided a null pointer to a database handle.
+    NullHandlePointer,
+    /// The keys were removed and the result was committed.
+    Ok {
+        /// The new root hash of the database. Zeroed if the database is now
+        /// empty.
+        root_hash: HashKey,
+        /// The number of keys removed.
+        removed: u64,
+    },
+    /// An error occurred and the message is returned as an [`OwnedBytes`]. If
+    /// value is guaranteed to contain only valid UTF-8.
+    ///
+    /// The caller must call [`fwd_free_owned_bytes`] to free the memory
+    /// associated with this error.
+    ///
+    /// [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+    Err(OwnedBytes),
+}
+
+impl<E: fmt::Display> From<Result<(Option<api::HashKey>, usize), E>> for DeleteRangeResult {
+    fn from(value: Result<(Option<api::HashKey>, usize), E>) -> Self {
+        match value {
+            Ok((root_hash, removed)) => DeleteRangeResult::Ok {
+                root_hash: root_hash.map(HashKey::from).unwrap_or_default(),
+                removed: removed as u64,
+            },
+            Err(err) => DeleteRangeResult::Err(err.to_string().into_bytes().into()),
+        }
+    }
+}
+
+/// A result type returned from FFI functions that propose removing a range of
+/// keys.
+#[derive(Debug)]
+#[repr(C)]
+pub enum ProposeDeleteRangeResult<'db> {
+    /// The caller provided a null pointer to a database handle.
+    NullHandlePointer,
+    /// Building the proposal was successful and the proposal handle, root
+    /// hash and number of keys removed are returned.
+    Ok {
+        /// An opaque pointer to the [`ProposalHandle`] that can be use to create
+        /// an additional proposal or later commit. The caller must ensure that this
+        /// pointer is freed with [`fwd_free_proposal`] if it is not committed.
+        ///
+        /// [`fwd_free_proposal`]: crate::fwd_free_proposal
+        handle: Box<ProposalHandle<'db>>,
+        /// The root hash of the proposal. Zeroed if the proposal resulted in an
+        /// empty database.
+        root_hash: HashKey,
+        /// The number of keys the proposal removes.
+        removed: u64,
+    },
+    /// An error occurred and the message is returned as an [`OwnedBytes`]. If
+    /// value is guaranteed to contain only valid UTF-8.
+    ///
+    /// The caller must call [`fwd_free_owned_bytes`] to free the memory
+    /// associated with this error.
+    ///
+    /// [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+    Err(OwnedBytes),
+}
+
+impl<'db, E: fmt::Display> From<Result<(CreateProposalResult<'db>, usize), E>>
+    for ProposeDeleteRangeResult<'db>
+{
+    fn from(value: Result<(CreateProposalResult<'db>, usize), E>) -> Self {
+        match value {
+            Ok((CreateProposalResult { handle, .. }, removed)) => ProposeDeleteRangeResult::Ok {
+                root_hash: handle.hash_key().unwrap_or_default(),
+                handle: Box::new(handle),
+                removed: removed as u64,
+            },
+            Err(err) => ProposeDeleteRangeResult::Err(err.to_string().into_bytes().into()),
+        }
+    }
+}
+
 /// A result type returned from FFI functions that create or parse range proofs.
 ///
 /// The caller must ensure that [`fwd_free_range_proof`] is called to
@@ -398,6 +482,81 @@ impl From<Result<Vec<(merkle::Key, merkle::Value)>, api::Error>> for KeyValueBat
     }
 }
 
+/// A result type returned from FFI functions that retrieve many values.
+#[derive(Debug)]
+#[repr(C)]
+pub enum ValuesResult {
+    /// The caller provided a null pointer to a revision handle.
+    NullHandlePointer,
+    /// The value of each key, in the order the keys were given. Keys that were
+    /// not found are [`Maybe::None`].
+    ///
+    /// The caller must call [`fwd_free_owned_values`] to free the memory
+    /// associated with these values.
+    ///
+    /// [`fwd_free_owned_values`]: crate::fwd_free_owned_values
+    Some(OwnedValues),
+    /// An error occurred and the message is returned as an [`OwnedBytes`]. If
+    /// value is guaranteed to contain only valid UTF-8.
+    ///
+    /// The caller must call [`fwd_free_owned_bytes`] to free the memory
+    /// associated with this error.
+    ///
+    /// [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+    Err(OwnedBytes),
+}
+
+impl From<Result<Vec<Option<merkle::Value>>, api::Error>> for ValuesResult {
+    fn from(value: Result<Vec<Option<merkle::Value>>, api::Error>) -> Self {
+        match value {
+            Ok(values) => {
+                let values: Vec<Maybe<OwnedBytes>> = values
+                    .into_iter()
+                    .map(|value| Maybe::from(value).map(Into::into))
+                    .collect();
+                ValuesResult::Some(values.into())
+            }
+            Err(err) => ValuesResult::Err(err.to_string().into_bytes().into()),
+        }
+    }
+}
+
+/// A result type returned from FFI functions that list the changes made by a
+/// proposal.
+#[derive(Debug)]
+#[repr(C)]
+pub enum KeyChangesResult {
+    /// The caller provided a null pointer to a proposal handle.
+    NullHandlePointer,
+    /// The keys that changed, in ascending key order.
+    ///
+    /// The caller must call [`fwd_free_owned_key_changes`] to free the memory
+    /// associated with these changes.
+    ///
+    /// [`fwd_free_owned_key_changes`]: crate::fwd_free_owned_key_changes
+    Some(OwnedKeyChanges),
+    /// An error occurred and the message is returned as an [`OwnedBytes`]. If
+    /// value is guaranteed to contain only valid UTF-8.
+    ///
+    /// The caller must call [`fwd_free_owned_bytes`] to free the memory
+    /// associated with this error.
+    ///
+    /// [`fwd_free_owned_bytes`]: crate::fwd_free_owned_bytes
+    Err(OwnedBytes),
+}
+
+impl From<Result<Vec<api::KeyChange>, api::Error>> for KeyChangesResult {
+    fn from(value: Result<Vec<api::KeyChange>, api::Error>) -> Self {
+        match value {
+            Ok(changes) => {
+                let changes: Vec<_> = changes.into_iter().map(Into::into).collect();
+                KeyChangesResult::Some(changes.into())
+            }
+            Err(err) => KeyChangesResult::Err(err.to_string().into_bytes().into()),
+        }
+    }
+}
+
 impl<'db> From<CreateIteratorResult<'db>> for IteratorResult<'db> {
     fn from(value: CreateIteratorResult<'db>) -> Self {
         IteratorResult::Ok {
@@ -546,6 +705,10 @@ impl_null_handle_result!(
     RevisionResult,
     KeyValueBatchResult,
     KeyValueResult,
+    ValuesResult,
+    KeyChangesResult,
+    DeleteRangeResult,
+    ProposeDeleteRangeResult<'_>,
 );
 
 impl_cresult!(
@@ -561,6 +724,10 @@ impl_cresult!(
     RevisionResult,
     KeyValueBatchResult,
     KeyValueResult,
+    ValuesResult,
+    KeyChangesResult,
+    DeleteRangeResult,
+    ProposeDeleteRangeResult<'_>,
 );
 
 enum Panic {
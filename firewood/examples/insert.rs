@@ -7,7 +7,6 @@
 use clap::Parser;
 use std::collections::HashMap;
 use std::error::Error;
-use std::num::NonZeroUsize;
 use std::ops::RangeInclusive;
 use std::time::Instant;
 
@@ -30,8 +29,10 @@ struct Args {
     read_verify_percent: u16,
     #[arg(short, long)]
     seed: Option<u64>,
-    #[arg(short, long, default_value_t = NonZeroUsize::new(20480).expect("is non-zero"))]
-    cache_size: NonZeroUsize,
+    #[arg(long, default_value_t = 16 << 20)]
+    branch_cache_bytes: usize,
+    #[arg(long, default_value_t = 4 << 20)]
+    leaf_cache_bytes: usize,
     #[arg(short, long, default_value_t = true)]
     truncate: bool,
     #[arg(short, long, default_value_t = 128)]
@@ -54,7 +55,8 @@ fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
 
     let mgrcfg = RevisionManagerConfig::builder()
-        .node_cache_size(args.cache_size)
+        .branch_cache_bytes(args.branch_cache_bytes)
+        .leaf_cache_bytes(args.leaf_cache_bytes)
         .max_revisions(args.revisions)
         .build();
     let cfg = DbConfig::builder()
@@ -0,0 +1,296 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! Incremental backups: the areas written between two revisions.
+//!
+//! Unlike [`Db::backup_to`](crate::db::Db::backup_to), which packs a revision
+//! into a new file, an incremental backup keeps every node at the address it
+//! has in the database. A chain starts with a backup that has no base and
+//! holds every node of its revision; each later backup holds only the areas
+//! that are not in the revision before it, plus the new root. [`restore`]
+//! writes the chain into a new database file in order, verifying the root
+//! hash and every node of each intermediate revision.
+//!
+//! # Format
+//!
+//! - 8 bytes: the magic value `b"fwdincr\0"`.
+//! - 1 byte: the version of the format, currently `0`.
+//! - The root hash of the base revision, then the root hash of the backed up
+//!   revision, each as a single byte `0` for the empty trie or none, or `1`
+//!   followed by the 32-byte hash.
+//! - The address of the root node, as a single byte `0` for the empty trie,
+//!   or `1` followed by the address.
+//! - Each area as a single byte `1`, followed by its address and then its
+//!   contents as a length-prefixed sequence of bytes.
+//! - A single byte `0` marking the end of the areas.
+//! - 32 bytes: the SHA-256 digest of everything before it.
+//!
+//! Addresses and lengths are unsigned LEB128 variable-length integers.
+
+use std::io::{self, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::sync::Arc;
+
+use firewood_storage::{
+    CheckOpt, CheckerError, Committed, CopyProgress, FileBacked, FileIoError, HashedNodeReader,
+    LinearAddress, NodeStore, StorageConfig,
+};
+use integer_encoding::{VarIntReader, VarIntWriter};
+use sha2::{Digest, Sha256};
+
+use crate::manager::{CacheReadStrategy, NodeCacheBudget};
+use crate::snapshot::{HashingReader, HashingWriter, read_byte, read_bytes};
+use crate::v2::api::{self, HashKey};
+
+const MAGIC: &[u8; 8] = b"fwdincr\0";
+const VERSION: u8 = 0;
+
+const END: u8 = 0;
+const AREA: u8 = 1;
+
+/// An error reading or restoring an incremental backup.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum BackupError {
+    /// The data does not start with the magic bytes of an incremental backup
+    #[error("not a firewood incremental backup")]
+    InvalidMagic,
+
+    /// The backup was written by an unsupported version of the format
+    #[error("unsupported backup version {found}")]
+    UnsupportedVersion {
+        /// The version found in the backup
+        found: u8,
+    },
+
+    /// An item has an unknown tag
+    #[error("invalid backup tag {found}")]
+    InvalidTag {
+        /// The tag found in the backup
+        found: u8,
+    },
+
+    /// The trailing checksum does not match the contents
+    #[error("backup checksum mismatch")]
+    ChecksumMismatch,
+
+    /// A backup is not based on the revision restored before it
+    #[error("backup is based on {expected:?}, but the restored root hash is {found:?}")]
+    BaseMismatch {
+        /// The root hash of the base revision of the backup
+        expected: Option<HashKey>,
+        /// The root hash restored so far
+        found: Option<HashKey>,
+    },
+
+    /// A restored revision does not have the root hash it was backed up with
+    #[error("restored root hash {found:?}, expected {expected:?}")]
+    RootMismatch {
+        /// The root hash the revision was backed up with
+        expected: Option<HashKey>,
+        /// The root hash of the restored revision
+        found: Option<HashKey>,
+    },
+
+    /// A restored revision has nodes that are missing or do not match their
+    /// hashes
+    #[error("restored revision is inconsistent: {0:?}")]
+    Inconsistent(Vec<CheckerError>),
+}
+
+/// Writes the areas of `revision` that are not in `base` to `writer`.
+///
+/// Both revisions must have been written to storage.
+pub(crate) fn write<W: Write>(
+    writer: W,
+    base: Option<&NodeStore<Committed, FileBacked>>,
+    revision: &NodeStore<Committed, FileBacked>,
+) -> Result<CopyProgress, api::Error> {
+    let mut writer = HashingWriter {
+        inner: writer,
+        hasher: Sha256::new(),
+    };
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    write_hash(&mut writer, base.and_then(HashedNodeReader::root_hash))?;
+    write_hash(&mut writer, revision.root_hash())?;
+    match revision.root_address() {
+        Some(addr) => {
+            writer.write_all(&[1])?;
+            writer.write_varint(addr.get())?;
+        }
+        None => writer.write_all(&[0])?,
+    }
+
+    let mut written = CopyProgress::default();
+    revision.new_areas(base, |addr, bytes| {
+        let write_area = |writer: &mut HashingWriter<W>| -> io::Result<()> {
+            writer.write_all(&[AREA])?;
+            writer.write_varint(addr.get())?;
+            writer.write_varint(bytes.len())?;
+            writer.write_all(bytes)
+        };
+        write_area(&mut writer)
+            .map_err(|e| FileIoError::from_generic_no_file(e, "writing incremental backup"))?;
+        written.nodes = written.nodes.saturating_add(1);
+        written.bytes = written.bytes.saturating_add(bytes.len() as u64);
+        Ok(())
+    })?;
+
+    writer.write_all(&[END])?;
+    let HashingWriter { mut inner, hasher } = writer;
+    inner.write_all(&hasher.finalize())?;
+    inner.flush()?;
+    Ok(written)
+}
+
+/// Restores a chain of incremental backups into a new database file at
+/// `path`, returning the root hash of the last revision.
+///
+/// The first backup must have no base, and each later one must be based on
+/// the revision before it. After each backup is applied, the root hash and
+/// the hash of every node of its revision are checked. Once the last one is
+/// applied, the areas between the restored nodes, and the nodes of earlier
+/// revisions the last one no longer uses, are added to the free lists.
+///
+/// If an error is returned, the file at `path` should be discarded.
+///
+/// # Errors
+///
+/// Returns an IO error if `path` already exists or a backup cannot be read,
+/// and [`api::Error::Backup`] if a backup is corrupt, does not apply to the
+/// revision restored before it, or restores a revision that fails its checks.
+pub fn restore<P: AsRef<Path>>(
+    path: impl AsRef<Path>,
+    backups: impl IntoIterator<Item = P>,
+) -> Result<Option<HashKey>, api::Error> {
+    let path = path.as_ref();
+    if path.exists() {
+        return Err(api::Error::IO(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("restore destination {} already exists", path.display()),
+        )));
+    }
+    let storage = Arc::new(FileBacked::new(
+        path.to_path_buf(),
+        NodeCacheBudget::default(),
+        NonZeroUsize::MIN,
+        false,
+        true,
+        CacheReadStrategy::WritesOnly,
+        StorageConfig::default(),
+    )?);
+    let mut store = NodeStore::new_empty_committed(storage);
+    store.flush_header_with_padding()?;
+
+    for backup in backups {
+        let file = std::fs::File::open(backup.as_ref())?;
+        apply(&mut store, io::BufReader::new(file))?;
+    }
+    store.reclaim_leaked_areas()?;
+    Ok(store.root_hash())
+}
+
+/// Applies one backup to `store`, then verifies the restored revision.
+fn apply(
+    store: &mut NodeStore<Committed, FileBacked>,
+    reader: impl Read,
+) -> Result<(), api::Error> {
+    let mut reader = HashingReader {
+        inner: reader,
+        hasher: Sha256::new(),
+    };
+    let mut magic = [0u8; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if magic != *MAGIC {
+        return Err(BackupError::InvalidMagic.into());
+    }
+    let version = read_byte(&mut reader)?;
+    if version != VERSION {
+        return Err(BackupError::UnsupportedVersion { found: version }.into());
+    }
+    let base = read_hash(&mut reader)?;
+    let expected = read_hash(&mut reader)?;
+    let root = match read_byte(&mut reader)? {
+        0 => None,
+        1 => Some(read_address(&mut reader)?),
+        found => return Err(BackupError::InvalidTag { found }.into()),
+    };
+
+    let found = store.root_hash();
+    if base != found {
+        return Err(BackupError::BaseMismatch {
+            expected: base,
+            found,
+        }
+        .into());
+    }
+
+    loop {
+        match read_byte(&mut reader)? {
+            AREA => {
+                let addr = read_address(&mut reader)?;
+                store.restore_area(addr, &read_bytes(&mut reader)?)?;
+            }
+            END => break,
+            found => return Err(BackupError::InvalidTag { found }.into()),
+        }
+    }
+    let checksum = reader.hasher.finalize();
+    let mut found = [0u8; 32];
+    reader.inner.read_exact(&mut found)?;
+    if found != *checksum {
+        return Err(BackupError::ChecksumMismatch.into());
+    }
+
+    store.restore_root(root)?;
+    let found = store.root_hash();
+    if found != expected {
+        return Err(BackupError::RootMismatch { expected, found }.into());
+    }
+    let errors: Vec<_> = store
+        .check(CheckOpt {
+            hash_check: true,
+            progress_bar: None,
+        })
+        .errors
+        .into_iter()
+        .filter(|e| !matches!(e, CheckerError::AreaLeaks(_)))
+        .collect();
+    if !errors.is_empty() {
+        return Err(BackupError::Inconsistent(errors).into());
+    }
+    Ok(())
+}
+
+fn write_hash(writer: &mut impl Write, hash: Option<HashKey>) -> io::Result<()> {
+    if let Some(hash) = hash {
+        writer.write_all(&[1])?;
+        writer.write_all(hash.as_ref())
+    } else {
+        writer.write_all(&[0])
+    }
+}
+
+fn read_hash(reader: &mut impl Read) -> Result<Option<HashKey>, api::Error> {
+    match read_byte(reader)? {
+        0 => Ok(None),
+        1 => {
+            let mut hash = [0u8; 32];
+            reader.read_exact(&mut hash)?;
+            Ok(Some(HashKey::from(hash)))
+        }
+        found => Err(BackupError::InvalidTag { found }.into()),
+    }
+}
+
+fn read_address(reader: &mut impl Read) -> Result<LinearAddress, api::Error> {
+    let addr: u64 = reader.read_varint()?;
+    LinearAddress::new(addr).ok_or_else(|| {
+        api::Error::IO(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid area address 0",
+        ))
+    })
+}
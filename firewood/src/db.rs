@@ -6,23 +6,33 @@
     reason = "Found 12 occurrences after enabling the lint."
 )]
 
+use crate::diff::DiffIter;
 use crate::iter::MerkleKeyValueIter;
+use crate::merkle::bulk::BulkBuilder;
 use crate::merkle::{Merkle, Value};
+use crate::proposal_export::ProposalExport;
 use crate::root_store::{NoOpStore, RootStore};
 pub use crate::v2::api::BatchOp;
 use crate::v2::api::{
     self, ArcDynDbView, FrozenProof, FrozenRangeProof, HashKey, KeyType, KeyValuePairIter,
-    OptionalHashKeyExt,
+    MergeOperator, OptionalHashKeyExt, ValueType,
 };
+pub use firewood_storage::CopyProgress;
+use firewood_storage::logger::warn;
 
-use crate::manager::{ConfigManager, RevisionManager, RevisionManagerConfig};
+use crate::manager::{
+    CacheReadStrategy, ConfigManager, Durability, NodeCacheBudget, RetentionPolicy, RevisionInfo,
+    RevisionManager, RevisionManagerConfig, StorageConfig,
+};
 use firewood_storage::{
     CheckOpt, CheckerReport, Committed, FileBacked, FileIoError, HashedNodeReader,
-    ImmutableProposal, NodeStore, Parentable, ReadableStorage, TrieReader,
+    ImmutableProposal, NodeStore, NodeStoreParent, Parentable, ReadableStorage, TrieReader,
 };
 use metrics::{counter, describe_counter};
+use rayon::ThreadPool;
 use std::io::Write;
 use std::num::NonZeroUsize;
+use std::ops::RangeBounds;
 use std::path::Path;
 use std::sync::Arc;
 use thiserror::Error;
@@ -51,7 +61,7 @@ impl std::fmt::Debug for DbMetrics {
     }
 }
 
-impl<P: Parentable, S: ReadableStorage> api::DbView for NodeStore<P, S>
+impl<P: Parentable + Sync, S: ReadableStorage> api::DbView for NodeStore<P, S>
 where
     NodeStore<P, S>: TrieReader,
 {
@@ -69,6 +79,11 @@ where
         Ok(merkle.get_value(key.as_ref())?)
     }
 
+    fn val_many<K: KeyType>(&self, keys: &[K]) -> Result<Vec<Option<Value>>, api::Error> {
+        let merkle = Merkle::from(self);
+        Ok(merkle.get_values(keys)?)
+    }
+
     fn single_key_proof<K: api::KeyType>(&self, key: K) -> Result<FrozenProof, api::Error> {
         let merkle = Merkle::from(self);
         merkle.prove(key.as_ref()).map_err(api::Error::from)
@@ -93,6 +108,21 @@ where
             None => Ok(MerkleKeyValueIter::from(self)),
         }
     }
+
+    fn iter_rev_option<K: KeyType>(
+        &self,
+        last_key: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        Ok(MerkleKeyValueIter::descending(self, last_key))
+    }
+
+    fn iter_range<K: KeyType>(
+        &self,
+        start: Option<K>,
+        end: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        Ok(MerkleKeyValueIter::range(self, start, end))
+    }
 }
 
 #[allow(dead_code)]
@@ -103,6 +133,17 @@ pub enum UseParallel {
     Always,
 }
 
+/// How [`Proposal::rebase`] handles keys that were changed both by the
+/// proposal and between its parent and the new parent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RebaseConflicts {
+    /// The change made by the proposal wins.
+    #[default]
+    Overwrite,
+    /// The rebase fails with [`api::Error::RebaseConflict`].
+    Report,
+}
+
 /// Database configuration.
 #[derive(Clone, TypedBuilder, Debug)]
 #[non_exhaustive]
@@ -114,6 +155,29 @@ pub struct DbConfig {
     /// existing contents will be lost.
     #[builder(default = false)]
     pub truncate: bool,
+    /// Storage configuration: the durability policy applied on commit (see
+    /// [`Durability`](crate::manager::Durability) for the ordering guarantees
+    /// each policy provides) and the io-uring tunables used when writing
+    /// revisions.
+    ///
+    /// The builder sets it with `storage`, or just its durability policy with
+    /// `durability`.
+    #[builder(
+        via_mutators(init = StorageConfig::default()),
+        mutators(
+            /// Sets the whole storage configuration.
+            #[allow(clippy::missing_const_for_fn, reason = "typed-builder mutators cannot be const")]
+            pub fn storage(&mut self, storage: StorageConfig) {
+                self.storage = storage;
+            }
+            /// Sets only the durability policy of the storage configuration.
+            #[allow(clippy::missing_const_for_fn, reason = "typed-builder mutators cannot be const")]
+            pub fn durability(&mut self, durability: Durability) {
+                self.storage.durability = durability;
+            }
+        )
+    )]
+    pub storage: StorageConfig,
     /// Revision manager configuration.
     #[builder(default = RevisionManagerConfig::builder().build())]
     pub manager: RevisionManagerConfig,
@@ -122,6 +186,16 @@ pub struct DbConfig {
     // TODO: Experimentally determine the right value for BatchSize.
     #[builder(default = UseParallel::BatchSize(8))]
     pub use_parallel: UseParallel,
+    /// The merge operator applied by [`BatchOp::Merge`]. Without one, a batch
+    /// containing a merge fails with [`api::Error::NoMergeOperator`].
+    #[builder(default, setter(strip_option))]
+    pub merge_operator: Option<Arc<dyn MergeOperator>>,
+    /// Which committed revisions are retained, such as
+    /// [`Archival`](crate::manager::Archival) to never
+    /// reap any. Without a policy, the latest
+    /// [`max_revisions`](RevisionManagerConfig) revisions are retained.
+    #[builder(default, setter(strip_option))]
+    pub retention: Option<Arc<dyn RetentionPolicy>>,
 }
 
 #[derive(Debug)]
@@ -130,6 +204,7 @@ pub struct Db {
     metrics: Arc<DbMetrics>,
     manager: RevisionManager,
     use_parallel: UseParallel,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
 }
 
 impl api::Db for Db {
@@ -157,30 +232,32 @@ impl api::Db for Db {
         &self,
         batch: impl IntoIterator<IntoIter: KeyValuePairIter>,
     ) -> Result<Self::Proposal<'_>, api::Error> {
-        self.propose_with_parent(batch, &self.manager.current_revision())
+        self.propose_on_revision(batch, self.manager.current_revision())
     }
 }
 
 impl Db {
     /// Create a new database instance.
     pub fn new<P: AsRef<Path>>(db_path: P, cfg: DbConfig) -> Result<Self, api::Error> {
-        Self::with_root_store(db_path, cfg, Box::new(NoOpStore {}))
+        Self::with_root_store(db_path, cfg, Arc::new(NoOpStore {}))
     }
 
     fn with_root_store<P: AsRef<Path>>(
         db_path: P,
         cfg: DbConfig,
-        root_store: Box<dyn RootStore + Send + Sync>,
+        root_store: Arc<dyn RootStore + Send + Sync>,
     ) -> Result<Self, api::Error> {
         let metrics = Arc::new(DbMetrics {
             proposals: counter!("firewood.proposals"),
         });
         describe_counter!("firewood.proposals", "Number of proposals created");
-        let config_manager = ConfigManager::builder()
-            .create(cfg.create_if_missing)
-            .truncate(cfg.truncate)
-            .manager(cfg.manager)
-            .build();
+        let config_manager = ConfigManager {
+            create: cfg.create_if_missing,
+            truncate: cfg.truncate,
+            storage: cfg.storage,
+            manager: cfg.manager,
+            retention: cfg.retention,
+        };
 
         let manager =
             RevisionManager::new(db_path.as_ref().to_path_buf(), config_manager, root_store)?;
@@ -188,6 +265,7 @@ impl Db {
             metrics,
             manager,
             use_parallel: cfg.use_parallel,
+            merge_operator: cfg.merge_operator,
         };
         Ok(db)
     }
@@ -197,6 +275,62 @@ impl Db {
         self.manager.view(root_hash).map_err(Into::into)
     }
 
+    /// Lists the changes that turn the revision at `from_root` into the one at
+    /// `to_root`, for keys within `range`, in ascending key order.
+    ///
+    /// Both revisions must be committed. Subtries whose hashes are equal in
+    /// both revisions are skipped without being read, so the cost depends on
+    /// how much changed between them rather than on the size of the trie.
+    pub fn diff<K: KeyType>(
+        &self,
+        from_root: HashKey,
+        to_root: HashKey,
+        range: impl RangeBounds<K>,
+    ) -> Result<api::BoxKeyChangeIter<'static>, api::Error> {
+        let from = self.manager.committed_revision(from_root)?;
+        let to = self.manager.committed_revision(to_root)?;
+        Ok(Box::new(DiffIter::new(from, to).with_range(range)))
+    }
+
+    /// Restores a proposal serialized by [`Proposal::export`].
+    ///
+    /// The parent of the proposal must be a committed revision or a proposal
+    /// that is still uncommitted, so a chain of proposals is imported parent
+    /// first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::ProposalImport`] if `data` is not an exported
+    /// proposal, [`api::Error::RevisionNotFound`] if the parent is not
+    /// available and [`api::Error::ImportRootMismatch`] if the restored
+    /// proposal does not have the root hash it was exported with.
+    pub fn import_proposal(&self, data: &[u8]) -> Result<Proposal<'_>, api::Error> {
+        let export = ProposalExport::from_slice(data)?;
+        let proposal = if let Some(parent) = export.parent {
+            if let Some(parent) = self.manager.proposal(&parent) {
+                self.propose_with_parent(&export.ops, &parent)?
+            } else {
+                let parent = self.manager.committed_revision(parent)?;
+                self.propose_on_revision(&export.ops, parent)?
+            }
+        } else {
+            let latest = self.manager.current_revision();
+            if latest.root_hash().is_some() {
+                return Err(api::Error::RevisionNotFound { provided: None });
+            }
+            self.propose_on_revision(&export.ops, latest)?
+        };
+
+        let found = proposal.nodestore.root_hash();
+        if found != export.root {
+            return Err(api::Error::ImportRootMismatch {
+                expected: export.root,
+                found,
+            });
+        }
+        Ok(proposal)
+    }
+
     /// Dump the Trie of the latest revision.
     pub fn dump(&self, w: &mut dyn Write) -> Result<(), std::io::Error> {
         let latest_rev_nodestore = self.manager.current_revision();
@@ -209,24 +343,441 @@ impl Db {
         self.metrics.clone()
     }
 
+    /// The thread pool the database uses for parallel work, such as building
+    /// proposals and [`val_many`](api::DbView::val_many) lookups on proposals.
+    ///
+    /// Views returned by [`Db::revision`] and [`Db::view`] search on the
+    /// current rayon pool; run their `val_many` within
+    /// [`ThreadPool::install`] on this pool to share its threads.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread pool cannot be created.
+    #[must_use]
+    pub fn threadpool(&self) -> Arc<ThreadPool> {
+        Arc::clone(self.manager.threadpool())
+    }
+
+    /// Wait until every committed revision has been written to disk.
+    ///
+    /// Commits return as soon as the new revision is available in memory and
+    /// are persisted in the background, in commit order. Once this returns
+    /// successfully, every revision committed before the call is on disk, as
+    /// durable as the configured [`Durability`](crate::manager::Durability)
+    /// makes it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from the first revision that could not be persisted.
+    /// After such a failure, no later revision is persisted and subsequent
+    /// calls and commits return [`api::Error::PersistFailed`].
+    pub fn flush(&self) -> Result<(), api::Error> {
+        self.manager.flush().map_err(Into::into)
+    }
+
+    /// Makes the retained revision `root_hash` the latest revision again,
+    /// discarding every revision committed after it.
+    ///
+    /// The nodes written by the discarded revisions are returned to the free
+    /// lists, so they are reused by later commits. Rolling back to the latest
+    /// revision does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::RevisionNotFound`] if `root_hash` is not a
+    /// retained revision, and [`api::Error::RevisionInUse`] if a discarded
+    /// revision, or any proposal, is still held; nothing is changed in either
+    /// case. If the file cannot be rolled back, later commits return
+    /// [`api::Error::PersistFailed`] until the database is reopened.
+    pub fn rollback_to(&self, root_hash: HashKey) -> Result<(), api::Error> {
+        self.manager.rollback_to(root_hash).map_err(Into::into)
+    }
+
+    /// Creates the named head `name`, a fork of the main head starting at its
+    /// retained revision `root_hash`.
+    ///
+    /// Proposals from [`Db::propose_on_head`] are committed to the head
+    /// without affecting the main head, and its revisions can be found with
+    /// [`api::Db::revision`]. The head shares the nodes of its base, so its
+    /// base and every later revision of the main head are retained until the
+    /// head is deleted or promoted. Heads live in memory only: after a
+    /// restart, only the main head remains, and the nodes written for other
+    /// heads are leaked until `fwdctl check --fix` reclaims them.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::HeadExists`] if there already is a head called
+    /// `name`, and [`api::Error::RevisionNotFound`] if `root_hash` is not a
+    /// retained revision of the main head.
+    pub fn create_head(&self, name: &str, root_hash: HashKey) -> Result<(), api::Error> {
+        self.manager
+            .create_head(name, root_hash)
+            .map_err(Into::into)
+    }
+
+    /// Returns the root hash of the latest revision of the named head `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::HeadNotFound`] if there is no head called `name`.
+    pub fn head_root_hash(&self, name: &str) -> Result<Option<HashKey>, api::Error> {
+        Ok(self.manager.head_revision(name)?.root_hash())
+    }
+
+    /// Returns the name of each named head with the root hash of its latest
+    /// revision, sorted by name.
+    #[must_use]
+    pub fn heads(&self) -> Vec<(String, Option<HashKey>)> {
+        self.manager.heads()
+    }
+
+    /// Proposes `batch` on top of the latest revision of the named head
+    /// `name`. Committing the proposal, or any proposal built on it, adds the
+    /// revision to that head.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::HeadNotFound`] if there is no head called `name`.
+    pub fn propose_on_head(
+        &self,
+        name: &str,
+        batch: impl IntoIterator<IntoIter: KeyValuePairIter>,
+    ) -> Result<Proposal<'_>, api::Error> {
+        let tip = self.manager.head_revision(name)?;
+        let mut proposal = self.propose_on_revision(batch, tip)?;
+        proposal.head = Some(name.to_owned());
+        Ok(proposal)
+    }
+
+    /// Deletes the named head `name`, returning the nodes written by its
+    /// revisions to the free lists.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::HeadNotFound`] if there is no head called
+    /// `name`, and [`api::Error::RevisionInUse`] if one of its revisions, or
+    /// any proposal, is still held; nothing is changed in either case. If the
+    /// file cannot be updated, later commits return
+    /// [`api::Error::PersistFailed`] until the database is reopened.
+    pub fn delete_head(&self, name: &str) -> Result<(), api::Error> {
+        self.manager.delete_head(name).map_err(Into::into)
+    }
+
+    /// Makes the named head `name` the main head, for example once its branch
+    /// of the chain has won, and deletes it.
+    ///
+    /// The revisions of the main head after the base of `name` are
+    /// discarded, as by [`Db::rollback_to`], and the revisions of `name`
+    /// take their place.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::HeadNotFound`] if there is no head called
+    /// `name`, and [`api::Error::RevisionInUse`] if a discarded revision, or
+    /// any proposal, is still held, including by another head based on a
+    /// discarded revision; nothing is changed in either case. If the file
+    /// cannot be updated, later commits return [`api::Error::PersistFailed`]
+    /// until the database is reopened.
+    pub fn promote_head(&self, name: &str) -> Result<(), api::Error> {
+        self.manager.promote_head(name).map_err(Into::into)
+    }
+
+    /// Returns the revisions of the main head that are retained, oldest
+    /// first.
+    #[must_use]
+    pub fn retained_revisions(&self) -> Vec<RevisionInfo> {
+        self.manager.retained_revisions()
+    }
+
+    /// Returns the revisions of the main head that the retention policy no
+    /// longer retains, but that could not be reaped because they were still
+    /// held, by a view or the base of a named head, when the latest revision
+    /// was committed. Oldest first.
+    ///
+    /// Their number is also reported by the `firewood.revisions.stuck` gauge.
+    #[must_use]
+    pub fn stuck_revisions(&self) -> Vec<RevisionInfo> {
+        self.manager.stuck_revisions()
+    }
+
+    /// Pins the retained revision of the main head with `root_hash`, so it is
+    /// retained whatever the retention policy says until it is unpinned.
+    ///
+    /// Pins are held in memory only, and are dropped along with the revision
+    /// by [`Db::rollback_to`] or [`Db::promote_head`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::RevisionNotFound`] if no revision with
+    /// `root_hash` is retained.
+    pub fn pin_revision(&self, root_hash: HashKey) -> Result<(), api::Error> {
+        self.manager.pin_revision(root_hash).map_err(Into::into)
+    }
+
+    /// Unpins the revision with `root_hash`, returning whether it was pinned.
+    /// The next commit reaps it if the retention policy no longer retains it.
+    pub fn unpin_revision(&self, root_hash: &HashKey) -> bool {
+        self.manager.unpin_revision(root_hash)
+    }
+
+    /// Returns the root hashes of the pinned revisions, sorted.
+    ///
+    /// Their number is also reported by the `firewood.revisions.pinned` gauge.
+    #[must_use]
+    pub fn pinned_revisions(&self) -> Vec<HashKey> {
+        self.manager.pinned_revisions()
+    }
+
     /// Check the database for consistency
+    ///
+    /// Pending commits are flushed first, so the check covers the latest
+    /// revision that could be persisted.
     pub fn check(&self, opt: CheckOpt) -> CheckerReport {
-        let latest_rev_nodestore = self.manager.current_revision();
+        if let Err(err) = self.manager.flush() {
+            warn!("checking the database after a failed persist: {err}");
+        }
+        let latest_rev_nodestore = self.manager.persisted_revision();
         latest_rev_nodestore.check(opt)
     }
 
-    /// Create a proposal with a specified parent. A proposal is created in parallel if `use_parallel`
-    /// is `Always` or if `use_parallel` is `BatchSize` and the batch is >= to the `BatchSize` value.
+    /// Proposes removing the keys in the half-open range `[start, end)` from
+    /// the latest revision, or every key from `start` onwards if `end` is
+    /// `None`. Returns the proposal and the number of keys it removes.
     ///
-    /// # Panics
+    /// Use [`BatchOp::DeleteKeyRange`] to remove a range as part of a larger
+    /// batch.
+    pub fn propose_delete_range<K: KeyType>(
+        &self,
+        start: K,
+        end: Option<K>,
+    ) -> Result<(Proposal<'_>, usize), api::Error> {
+        let parent = self.manager.current_revision();
+        let proposal = NodeStore::new(&parent)?;
+        let mut merkle = Merkle::from(proposal);
+        let removed = merkle.remove_range(start.as_ref(), end.as_ref().map(AsRef::as_ref))?;
+        let immutable: Arc<NodeStore<Arc<ImmutableProposal>, FileBacked>> =
+            Arc::new(merkle.into_inner().try_into()?);
+        self.manager.add_proposal(immutable.clone());
+
+        self.metrics.proposals.increment(1);
+
+        Ok((
+            Proposal {
+                nodestore: immutable,
+                db: self,
+                head: None,
+                parent: Some(parent),
+            },
+            removed,
+        ))
+    }
+
+    /// Writes a backup of the committed revision `root` to a new database file
+    /// at `path`.
     ///
-    /// Panics if the revision manager cannot create a thread pool.
+    /// Only the nodes of that revision are copied, packed one after the other,
+    /// so the backup is usually much smaller than the database. Commits may
+    /// continue while the backup is written: the revision is held for the
+    /// duration, so its nodes cannot be reaped and reused.
+    ///
+    /// Returns the number of nodes and bytes written.
+    pub fn backup_to(
+        &self,
+        path: impl AsRef<Path>,
+        root: HashKey,
+    ) -> Result<CopyProgress, api::Error> {
+        self.backup_to_with_progress(path, root, |_| {})
+    }
+
+    /// Like [`Db::backup_to`], calling `progress` after each node is written.
+    pub fn backup_to_with_progress(
+        &self,
+        path: impl AsRef<Path>,
+        root: HashKey,
+        mut progress: impl FnMut(CopyProgress),
+    ) -> Result<CopyProgress, api::Error> {
+        let revision = self.manager.revision(root)?;
+        let path = path.as_ref();
+        // the file is opened again as storage below, which does not create it
+        drop(create_backup_destination(path)?);
+        let dest = FileBacked::new(
+            path.to_path_buf(),
+            NodeCacheBudget::default(),
+            NonZeroUsize::MIN,
+            false,
+            false,
+            CacheReadStrategy::WritesOnly,
+            StorageConfig::default(),
+        )?;
+
+        let mut copied = CopyProgress::default();
+        revision.copy_to(Arc::new(dest), |update| {
+            copied = update;
+            progress(update);
+        })?;
+        Ok(copied)
+    }
+
+    /// Writes an incremental backup of the committed revision `root` to a new
+    /// file at `path`, holding only the nodes that are not in `base`.
+    ///
+    /// With no `base`, every node of `root` is written, starting a chain that
+    /// [`backup::restore`](crate::backup::restore) can replay. Otherwise
+    /// `base` must be a revision the database still retains in memory, which
+    /// it keeps until the backup is written; it is usually the revision
+    /// backed up last. A revision only found in the root store may have had
+    /// its nodes freed and their areas reused, so the nodes it seems to
+    /// share with `root` could not be told apart from new ones.
+    /// Nodes keep their addresses in the database, and only subtrees that
+    /// changed since `base` are read, so the cost is proportional to the
+    /// commits in between rather than to the size of the trie.
+    ///
+    /// Returns the number of nodes and bytes written.
+    pub fn incremental_backup_to(
+        &self,
+        path: impl AsRef<Path>,
+        base: Option<HashKey>,
+        root: HashKey,
+    ) -> Result<CopyProgress, api::Error> {
+        // Nodes that are still waiting to be persisted have no address yet.
+        self.manager.flush()?;
+        let revision = self.manager.committed_revision(root)?;
+        let base = base.map(|base| self.manager.revision(base)).transpose()?;
+        let path = path.as_ref();
+        let file = create_backup_destination(path)?;
+        crate::backup::write(std::io::BufWriter::new(file), base.as_deref(), &revision)
+    }
+
+    /// Proposes the contents of an empty database from `pairs`, which must be
+    /// in strictly ascending key order.
+    ///
+    /// Rather than inserting the keys one at a time, the trie is built from
+    /// the leaves up as the pairs arrive, so each node is created once and
+    /// never restructured. Each node is written to the end of the file, after
+    /// its children, as soon as its subtrie is complete, so memory use
+    /// depends on the length of the keys rather than their number; only the
+    /// root is left for the commit to write.
+    ///
+    /// No revision is persisted while the pairs are loaded, and the space
+    /// the nodes take is not reused if the proposal is dropped rather than
+    /// committed, until leaked areas are reclaimed.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::BulkLoadNotEmpty`] if the latest revision has
+    /// any keys, [`api::Error::UnsortedBulkLoad`] if a key is not greater than
+    /// the key before it, or the first error yielded by `pairs`.
+    pub fn bulk_load<K, V, E>(
+        &self,
+        pairs: impl IntoIterator<Item = Result<(K, V), E>>,
+    ) -> Result<Proposal<'_>, api::Error>
+    where
+        K: KeyType,
+        V: ValueType,
+        api::Error: From<E>,
+    {
+        let parent = self.manager.current_revision();
+        if parent.root_hash().is_some() {
+            return Err(api::Error::BulkLoadNotEmpty);
+        }
+
+        let mut proposal = NodeStore::new(&parent)?;
+        let root = self.manager.write_at_end(&mut proposal, |proposal| {
+            let mut builder = BulkBuilder::new(proposal);
+            for pair in pairs {
+                let (key, value) = pair?;
+                builder.push(key.as_ref(), value.as_ref().into())?;
+            }
+            builder.finish()
+        })?;
+        *proposal.root_mut() = root;
+        let immutable: Arc<NodeStore<Arc<ImmutableProposal>, FileBacked>> =
+            Arc::new(proposal.try_into().map_err(api::Error::FileIO)?);
+        self.manager.add_proposal(immutable.clone());
+
+        self.metrics.proposals.increment(1);
+
+        Ok(Proposal {
+            nodestore: immutable,
+            db: self,
+            head: None,
+            parent: Some(parent),
+        })
+    }
+
+    /// Computes the root hash that `batch` would produce on top of `parent`,
+    /// or on top of the latest revision if `parent` is `None`, without
+    /// creating a proposal. `parent` may be a committed revision or an
+    /// uncommitted proposal.
+    ///
+    /// The new trie is hashed in memory and discarded, so no disk space is
+    /// allocated for it and nothing is registered with the revision manager.
+    /// As when proposing, the batch is applied in parallel according to
+    /// [`DbConfig::use_parallel`].
+    pub fn compute_root(
+        &self,
+        parent: Option<HashKey>,
+        batch: impl IntoIterator<IntoIter: KeyValuePairIter>,
+    ) -> Result<Option<HashKey>, api::Error> {
+        let nodestore = match parent {
+            None => self.build_proposal(batch, &self.manager.current_revision())?,
+            Some(parent) => {
+                if let Some(proposal) = self.manager.proposal(&parent) {
+                    self.build_proposal(batch, &proposal)?
+                } else {
+                    let committed = self.manager.committed_revision(parent)?;
+                    self.build_proposal(batch, &committed)?
+                }
+            }
+        };
+        Ok(nodestore.root_hash())
+    }
+
+    /// Create a proposal with a specified parent and register it with the
+    /// revision manager.
     #[fastrace::trace(name = "propose")]
     fn propose_with_parent<F: Parentable>(
         &self,
         batch: impl IntoIterator<IntoIter: KeyValuePairIter>,
         parent: &NodeStore<F, FileBacked>,
     ) -> Result<Proposal<'_>, api::Error> {
+        let immutable = self.build_proposal(batch, parent)?;
+        self.manager.add_proposal(immutable.clone());
+
+        self.metrics.proposals.increment(1);
+
+        Ok(Proposal {
+            nodestore: immutable,
+            db: self,
+            head: None,
+            parent: None,
+        })
+    }
+
+    /// Create a proposal on top of the committed revision `parent`, which the
+    /// proposal holds until it is dropped or committed.
+    fn propose_on_revision(
+        &self,
+        batch: impl IntoIterator<IntoIter: KeyValuePairIter>,
+        parent: Arc<NodeStore<Committed, FileBacked>>,
+    ) -> Result<Proposal<'_>, api::Error> {
+        let mut proposal = self.propose_with_parent(batch, &parent)?;
+        proposal.parent = Some(parent);
+        Ok(proposal)
+    }
+
+    /// Apply a batch to a specified parent and hash the result. The batch is applied in parallel
+    /// if `use_parallel` is `Always` or if `use_parallel` is `BatchSize` and the batch is >= to
+    /// the `BatchSize` value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the revision manager cannot create a thread pool.
+    fn build_proposal<F: Parentable>(
+        &self,
+        batch: impl IntoIterator<IntoIter: KeyValuePairIter>,
+        parent: &NodeStore<F, FileBacked>,
+    ) -> Result<Arc<NodeStore<Arc<ImmutableProposal>, FileBacked>>, api::Error> {
         // If use_parallel is BatchSize, then perform parallel proposal creation if the batch
         // size is >= BatchSize.
         let batch = batch.into_iter();
@@ -236,7 +787,7 @@ impl Db {
             UseParallel::BatchSize(required_size) => batch.size_hint().0 >= required_size,
         };
         let immutable = if use_parallel {
-            let mut parallel_merkle = ParallelMerkle::default();
+            let mut parallel_merkle = ParallelMerkle::new(self.merge_operator.clone());
             let _span = fastrace::Span::enter_with_local_parent("parallel_merkle");
             parallel_merkle.create_proposal(parent, batch, self.manager.threadpool())?
         } else {
@@ -244,16 +795,34 @@ impl Db {
             let mut merkle = Merkle::from(proposal);
             let span = fastrace::Span::enter_with_local_parent("merkleops");
             for op in batch.into_iter().map_into_batch() {
+                if op.is_conditional() {
+                    let current = merkle.get_value(op.key().as_ref())?;
+                    op.check_condition(current.as_deref())?;
+                }
                 match op {
-                    BatchOp::Put { key, value } => {
+                    BatchOp::Put { key, value }
+                    | BatchOp::PutIfAbsent { key, value }
+                    | BatchOp::CompareAndSwap { key, value, .. } => {
                         merkle.insert(key.as_ref(), value.as_ref().into())?;
                     }
-                    BatchOp::Delete { key } => {
+                    BatchOp::Delete { key } | BatchOp::DeleteIfEquals { key, .. } => {
                         merkle.remove(key.as_ref())?;
                     }
                     BatchOp::DeleteRange { prefix } => {
                         merkle.remove_prefix(prefix.as_ref())?;
                     }
+                    BatchOp::DeleteKeyRange { start, end } => {
+                        merkle.remove_range(start.as_ref(), end.as_ref().map(AsRef::as_ref))?;
+                    }
+                    BatchOp::Merge { key, operand } => {
+                        let merge_operator = self
+                            .merge_operator
+                            .as_deref()
+                            .ok_or(api::Error::NoMergeOperator)?;
+                        merkle.insert_with(key.as_ref(), |existing| {
+                            merge_operator.merge(key.as_ref(), existing, operand.as_ref())
+                        })?;
+                    }
                 }
             }
 
@@ -262,14 +831,7 @@ impl Db {
             let nodestore = merkle.into_inner();
             Arc::new(nodestore.try_into()?)
         };
-        self.manager.add_proposal(immutable.clone());
-
-        self.metrics.proposals.increment(1);
-
-        Ok(Proposal {
-            nodestore: immutable,
-            db: self,
-        })
+        Ok(immutable)
     }
 }
 
@@ -278,6 +840,11 @@ impl Db {
 pub struct Proposal<'db> {
     nodestore: Arc<NodeStore<Arc<ImmutableProposal>, FileBacked>>,
     db: &'db Db,
+    /// The named head this proposal is committed to, or `None` for the main head
+    head: Option<String>,
+    /// The committed revision this proposal was proposed on, held so that it
+    /// is not reaped while the proposal can still be rebased
+    parent: Option<Arc<NodeStore<Committed, FileBacked>>>,
 }
 
 impl api::DbView for Proposal<'_> {
@@ -294,6 +861,14 @@ impl api::DbView for Proposal<'_> {
         api::DbView::val(&*self.nodestore, key)
     }
 
+    fn val_many<K: KeyType>(&self, keys: &[K]) -> Result<Vec<Option<Value>>, api::Error> {
+        let keys: Vec<&[u8]> = keys.iter().map(AsRef::as_ref).collect();
+        self.db
+            .manager
+            .threadpool()
+            .install(|| api::DbView::val_many(&*self.nodestore, &keys))
+    }
+
     fn single_key_proof<K: KeyType>(&self, key: K) -> Result<FrozenProof, api::Error> {
         api::DbView::single_key_proof(&*self.nodestore, key)
     }
@@ -310,6 +885,21 @@ impl api::DbView for Proposal<'_> {
     fn iter_option<K: KeyType>(&self, first_key: Option<K>) -> Result<Self::Iter<'_>, api::Error> {
         api::DbView::iter_option(&*self.nodestore, first_key)
     }
+
+    fn iter_rev_option<K: KeyType>(
+        &self,
+        last_key: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        api::DbView::iter_rev_option(&*self.nodestore, last_key)
+    }
+
+    fn iter_range<K: KeyType>(
+        &self,
+        start: Option<K>,
+        end: Option<K>,
+    ) -> Result<Self::Iter<'_>, api::Error> {
+        api::DbView::iter_range(&*self.nodestore, start, end)
+    }
 }
 
 impl<'db> api::Proposal for Proposal<'db> {
@@ -324,39 +914,171 @@ impl<'db> api::Proposal for Proposal<'db> {
     }
 
     fn commit(self) -> Result<(), api::Error> {
-        Ok(self.db.manager.commit(self.nodestore)?)
+        match &self.head {
+            Some(head) => Ok(self.db.manager.commit_to_head(head, self.nodestore)?),
+            None => Ok(self.db.manager.commit(self.nodestore)?),
+        }
     }
 }
 
 impl Proposal<'_> {
+    /// Lists the keys this proposal inserts, updates or deletes relative to the
+    /// revision it was proposed on top of, in ascending key order.
+    ///
+    /// Only the subtries that differ from the parent are read.
+    pub fn diff(&self) -> Result<api::BoxKeyChangeIter<'static>, api::Error> {
+        Ok(self.db.manager.diff_from_parent(&self.nodestore)?)
+    }
+
+    /// Serializes this proposal as the puts and deletes it makes to its
+    /// parent, so it can be restored with [`Db::import_proposal`], for example
+    /// after a restart. The bytes can be written to a file.
+    pub fn export(&self) -> Result<Vec<u8>, api::Error> {
+        let parent = match self.nodestore.parent() {
+            NodeStoreParent::Committed(hash) => hash,
+            NodeStoreParent::Proposed(parent) => parent.root_hash(),
+        };
+        let ops = self
+            .diff()?
+            .map(|change| {
+                change.map(|change| match change {
+                    api::KeyChange::Inserted { key, value }
+                    | api::KeyChange::Updated {
+                        key, new: value, ..
+                    } => BatchOp::Put { key, value },
+                    api::KeyChange::Deleted { key, .. } => BatchOp::Delete { key },
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        let export = ProposalExport {
+            parent,
+            root: self.nodestore.root_hash(),
+            ops,
+        };
+        Ok(export.to_vec())
+    }
+
+    /// Replays the changes of this proposal onto the committed revision
+    /// `new_parent`, returning a new proposal. This proposal is unchanged, so
+    /// it can still be dropped or committed.
+    ///
+    /// See [`RebaseConflicts`] for how keys that were also changed between
+    /// the parent of this proposal and `new_parent` are handled.
+    ///
+    /// A proposal holds the committed revision it was proposed on, so that
+    /// revision is not reaped while the proposal is alive and the rebase
+    /// works however many revisions were committed since.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::RebaseUncommittedParent`] if this proposal was
+    /// proposed on another proposal that has not been committed, as the
+    /// changes of that proposal would not be carried over to `new_parent`.
+    pub fn rebase(
+        &self,
+        new_parent: HashKey,
+        conflicts: RebaseConflicts,
+    ) -> Result<Self, api::Error> {
+        if matches!(self.nodestore.parent(), NodeStoreParent::Proposed(_)) {
+            return Err(api::Error::RebaseUncommittedParent);
+        }
+        let manager = &self.db.manager;
+        let new_parent = manager.committed_revision(new_parent)?;
+        // proposals on a proposal that has since been committed do not hold
+        // their parent, so it is looked up in the revision manager
+        let changes = match &self.parent {
+            Some(parent) => DiffIter::new(Arc::clone(parent), Arc::clone(&self.nodestore))
+                .collect::<Result<Vec<_>, _>>()?,
+            None => manager
+                .diff_from_parent(&self.nodestore)?
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+
+        if conflicts == RebaseConflicts::Report {
+            let theirs: api::BoxKeyChangeIter<'_> = match &self.parent {
+                Some(parent) => Box::new(DiffIter::new(Arc::clone(parent), new_parent.clone())),
+                None => manager.diff_parent_to(&self.nodestore, new_parent.clone())?,
+            };
+            let theirs = theirs
+                .map(|change| change.map(|change| change.key().to_vec()))
+                .collect::<Result<Vec<_>, _>>()?;
+            let keys: Vec<_> = changes
+                .iter()
+                .map(api::KeyChange::key)
+                .filter(|key| {
+                    theirs
+                        .binary_search_by(|theirs| theirs.as_slice().cmp(key))
+                        .is_ok()
+                })
+                .map(Into::into)
+                .collect();
+            if !keys.is_empty() {
+                return Err(api::Error::RebaseConflict { keys });
+            }
+        }
+
+        let batch = changes.iter().map(|change| match change.new_value() {
+            Some(value) => BatchOp::Put {
+                key: change.key(),
+                value,
+            },
+            None => BatchOp::Delete { key: change.key() },
+        });
+        self.db.propose_on_revision(batch, new_parent)
+    }
+
     #[crate::metrics("firewood.proposal.create", "database proposal creation")]
     fn create_proposal(
         &self,
         batch: impl IntoIterator<IntoIter: KeyValuePairIter>,
     ) -> Result<Self, api::Error> {
-        self.db.propose_with_parent(batch, &self.nodestore)
+        let mut proposal = self.db.propose_with_parent(batch, &self.nodestore)?;
+        proposal.head.clone_from(&self.head);
+        Ok(proposal)
     }
 }
 
+/// Creates the file a backup is written to, failing if `path` already exists.
+/// The check and the creation are a single step, so a file created at `path`
+/// in the meantime is never overwritten.
+fn create_backup_destination(path: &Path) -> Result<std::fs::File, api::Error> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|err| {
+            if err.kind() == std::io::ErrorKind::AlreadyExists {
+                api::Error::IO(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("backup destination {} already exists", path.display()),
+                ))
+            } else {
+                api::Error::IO(err)
+            }
+        })
+}
+
 #[cfg(test)]
 mod test {
     #![expect(clippy::unwrap_used)]
 
     use core::iter::Take;
     use std::iter::Peekable;
-    use std::num::NonZeroUsize;
+    use std::num::{NonZeroU64, NonZeroUsize};
     use std::ops::{Deref, DerefMut};
     use std::path::PathBuf;
+    use std::sync::Arc;
 
     use firewood_storage::{
         CheckOpt, CheckerError, HashedNodeReader, IntoHashType, NodeStore, TrieHash,
     };
 
     use crate::db::{Db, Proposal, UseParallel};
+    use crate::manager::{CacheWarmingConfig, Durability, RevisionManagerConfig, StorageConfig};
     use crate::root_store::{MockStore, RootStore};
-    use crate::v2::api::{Db as _, DbView, KeyValuePairIter, Proposal as _};
+    use crate::v2::api::{self, Db as _, DbView, KeyValuePairIter, Proposal as _};
 
-    use super::{BatchOp, DbConfig};
+    use super::{BatchOp, DbConfig, RevisionInfo};
 
     /// A chunk of an iterator, provided by [`IterExt::chunk_fold`] to the folding
     /// function.
@@ -398,7 +1120,7 @@ mod test {
     impl Db {
         /// Extract the root store by consuming the database instance.
         /// This is primarily used for reopening or replacing the database with the same root store.
-        pub fn into_root_store(self) -> Box<dyn RootStore + Send + Sync> {
+        pub fn into_root_store(self) -> Arc<dyn RootStore + Send + Sync> {
             self.manager.into_root_store()
         }
     }
@@ -835,7 +1557,7 @@ mod test {
         let committed = db.root_hash().unwrap().unwrap();
         let revision = db.revision(committed).unwrap();
 
-        for (k, v) in keys.into_iter().zip(vals.into_iter()) {
+        for (k, v) in keys.into_iter().zip(vals) {
             assert_eq!(revision.val(k).unwrap().unwrap(), v);
         }
     }
@@ -999,6 +1721,7 @@ mod test {
         let proposal = db.propose(batch).unwrap();
         let root_hash = proposal.root_hash().unwrap().unwrap();
         proposal.commit().unwrap();
+        db.flush().unwrap();
 
         let root_address = db
             .revision(root_hash.clone())
@@ -1077,8 +1800,10 @@ mod test {
             value: b"v",
         }];
 
+        // the root store is updated in the background, so the error surfaces on flush
         let proposal = db.propose(batch).unwrap();
-        assert!(proposal.commit().is_err());
+        proposal.commit().unwrap();
+        assert!(db.flush().is_err());
     }
 
     #[test]
@@ -1089,6 +1814,1586 @@ mod test {
         db.reopen();
     }
 
+    #[test]
+    fn test_background_persist_in_order() {
+        // few retained revisions, so reaping happens while commits are still
+        // pending, and a short backlog, so commits wait on the persist worker
+        let dbconfig = DbConfig::builder()
+            .manager(
+                RevisionManagerConfig::builder()
+                    .max_revisions(2)
+                    .max_pending_persists(1)
+                    .build(),
+            )
+            .build();
+        let db = TestDb::new_with_config(dbconfig);
+
+        for i in 0u32..64 {
+            // 32 bytes long so that the checker accepts the keys with ethhash
+            let key = [u8::try_from(i % 8).unwrap(); 32];
+            let value = i.to_be_bytes();
+            let proposal = db.propose(vec![BatchOp::Put { key, value }]).unwrap();
+            proposal.commit().unwrap();
+
+            // the new revision is readable before it has been flushed
+            let latest = db.revision(db.root_hash().unwrap().unwrap()).unwrap();
+            assert_eq!(&*latest.val(key).unwrap().unwrap(), &value);
+        }
+
+        db.flush().unwrap();
+        let report = db.check(CheckOpt {
+            hash_check: true,
+            progress_bar: None,
+        });
+        assert!(
+            report
+                .errors
+                .iter()
+                .all(|e| matches!(e, CheckerError::AreaLeaks(_))),
+            "{:?}",
+            report.errors
+        );
+
+        let root_hash = db.root_hash().unwrap();
+        let db = db.reopen();
+        assert_eq!(db.root_hash().unwrap(), root_hash);
+        let latest = db.revision(root_hash.unwrap()).unwrap();
+        for i in 56u32..64 {
+            let value = latest
+                .val([u8::try_from(i % 8).unwrap(); 32])
+                .unwrap()
+                .unwrap();
+            assert_eq!(&*value, &i.to_be_bytes());
+        }
+    }
+
+    #[test]
+    fn test_referenced_revision_stays_findable() {
+        let dbconfig = DbConfig::builder()
+            .manager(RevisionManagerConfig::builder().max_revisions(2).build())
+            .build();
+        let db = TestDb::new_with_config(dbconfig);
+
+        let commit = |i: u32| {
+            db.propose(vec![BatchOp::Put {
+                key: b"k",
+                value: i.to_be_bytes(),
+            }])
+            .unwrap()
+            .commit()
+            .unwrap();
+            // let the persist worker catch up, so reaping is only held up by `held`
+            db.flush().unwrap();
+            db.root_hash().unwrap().unwrap()
+        };
+
+        // holding a revision keeps it from being reaped, and it can still be
+        // looked up by hash after newer commits would have reaped it
+        let first = commit(0);
+        let held = db.revision(first.clone()).unwrap();
+        for i in 1..4 {
+            commit(i);
+        }
+        let found = db.revision(first.clone()).unwrap();
+        assert!(Arc::ptr_eq(&held, &found));
+        assert_eq!(&*found.val(b"k").unwrap().unwrap(), &0u32.to_be_bytes());
+
+        // once released, the next commit reaps it
+        drop((held, found));
+        commit(4);
+        assert!(db.revision(first).is_err());
+    }
+
+    #[test]
+    fn test_rollback_to() {
+        // keys are 32 bytes long so that the checker accepts them with ethhash
+        let key = |i: u16| {
+            let mut key = [0u8; 32];
+            key[..2].copy_from_slice(&i.to_be_bytes());
+            key
+        };
+        let db = TestDb::new();
+        db.propose((0u16..200).map(|i| BatchOp::Put {
+            key: key(i),
+            value: [1],
+        }))
+        .unwrap()
+        .commit()
+        .unwrap();
+        let target = db.root_hash().unwrap().unwrap();
+        let expected: Vec<_> = db
+            .revision(target.clone())
+            .unwrap()
+            .iter()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+
+        let commit_newer = || {
+            let mut roots = Vec::new();
+            for value in 2u8..4 {
+                db.propose((0u16..300).step_by(3).map(|i| BatchOp::Put {
+                    key: key(i),
+                    value: [value],
+                }))
+                .unwrap()
+                .commit()
+                .unwrap();
+                roots.push(db.root_hash().unwrap().unwrap());
+            }
+            db.flush().unwrap();
+            roots
+        };
+        let newer = commit_newer();
+        let size = || std::fs::metadata(db.path()).unwrap().len();
+        let grown = size();
+
+        // nothing changes while a discarded revision is held
+        let held = db.revision(newer.first().unwrap().clone()).unwrap();
+        assert!(matches!(
+            db.rollback_to(target.clone()),
+            Err(api::Error::RevisionInUse { .. })
+        ));
+        drop(held);
+        assert!(matches!(
+            db.rollback_to(api::HashKey::from([0u8; 32])),
+            Err(api::Error::RevisionNotFound { .. })
+        ));
+        assert_eq!(db.root_hash().unwrap(), newer.last().cloned());
+
+        db.rollback_to(target.clone()).unwrap();
+        assert_eq!(db.root_hash().unwrap(), Some(target.clone()));
+        for root in &newer {
+            assert!(db.revision(root.clone()).is_err());
+        }
+        let found: Vec<_> = db
+            .revision(target.clone())
+            .unwrap()
+            .iter()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(found, expected);
+        let report = db.check(CheckOpt {
+            hash_check: true,
+            progress_bar: None,
+        });
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+
+        // the discarded nodes are reused, so committing again does not grow
+        // the file; the nodes it replaces are only pending until reaped
+        assert_eq!(commit_newer(), newer);
+        assert_eq!(size(), grown);
+        let report = db.check(CheckOpt {
+            hash_check: true,
+            progress_bar: None,
+        });
+        assert!(
+            report
+                .errors
+                .iter()
+                .all(|e| matches!(e, CheckerError::AreaLeaks(_))),
+            "{:?}",
+            report.errors
+        );
+
+        let db = db.reopen();
+        assert_eq!(db.root_hash().unwrap(), newer.last().cloned());
+    }
+
+    #[test]
+    #[expect(clippy::too_many_lines)]
+    fn test_named_heads() {
+        // keys are 32 bytes long so that the checker accepts them with ethhash
+        let key = |i: u16| {
+            let mut key = [0u8; 32];
+            key[..2].copy_from_slice(&i.to_be_bytes());
+            key
+        };
+        let dbconfig = DbConfig::builder()
+            .manager(RevisionManagerConfig::builder().max_revisions(2).build())
+            .build();
+        let db = TestDb::new_with_config(dbconfig);
+        let batch = |value: u8, step: usize| {
+            (0u16..100).step_by(step).map(move |i| BatchOp::Put {
+                key: key(i),
+                value: [value],
+            })
+        };
+        let contents = |root: api::HashKey| -> Vec<_> {
+            db.revision(root)
+                .unwrap()
+                .iter()
+                .unwrap()
+                .map(Result::unwrap)
+                .collect()
+        };
+        let commit_main = |value: u8| {
+            db.propose(batch(value, 3)).unwrap().commit().unwrap();
+            db.flush().unwrap();
+            db.root_hash().unwrap().unwrap()
+        };
+        let commit_fork = |name: &str, value: u8| {
+            db.propose_on_head(name, batch(value, 5))
+                .unwrap()
+                .commit()
+                .unwrap();
+            db.flush().unwrap();
+            db.head_root_hash(name).unwrap().unwrap()
+        };
+
+        let base = commit_main(1);
+        db.create_head("fork", base.clone()).unwrap();
+        assert!(matches!(
+            db.create_head("fork", base.clone()),
+            Err(api::Error::HeadExists { .. })
+        ));
+        assert!(matches!(
+            db.propose_on_head("missing", batch(0, 1)),
+            Err(api::Error::HeadNotFound { .. })
+        ));
+
+        // both heads move on, sharing the nodes of the base; the main head
+        // keeps its base while the fork needs it, and allocates from the
+        // areas it reaps without touching the nodes of the fork
+        let mut fork = base.clone();
+        let mut main = base.clone();
+        for value in 2..6 {
+            fork = commit_fork("fork", value + 10);
+            main = commit_main(value);
+        }
+        assert_eq!(db.root_hash().unwrap(), Some(main.clone()));
+        assert_eq!(db.heads(), vec![("fork".to_owned(), Some(fork.clone()))]);
+        assert!(db.revision(base.clone()).is_ok());
+        let fork_contents = contents(fork.clone());
+        assert!(fork_contents.iter().any(|(_, value)| **value == [15]));
+        assert!(!fork_contents.iter().any(|(_, value)| **value == [5]));
+        for _ in 0..3 {
+            commit_main(5);
+        }
+        assert_eq!(contents(fork.clone()), fork_contents);
+
+        // a deleted head returns its nodes, so the same changes committed to
+        // the main head allocate nothing new
+        db.create_head("other", main.clone()).unwrap();
+        let other = commit_fork("other", 20);
+        let size = || {
+            db.check(CheckOpt {
+                hash_check: false,
+                progress_bar: None,
+            })
+            .db_stats
+            .high_watermark
+        };
+        let grown = size();
+        let held = db.revision(other.clone()).unwrap();
+        assert!(matches!(
+            db.delete_head("other"),
+            Err(api::Error::RevisionInUse { .. })
+        ));
+        drop(held);
+        db.delete_head("other").unwrap();
+        assert!(db.revision(other.clone()).is_err());
+        db.propose(batch(20, 5)).unwrap().commit().unwrap();
+        db.flush().unwrap();
+        assert_eq!(db.root_hash().unwrap(), Some(other));
+        assert_eq!(size(), grown);
+
+        // promoting the fork discards the revisions of the main head after
+        // its base
+        db.promote_head("fork").unwrap();
+        assert!(db.heads().is_empty());
+        assert_eq!(db.root_hash().unwrap(), Some(fork.clone()));
+        assert!(db.revision(main).is_err());
+        assert_eq!(contents(fork), fork_contents);
+        for value in 30..34 {
+            commit_main(value);
+        }
+        let report = db.check(CheckOpt {
+            hash_check: true,
+            progress_bar: None,
+        });
+        assert!(
+            report
+                .errors
+                .iter()
+                .all(|e| matches!(e, CheckerError::AreaLeaks(_))),
+            "{:?}",
+            report.errors
+        );
+    }
+
+    #[test]
+    #[expect(clippy::too_many_lines)]
+    fn test_retention_policies() {
+        use crate::manager::{AnyOf, Archival, Checkpoints, KeepLast};
+
+        // keys are 32 bytes long so that the checker accepts them with ethhash
+        let key = |i: u16| {
+            let mut key = [0u8; 32];
+            key[..2].copy_from_slice(&i.to_be_bytes());
+            key
+        };
+        let batch = |value: u8| {
+            (0u16..100).map(move |i| BatchOp::Put {
+                key: key(i),
+                value: [value],
+            })
+        };
+        let commit = |db: &TestDb, value: u8| {
+            db.propose(batch(value)).unwrap().commit().unwrap();
+            db.flush().unwrap();
+            db.root_hash().unwrap().unwrap()
+        };
+        let numbers = |revisions: Vec<RevisionInfo>| -> Vec<u64> {
+            revisions.iter().map(|info| info.number).collect()
+        };
+        let check = |db: &TestDb| {
+            let report = db.check(CheckOpt {
+                hash_check: true,
+                progress_bar: None,
+            });
+            assert!(
+                report
+                    .errors
+                    .iter()
+                    .all(|e| matches!(e, CheckerError::AreaLeaks(_))),
+                "{:?}",
+                report.errors
+            );
+            report.db_stats.high_watermark
+        };
+
+        // checkpoints drop the revisions between them, reusing the nodes
+        // only those used
+        let checkpoints = Checkpoints {
+            every: NonZeroU64::new(4).unwrap(),
+            count: 2,
+        };
+        let db = TestDb::new_with_config(
+            DbConfig::builder()
+                .retention(Arc::new(AnyOf(vec![
+                    Arc::new(KeepLast(2)),
+                    Arc::new(checkpoints),
+                ])))
+                .build(),
+        );
+        let mut hashes = std::collections::HashMap::new();
+        for value in 1..=13 {
+            hashes.insert(value, commit(&db, value));
+        }
+        assert_eq!(numbers(db.retained_revisions()), [8, 12, 13]);
+        for number in [8, 12] {
+            let contents: Vec<_> = db
+                .revision(hashes.get(&number).cloned().unwrap())
+                .unwrap()
+                .iter()
+                .unwrap()
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(contents.len(), 100);
+            assert!(contents.iter().all(|(_, value)| **value == [number]));
+        }
+        assert!(db.revision(hashes.get(&11).cloned().unwrap()).is_err());
+        check(&db);
+        for value in 14..=21 {
+            hashes.insert(value, commit(&db, value));
+        }
+        assert_eq!(numbers(db.retained_revisions()), [16, 20, 21]);
+        let size = check(&db);
+        for value in 22..=29 {
+            hashes.insert(value, commit(&db, value));
+        }
+        assert_eq!(numbers(db.retained_revisions()), [24, 28, 29]);
+        assert_eq!(check(&db), size);
+
+        // a pinned revision is retained until unpinned, and a held one is
+        // reported as stuck until released
+        assert!(matches!(
+            db.pin_revision(hashes.get(&20).cloned().unwrap()),
+            Err(api::Error::RevisionNotFound { .. })
+        ));
+        let pinned = hashes.get(&29).cloned().unwrap();
+        db.pin_revision(pinned.clone()).unwrap();
+        assert_eq!(db.pinned_revisions(), std::slice::from_ref(&pinned));
+        let held = db.revision(commit(&db, 30)).unwrap();
+        for value in 31..=34 {
+            commit(&db, value);
+        }
+        assert_eq!(numbers(db.retained_revisions()), [28, 29, 30, 32, 33, 34]);
+        assert_eq!(numbers(db.stuck_revisions()), [30]);
+        assert!(db.unpin_revision(&pinned));
+        assert!(!db.unpin_revision(&pinned));
+        assert!(db.pinned_revisions().is_empty());
+        drop(held);
+        commit(&db, 35);
+        assert_eq!(numbers(db.retained_revisions()), [28, 32, 34, 35]);
+        assert!(db.stuck_revisions().is_empty());
+        check(&db);
+
+        // an archival database never reaps
+        let db = TestDb::new_with_config(DbConfig::builder().retention(Arc::new(Archival)).build());
+        let mut hashes = Vec::new();
+        for value in 1..=5 {
+            hashes.push(commit(&db, value));
+        }
+        assert_eq!(numbers(db.retained_revisions()), [0, 1, 2, 3, 4, 5]);
+        for hash in hashes {
+            assert!(db.revision(hash).is_ok());
+        }
+        check(&db);
+    }
+
+    #[test]
+    fn test_background_persist_failure_is_sticky() {
+        let db = TestDb::with_mockstore(MockStore::with_failures());
+
+        let proposal = db
+            .propose(vec![BatchOp::Put {
+                key: b"k",
+                value: b"v",
+            }])
+            .unwrap();
+        proposal.commit().unwrap();
+
+        assert!(matches!(db.flush(), Err(api::Error::RootStoreError(_))));
+        assert!(matches!(db.flush(), Err(api::Error::PersistFailed)));
+
+        let proposal = db
+            .propose(vec![BatchOp::Put {
+                key: b"k",
+                value: b"v2",
+            }])
+            .unwrap();
+        assert!(matches!(proposal.commit(), Err(api::Error::PersistFailed)));
+    }
+
+    #[test]
+    fn test_iter_rev_on_revision_and_proposal() {
+        let db = TestDb::new();
+
+        let keys: Vec<[u8; 2]> = (0u16..300).map(u16::to_be_bytes).collect();
+        db.propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap()
+            .commit()
+            .unwrap();
+        let revision = db.revision(db.root_hash().unwrap().unwrap()).unwrap();
+
+        // the latest 5 entries under the prefix 0x00
+        let latest: Vec<_> = revision
+            .iter_rev_from([0x00, 0xFF])
+            .unwrap()
+            .take(5)
+            .map(|kv| kv.unwrap().0)
+            .collect();
+        let expected: Vec<_> = (251u16..=255).rev().map(u16::to_be_bytes).collect();
+        assert_eq!(
+            latest,
+            expected
+                .iter()
+                .map(|k| Box::from(&k[..]))
+                .collect::<Vec<_>>()
+        );
+
+        // a proposal sees its own changes in descending order too
+        let proposal = db
+            .propose(vec![
+                BatchOp::Delete { key: [0x01, 0x2B] },
+                BatchOp::Put {
+                    key: [0x01, 0x2C],
+                    value: [0x01, 0x2C],
+                },
+            ])
+            .unwrap();
+        let keys_rev: Vec<_> = proposal
+            .iter_rev()
+            .unwrap()
+            .take(2)
+            .map(|kv| kv.unwrap().0)
+            .collect();
+        assert_eq!(
+            keys_rev,
+            vec![Box::from([0x01, 0x2C]), Box::from([0x01, 0x2A])]
+        );
+        assert_eq!(
+            proposal.iter_rev().unwrap().count(),
+            revision.iter().unwrap().count()
+        );
+    }
+
+    #[test]
+    fn test_iteration_modes_on_every_iterator() {
+        use crate::v2::api::{IterationMode, KeyValueIter as _};
+        use sha2::{Digest, Sha256};
+
+        let db = TestDb::new();
+        let keys: Vec<[u8; 2]> = (0u16..300).map(u16::to_be_bytes).collect();
+        db.propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap()
+            .commit()
+            .unwrap();
+        let revision = db.revision(db.root_hash().unwrap().unwrap()).unwrap();
+
+        let prefixed: Vec<_> = revision
+            .iter_prefix([0x01])
+            .unwrap()
+            .with_mode(IterationMode::KeysOnly)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(prefixed.len(), 44);
+        assert!(prefixed.iter().all(|(_, value)| value.is_empty()));
+
+        let descending: Vec<_> = revision
+            .iter_rev()
+            .unwrap()
+            .with_mode(IterationMode::ValueDigests)
+            .take(2)
+            .map(Result::unwrap)
+            .collect();
+        for (key, digest) in &descending {
+            assert_eq!(**digest, *Sha256::digest(key));
+        }
+
+        // the mode can change part way through, including on a boxed iterator
+        let mut iter = api::DynDbView::iter_from(&*revision, &[0x00, 0x10]).unwrap();
+        assert_eq!(&*iter.next().unwrap().unwrap().1, &[0x00, 0x10]);
+        iter.set_mode(IterationMode::KeysOnly);
+        let (key, value) = iter.next().unwrap().unwrap();
+        assert_eq!(&*key, &[0x00, 0x11]);
+        assert!(value.is_empty());
+    }
+
+    /// Adds little-endian `u64` values, treating a missing value as zero.
+    #[derive(Debug)]
+    struct AddU64;
+
+    impl api::MergeOperator for AddU64 {
+        fn merge(
+            &self,
+            _key: &[u8],
+            existing: Option<&[u8]>,
+            operand: &[u8],
+        ) -> crate::merkle::Value {
+            let parse = |bytes: Option<&[u8]>| {
+                bytes
+                    .and_then(|bytes| <[u8; 8]>::try_from(bytes).ok())
+                    .map_or(0, u64::from_le_bytes)
+            };
+            parse(existing)
+                .wrapping_add(parse(Some(operand)))
+                .to_le_bytes()
+                .into()
+        }
+    }
+
+    #[test]
+    fn test_merge_operator() {
+        type Op = BatchOp<&'static [u8], [u8; 8]>;
+        let merge = |key: &'static [u8], n: u64| Op::Merge {
+            key,
+            operand: n.to_le_bytes(),
+        };
+
+        for use_parallel in [UseParallel::Never, UseParallel::Always] {
+            let db = TestDb::new_with_config(
+                DbConfig::builder()
+                    .use_parallel(use_parallel.clone())
+                    .merge_operator(Arc::new(AddU64))
+                    .build(),
+            );
+            db.propose(vec![Op::Put {
+                key: b"a",
+                value: 1u64.to_le_bytes(),
+            }])
+            .unwrap()
+            .commit()
+            .unwrap();
+
+            let proposal = db
+                .propose(vec![
+                    merge(b"a", 2),
+                    merge(b"a", 3),
+                    merge(b"b", 5),
+                    merge(b"", 7),
+                ])
+                .unwrap();
+            for (key, expected) in [(&b"a"[..], 6u64), (b"b", 5), (b"", 7)] {
+                assert_eq!(
+                    proposal.val(key).unwrap().as_deref(),
+                    Some(&expected.to_le_bytes()[..]),
+                );
+            }
+
+            let db = TestDb::new_with_config(
+                DbConfig::builder()
+                    .use_parallel(use_parallel.clone())
+                    .build(),
+            );
+            assert!(matches!(
+                db.propose(vec![merge(b"a", 1)]),
+                Err(api::Error::NoMergeOperator)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_delete_key_range() {
+        for use_parallel in [UseParallel::Never, UseParallel::Always] {
+            let db = TestDb::new_with_config(
+                DbConfig::builder()
+                    .use_parallel(use_parallel.clone())
+                    .build(),
+            );
+            let keys: [&[u8]; 7] = [b"", b"a", b"ab", b"b", b"ba", b"c", b"d"];
+            db.propose(keys.iter().map(|key| (key, b"v")))
+                .unwrap()
+                .commit()
+                .unwrap();
+
+            let remaining = |proposal: &Proposal<'_>| {
+                keys.iter()
+                    .filter(|key| proposal.val(key).unwrap().is_some())
+                    .copied()
+                    .collect::<Vec<_>>()
+            };
+
+            let proposal = db
+                .propose(vec![BatchOp::<&[u8], &[u8]>::DeleteKeyRange {
+                    start: b"ab",
+                    end: Some(b"c"),
+                }])
+                .unwrap();
+            assert_eq!(remaining(&proposal), [&b""[..], b"a", b"c", b"d"]);
+
+            let proposal = db
+                .propose(vec![BatchOp::<&[u8], &[u8]>::DeleteKeyRange {
+                    start: b"",
+                    end: Some(b"b"),
+                }])
+                .unwrap();
+            assert_eq!(remaining(&proposal), [&b"b"[..], b"ba", b"c", b"d"]);
+
+            let proposal = db
+                .propose(vec![BatchOp::<&[u8], &[u8]>::DeleteKeyRange {
+                    start: b"b",
+                    end: None,
+                }])
+                .unwrap();
+            assert_eq!(remaining(&proposal), [&b""[..], b"a", b"ab"]);
+
+            let (proposal, removed) = db.propose_delete_range(&b"a"[..], Some(b"ba")).unwrap();
+            assert_eq!(removed, 3);
+            assert_eq!(remaining(&proposal), [&b""[..], b"ba", b"c", b"d"]);
+        }
+    }
+
+    #[test]
+    fn test_conditional_batch_ops() {
+        type Op<'a> = BatchOp<&'a [u8], &'a [u8]>;
+
+        for use_parallel in [UseParallel::Never, UseParallel::Always] {
+            let db = TestDb::new_with_config(
+                DbConfig::builder()
+                    .use_parallel(use_parallel.clone())
+                    .build(),
+            );
+            db.propose(vec![BatchOp::Put {
+                key: b"a",
+                value: b"1",
+            }])
+            .unwrap()
+            .commit()
+            .unwrap();
+
+            let batch: Vec<Op<'_>> = vec![
+                BatchOp::PutIfAbsent {
+                    key: b"b",
+                    value: b"2",
+                },
+                BatchOp::CompareAndSwap {
+                    key: b"a",
+                    expected: b"1",
+                    value: b"3",
+                },
+                // sees the swap made earlier in the batch
+                BatchOp::DeleteIfEquals {
+                    key: b"a",
+                    expected: b"3",
+                },
+                BatchOp::PutIfAbsent {
+                    key: b"",
+                    value: b"root",
+                },
+            ];
+            let proposal = db.propose(batch).unwrap();
+            assert_eq!(proposal.val(b"a").unwrap(), None);
+            assert_eq!(proposal.val(b"b").unwrap().as_deref(), Some(&b"2"[..]));
+            assert_eq!(proposal.val(b"").unwrap().as_deref(), Some(&b"root"[..]));
+
+            // each failing operation, with the value its key had
+            let failures: [(Op<'_>, Option<&[u8]>); 4] = [
+                (
+                    BatchOp::PutIfAbsent {
+                        key: b"a",
+                        value: b"4",
+                    },
+                    Some(b"1"),
+                ),
+                (
+                    BatchOp::CompareAndSwap {
+                        key: b"a",
+                        expected: b"2",
+                        value: b"4",
+                    },
+                    Some(b"1"),
+                ),
+                (
+                    BatchOp::DeleteIfEquals {
+                        key: b"c",
+                        expected: b"1",
+                    },
+                    None,
+                ),
+                (
+                    BatchOp::CompareAndSwap {
+                        key: b"",
+                        expected: b"x",
+                        value: b"y",
+                    },
+                    None,
+                ),
+            ];
+            for (op, found) in failures {
+                let Err(api::Error::ConditionFailed(failed)) = db.propose(vec![op]) else {
+                    panic!("{op:?} should fail with {use_parallel:?}");
+                };
+                assert_eq!(*failed.key, **op.key());
+                assert_eq!(failed.expected.as_deref(), op.expected().copied());
+                assert_eq!(failed.found.as_deref(), found);
+            }
+        }
+    }
+
+    #[test]
+    fn test_proposal_diff() {
+        use crate::v2::api::KeyChange;
+
+        let db = TestDb::new();
+        let empty = db.propose(Vec::<BatchOp<&[u8], &[u8]>>::new()).unwrap();
+        assert_eq!(empty.diff().unwrap().count(), 0);
+
+        let first = db
+            .propose(vec![
+                BatchOp::Put {
+                    key: b"a",
+                    value: b"1",
+                },
+                BatchOp::Put {
+                    key: b"b",
+                    value: b"2",
+                },
+            ])
+            .unwrap();
+        let inserted: Vec<_> = first.diff().unwrap().map(Result::unwrap).collect();
+        assert_eq!(
+            inserted.iter().map(KeyChange::key).collect::<Vec<_>>(),
+            [b"a", b"b"]
+        );
+
+        // on top of a proposal
+        let second = first
+            .propose(vec![
+                BatchOp::Put {
+                    key: b"a",
+                    value: b"3",
+                },
+                BatchOp::Delete { key: b"b" },
+                BatchOp::Put {
+                    key: b"c",
+                    value: b"4",
+                },
+            ])
+            .unwrap();
+        let expected = vec![
+            KeyChange::Updated {
+                key: Box::from(&b"a"[..]),
+                old: Box::from(&b"1"[..]),
+                new: Box::from(&b"3"[..]),
+            },
+            KeyChange::Deleted {
+                key: Box::from(&b"b"[..]),
+                old: Box::from(&b"2"[..]),
+            },
+            KeyChange::Inserted {
+                key: Box::from(&b"c"[..]),
+                value: Box::from(&b"4"[..]),
+            },
+        ];
+        let changes: Vec<_> = second.diff().unwrap().map(Result::unwrap).collect();
+        assert_eq!(changes, expected);
+
+        // on top of a committed revision, once its parent is committed
+        first.commit().unwrap();
+        let changes: Vec<_> = second.diff().unwrap().map(Result::unwrap).collect();
+        assert_eq!(changes, expected);
+    }
+
+    #[test]
+    fn test_proposal_rebase() {
+        use super::RebaseConflicts;
+
+        type Op = BatchOp<&'static [u8], &'static [u8]>;
+
+        let db = TestDb::new();
+        db.propose(vec![
+            Op::Put {
+                key: b"a",
+                value: b"1",
+            },
+            Op::Put {
+                key: b"b",
+                value: b"1",
+            },
+        ])
+        .unwrap()
+        .commit()
+        .unwrap();
+
+        let ours = db
+            .propose(vec![
+                Op::Put {
+                    key: b"a",
+                    value: b"2",
+                },
+                Op::Delete { key: b"b" },
+                Op::Put {
+                    key: b"c",
+                    value: b"2",
+                },
+            ])
+            .unwrap();
+        db.propose(vec![
+            Op::Put {
+                key: b"b",
+                value: b"3",
+            },
+            Op::Put {
+                key: b"d",
+                value: b"3",
+            },
+        ])
+        .unwrap()
+        .commit()
+        .unwrap();
+        let new_parent = db.root_hash().unwrap().unwrap();
+
+        let err = ours
+            .rebase(new_parent.clone(), RebaseConflicts::Report)
+            .unwrap_err();
+        assert!(
+            matches!(&err, api::Error::RebaseConflict { keys } if keys == &[Box::from(&b"b"[..])]),
+            "{err:?}"
+        );
+
+        let rebased = ours.rebase(new_parent, RebaseConflicts::Overwrite).unwrap();
+        drop(ours);
+        for (key, expected) in [
+            (&b"a"[..], Some(&b"2"[..])),
+            (b"b", None),
+            (b"c", Some(b"2")),
+            (b"d", Some(b"3")),
+        ] {
+            assert_eq!(rebased.val(key).unwrap().as_deref(), expected);
+        }
+        rebased.commit().unwrap();
+    }
+
+    #[test]
+    fn test_rebase_after_parent_would_be_reaped() {
+        use super::RebaseConflicts;
+
+        let dbconfig = DbConfig::builder()
+            .manager(RevisionManagerConfig::builder().max_revisions(2).build())
+            .build();
+        let db = TestDb::new_with_config(dbconfig);
+        db.propose(vec![BatchOp::Put {
+            key: b"a",
+            value: b"1",
+        }])
+        .unwrap()
+        .commit()
+        .unwrap();
+        let parent = db.root_hash().unwrap().unwrap();
+
+        let ours = db
+            .propose(vec![BatchOp::Put {
+                key: b"ours",
+                value: b"1",
+            }])
+            .unwrap();
+
+        // enough commits that the parent of `ours` falls out of `max_revisions`
+        for i in 0u32..5 {
+            db.propose(vec![BatchOp::Put {
+                key: b"k",
+                value: i.to_be_bytes(),
+            }])
+            .unwrap()
+            .commit()
+            .unwrap();
+            db.flush().unwrap();
+        }
+        // the proposal holds its parent, so it is still there
+        assert!(db.revision(parent).is_ok());
+
+        let new_parent = db.root_hash().unwrap().unwrap();
+        let rebased = ours.rebase(new_parent, RebaseConflicts::Report).unwrap();
+        drop(ours);
+        assert_eq!(rebased.val(b"ours").unwrap().as_deref(), Some(&b"1"[..]));
+        assert_eq!(rebased.val(b"a").unwrap().as_deref(), Some(&b"1"[..]));
+        assert_eq!(
+            rebased.val(b"k").unwrap().as_deref(),
+            Some(&4u32.to_be_bytes()[..])
+        );
+        rebased.commit().unwrap();
+    }
+
+    #[test]
+    fn test_rebase_rejects_uncommitted_parent() {
+        use super::RebaseConflicts;
+
+        let db = TestDb::new();
+        let parent = db
+            .propose(vec![BatchOp::Put {
+                key: b"a",
+                value: b"1",
+            }])
+            .unwrap();
+        let child = parent
+            .propose(vec![BatchOp::Put {
+                key: b"b",
+                value: b"1",
+            }])
+            .unwrap();
+        db.propose(vec![BatchOp::Put {
+            key: b"c",
+            value: b"1",
+        }])
+        .unwrap()
+        .commit()
+        .unwrap();
+        let new_parent = db.root_hash().unwrap().unwrap();
+
+        let err = child
+            .rebase(new_parent.clone(), RebaseConflicts::Overwrite)
+            .unwrap_err();
+        assert!(
+            matches!(err, api::Error::RebaseUncommittedParent),
+            "{err:?}"
+        );
+        drop(parent);
+    }
+
+    #[test]
+    fn test_proposal_export_import() {
+        type Op = BatchOp<&'static [u8], &'static [u8]>;
+
+        let db = TestDb::new();
+        db.propose(vec![
+            Op::Put {
+                key: b"a",
+                value: b"1",
+            },
+            Op::Put {
+                key: b"b",
+                value: b"1",
+            },
+        ])
+        .unwrap()
+        .commit()
+        .unwrap();
+
+        let first = db
+            .propose(vec![
+                Op::Put {
+                    key: b"a",
+                    value: b"2",
+                },
+                Op::Delete { key: b"b" },
+                Op::Put {
+                    key: b"c",
+                    value: b"",
+                },
+            ])
+            .unwrap();
+        let second = first
+            .propose(vec![Op::Put {
+                key: b"d",
+                value: b"3",
+            }])
+            .unwrap();
+        let exported = [first.export().unwrap(), second.export().unwrap()];
+        let roots = [first.root_hash().unwrap(), second.root_hash().unwrap()];
+        drop(second);
+        drop(first);
+
+        // the proposals are gone after a restart
+        let db = db.reopen();
+
+        // the second proposal needs its parent
+        assert!(matches!(
+            db.import_proposal(&exported[1]),
+            Err(api::Error::RevisionNotFound { .. })
+        ));
+
+        let first = db.import_proposal(&exported[0]).unwrap();
+        assert_eq!(first.root_hash().unwrap(), roots[0]);
+        assert_eq!(first.val(b"c").unwrap().as_deref(), Some(&b""[..]));
+        let second = db.import_proposal(&exported[1]).unwrap();
+        assert_eq!(second.root_hash().unwrap(), roots[1]);
+
+        // a different parent leads to a different root hash
+        let mut mismatched = exported[0].clone();
+        let parent_hash = mismatched.get_mut(10..42).unwrap();
+        parent_hash.copy_from_slice(roots[1].as_ref().unwrap().as_ref());
+        assert!(matches!(
+            db.import_proposal(&mismatched),
+            Err(api::Error::ImportRootMismatch { .. })
+        ));
+
+        assert!(matches!(
+            db.import_proposal(b"not a proposal"),
+            Err(api::Error::ProposalImport(_))
+        ));
+
+        first.commit().unwrap();
+        second.commit().unwrap();
+    }
+
+    #[test]
+    fn test_compute_root() {
+        let batch = |n: u8| -> Vec<_> {
+            (0..20u8)
+                .map(|i| BatchOp::Put {
+                    key: [i, n],
+                    value: [n],
+                })
+                .collect()
+        };
+
+        for use_parallel in [UseParallel::Never, UseParallel::Always] {
+            let db = TestDb::new_with_config(
+                DbConfig::builder()
+                    .use_parallel(use_parallel.clone())
+                    .build(),
+            );
+            db.propose(batch(0)).unwrap().commit().unwrap();
+            let committed = db.root_hash().unwrap();
+            let revisions = db.manager.all_hashes().len();
+
+            let computed = db.compute_root(None, batch(1)).unwrap();
+            assert_eq!(db.manager.all_hashes().len(), revisions);
+            let proposal = db.propose(batch(1)).unwrap();
+            assert_eq!(computed, proposal.root_hash().unwrap());
+
+            // on top of an uncommitted proposal
+            let computed = db.compute_root(computed, batch(2)).unwrap();
+            assert_eq!(
+                computed,
+                proposal.propose(batch(2)).unwrap().root_hash().unwrap()
+            );
+
+            // a committed revision is the same as itself
+            let unchanged = db
+                .compute_root(committed.clone(), Vec::<BatchOp<[u8; 1], [u8; 1]>>::new())
+                .unwrap();
+            assert_eq!(unchanged, committed);
+        }
+    }
+
+    #[test]
+    fn test_bulk_load() {
+        let pairs: Vec<([u8; 2], [u8; 2])> = (0u16..1000)
+            .map(|i| (i.to_be_bytes(), (i % 7).to_be_bytes()))
+            .collect();
+
+        let inserted = TestDb::new();
+        let batch = pairs.iter().map(|(key, value)| BatchOp::Put { key, value });
+        inserted
+            .propose(batch.collect::<Vec<_>>())
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        let db = TestDb::new();
+        let proposal = db
+            .bulk_load(pairs.iter().copied().map(Ok::<_, api::Error>))
+            .unwrap();
+        assert_eq!(proposal.root_hash().unwrap(), inserted.root_hash().unwrap());
+        proposal.commit().unwrap();
+        assert_eq!(db.root_hash().unwrap(), inserted.root_hash().unwrap());
+        let db = db.reopen();
+        assert_eq!(db.root_hash().unwrap(), inserted.root_hash().unwrap());
+        assert_eq!(
+            *db.revision(db.root_hash().unwrap().unwrap())
+                .unwrap()
+                .val([0, 9])
+                .unwrap()
+                .unwrap(),
+            [0, 2]
+        );
+
+        assert!(matches!(
+            db.bulk_load(pairs.iter().copied().map(Ok::<_, api::Error>)),
+            Err(api::Error::BulkLoadNotEmpty)
+        ));
+
+        // a revision persisted while the proposal is outstanding does not
+        // allocate over the nodes it has written
+        let db = TestDb::new();
+        let proposal = db
+            .bulk_load(pairs.iter().copied().map(Ok::<_, api::Error>))
+            .unwrap();
+        db.propose(vec![BatchOp::Put {
+            key: [0u8, 9],
+            value: [9u8; 64],
+        }])
+        .unwrap()
+        .commit()
+        .unwrap();
+        db.manager.flush().unwrap();
+        assert_eq!(*proposal.val([0, 9]).unwrap().unwrap(), [0, 2]);
+        assert_eq!(*proposal.val([3, 231]).unwrap().unwrap(), [0, 5]);
+
+        let unsorted = [([1u8], [1u8]), ([0], [0])];
+        assert!(matches!(
+            TestDb::new().bulk_load(unsorted.into_iter().map(Ok::<_, api::Error>)),
+            Err(api::Error::UnsortedBulkLoad { .. })
+        ));
+    }
+
+    #[test]
+    fn test_backup_to() {
+        let db = TestDb::new();
+        for i in 0u8..10 {
+            // 32 bytes long so that the checker accepts the keys with ethhash
+            let batch: Vec<_> = (0u8..50)
+                .map(|j| {
+                    let mut key = [j; 32];
+                    *key.last_mut().unwrap() = i % 3;
+                    BatchOp::Put { key, value: [i] }
+                })
+                .collect();
+            db.propose(batch).unwrap().commit().unwrap();
+        }
+        let root = db.root_hash().unwrap().unwrap();
+        let revision = db.revision(root.clone()).unwrap();
+
+        let path = db.path().with_extension("backup");
+        let copied = db.backup_to(&path, root.clone()).unwrap();
+        assert!(copied.nodes > 0);
+
+        // later commits do not change the backup
+        db.propose(vec![BatchOp::<_, [u8; 1]>::Delete { key: [0u8; 32] }])
+            .unwrap()
+            .commit()
+            .unwrap();
+
+        // the backup holds one revision, the database holds all of them
+        db.manager.flush().unwrap();
+        let size = |path| std::fs::metadata(path).unwrap().len();
+        assert!(size(&path) < size(&db.path()));
+        // an existing destination is refused and left as it was
+        let backup_size = std::fs::metadata(&path).unwrap().len();
+        assert!(matches!(
+            db.backup_to(&path, root.clone()),
+            Err(api::Error::IO(err)) if err.kind() == std::io::ErrorKind::AlreadyExists
+        ));
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), backup_size);
+
+        let backup = Db::new(&path, DbConfig::builder().create_if_missing(false).build()).unwrap();
+        assert_eq!(backup.root_hash().unwrap(), Some(root.clone()));
+        let restored = backup.revision(root).unwrap();
+        let expected: Vec<_> = revision.iter().unwrap().map(Result::unwrap).collect();
+        let found: Vec<_> = restored.iter().unwrap().map(Result::unwrap).collect();
+        assert_eq!(found, expected);
+
+        let report = backup.check(CheckOpt {
+            hash_check: true,
+            progress_bar: None,
+        });
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+    }
+
+    #[test]
+    #[expect(clippy::too_many_lines)]
+    fn test_incremental_backup() {
+        use crate::v2::api::BackupError;
+
+        // keys are 32 bytes long so that the checker accepts them with ethhash
+        let key = |hi: u8, lo: u8| {
+            let mut key = [0u8; 32];
+            key[0] = hi;
+            key[1] = lo;
+            key
+        };
+        let db = TestDb::new();
+        let mut roots = Vec::new();
+        let keys: Vec<_> = (0u16..500)
+            .map(|i| {
+                let [hi, lo] = i.to_be_bytes();
+                key(hi, lo)
+            })
+            .collect();
+        db.propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap()
+            .commit()
+            .unwrap();
+        roots.push(db.root_hash().unwrap().unwrap());
+        for i in 1u8..3 {
+            let batch = vec![
+                BatchOp::Put {
+                    key: key(0x01, i),
+                    value: [i],
+                },
+                BatchOp::Put {
+                    key: key(0xff, i),
+                    value: [i],
+                },
+            ];
+            db.propose(batch).unwrap().commit().unwrap();
+            roots.push(db.root_hash().unwrap().unwrap());
+        }
+
+        let path = |name: &str| db.path().with_extension(name);
+        let full = db
+            .incremental_backup_to(path("0"), None, roots.first().unwrap().clone())
+            .unwrap();
+        let mut backups = vec![path("0")];
+        for (i, pair) in roots.windows(2).enumerate() {
+            let [base, root] = pair else { unreachable!() };
+            let backup = path(&(i + 1).to_string());
+            let written = db
+                .incremental_backup_to(&backup, Some(base.clone()), root.clone())
+                .unwrap();
+            assert!(written.nodes > 0);
+            assert!(written.nodes < full.nodes / 10, "{written:?} {full:?}");
+            backups.push(backup);
+        }
+        let latest = roots.last().unwrap().clone();
+
+        let restored = path("restored");
+        let root = crate::backup::restore(&restored, &backups).unwrap();
+        assert_eq!(root, Some(latest.clone()));
+        let backup = Db::new(
+            &restored,
+            DbConfig::builder().create_if_missing(false).build(),
+        )
+        .unwrap();
+        let expected: Vec<_> = db
+            .revision(latest.clone())
+            .unwrap()
+            .iter()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        let found: Vec<_> = backup
+            .revision(latest)
+            .unwrap()
+            .iter()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(found, expected);
+
+        // the space the restore left unused is on the free lists rather than
+        // leaked
+        let report = backup.check(CheckOpt {
+            hash_check: true,
+            progress_bar: None,
+        });
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert!(
+            report
+                .db_stats
+                .free_list_stats
+                .area_counts
+                .values()
+                .any(|&count| count > 0)
+        );
+        drop(backup);
+
+        // a chain must be replayed in order
+        std::fs::remove_file(&restored).unwrap();
+        assert!(matches!(
+            crate::backup::restore(&restored, backups.iter().step_by(2)),
+            Err(api::Error::Backup(BackupError::BaseMismatch { .. }))
+        ));
+
+        let first = backups.first().unwrap();
+        let mut corrupt = std::fs::read(first).unwrap();
+        *corrupt.last_mut().unwrap() ^= 1;
+        std::fs::write(first, corrupt).unwrap();
+        std::fs::remove_file(&restored).unwrap();
+        assert!(matches!(
+            crate::backup::restore(&restored, [first]),
+            Err(api::Error::Backup(BackupError::ChecksumMismatch))
+        ));
+    }
+
+    #[test]
+    fn test_incremental_backup_base_must_be_retained() {
+        let db = TestDb::with_mockstore(MockStore::default());
+        let mut roots = Vec::new();
+        for value in 0u8..2 {
+            db.propose(vec![BatchOp::Put {
+                key: [0u8; 32],
+                value: [value],
+            }])
+            .unwrap()
+            .commit()
+            .unwrap();
+            roots.push(db.root_hash().unwrap().unwrap());
+        }
+        let [first, latest] = <[_; 2]>::try_from(roots).unwrap();
+
+        // only the latest revision is retained once reopened; the first is
+        // still in the root store, but its nodes may have been reused
+        let db = db.reopen();
+        assert!(db.view(first.clone()).is_ok());
+        let path = db.path().with_extension("incr");
+        assert!(matches!(
+            db.incremental_backup_to(&path, Some(first), latest.clone()),
+            Err(api::Error::RevisionNotFound { .. })
+        ));
+        db.incremental_backup_to(&path, Some(latest.clone()), latest)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_revision_diff() {
+        use crate::v2::api::KeyChange;
+
+        let db = TestDb::new();
+        let keys: Vec<[u8; 2]> = (0u16..1000).map(u16::to_be_bytes).collect();
+        db.propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap()
+            .commit()
+            .unwrap();
+        let from = db.root_hash().unwrap().unwrap();
+        db.propose(vec![
+            BatchOp::Put {
+                key: [0x01, 0x00],
+                value: [0xff],
+            },
+            BatchOp::Delete { key: [0x02, 0x00] },
+        ])
+        .unwrap()
+        .commit()
+        .unwrap();
+        let to = db.root_hash().unwrap().unwrap();
+
+        let changes: Vec<_> = db
+            .diff::<&[u8]>(from.clone(), to.clone(), ..)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                KeyChange::Updated {
+                    key: Box::new([0x01, 0x00]),
+                    old: Box::new([0x01, 0x00]),
+                    new: Box::new([0xff]),
+                },
+                KeyChange::Deleted {
+                    key: Box::new([0x02, 0x00]),
+                    old: Box::new([0x02, 0x00]),
+                },
+            ]
+        );
+
+        // the range leaves out the deletion
+        let changes: Vec<_> = db
+            .diff(
+                from.clone(),
+                to.clone(),
+                [0x00].as_slice()..[0x02].as_slice(),
+            )
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes.first().unwrap().key(), [0x01, 0x00]);
+
+        // a revision is the same as itself
+        assert_eq!(db.diff::<&[u8]>(to.clone(), to, ..).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_val_many_on_revision_and_proposal() {
+        let db = TestDb::new();
+
+        let keys: Vec<[u8; 2]> = (0u16..300).map(u16::to_be_bytes).collect();
+        db.propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap()
+            .commit()
+            .unwrap();
+        let revision = db.revision(db.root_hash().unwrap().unwrap()).unwrap();
+
+        // unsorted, with a duplicate and a missing key
+        let lookup: Vec<&[u8]> = vec![&[0x01, 0x2B], &[0x00, 0x07], &[0xFF], &[0x00, 0x07]];
+        let expected = vec![
+            Some(Box::from([0x01, 0x2B])),
+            Some(Box::from([0x00, 0x07])),
+            None,
+            Some(Box::from([0x00, 0x07])),
+        ];
+        assert_eq!(revision.val_many(&lookup).unwrap(), expected);
+
+        // proposals search on the database's thread pool and see their own changes
+        let proposal = db
+            .propose(vec![BatchOp::Delete::<_, [u8; 0]> { key: [0x01, 0x2B] }])
+            .unwrap();
+        let values = proposal.val_many(&keys).unwrap();
+        assert_eq!(values.len(), keys.len());
+        for (key, value) in keys.iter().zip(values) {
+            let expected = (key != &[0x01, 0x2B]).then(|| Box::from(&key[..]));
+            assert_eq!(value, expected);
+        }
+    }
+
+    #[test]
+    fn test_durability_builder_setter() {
+        let config = DbConfig::builder().durability(Durability::NoSync).build();
+        assert_eq!(config.storage.durability, Durability::NoSync);
+
+        // setting the durability keeps the rest of an earlier storage configuration
+        let storage = StorageConfig {
+            ring_size: 8,
+            ..StorageConfig::default()
+        };
+        let config = DbConfig::builder()
+            .storage(storage)
+            .durability(Durability::SyncOnCommit)
+            .build();
+        assert_eq!(config.storage.ring_size, 8);
+        assert_eq!(config.storage.durability, Durability::SyncOnCommit);
+    }
+
+    #[test]
+    fn test_tuned_storage_roundtrip() {
+        // a tiny ring and small batches force the ring to fill and wrap
+        let storage = StorageConfig {
+            ring_size: 2,
+            sqpoll_idle_ms: 10,
+            batch_bytes: 256,
+            coalesce_writes: true,
+            ..StorageConfig::default()
+        };
+        let db = TestDb::new_with_config(DbConfig::builder().storage(storage).build());
+
+        let keys: Vec<[u8; 4]> = (0u32..500).map(u32::to_be_bytes).collect();
+        let proposal = db
+            .propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap();
+        proposal.commit().unwrap();
+        db.flush().unwrap();
+
+        let root_hash = db.root_hash().unwrap();
+        let db =
+            db.reopen_with_config(DbConfig::builder().truncate(false).storage(storage).build());
+        assert_eq!(db.root_hash().unwrap(), root_hash);
+        let latest = db.revision(root_hash.unwrap()).unwrap();
+        for key in &keys {
+            assert_eq!(&*latest.val(key).unwrap().unwrap(), key);
+        }
+    }
+
+    #[test]
+    fn test_cache_warming_hot_nodes() {
+        let config = |background| {
+            let warming = CacheWarmingConfig::builder()
+                .levels(2)
+                .background(background)
+                .hot_nodes(true)
+                .build();
+            DbConfig::builder()
+                .truncate(false)
+                .manager(
+                    RevisionManagerConfig::builder()
+                        .cache_warming(warming)
+                        .build(),
+                )
+                .build()
+        };
+        let db = TestDb::new_with_config(config(false));
+        let hot_path = db.path().with_file_name("testdb.hot");
+
+        let keys: Vec<[u8; 4]> = (0u32..100).map(u32::to_be_bytes).collect();
+        let proposal = db
+            .propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap();
+        proposal.commit().unwrap();
+        assert!(!hot_path.exists());
+
+        // the cached addresses are saved on close and consumed on open
+        let db = db.reopen_with_config(config(true));
+        assert!(!hot_path.exists());
+        let db = db.reopen_with_config(config(false));
+        assert!(!hot_path.exists());
+
+        let latest = db.revision(db.root_hash().unwrap().unwrap()).unwrap();
+        for key in &keys {
+            assert_eq!(&*latest.val(key).unwrap().unwrap(), key);
+        }
+        drop(latest);
+        let TestDb { db, tmpdir } = db;
+        drop(db);
+        assert!(std::fs::metadata(&hot_path).unwrap().len() > 8);
+        drop(tmpdir);
+    }
+
+    #[test]
+    fn test_background_warming_does_not_block_reaping() {
+        let db = TestDb::new();
+        let keys: Vec<[u8; 4]> = (0u32..10_000).map(u32::to_be_bytes).collect();
+        db.propose(keys.iter().map(|key| BatchOp::Put { key, value: key }))
+            .unwrap()
+            .commit()
+            .unwrap();
+        let opened = db.root_hash().unwrap().unwrap();
+
+        let warming = CacheWarmingConfig::builder()
+            .levels(usize::MAX)
+            .background(true)
+            .build();
+        let db = db.reopen_with_config(
+            DbConfig::builder()
+                .truncate(false)
+                .manager(
+                    RevisionManagerConfig::builder()
+                        .max_revisions(2)
+                        .cache_warming(warming)
+                        .build(),
+                )
+                .build(),
+        );
+
+        // the revision being warmed is reaped once enough newer ones exist
+        for i in 0u32..2 {
+            db.propose(vec![BatchOp::Put {
+                key: b"k",
+                value: i.to_be_bytes(),
+            }])
+            .unwrap()
+            .commit()
+            .unwrap();
+            db.flush().unwrap();
+        }
+        assert!(db.revision(opened).is_err());
+    }
+
     // Testdb is a helper struct for testing the Db. Once it's dropped, the directory and file disappear
     struct TestDb {
         db: Db,
@@ -1126,7 +3431,7 @@ mod test {
                 .iter()
                 .collect();
             let dbconfig = DbConfig::builder().build();
-            let db = Db::with_root_store(dbpath, dbconfig, Box::new(mock_store)).unwrap();
+            let db = Db::with_root_store(dbpath, dbconfig, Arc::new(mock_store)).unwrap();
             TestDb { db, tmpdir }
         }
 
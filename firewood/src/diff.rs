@@ -0,0 +1,491 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! # Diff Module
+//!
+//! Lists the keys whose values differ between two tries, in ascending key
+//! order.
+//!
+//! Both tries are walked together from the root. When the child slots at the
+//! same path hold the same hash in both tries, the subtries below them are
+//! identical and are not read at all, so the cost of a diff depends on how
+//! much changed rather than on the size of the tries.
+
+use crate::iter::key_from_nibble_iter;
+use crate::merkle::{Key, Value};
+use crate::v2::api;
+use firewood_storage::{
+    Child, FileIoError, HashType, NibblesIterator, Node, PathComponent, SharedNode, TrieReader,
+};
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+/// A change to the value of one key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyChange {
+    /// The key was added.
+    Inserted {
+        /// The key
+        key: Key,
+        /// Its new value
+        value: Value,
+    },
+    /// The value of the key changed.
+    Updated {
+        /// The key
+        key: Key,
+        /// Its value before the change
+        old: Value,
+        /// Its value after the change
+        new: Value,
+    },
+    /// The key was removed.
+    Deleted {
+        /// The key
+        key: Key,
+        /// Its value before it was removed
+        old: Value,
+    },
+}
+
+impl KeyChange {
+    /// The key that changed
+    #[must_use]
+    pub fn key(&self) -> &[u8] {
+        match self {
+            Self::Inserted { key, .. } | Self::Updated { key, .. } | Self::Deleted { key, .. } => {
+                key
+            }
+        }
+    }
+
+    /// The value of the key before the change, if it had one
+    #[must_use]
+    pub fn old_value(&self) -> Option<&[u8]> {
+        match self {
+            Self::Inserted { .. } => None,
+            Self::Updated { old, .. } | Self::Deleted { old, .. } => Some(old),
+        }
+    }
+
+    /// The value of the key after the change, if it still has one
+    #[must_use]
+    pub fn new_value(&self) -> Option<&[u8]> {
+        match self {
+            Self::Inserted { value: new, .. } | Self::Updated { new, .. } => Some(new),
+            Self::Deleted { .. } => None,
+        }
+    }
+
+    /// The change from `old` to `new`, if they differ
+    fn between(nibbles: &[u8], old: Option<&[u8]>, new: Option<&[u8]>) -> Option<Self> {
+        let key = || key_from_nibble_iter(nibbles.iter().copied());
+        match (old, new) {
+            (None, None) => None,
+            (None, Some(value)) => Some(Self::Inserted {
+                key: key(),
+                value: value.into(),
+            }),
+            (Some(old), None) => Some(Self::Deleted {
+                key: key(),
+                old: old.into(),
+            }),
+            (Some(old), Some(new)) if old == new => None,
+            (Some(old), Some(new)) => Some(Self::Updated {
+                key: key(),
+                old: old.into(),
+                new: new.into(),
+            }),
+        }
+    }
+}
+
+/// The part of one trie found at the path of a [`Frame`].
+#[derive(Debug)]
+enum Side {
+    /// A child slot that has not been read yet.
+    Child(Child),
+    /// `node`, of which the first `consumed` nibbles of the partial path are
+    /// already part of the frame's path.
+    Node { node: SharedNode, consumed: usize },
+}
+
+impl Side {
+    /// The hash of everything below this side, if known without reading it
+    const fn hash(&self) -> Option<&HashType> {
+        match self {
+            Self::Child(child) => child.hash(),
+            Self::Node { .. } => None,
+        }
+    }
+
+    fn resolve<T: TrieReader>(self, trie: &T) -> Result<Expanded, FileIoError> {
+        let (node, consumed) = match self {
+            Self::Child(Child::AddressWithHash(addr, _)) => (trie.read_node(addr)?, 0),
+            Self::Child(Child::Node(node)) => (node.into(), 0),
+            Self::Child(Child::MaybePersisted(maybe_persisted, _)) => {
+                (maybe_persisted.as_shared_node(trie)?, 0)
+            }
+            Self::Node { node, consumed } => (node, consumed),
+        };
+        Ok(Expanded { node, consumed })
+    }
+}
+
+/// A [`Side`] whose node has been read.
+struct Expanded {
+    node: SharedNode,
+    consumed: usize,
+}
+
+impl Expanded {
+    /// The value stored exactly at the frame's path
+    fn value(&self) -> Option<&[u8]> {
+        if self.consumed == self.node.partial_path().len() {
+            self.node.value()
+        } else {
+            None
+        }
+    }
+
+    /// What this side holds one nibble further down, at `component`
+    fn child(&self, component: PathComponent) -> Option<Side> {
+        if let Some(&next) = self.node.partial_path().get(self.consumed) {
+            return (next == component.as_u8()).then(|| Side::Node {
+                node: self.node.clone(),
+                consumed: self.consumed.saturating_add(1),
+            });
+        }
+        match &*self.node {
+            Node::Branch(branch) => branch.children[component].clone().map(Side::Child),
+            Node::Leaf(_) => None,
+        }
+    }
+}
+
+/// A path, as nibbles, and what each trie holds there.
+#[derive(Debug)]
+struct Frame {
+    path: Vec<u8>,
+    left: Option<Side>,
+    right: Option<Side>,
+}
+
+/// An iterator over the [`KeyChange`]s that turn the `left` trie into the
+/// `right` trie, in ascending key order.
+#[derive(Debug)]
+pub struct DiffIter<L, R> {
+    left: L,
+    right: R,
+    stack: Vec<Frame>,
+    /// Only keys within these bounds, as nibbles, are listed
+    start: Bound<Vec<u8>>,
+    end: Bound<Vec<u8>>,
+}
+
+impl<L: TrieReader, R: TrieReader> DiffIter<L, R> {
+    /// Lists the changes from `left` to `right`.
+    pub fn new(left: L, right: R) -> Self {
+        let root = Frame {
+            path: Vec::new(),
+            left: left
+                .root_node()
+                .map(|node| Side::Node { node, consumed: 0 }),
+            right: right
+                .root_node()
+                .map(|node| Side::Node { node, consumed: 0 }),
+        };
+        Self {
+            left,
+            right,
+            stack: vec![root],
+            start: Bound::Unbounded,
+            end: Bound::Unbounded,
+        }
+    }
+
+    /// Only lists the changes to keys within `range`.
+    ///
+    /// Subtries that only hold keys outside of `range` are never read.
+    #[must_use]
+    pub fn with_range<K: AsRef<[u8]>>(mut self, range: impl RangeBounds<K>) -> Self {
+        let nibbles = |key: &K| NibblesIterator::new(key.as_ref()).collect();
+        self.start = range.start_bound().map(nibbles);
+        self.end = range.end_bound().map(nibbles);
+        self
+    }
+
+    /// Returns true if the key at `path` is within the range
+    fn contains(&self, path: &[u8]) -> bool {
+        let after_start = match &self.start {
+            Bound::Included(start) => path >= start.as_slice(),
+            Bound::Excluded(start) => path > start.as_slice(),
+            Bound::Unbounded => true,
+        };
+        after_start && self.before_end(path)
+    }
+
+    /// Returns true if some key starting with `path` may be within the range
+    fn overlaps(&self, path: &[u8]) -> bool {
+        let after_start = match &self.start {
+            Bound::Included(start) | Bound::Excluded(start) => {
+                start.starts_with(path) || path > start.as_slice()
+            }
+            Bound::Unbounded => true,
+        };
+        after_start && self.before_end(path)
+    }
+
+    fn before_end(&self, path: &[u8]) -> bool {
+        match &self.end {
+            Bound::Included(end) => path <= end.as_slice(),
+            Bound::Excluded(end) => path < end.as_slice(),
+            Bound::Unbounded => true,
+        }
+    }
+
+    /// Visits one frame, returning the change at its path, if any.
+    fn visit(&mut self, frame: Frame) -> Result<Option<KeyChange>, FileIoError> {
+        if !self.overlaps(&frame.path) {
+            return Ok(None);
+        }
+        if let (Some(left), Some(right)) = (&frame.left, &frame.right)
+            && left.hash().is_some()
+            && left.hash() == right.hash()
+        {
+            // identical subtries
+            return Ok(None);
+        }
+
+        let left = frame
+            .left
+            .map(|side| side.resolve(&self.left))
+            .transpose()?;
+        let right = frame
+            .right
+            .map(|side| side.resolve(&self.right))
+            .transpose()?;
+
+        // push the children in reverse, so the lowest is visited first
+        for component in PathComponent::ALL.into_iter().rev() {
+            let left_child = left.as_ref().and_then(|left| left.child(component));
+            let right_child = right.as_ref().and_then(|right| right.child(component));
+            if left_child.is_some() || right_child.is_some() {
+                let mut path = frame.path.clone();
+                path.push(component.as_u8());
+                self.stack.push(Frame {
+                    path,
+                    left: left_child,
+                    right: right_child,
+                });
+            }
+        }
+
+        if !self.contains(&frame.path) {
+            return Ok(None);
+        }
+        Ok(KeyChange::between(
+            &frame.path,
+            left.as_ref().and_then(Expanded::value),
+            right.as_ref().and_then(Expanded::value),
+        ))
+    }
+}
+
+impl<T: TrieReader + Clone> DiffIter<T, T> {
+    /// Lists the changes from an empty trie to `right`, which are all insertions.
+    pub fn from_empty(right: T) -> Self {
+        let mut iter = Self::new(right.clone(), right);
+        if let Some(root) = iter.stack.first_mut() {
+            root.left = None;
+        }
+        iter
+    }
+}
+
+impl<L: TrieReader, R: TrieReader> Iterator for DiffIter<L, R> {
+    type Item = Result<KeyChange, api::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(frame) = self.stack.pop() {
+            match self.visit(frame) {
+                Ok(Some(change)) => return Some(Ok(change)),
+                Ok(None) => {}
+                Err(err) => {
+                    self.stack.clear();
+                    return Some(Err(err.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<L: TrieReader, R: TrieReader> FusedIterator for DiffIter<L, R> {}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, clippy::arithmetic_side_effects)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::merkle::Merkle;
+    use crate::merkle::tests::init_merkle;
+    use firewood_storage::{
+        ImmutableProposal, LinearAddress, MaybePersistedNode, MemStore, NodeReader, NodeStore,
+        RootReader, SeededRng,
+    };
+    use std::sync::Arc;
+
+    type Proposal = Merkle<NodeStore<Arc<ImmutableProposal>, MemStore>>;
+
+    /// The changes from `old` to `new`, computed from their full contents
+    fn expected_changes(
+        old: &BTreeMap<Vec<u8>, Vec<u8>>,
+        new: &BTreeMap<Vec<u8>, Vec<u8>>,
+    ) -> Vec<KeyChange> {
+        let keys: std::collections::BTreeSet<_> = old.keys().chain(new.keys()).collect();
+        keys.into_iter()
+            .filter_map(|key| {
+                let nibbles: Vec<u8> = key.iter().flat_map(|b| [b >> 4, b & 0xf]).collect();
+                KeyChange::between(
+                    &nibbles,
+                    old.get(key).map(Vec::as_slice),
+                    new.get(key).map(Vec::as_slice),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    #[cfg(not(feature = "branch_factor_256"))]
+    fn diff_matches_contents() {
+        let rng = SeededRng::from_env_or_random();
+        let key = |rng: &SeededRng| -> Vec<u8> {
+            let len = rng.random_range(1..=4);
+            (0..len).map(|_| rng.random_range(0..4u8)).collect()
+        };
+
+        for _ in 0..20 {
+            let old: BTreeMap<Vec<u8>, Vec<u8>> = (0..50).map(|_| (key(&rng), key(&rng))).collect();
+            let base = init_merkle(old.clone());
+
+            let mut new = old.clone();
+            let mut proposal = base.fork().unwrap();
+            for _ in 0..10 {
+                let key = key(&rng);
+                if rng.random_range(0..3u8) == 0 {
+                    new.remove(&key);
+                    proposal.remove(&key).unwrap();
+                } else {
+                    let value = vec![rng.random_range(0..4u8)];
+                    proposal.insert(&key, value.clone().into()).unwrap();
+                    new.insert(key, value);
+                }
+            }
+            let proposal: Proposal = proposal.hash();
+
+            let changes: Vec<KeyChange> = DiffIter::new(base.nodestore(), proposal.nodestore())
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(changes, expected_changes(&old, &new));
+
+            // and back again
+            let changes: Vec<KeyChange> = DiffIter::new(proposal.nodestore(), base.nodestore())
+                .map(Result::unwrap)
+                .collect();
+            assert_eq!(changes, expected_changes(&new, &old));
+
+            // and within a range, with each kind of bound
+            let (low, high) = (key(&rng), key(&rng));
+            let ranges = [
+                (Bound::Included(&low), Bound::Excluded(&high)),
+                (Bound::Excluded(&low), Bound::Included(&high)),
+                (Bound::Unbounded, Bound::Included(&high)),
+                (Bound::Included(&low), Bound::Unbounded),
+            ];
+            for range in ranges {
+                let changes: Vec<KeyChange> = DiffIter::new(base.nodestore(), proposal.nodestore())
+                    .with_range::<Vec<u8>>(range)
+                    .map(Result::unwrap)
+                    .collect();
+                let mut expected = expected_changes(&old, &new);
+                expected.retain(|change| {
+                    RangeBounds::<Vec<u8>>::contains(&range, &change.key().to_vec())
+                });
+                assert_eq!(changes, expected, "{range:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn diff_from_empty_inserts_everything() {
+        let merkle = init_merkle([(b"a".as_slice(), b"1".as_slice()), (b"ab", b"2")]);
+        let changes: Vec<KeyChange> = DiffIter::from_empty(merkle.nodestore())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                KeyChange::Inserted {
+                    key: b"a".as_slice().into(),
+                    value: b"1".as_slice().into(),
+                },
+                KeyChange::Inserted {
+                    key: b"ab".as_slice().into(),
+                    value: b"2".as_slice().into(),
+                },
+            ]
+        );
+    }
+
+    /// Counts the nodes read from the trie it wraps
+    struct CountingReader<'a, T> {
+        trie: &'a T,
+        reads: std::cell::Cell<usize>,
+    }
+
+    impl<T: TrieReader> NodeReader for CountingReader<'_, T> {
+        fn read_node(&self, addr: LinearAddress) -> Result<SharedNode, FileIoError> {
+            self.reads.set(self.reads.get() + 1);
+            self.trie.read_node(addr)
+        }
+    }
+
+    impl<T: TrieReader> RootReader for CountingReader<'_, T> {
+        fn root_node(&self) -> Option<SharedNode> {
+            self.trie.root_node()
+        }
+
+        fn root_as_maybe_persisted_node(&self) -> Option<MaybePersistedNode> {
+            self.trie.root_as_maybe_persisted_node()
+        }
+    }
+
+    #[test]
+    fn unchanged_subtries_are_not_read() {
+        let old: Vec<([u8; 2], [u8; 1])> = (0u8..=255).map(|i| ([i, i], [i])).collect();
+        let base = init_merkle(old);
+        let mut proposal = base.fork().unwrap();
+        proposal.insert(&[7, 7], Box::new([0])).unwrap();
+        let proposal: Proposal = proposal.hash();
+
+        let left = CountingReader {
+            trie: base.nodestore(),
+            reads: 0.into(),
+        };
+        let changes: Vec<KeyChange> = DiffIter::new(&left, proposal.nodestore())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            changes,
+            vec![KeyChange::Updated {
+                key: Box::new([7, 7]),
+                old: Box::new([7]),
+                new: Box::new([0]),
+            }]
+        );
+        // only the path to the changed key is read, not the other 255 leaves
+        assert!(left.reads.get() <= 2, "read {} nodes", left.reads.get());
+    }
+}
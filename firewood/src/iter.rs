@@ -5,7 +5,7 @@ mod try_extend;
 
 pub(crate) use self::try_extend::TryExtend;
 use crate::merkle::{Key, Value};
-use crate::v2::api;
+use crate::v2::api::{self, IterationMode};
 
 use firewood_storage::{
     BranchNode, Child, FileIoError, NibblesIterator, Node, PathBuf, PathComponent, PathIterItem,
@@ -30,6 +30,14 @@ enum IterationNode {
         /// in the node's children array.
         children_iter: Box<dyn Iterator<Item = (PathComponent, Child)> + Send>,
     },
+    /// This node's descendants have been visited, or are above it on the stack.
+    /// Only used when iterating in descending order, where a node is returned
+    /// after all of its descendants.
+    Expanded {
+        /// The key (as nibbles) of this node.
+        key: Key,
+        node: SharedNode,
+    },
 }
 
 impl std::fmt::Debug for IterationNode {
@@ -44,6 +52,11 @@ impl std::fmt::Debug for IterationNode {
                 key,
                 children_iter: _,
             } => f.debug_struct("Visited").field("key", key).finish(),
+            Self::Expanded { key, node } => f
+                .debug_struct("Expanded")
+                .field("key", key)
+                .field("node", node)
+                .finish(),
         }
     }
 }
@@ -53,6 +66,9 @@ enum NodeIterState {
     /// The iterator state is lazily initialized when `poll_next` is called
     /// for the first time. The iteration start key is stored here.
     StartFromKey(Key),
+    /// Like [`NodeIterState::StartFromKey`], for an iterator in descending
+    /// order. If there is no key, the iteration starts at the last node.
+    StartFromKeyDescending(Option<Key>),
     Iterating {
         /// Each element is a node that will be visited (i.e. returned)
         /// or has been visited but has unvisited children.
@@ -68,6 +84,9 @@ enum NodeIterState {
 pub struct MerkleNodeIter<'a, T> {
     state: NodeIterState,
     merkle: &'a T,
+    descending: bool,
+    /// The key (as nibbles) at which an ascending iteration stops, if any.
+    end: Option<Key>,
 }
 
 impl From<Key> for NodeIterState {
@@ -83,6 +102,34 @@ impl<'a, T: TrieReader> MerkleNodeIter<'a, T> {
         Self {
             state: NodeIterState::from(key),
             merkle,
+            descending: false,
+            end: None,
+        }
+    }
+
+    /// Returns a new iterator that will iterate over all the nodes in `merkle`
+    /// with keys greater than or equal to `key` and, if `end` is set, less
+    /// than `end`.
+    ///
+    /// Subtries that only contain keys at or after `end` are never read.
+    pub(super) fn new_range(merkle: &'a T, key: Key, end: Option<&[u8]>) -> Self {
+        Self {
+            state: NodeIterState::from(key),
+            merkle,
+            descending: false,
+            end: end.map(|end| NibblesIterator::new(end).collect()),
+        }
+    }
+
+    /// Returns a new iterator that will iterate over the nodes in `merkle` in
+    /// descending order, with keys less than or equal to `key`. If `key` is
+    /// `None`, all nodes are visited.
+    pub(super) const fn new_descending(merkle: &'a T, key: Option<Key>) -> Self {
+        Self {
+            state: NodeIterState::StartFromKeyDescending(key),
+            merkle,
+            descending: true,
+            end: None,
         }
     }
 }
@@ -99,12 +146,38 @@ impl<T: TrieReader> Iterator for MerkleNodeIter<'_, T> {
                         Err(e) => return Some(Err(e)),
                     }
                 }
+                NodeIterState::StartFromKeyDescending(key) => {
+                    match get_descending_iterator_initial_state(self.merkle, key.as_deref()) {
+                        Ok(state) => self.state = state,
+                        Err(e) => return Some(Err(e)),
+                    }
+                }
                 NodeIterState::Iterating { iter_stack } => {
                     while let Some(mut iter_node) = iter_stack.pop() {
                         match iter_node {
+                            IterationNode::Unvisited { key, .. }
+                                if past_end(self.end.as_deref(), &key, None) =>
+                            {
+                                // This node, and every node after it, is past the end.
+                                iter_stack.clear();
+                                return None;
+                            }
                             IterationNode::Unvisited { key, node } => {
                                 match &*node {
                                     Node::Leaf(_) => {}
+                                    Node::Branch(branch) if self.descending => {
+                                        // Visit `node`'s children, last first, and
+                                        // return `node` once they are all visited.
+                                        let children_iter =
+                                            Box::new(as_enumerated_children_iter(branch).rev());
+                                        iter_stack.push(IterationNode::Expanded {
+                                            key: key.clone(),
+                                            node: node.clone(),
+                                        });
+                                        iter_stack
+                                            .push(IterationNode::Visited { key, children_iter });
+                                        continue 'outer;
+                                    }
                                     Node::Branch(branch) => {
                                         // `node` is a branch node. Visit its children next.
                                         iter_stack.push(IterationNode::Visited {
@@ -119,6 +192,10 @@ impl<T: TrieReader> Iterator for MerkleNodeIter<'_, T> {
                                 let key = key_from_nibble_iter(key.iter().copied());
                                 return Some(Ok((key, node)));
                             }
+                            IterationNode::Expanded { key, node } => {
+                                let key = key_from_nibble_iter(key.iter().copied());
+                                return Some(Ok((key, node)));
+                            }
                             IterationNode::Visited {
                                 ref key,
                                 ref mut children_iter,
@@ -129,6 +206,12 @@ impl<T: TrieReader> Iterator for MerkleNodeIter<'_, T> {
                                     continue;
                                 };
 
+                                if past_end(self.end.as_deref(), key, Some(pos.as_u8())) {
+                                    // Don't read the child, or anything after it.
+                                    iter_stack.clear();
+                                    return None;
+                                }
+
                                 let child = match child {
                                     Child::AddressWithHash(addr, _) => {
                                         match self.merkle.read_node(addr) {
@@ -180,6 +263,19 @@ impl<T: TrieReader> Iterator for MerkleNodeIter<'_, T> {
 
 impl<T: TrieReader> FusedIterator for MerkleNodeIter<'_, T> {}
 
+/// Returns true if every key that starts with the nibbles of `prefix`, followed
+/// by `next` (if any), is at or after `end`.
+fn past_end(end: Option<&[u8]>, prefix: &[u8], next: Option<u8>) -> bool {
+    end.is_some_and(|end| {
+        prefix
+            .iter()
+            .copied()
+            .chain(next)
+            .cmp(end.iter().copied())
+            .is_ge()
+    })
+}
+
 /// Returns the initial state for an iterator over the given `merkle` which starts at `key`.
 fn get_iterator_intial_state<T: TrieReader>(
     merkle: &T,
@@ -273,16 +369,121 @@ fn get_iterator_intial_state<T: TrieReader>(
     }
 }
 
+/// Returns the initial state for an iterator over the given `merkle` which
+/// visits nodes in descending order, starting at `key` (or the last node).
+fn get_descending_iterator_initial_state<T: TrieReader>(
+    merkle: &T,
+    key: Option<&[u8]>,
+) -> Result<NodeIterState, FileIoError> {
+    let Some(mut node) = merkle.root_node() else {
+        // This merkle is empty.
+        return Ok(NodeIterState::Iterating { iter_stack: vec![] });
+    };
+
+    let Some(key) = key else {
+        // Visit the whole trie.
+        return Ok(NodeIterState::Iterating {
+            iter_stack: vec![IterationNode::Unvisited {
+                key: node.partial_path().iter().copied().collect(),
+                node,
+            }],
+        });
+    };
+
+    // Invariant: `matched_key_nibbles` is the path before `node`'s
+    // partial path at the start of each loop iteration.
+    let mut matched_key_nibbles = vec![];
+
+    let mut unmatched_key_nibbles = NibblesIterator::new(key);
+
+    let mut iter_stack: Vec<IterationNode> = vec![];
+
+    loop {
+        // See if `node`'s key is a prefix of `key`.
+        let partial_path = node.partial_path();
+
+        let (comparison, new_unmatched_key_nibbles) =
+            compare_partial_path(partial_path.iter(), unmatched_key_nibbles);
+        unmatched_key_nibbles = new_unmatched_key_nibbles;
+
+        matched_key_nibbles.extend(partial_path.iter());
+
+        match comparison {
+            Ordering::Less => {
+                // `node` and all of its descendants are before `key`.
+                iter_stack.push(IterationNode::Unvisited {
+                    key: Box::from(matched_key_nibbles),
+                    node,
+                });
+                return Ok(NodeIterState::Iterating { iter_stack });
+            }
+            Ordering::Greater => {
+                // `node` and all of its descendants are after `key`.
+                return Ok(NodeIterState::Iterating { iter_stack });
+            }
+            Ordering::Equal => match &*node {
+                Node::Leaf(_) => {
+                    iter_stack.push(IterationNode::Unvisited {
+                        key: matched_key_nibbles.into_boxed_slice(),
+                        node,
+                    });
+                    return Ok(NodeIterState::Iterating { iter_stack });
+                }
+                Node::Branch(branch) => {
+                    // `node` is at or before `key`, and is visited after
+                    // any of its descendants that are.
+                    iter_stack.push(IterationNode::Expanded {
+                        key: matched_key_nibbles.clone().into_boxed_slice(),
+                        node: node.clone(),
+                    });
+
+                    let Some(next_unmatched_key_nibble) = unmatched_key_nibbles.next() else {
+                        // All of `node`'s descendants are after `key`.
+                        return Ok(NodeIterState::Iterating { iter_stack });
+                    };
+                    let next_unmatched_key_nibble =
+                        PathComponent::try_new(next_unmatched_key_nibble).expect("valid nibble");
+
+                    // After the child at `next_unmatched_key_nibble`, visit
+                    // the children before it, last first.
+                    iter_stack.push(IterationNode::Visited {
+                        key: matched_key_nibbles.clone().into_boxed_slice(),
+                        children_iter: Box::new(
+                            as_enumerated_children_iter(branch)
+                                .rev()
+                                .filter(move |(pos, _)| *pos < next_unmatched_key_nibble),
+                        ),
+                    });
+
+                    let child = &branch.children[next_unmatched_key_nibble];
+                    node = match child {
+                        None => return Ok(NodeIterState::Iterating { iter_stack }),
+                        Some(Child::AddressWithHash(addr, _)) => merkle.read_node(*addr)?,
+                        Some(Child::Node(node)) => (*node).clone().into(),
+                        Some(Child::MaybePersisted(maybe_persisted, _)) => {
+                            maybe_persisted.as_shared_node(merkle)?
+                        }
+                    };
+
+                    matched_key_nibbles.push(next_unmatched_key_nibble.as_u8());
+                }
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 /// An iterator of key-value pairs in order starting from a specific point in the trie.
 pub struct MerkleKeyValueIter<'a, T> {
     iter: MerkleNodeIter<'a, T>,
+    mode: IterationMode,
 }
 
 impl<'a, T: TrieReader> From<&'a T> for MerkleKeyValueIter<'a, T> {
     fn from(merkle: &'a T) -> Self {
         Self {
             iter: MerkleNodeIter::new(merkle, Box::new([])),
+            mode: IterationMode::KeyValue,
         }
     }
 }
@@ -293,6 +494,27 @@ impl<'a, T: TrieReader> MerkleKeyValueIter<'a, T> {
     pub fn from_key<K: AsRef<[u8]>>(merkle: &'a T, key: K) -> Self {
         Self {
             iter: MerkleNodeIter::new(merkle, key.as_ref().into()),
+            mode: IterationMode::KeyValue,
+        }
+    }
+
+    /// Construct a [`MerkleKeyValueIter`] that will iterate over the key-value pairs in `merkle`
+    /// with keys in the range [`start`, `end`). If `start` is `None`, the iteration starts at the
+    /// first key; if `end` is `None`, it continues to the last key.
+    pub fn range<K: AsRef<[u8]>>(merkle: &'a T, start: Option<K>, end: Option<K>) -> Self {
+        let start = start.as_ref().map_or(&[][..], AsRef::as_ref);
+        Self {
+            iter: MerkleNodeIter::new_range(merkle, start.into(), end.as_ref().map(AsRef::as_ref)),
+            mode: IterationMode::KeyValue,
+        }
+    }
+
+    /// Construct a [`MerkleKeyValueIter`] that will iterate over the key-value pairs in `merkle`
+    /// in descending order, starting from `last_key` (inclusive), or from the last key if `None`
+    pub fn descending<K: AsRef<[u8]>>(merkle: &'a T, last_key: Option<K>) -> Self {
+        Self {
+            iter: MerkleNodeIter::new_descending(merkle, last_key.map(|key| key.as_ref().into())),
+            mode: IterationMode::KeyValue,
         }
     }
 }
@@ -301,6 +523,7 @@ impl<T: TrieReader> Iterator for MerkleKeyValueIter<'_, T> {
     type Item = Result<(Key, Value), api::Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let mode = self.mode;
         self.iter.find_map(|result| {
             result
                 .map(|(key, node)| {
@@ -311,9 +534,9 @@ impl<T: TrieReader> Iterator for MerkleKeyValueIter<'_, T> {
                                 // Continue to the next node.
                                 return None;
                             };
-                            Some((key, value.clone()))
+                            Some((key, mode.value_of(value)))
                         }
-                        Node::Leaf(leaf) => Some((key, leaf.value.clone())),
+                        Node::Leaf(leaf) => Some((key, mode.value_of(&leaf.value))),
                     }
                 })
                 .map_err(Into::into)
@@ -324,6 +547,12 @@ impl<T: TrieReader> Iterator for MerkleKeyValueIter<'_, T> {
 
 impl<T: TrieReader> FusedIterator for MerkleKeyValueIter<'_, T> {}
 
+impl<T: TrieReader> api::KeyValueIter for MerkleKeyValueIter<'_, T> {
+    fn set_mode(&mut self, mode: IterationMode) {
+        self.mode = mode;
+    }
+}
+
 #[derive(Debug)]
 enum PathIteratorState<'a> {
     Iterating {
@@ -532,7 +761,7 @@ where
 /// where `pos` is the position of the child in `branch`'s children array.
 fn as_enumerated_children_iter(
     branch: &BranchNode,
-) -> impl Iterator<Item = (PathComponent, Child)> + use<> {
+) -> impl DoubleEndedIterator<Item = (PathComponent, Child)> + use<> {
     branch
         .children
         .clone()
@@ -541,12 +770,12 @@ fn as_enumerated_children_iter(
 }
 
 #[cfg(feature = "branch_factor_256")]
-fn key_from_nibble_iter<Iter: Iterator<Item = u8>>(nibbles: Iter) -> Key {
+pub(crate) fn key_from_nibble_iter<Iter: Iterator<Item = u8>>(nibbles: Iter) -> Key {
     nibbles.collect()
 }
 
 #[cfg(not(feature = "branch_factor_256"))]
-fn key_from_nibble_iter<Iter: Iterator<Item = u8>>(mut nibbles: Iter) -> Key {
+pub(crate) fn key_from_nibble_iter<Iter: Iterator<Item = u8>>(mut nibbles: Iter) -> Key {
     let mut data = Vec::with_capacity(nibbles.size_hint().0 / 2);
 
     while let (Some(hi), Some(lo)) = (nibbles.next(), nibbles.next()) {
@@ -1357,6 +1586,171 @@ mod tests {
         assert_iterator_is_exhausted(iter);
     }
 
+    #[test]
+    fn node_iterator_descending_no_start_key() {
+        let merkle = created_populated_merkle();
+
+        let keys: Vec<Key> = MerkleNodeIter::new_descending(merkle.nodestore(), None)
+            .map(|item| item.unwrap().0)
+            .collect();
+
+        // Each branch is returned after all of its descendants
+        assert_eq!(
+            keys,
+            vec![
+                Box::from([0x00, 0xFF]),
+                Box::from([0x00, 0xD0, 0xD0]),
+                Box::from([0x00, 0x00, 0x00, 0xFF]),
+                Box::from([0x00, 0x00, 0x00, 0x01]),
+                Box::from([0x00, 0x00, 0x00]),
+                Box::from([0x00]),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_value_iterate_descending_empty() {
+        let merkle = create_test_merkle();
+        assert_iterator_is_exhausted(MerkleKeyValueIter::descending(
+            merkle.nodestore(),
+            Option::<&[u8]>::None,
+        ));
+        assert_iterator_is_exhausted(MerkleKeyValueIter::descending(
+            merkle.nodestore(),
+            Some([0x00]),
+        ));
+    }
+
+    #[test_case(None; "from the end")]
+    #[test_case(Some(&[]); "empty key")]
+    #[test_case(Some(&[0x00]); "branch with no value")]
+    #[test_case(Some(&[0x00, 0x00, 0x00]); "branch with value")]
+    #[test_case(Some(&[0x00, 0x00, 0x00, 0x00]); "between branch and its first child")]
+    #[test_case(Some(&[0x00, 0x00, 0x00, 0x01]); "on leaf")]
+    #[test_case(Some(&[0x00, 0x00, 0x00, 0x01, 0x00]); "after leaf")]
+    #[test_case(Some(&[0x00, 0x00, 0x01]); "between siblings")]
+    #[test_case(Some(&[0x00, 0xD0]); "prefix of leaf")]
+    #[test_case(Some(&[0x00, 0xFF]); "last key")]
+    #[test_case(Some(&[0xFF]); "after all keys")]
+    fn key_value_iterate_descending(last_key: Option<&[u8]>) {
+        let merkle = created_populated_merkle();
+
+        let mut expected: Vec<(Key, Value)> = merkle
+            .key_value_iter()
+            .map(Result::unwrap)
+            .filter(|(key, _)| last_key.is_none_or(|last| **key <= *last))
+            .collect();
+        expected.reverse();
+
+        let actual: Vec<(Key, Value)> =
+            MerkleKeyValueIter::descending(merkle.nodestore(), last_key)
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn key_value_iterate_descending_table_test() {
+        let mut merkle = create_test_merkle();
+
+        let max: u8 = 30;
+        for i in 0..=max {
+            for j in 0..=max {
+                merkle.insert(&[i, j], Box::new([i, j])).unwrap();
+            }
+        }
+
+        // the last keys under each one-byte prefix
+        for i in 0..=max {
+            let mut iter = MerkleKeyValueIter::descending(merkle.nodestore(), Some([i, 0xFF]));
+            for j in (0..=max).rev() {
+                assert_eq!(
+                    iter.next().unwrap().unwrap(),
+                    (Box::from([i, j]), Box::from([i, j])),
+                    "i: {i}, j: {j}",
+                );
+            }
+            if i == 0 {
+                assert_iterator_is_exhausted(iter);
+            } else {
+                assert_eq!(
+                    iter.next().unwrap().unwrap().0,
+                    Box::from([i - 1, max]),
+                    "i: {i}"
+                );
+            }
+        }
+    }
+
+    #[test_case(None, None; "unbounded")]
+    #[test_case(Some(&[0x00, 0x00]), Some(&[0x00, 0xD0]); "within a subtrie")]
+    #[test_case(Some(&[0x00, 0x00, 0x00]), Some(&[0x00, 0x00, 0x00, 0x01]); "end on leaf")]
+    #[test_case(None, Some(&[0x00, 0x00, 0x00]); "end on branch with value")]
+    #[test_case(Some(&[0x00, 0xD0, 0xD0]), Some(&[0x00, 0xD0, 0xD0, 0x00]); "single key")]
+    #[test_case(Some(&[0x00, 0xFF]), Some(&[0x00, 0xFF]); "empty range")]
+    #[test_case(Some(&[0x01]), Some(&[0x00]); "end before start")]
+    #[test_case(Some(&[0x00, 0x00, 0x00, 0x02]), None; "no end")]
+    fn key_value_iterate_range(start: Option<&[u8]>, end: Option<&[u8]>) {
+        let merkle = created_populated_merkle();
+
+        let expected: Vec<(Key, Value)> = merkle
+            .key_value_iter()
+            .map(Result::unwrap)
+            .filter(|(key, _)| start.is_none_or(|start| **key >= *start))
+            .filter(|(key, _)| end.is_none_or(|end| **key < *end))
+            .collect();
+
+        let iter = MerkleKeyValueIter::range(merkle.nodestore(), start, end);
+        let actual: Vec<(Key, Value)> = iter.map(Result::unwrap).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn key_value_iterate_modes() {
+        use crate::v2::api::KeyValueIter as _;
+        use sha2::{Digest, Sha256};
+
+        let merkle = created_populated_merkle();
+        let all: Vec<(Key, Value)> = merkle.key_value_iter().map(Result::unwrap).collect();
+
+        let keys_only: Vec<(Key, Value)> =
+            MerkleKeyValueIter::range(merkle.nodestore(), None::<&[u8]>, None)
+                .with_mode(IterationMode::KeysOnly)
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(keys_only.len(), all.len());
+        for ((key, value), (expected_key, _)) in keys_only.iter().zip(&all) {
+            assert_eq!(key, expected_key);
+            assert!(value.is_empty());
+        }
+
+        let digests: Vec<(Key, Value)> =
+            MerkleKeyValueIter::descending(merkle.nodestore(), None::<&[u8]>)
+                .with_mode(IterationMode::ValueDigests)
+                .map(Result::unwrap)
+                .collect();
+        for ((key, digest), (expected_key, value)) in digests.iter().zip(all.iter().rev()) {
+            assert_eq!(key, expected_key);
+            assert_eq!(**digest, *Sha256::digest(value));
+        }
+    }
+
+    #[test]
+    fn node_iterator_range_stops_before_end() {
+        let merkle = created_populated_merkle();
+
+        // the leaf at 0x00FF, after the end, is never returned or read
+        let mut iter =
+            MerkleNodeIter::new_range(merkle.nodestore(), Box::new([]), Some(&[0x00, 0xD1]));
+        let keys: Vec<Key> = iter.by_ref().map(|item| item.unwrap().0).collect();
+        assert_eq!(keys.last().unwrap(), &Box::from([0x00, 0xD0, 0xD0]));
+        assert!(matches!(
+            iter.state,
+            NodeIterState::Iterating { ref iter_stack } if iter_stack.is_empty()
+        ));
+        assert_iterator_is_exhausted(iter);
+    }
+
     fn assert_iterator_is_exhausted<I: FusedIterator>(mut iter: I) {
         assert!(iter.next().is_none());
     }
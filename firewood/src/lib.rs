@@ -120,9 +120,15 @@ compile_error!(
     "feature \"ethhash\" and feature \"branch_factor_256\" cannot be enabled at the same time"
 );
 
+/// Backup module, writing and restoring incremental backups of revisions
+pub mod backup;
+
 /// Database module for Firewood.
 pub mod db;
 
+/// Diff module, listing the keys that changed between two tries
+pub mod diff;
+
 /// Iterator module, for both node and key-value streams
 pub mod iter;
 
@@ -142,12 +148,20 @@ pub mod proofs;
 // Re-export the proc macro from firewood-macros
 pub use firewood_macros::metrics;
 
+/// Proposal export module, saving uncommitted proposals so they can be
+/// imported again later
+pub mod proposal_export;
+
 /// Range proof module
 pub mod range_proof;
 
 /// Root store module
 pub mod root_store;
 
+/// Snapshot module, a streaming binary container for the key/value pairs of
+/// a revision
+pub mod snapshot;
+
 /// Version 2 API
 pub mod v2;
 
@@ -10,42 +10,75 @@
     reason = "Found 3 occurrences after enabling the lint."
 )]
 
-use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::mem::take;
 use std::num::NonZero;
+use std::panic::AssertUnwindSafe;
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::sync::mpsc::{Receiver, RecvTimeoutError, SyncSender, sync_channel};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use firewood_storage::logger::{trace, warn};
 use metrics::gauge;
 use rayon::{ThreadPool, ThreadPoolBuilder};
 use typed_builder::TypedBuilder;
 
+use crate::diff::DiffIter;
 use crate::merkle::Merkle;
 use crate::root_store::{RootStore, RootStoreError};
-use crate::v2::api::{ArcDynDbView, HashKey, OptionalHashKeyExt};
+use crate::v2::api::{ArcDynDbView, BoxKeyChangeIter, HashKey, OptionalHashKeyExt};
 
-pub use firewood_storage::CacheReadStrategy;
 use firewood_storage::{
     BranchNode, Committed, FileBacked, FileIoError, HashedNodeReader, ImmutableProposal,
-    IntoHashType, NodeStore, TrieHash,
+    IntoHashType, MaybePersistedNode, MutableProposal, NodeStore, NodeStoreParent, Parentable,
+    TrieHash,
 };
+pub use firewood_storage::{CacheReadStrategy, Durability, NodeCacheBudget, StorageConfig};
+pub use retention::{
+    AnyOf, Archival, Checkpoints, KeepFor, KeepLast, RetentionPolicy, RevisionInfo,
+};
+pub use warm::CacheWarmingConfig;
+
+mod retention;
+mod warm;
+use warm::CacheWarmer;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, TypedBuilder)]
 /// Revision manager configuratoin
 pub struct RevisionManagerConfig {
-    /// The number of historical revisions to keep in memory.
+    /// The number of historical revisions to keep in memory, unless a
+    /// [`RetentionPolicy`] is set.
     #[builder(default = 128)]
     max_revisions: usize,
 
-    /// The size of the node cache
-    #[builder(default_code = "NonZero::new(1500000).expect(\"non-zero\")")]
-    node_cache_size: NonZero<usize>,
+    /// The number of committed revisions that may be waiting to be written to
+    /// disk. Once this many are pending, commits block until the oldest has
+    /// been written. Defaults to 128.
+    #[builder(default = 128)]
+    max_pending_persists: usize,
+
+    /// The number of bytes the node cache may use for branch nodes. Zero
+    /// disables caching of branches.
+    #[builder(default = NodeCacheBudget::DEFAULT_BRANCH_BYTES)]
+    branch_cache_bytes: usize,
+
+    /// The number of bytes the node cache may use for leaf nodes. Zero
+    /// disables caching of leaves.
+    #[builder(default = NodeCacheBudget::DEFAULT_LEAF_BYTES)]
+    leaf_cache_bytes: usize,
 
     #[builder(default_code = "NonZero::new(40000).expect(\"non-zero\")")]
     free_list_cache_size: NonZero<usize>,
 
     #[builder(default = CacheReadStrategy::WritesOnly)]
     cache_read_strategy: CacheReadStrategy,
+
+    /// How the node cache is warmed when the database is opened.
+    #[builder(default)]
+    cache_warming: CacheWarmingConfig,
 }
 
 #[derive(Clone, Debug, TypedBuilder)]
@@ -59,27 +92,103 @@ pub struct ConfigManager {
     /// existing contents will be lost.
     #[builder(default = false)]
     pub truncate: bool,
+    /// Storage configuration, including the durability policy applied when
+    /// revisions are persisted.
+    #[builder(default)]
+    pub storage: StorageConfig,
     /// Revision manager configuration.
     #[builder(default = RevisionManagerConfig::builder().build())]
     pub manager: RevisionManagerConfig,
+    /// Which revisions are retained. Without a policy, the latest
+    /// `max_revisions` revisions are.
+    #[builder(default, setter(strip_option))]
+    pub retention: Option<Arc<dyn RetentionPolicy>>,
 }
 
 type CommittedRevision = Arc<NodeStore<Committed, FileBacked>>;
 type ProposedRevision = Arc<NodeStore<Arc<ImmutableProposal>, FileBacked>>;
 
+/// The nodes of the previous retained revision that a revision no longer
+/// uses, which are freed once the previous one is dropped.
+///
+/// Only the [`PersistWorker`] reads or changes them, in commit order.
+type PendingDeletes = Arc<Mutex<Vec<MaybePersistedNode>>>;
+
+/// A retained revision.
+#[derive(Clone, Debug)]
+struct Retained {
+    revision: CommittedRevision,
+    info: RevisionInfo,
+    pending: PendingDeletes,
+}
+
+impl Retained {
+    fn new(revision: CommittedRevision, number: u64) -> Self {
+        let info = RevisionInfo {
+            number,
+            committed_at: Instant::now(),
+            root_hash: revision.root_hash(),
+        };
+        let pending = Arc::new(Mutex::new(revision.deleted_nodes().to_vec()));
+        Self {
+            revision,
+            info,
+            pending,
+        }
+    }
+
+    /// The number the revision committed on top of this one gets.
+    const fn next_number(&self) -> u64 {
+        self.info.number.wrapping_add(1)
+    }
+}
+
+/// A named head: a fork of the main head, committed to independently.
+#[derive(Debug)]
+struct Head {
+    /// The revision of the main head the fork starts from. Holding it keeps
+    /// it from being reaped.
+    base: Retained,
+    /// The revisions committed to this head, oldest first.
+    revisions: Vec<Retained>,
+}
+
+impl Head {
+    fn tip(&self) -> &Retained {
+        self.revisions.last().unwrap_or(&self.base)
+    }
+}
+
+/// The revision a proposal was proposed on top of.
+enum ParentRevision {
+    Proposed(ProposedRevision),
+    Committed(CommittedRevision),
+    Empty,
+}
+
 #[derive(Debug)]
 pub(crate) struct RevisionManager {
-    /// Maximum number of revisions to keep on disk
+    /// Maximum number of revisions to keep on disk, without a retention policy
     max_revisions: usize,
+    /// Which revisions of the main head are retained
+    retention: Arc<dyn RetentionPolicy>,
+    /// The root hashes of the revisions retained whatever the policy says
+    pinned: RwLock<HashSet<HashKey>>,
 
     /// The list of revisions that are on disk; these point to the different roots
     /// stored in the filebacked storage.
-    historical: RwLock<VecDeque<CommittedRevision>>,
+    historical: RwLock<VecDeque<Retained>>,
     proposals: Mutex<Vec<ProposedRevision>>,
     // committing_proposals: VecDeque<Arc<ProposedImmutable>>,
     by_hash: RwLock<HashMap<TrieHash, CommittedRevision>>,
-    threadpool: OnceLock<ThreadPool>,
-    root_store: Box<dyn RootStore + Send + Sync>,
+    /// The named heads, which share the nodes of the main head up to their base.
+    heads: RwLock<HashMap<String, Head>>,
+    threadpool: OnceLock<Arc<ThreadPool>>,
+    root_store: Arc<dyn RootStore + Send + Sync>,
+    persist_worker: PersistWorker,
+    // dropped after the persist worker has drained, so the hot node addresses
+    // it saves reflect every committed revision
+    _cache_warmer: CacheWarmer,
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -99,21 +208,33 @@ pub(crate) enum RevisionManagerError {
     FileIoError(#[from] FileIoError),
     #[error("A RootStore error occurred")]
     RootStoreError(#[from] RootStoreError),
+    #[error("A previous revision could not be persisted; the database must be reopened")]
+    PersistFailed,
+    #[error("Revision {provided:?} is still in use")]
+    RevisionInUse { provided: Option<HashKey> },
+    #[error("Head {name:?} not found")]
+    HeadNotFound { name: String },
+    #[error("Head {name:?} already exists")]
+    HeadExists { name: String },
 }
 
 impl RevisionManager {
     pub fn new(
         filename: PathBuf,
         config: ConfigManager,
-        root_store: Box<dyn RootStore + Send + Sync>,
+        root_store: Arc<dyn RootStore + Send + Sync>,
     ) -> Result<Self, RevisionManagerError> {
         let fb = FileBacked::new(
-            filename,
-            config.manager.node_cache_size,
+            filename.clone(),
+            NodeCacheBudget {
+                branch_bytes: config.manager.branch_cache_bytes,
+                leaf_bytes: config.manager.leaf_cache_bytes,
+            },
             config.manager.free_list_cache_size,
             config.truncate,
             config.create,
             config.manager.cache_read_strategy,
+            config.storage,
         )?;
 
         // Acquire an advisory lock on the database file to prevent multiple processes
@@ -122,14 +243,40 @@ impl RevisionManager {
 
         let storage = Arc::new(fb);
         let nodestore = Arc::new(NodeStore::open(storage.clone())?);
+        if config.truncate {
+            nodestore.flush_header_with_padding()?;
+        }
+        let cache_warmer = CacheWarmer::start(
+            config.manager.cache_warming,
+            &filename,
+            &storage,
+            &nodestore,
+            config.truncate,
+        )?;
+
+        // the worker gets its own copy of the latest revision, so that holding
+        // it does not stop the revision in `historical` from being reaped
+        let persist_worker = PersistWorker::new(
+            Arc::new(NodeStore::open(storage.clone())?),
+            root_store.clone(),
+            config.manager.max_pending_persists,
+        )?;
+        let retention = config
+            .retention
+            .unwrap_or_else(|| Arc::new(KeepLast(config.manager.max_revisions)));
         let manager = Self {
             max_revisions: config.manager.max_revisions,
-            historical: RwLock::new(VecDeque::from([nodestore.clone()])),
+            retention,
+            pinned: RwLock::new(Default::default()),
+            historical: RwLock::new(VecDeque::from([Retained::new(nodestore.clone(), 0)])),
             by_hash: RwLock::new(Default::default()),
+            heads: RwLock::new(Default::default()),
             proposals: Mutex::new(Default::default()),
             // committing_proposals: Default::default(),
             threadpool: OnceLock::new(),
             root_store,
+            persist_worker,
+            _cache_warmer: cache_warmer,
         };
 
         if let Some(hash) = nodestore.root_hash().or_default_root_hash() {
@@ -140,10 +287,6 @@ impl RevisionManager {
                 .insert(hash, nodestore.clone());
         }
 
-        if config.truncate {
-            nodestore.flush_header_with_padding()?;
-        }
-
         // On startup, we always write the latest revision to RootStore
         if let Some(root_hash) = manager.current_revision().root_hash() {
             let root_address = manager.current_revision().root_address().ok_or(
@@ -167,111 +310,563 @@ impl RevisionManager {
     ///    The address of the root node and the root hash is also persisted.
     ///    Note that this is *not* a write ahead log.
     ///    It only contains the address of the nodes that are deleted, which should be very small.
-    /// 3. Revision reaping. If more than the maximum number of revisions are kept in memory, the
-    ///    oldest revision is reaped.
-    /// 4. Persist to disk. This includes flushing everything to disk.
-    /// 5. Persist the revision to `RootStore`.
-    /// 6. Set last committed revision.
+    /// 3. Revision reaping. The revisions that the retention policy no longer retains, and that
+    ///    are neither pinned nor referenced elsewhere, are dropped.
+    /// 4. Hand the revision to the persist worker, which reaps the nodes only used by the
+    ///    revisions dropped in step 3, flushes everything to disk and then persists the
+    ///    revision to `RootStore`. This happens asynchronously, in commit order; use
+    ///    [`RevisionManager::flush`] to wait for it.
+    /// 5. Set last committed revision.
     ///    Set last committed revision in memory.
-    /// 7. Proposal Cleanup.
+    /// 6. Proposal Cleanup.
     ///    Any other proposals that have this proposal as a parent should be reparented to the committed version.
     #[fastrace::trace(short_name = true)]
     #[crate::metrics("firewood.proposal.commit", "proposal commit to storage")]
     pub fn commit(&self, proposal: ProposedRevision) -> Result<(), RevisionManagerError> {
         // 1. Commit check
-        let current_revision = self.current_revision();
-        if !proposal.parent_hash_is(current_revision.root_hash()) {
+        let current = self.current();
+        if !proposal.parent_hash_is(current.revision.root_hash()) {
             return Err(RevisionManagerError::NotLatest {
                 provided: proposal.root_hash(),
-                expected: current_revision.root_hash(),
+                expected: current.revision.root_hash(),
             });
         }
 
-        let mut committed = proposal.as_committed(&current_revision);
+        // Once a revision fails to persist, the ones built on it cannot be persisted either
+        self.persist_worker.take_error()?;
+
+        let committed = Retained::new(
+            proposal.as_committed(&current.revision).into(),
+            current.next_number(),
+        );
+        drop(current);
 
         // 2. Persist delete list for this committed revision to disk for recovery
 
-        // 3 Take the deleted entries from the oldest revision and mark them as free for this revision
+        // 3. Drop the revisions the retention policy no longer retains; the persist worker
+        // marks the nodes only they used as free for this revision.
         // If you crash after freeing some of these, then the free list will point to nodes that are not actually free.
         // TODO: Handle the case where we get something off the free list that is not free
-        while self.historical.read().expect("poisoned lock").len() >= self.max_revisions {
-            let oldest = self
-                .historical
+        let reaped = self.reap(&committed);
+
+        // 4. Persist to disk and to the root store in the background.
+        self.persist_worker.submit(PersistJob {
+            proposal: proposal.clone(),
+            reaped,
+            fork: false,
+        })?;
+
+        // 5. Set last committed revision
+        let mut historical = self.historical.write().expect("poisoned lock");
+        historical.push_back(committed.clone());
+        gauge!("firewood.active_revisions").set(historical.len() as f64);
+        drop(historical);
+        if let Some(hash) = committed.revision.root_hash().or_default_root_hash() {
+            self.by_hash
                 .write()
                 .expect("poisoned lock")
-                .pop_front()
-                .expect("must be present");
-            if let Some(oldest_hash) = oldest.root_hash().or_default_root_hash() {
-                self.by_hash
-                    .write()
-                    .expect("poisoned lock")
-                    .remove(&oldest_hash);
+                .insert(hash, committed.revision.clone());
+        }
+        gauge!("firewood.revisions.stuck").set(self.stuck_revisions().len() as f64);
+
+        // 6. Proposal Cleanup
+        self.cleanup_proposals(&proposal);
+
+        if crate::logger::trace_enabled() {
+            let merkle = Merkle::from(committed.revision);
+            if let Ok(s) = merkle.dump_to_string() {
+                trace!("{s}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Frees the proposal that was committed as well as any proposals no
+    /// longer referenced by anyone else, then reparents the proposals that
+    /// have it as a parent to the committed revision.
+    fn cleanup_proposals(&self, committed: &ProposedRevision) {
+        let mut proposals = self.proposals.lock().expect("poisoned lock");
+        proposals.retain(|p| !Arc::ptr_eq(committed, p) && Arc::strong_count(p) > 1);
+        for p in &*proposals {
+            committed.commit_reparent(p);
+        }
+    }
+
+    /// Drops the revisions of the main head that the retention policy no
+    /// longer retains now that `latest` is being committed, returning what the
+    /// persist worker must reap for them.
+    ///
+    /// The revision `latest` is committed on top of is always retained, as
+    /// are pinned revisions. So is a revision still referenced outside the
+    /// manager; a later commit drops it once it is not.
+    fn reap(&self, latest: &Retained) -> Vec<Reap> {
+        let mut historical = self.historical.write().expect("poisoned lock");
+        let pinned = self.pinned.read().expect("poisoned lock");
+        let mut by_hash = self.by_hash.write().expect("poisoned lock");
+        let mut reaped = Vec::new();
+        let mut index = 0;
+        while index < historical.len().saturating_sub(1) {
+            let candidate = historical.get(index).expect("index is in range");
+            if self.retains(candidate, &latest.info, &pinned) {
+                index = index.saturating_add(1);
+                continue;
+            }
+
+            let dropped = historical.remove(index).expect("index is in range");
+            // `by_hash` holds its own reference, so drop it before checking
+            // whether anyone else still uses the revision
+            let hash = dropped.revision.root_hash().or_default_root_hash();
+            if let Some(hash) = &hash {
+                remove_hash(&mut by_hash, hash, &dropped.revision);
             }
 
             // This `try_unwrap` is safe because nobody else will call `try_unwrap` on this Arc
             // in a different thread, so we don't have to worry about the race condition where
             // the Arc we get back is not usable as indicated in the docs for `try_unwrap`.
-            // This guarantee is there because we have a `&mut self` reference to the manager, so
-            // the compiler guarantees we are the only one using this manager.
-            match Arc::try_unwrap(oldest) {
-                Ok(oldest) => oldest.reap_deleted(&mut committed)?,
-                Err(original) => {
-                    warn!("Oldest revision could not be reaped; still referenced");
-                    self.historical
-                        .write()
-                        .expect("poisoned lock")
-                        .push_front(original);
-                    break;
+            match Arc::try_unwrap(dropped.revision) {
+                Ok(revision) => {
+                    let child = historical
+                        .get(index)
+                        .expect("the latest revision is retained")
+                        .pending
+                        .clone();
+                    let parent = index.checked_sub(1).and_then(|i| historical.get(i));
+                    reaped.push(match parent {
+                        None => Reap::Oldest {
+                            pending: dropped.pending,
+                        },
+                        Some(parent) => Reap::Between {
+                            parent: Box::new(parent.revision.without_deleted()),
+                            revision: Box::new(revision),
+                            pending: dropped.pending,
+                            child,
+                        },
+                    });
+                }
+                Err(revision) => {
+                    // a revision that is still referenced can still be found
+                    if let Some(hash) = hash {
+                        by_hash.entry(hash).or_insert_with(|| revision.clone());
+                    }
+                    historical.insert(
+                        index,
+                        Retained {
+                            revision,
+                            info: dropped.info,
+                            pending: dropped.pending,
+                        },
+                    );
+                    index = index.saturating_add(1);
                 }
             }
-            gauge!("firewood.active_revisions")
-                .set(self.historical.read().expect("poisoned lock").len() as f64);
-            gauge!("firewood.max_revisions").set(self.max_revisions as f64);
         }
+        gauge!("firewood.max_revisions").set(self.max_revisions as f64);
+        reaped
+    }
 
-        // 4. Persist to disk.
-        // TODO: We can probably do this in another thread, but it requires that
-        // we move the header out of NodeStore, which is in a future PR.
-        committed.persist()?;
+    /// Whether `revision` of the main head is retained, now that `latest`
+    /// was committed.
+    fn retains(
+        &self,
+        revision: &Retained,
+        latest: &RevisionInfo,
+        pinned: &HashSet<HashKey>,
+    ) -> bool {
+        let hash = revision.revision.root_hash().or_default_root_hash();
+        hash.is_some_and(|hash| pinned.contains(&hash))
+            || self.retention.retain(&revision.info, latest)
+    }
 
-        // 5. Persist revision to root store
-        if let (Some(hash), Some(address)) = (committed.root_hash(), committed.root_address()) {
-            self.root_store.add_root(&hash, &address)?;
+    /// The retained revisions of the main head, oldest first.
+    pub fn retained_revisions(&self) -> Vec<RevisionInfo> {
+        self.historical
+            .read()
+            .expect("poisoned lock")
+            .iter()
+            .map(|retained| retained.info.clone())
+            .collect()
+    }
+
+    /// The revisions of the main head that the retention policy no longer
+    /// retains, but that were still referenced, so could not be dropped, when
+    /// the latest revision was committed. Oldest first.
+    pub fn stuck_revisions(&self) -> Vec<RevisionInfo> {
+        let historical = self.historical.read().expect("poisoned lock");
+        let pinned = self.pinned.read().expect("poisoned lock");
+        let Some(latest) = historical.back() else {
+            return Vec::new();
+        };
+        // the latest revision and its parent were not considered
+        historical
+            .range(..historical.len().saturating_sub(2))
+            .filter(|retained| !self.retains(retained, &latest.info, &pinned))
+            .map(|retained| retained.info.clone())
+            .collect()
+    }
+
+    /// Pins the retained revisions of the main head with `root_hash`, so
+    /// they are retained whatever the retention policy says until unpinned.
+    ///
+    /// Pins are kept in memory only, and are removed when the revision is
+    /// discarded by a rollback or a promoted head.
+    pub fn pin_revision(&self, root_hash: HashKey) -> Result<(), RevisionManagerError> {
+        let historical = self.historical.read().expect("poisoned lock");
+        if !historical.iter().any(|retained| {
+            retained
+                .revision
+                .root_hash()
+                .or_default_root_hash()
+                .as_ref()
+                == Some(&root_hash)
+        }) {
+            return Err(RevisionManagerError::RevisionNotFound {
+                provided: root_hash,
+            });
         }
+        let mut pinned = self.pinned.write().expect("poisoned lock");
+        pinned.insert(root_hash);
+        gauge!("firewood.revisions.pinned").set(pinned.len() as f64);
+        Ok(())
+    }
 
-        // 6. Set last committed revision
-        let committed: CommittedRevision = committed.into();
-        self.historical
-            .write()
+    /// Unpins the revisions with `root_hash`, returning whether they were
+    /// pinned. A later commit drops them if the retention policy no longer
+    /// retains them.
+    pub fn unpin_revision(&self, root_hash: &HashKey) -> bool {
+        let mut pinned = self.pinned.write().expect("poisoned lock");
+        let unpinned = pinned.remove(root_hash);
+        gauge!("firewood.revisions.pinned").set(pinned.len() as f64);
+        unpinned
+    }
+
+    /// The root hashes of the pinned revisions, sorted.
+    pub fn pinned_revisions(&self) -> Vec<HashKey> {
+        let mut pinned: Vec<_> = self
+            .pinned
+            .read()
             .expect("poisoned lock")
-            .push_back(committed.clone());
-        if let Some(hash) = committed.root_hash().or_default_root_hash() {
+            .iter()
+            .cloned()
+            .collect();
+        pinned.sort_unstable_by(|a, b| a.as_ref().cmp(b.as_ref()));
+        pinned
+    }
+
+    /// Removes the pins of revisions no longer retained.
+    fn prune_pins(&self, historical: &VecDeque<Retained>) {
+        let mut pinned = self.pinned.write().expect("poisoned lock");
+        pinned.retain(|hash| {
+            historical.iter().any(|retained| {
+                retained
+                    .revision
+                    .root_hash()
+                    .or_default_root_hash()
+                    .as_ref()
+                    == Some(hash)
+            })
+        });
+        gauge!("firewood.revisions.pinned").set(pinned.len() as f64);
+    }
+
+    /// Commit a proposal to the named head `name`.
+    ///
+    /// This works like [`RevisionManager::commit`], except that the proposal
+    /// must be a direct child of the tip of the head, and no revision is
+    /// reaped: a head keeps every revision committed to it until it is
+    /// deleted or promoted. The revision is written to disk in commit order
+    /// with the others, but the root recorded in the header stays the one of
+    /// the main head.
+    pub fn commit_to_head(
+        &self,
+        name: &str,
+        proposal: ProposedRevision,
+    ) -> Result<(), RevisionManagerError> {
+        let mut heads = self.heads.write().expect("poisoned lock");
+        let head = heads
+            .get_mut(name)
+            .ok_or_else(|| RevisionManagerError::HeadNotFound {
+                name: name.to_owned(),
+            })?;
+        let tip = head.tip();
+        if !proposal.parent_hash_is(tip.revision.root_hash()) {
+            return Err(RevisionManagerError::NotLatest {
+                provided: proposal.root_hash(),
+                expected: tip.revision.root_hash(),
+            });
+        }
+
+        self.persist_worker.take_error()?;
+
+        let committed = Retained::new(
+            proposal.as_committed(&tip.revision).into(),
+            tip.next_number(),
+        );
+        self.persist_worker.submit(PersistJob {
+            proposal: proposal.clone(),
+            reaped: Vec::new(),
+            fork: true,
+        })?;
+        let revision = committed.revision.clone();
+        head.revisions.push(committed);
+        drop(heads);
+
+        if let Some(hash) = revision.root_hash().or_default_root_hash() {
             self.by_hash
                 .write()
                 .expect("poisoned lock")
-                .insert(hash, committed.clone());
+                .insert(hash, revision);
         }
+        self.cleanup_proposals(&proposal);
+        Ok(())
+    }
 
-        // 7. Proposal Cleanup
-        // Free proposal that is being committed as well as any proposals no longer
-        // referenced by anyone else.
-        self.proposals
-            .lock()
+    /// Creates the named head `name`, starting at the retained revision of
+    /// the main head with `root_hash`.
+    ///
+    /// Until the head is deleted or promoted, its base is retained, since the
+    /// head shares its nodes.
+    pub fn create_head(&self, name: &str, root_hash: HashKey) -> Result<(), RevisionManagerError> {
+        let historical = self.historical.read().expect("poisoned lock");
+        let base = historical
+            .iter()
+            .rev()
+            .find(|r| r.revision.root_hash().or_default_root_hash().as_ref() == Some(&root_hash))
+            .cloned()
+            .ok_or(RevisionManagerError::RevisionNotFound {
+                provided: root_hash,
+            })?;
+        let mut heads = self.heads.write().expect("poisoned lock");
+        match heads.entry(name.to_owned()) {
+            Entry::Occupied(_) => {
+                return Err(RevisionManagerError::HeadExists {
+                    name: name.to_owned(),
+                });
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Head {
+                    base,
+                    revisions: Vec::new(),
+                });
+            }
+        }
+        gauge!("firewood.heads").set(heads.len() as f64);
+        Ok(())
+    }
+
+    /// The latest revision of the named head `name`.
+    pub fn head_revision(&self, name: &str) -> Result<CommittedRevision, RevisionManagerError> {
+        self.heads
+            .read()
+            .expect("poisoned lock")
+            .get(name)
+            .map(|head| head.tip().revision.clone())
+            .ok_or_else(|| RevisionManagerError::HeadNotFound {
+                name: name.to_owned(),
+            })
+    }
+
+    /// The names of the named heads and the root hashes of their latest
+    /// revisions, sorted by name.
+    pub fn heads(&self) -> Vec<(String, Option<HashKey>)> {
+        let mut heads: Vec<_> = self
+            .heads
+            .read()
             .expect("poisoned lock")
-            .retain(|p| !Arc::ptr_eq(&proposal, p) && Arc::strong_count(p) > 1);
+            .iter()
+            .map(|(name, head)| (name.clone(), head.tip().revision.root_hash()))
+            .collect();
+        heads.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        heads
+    }
+
+    /// Deletes the named head `name`, returning the nodes written by its
+    /// revisions to the free lists.
+    ///
+    /// Like [`RevisionManager::rollback_to`], this waits for pending
+    /// revisions to be persisted, must not race with a commit, and fails with
+    /// [`RevisionManagerError::RevisionInUse`], changing nothing, if a
+    /// revision of the head or any proposal is still referenced.
+    pub fn delete_head(&self, name: &str) -> Result<(), RevisionManagerError> {
+        self.persist_worker.flush()?;
+
+        let historical = self.historical.write().expect("poisoned lock");
+        let mut heads = self.heads.write().expect("poisoned lock");
+        let head = heads
+            .get(name)
+            .ok_or_else(|| RevisionManagerError::HeadNotFound {
+                name: name.to_owned(),
+            })?;
+        self.ensure_no_proposals()?;
+        let mut by_hash = self.by_hash.write().expect("poisoned lock");
+        ensure_unused(head.revisions.iter().map(|r| &r.revision), &by_hash)?;
+
+        let persisted = self.persist_worker.persisted();
+        let discarded: Vec<_> = head.revisions.iter().map(|r| &*r.revision).collect();
+        let latest = match persisted.discard(&head.base.revision, &discarded, &persisted) {
+            Ok(latest) => Arc::new(latest),
+            Err(err) => {
+                self.persist_worker.poison();
+                return Err(err.into());
+            }
+        };
 
-        // then reparent any proposals that have this proposal as a parent
-        for p in &*self.proposals.lock().expect("poisoned lock") {
-            proposal.commit_reparent(p);
+        let head = heads.remove(name).expect("head was found above");
+        for retained in &head.revisions {
+            if let Some(hash) = retained.revision.root_hash().or_default_root_hash() {
+                remove_hash(&mut by_hash, &hash, &retained.revision);
+            }
         }
+        reindex(&mut by_hash, &historical, &heads);
+        self.persist_worker.set_persisted(latest);
 
-        if crate::logger::trace_enabled() {
-            let merkle = Merkle::from(committed);
-            if let Ok(s) = merkle.dump_to_string() {
-                trace!("{s}");
+        gauge!("firewood.heads").set(heads.len() as f64);
+        Ok(())
+    }
+
+    /// Makes the named head `name` the main head, and deletes it.
+    ///
+    /// The revisions of the main head after the base of the named head are
+    /// discarded, as by [`RevisionManager::rollback_to`], and replaced by the
+    /// revisions of the named head. The same restrictions apply.
+    pub fn promote_head(&self, name: &str) -> Result<(), RevisionManagerError> {
+        self.persist_worker.flush()?;
+
+        let mut historical = self.historical.write().expect("poisoned lock");
+        let mut heads = self.heads.write().expect("poisoned lock");
+        let head = heads
+            .get(name)
+            .ok_or_else(|| RevisionManagerError::HeadNotFound {
+                name: name.to_owned(),
+            })?;
+        let first_discarded = historical
+            .iter()
+            .position(|r| Arc::ptr_eq(&r.revision, &head.base.revision))
+            .expect("the base of a head is retained")
+            .saturating_add(1);
+        self.ensure_no_proposals()?;
+        let mut by_hash = self.by_hash.write().expect("poisoned lock");
+        ensure_unused(
+            historical.range(first_discarded..).map(|r| &r.revision),
+            &by_hash,
+        )?;
+
+        let newer: Vec<_> = historical
+            .range(first_discarded..)
+            .map(|r| &*r.revision)
+            .collect();
+        let latest = match self.persist_worker.persisted().discard(
+            &head.base.revision,
+            &newer,
+            &head.tip().revision,
+        ) {
+            Ok(latest) => Arc::new(latest),
+            Err(err) => {
+                self.persist_worker.poison();
+                return Err(err.into());
+            }
+        };
+
+        let head = heads.remove(name).expect("head was found above");
+        for discarded in historical.drain(first_discarded..) {
+            if let Some(hash) = discarded.revision.root_hash().or_default_root_hash() {
+                remove_hash(&mut by_hash, &hash, &discarded.revision);
+            }
+        }
+        historical.extend(head.revisions);
+        reindex(&mut by_hash, &historical, &heads);
+        self.prune_pins(&historical);
+        self.persist_worker.set_persisted(latest);
+
+        gauge!("firewood.heads").set(heads.len() as f64);
+        gauge!("firewood.active_revisions").set(historical.len() as f64);
+        Ok(())
+    }
+
+    /// Drops the proposals no longer referenced by anyone else, and fails if
+    /// any are left, since they may be built on a revision about to be
+    /// discarded.
+    fn ensure_no_proposals(&self) -> Result<(), RevisionManagerError> {
+        let mut proposals = self.proposals.lock().expect("poisoned lock");
+        proposals.retain(|p| Arc::strong_count(p) > 1);
+        match proposals.first() {
+            Some(proposal) => Err(RevisionManagerError::RevisionInUse {
+                provided: proposal.root_hash(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Make the retained revision with `root_hash` the latest one again.
+    ///
+    /// The revisions committed after it are discarded, and the nodes they
+    /// wrote are returned to the free lists. This waits for pending revisions
+    /// to be persisted first, and must not race with a commit.
+    ///
+    /// Fails with [`RevisionManagerError::RevisionInUse`], changing nothing,
+    /// if a discarded revision or any proposal is still referenced outside
+    /// the manager, since their nodes are about to be reused. If the storage
+    /// cannot be rolled back, later commits fail until the database is
+    /// reopened.
+    pub fn rollback_to(&self, root_hash: HashKey) -> Result<(), RevisionManagerError> {
+        // the discarded revisions must be on disk to find the nodes they wrote
+        self.persist_worker.flush()?;
+
+        let mut historical = self.historical.write().expect("poisoned lock");
+        let target = historical
+            .iter()
+            .rposition(|r| {
+                r.revision.root_hash().or_default_root_hash().as_ref() == Some(&root_hash)
+            })
+            .ok_or(RevisionManagerError::RevisionNotFound {
+                provided: root_hash.clone(),
+            })?;
+        let first_discarded = target.saturating_add(1);
+        if first_discarded == historical.len() {
+            return Ok(());
+        }
+
+        let heads = self.heads.read().expect("poisoned lock");
+        // any proposal is built on the latest revision or one of its own
+        self.ensure_no_proposals()?;
+        let mut by_hash = self.by_hash.write().expect("poisoned lock");
+        // a head based on a discarded revision holds a reference to it
+        ensure_unused(
+            historical.range(first_discarded..).map(|r| &r.revision),
+            &by_hash,
+        )?;
+
+        let target_revision = &historical
+            .get(target)
+            .expect("position is in range")
+            .revision;
+        let newer: Vec<_> = historical
+            .range(first_discarded..)
+            .map(|r| &*r.revision)
+            .collect();
+        let rolled_back = match self
+            .persist_worker
+            .persisted()
+            .rollback(target_revision, &newer)
+        {
+            Ok(rolled_back) => Arc::new(rolled_back),
+            Err(err) => {
+                self.persist_worker.poison();
+                return Err(err.into());
+            }
+        };
+
+        for discarded in historical.drain(first_discarded..) {
+            if let Some(hash) = discarded.revision.root_hash().or_default_root_hash() {
+                remove_hash(&mut by_hash, &hash, &discarded.revision);
             }
         }
+        // a discarded revision may have had the same hash as a retained one
+        reindex(&mut by_hash, &historical, &heads);
+        self.prune_pins(&historical);
+        self.persist_worker.set_persisted(rolled_back);
 
+        gauge!("firewood.active_revisions").set(historical.len() as f64);
         Ok(())
     }
 
@@ -287,19 +882,40 @@ impl RevisionManager {
         }
 
         // 2. Try to find it in proposals.
-        let proposal = self
-            .proposals
+        if let Some(proposal) = self.proposal(&root_hash) {
+            return Ok(proposal);
+        }
+
+        // 3. Try to find it in `RootStore`.
+        Ok(self.revision_from_root_store(root_hash)?)
+    }
+
+    /// Find an uncommitted proposal by its root hash.
+    pub fn proposal(&self, root_hash: &HashKey) -> Option<ProposedRevision> {
+        self.proposals
             .lock()
             .expect("poisoned lock")
             .iter()
-            .find(|p| p.root_hash().as_ref() == Some(&root_hash))
-            .cloned();
+            .find(|p| p.root_hash().as_ref() == Some(root_hash))
+            .cloned()
+    }
 
-        if let Some(proposal) = proposal {
-            return Ok(proposal);
+    /// Find a committed revision, either among the revisions kept in memory
+    /// or in the `RootStore`.
+    pub fn committed_revision(
+        &self,
+        root_hash: HashKey,
+    ) -> Result<CommittedRevision, RevisionManagerError> {
+        match self.revision(root_hash.clone()) {
+            Ok(committed) => Ok(committed),
+            Err(_) => self.revision_from_root_store(root_hash),
         }
+    }
 
-        // 3. Try to find it in `RootStore`.
+    fn revision_from_root_store(
+        &self,
+        root_hash: HashKey,
+    ) -> Result<CommittedRevision, RevisionManagerError> {
         let revision_addr =
             self.root_store
                 .get(&root_hash)?
@@ -322,11 +938,12 @@ impl RevisionManager {
 
     /// TODO: should we support fetching all hashes from `RootStore`?
     pub fn all_hashes(&self) -> Vec<TrieHash> {
-        self.historical
-            .read()
-            .expect("poisoned lock")
+        let historical = self.historical.read().expect("poisoned lock");
+        let heads = self.heads.read().expect("poisoned lock");
+        historical
             .iter()
-            .filter_map(|r| r.root_hash().or_default_root_hash())
+            .chain(heads.values().flat_map(|head| &head.revisions))
+            .filter_map(|r| r.revision.root_hash().or_default_root_hash())
             .chain(
                 self.proposals
                     .lock()
@@ -337,6 +954,63 @@ impl RevisionManager {
             .collect()
     }
 
+    /// The changes `proposal` makes to the revision it was proposed on top of.
+    ///
+    /// If the parent proposal was committed while it was being looked up, the
+    /// committed revision, which holds the same trie, is used instead.
+    pub fn diff_from_parent(
+        &self,
+        proposal: &ProposedRevision,
+    ) -> Result<BoxKeyChangeIter<'static>, RevisionManagerError> {
+        let proposal = Arc::clone(proposal);
+        Ok(match self.parent_revision(&proposal)? {
+            ParentRevision::Proposed(parent) => Box::new(DiffIter::new(parent, proposal)),
+            ParentRevision::Committed(parent) => Box::new(DiffIter::new(parent, proposal)),
+            ParentRevision::Empty => Box::new(DiffIter::from_empty(proposal)),
+        })
+    }
+
+    /// The changes from the revision `proposal` was proposed on top of to the
+    /// committed revision `to`.
+    pub fn diff_parent_to(
+        &self,
+        proposal: &ProposedRevision,
+        to: CommittedRevision,
+    ) -> Result<BoxKeyChangeIter<'static>, RevisionManagerError> {
+        Ok(match self.parent_revision(proposal)? {
+            ParentRevision::Proposed(parent) => Box::new(DiffIter::new(parent, to)),
+            ParentRevision::Committed(parent) => Box::new(DiffIter::new(parent, to)),
+            ParentRevision::Empty => Box::new(DiffIter::from_empty(to)),
+        })
+    }
+
+    /// Finds the revision `proposal` was proposed on top of.
+    fn parent_revision(
+        &self,
+        proposal: &ProposedRevision,
+    ) -> Result<ParentRevision, RevisionManagerError> {
+        let parent_hash = match proposal.parent() {
+            NodeStoreParent::Committed(hash) => hash,
+            NodeStoreParent::Proposed(parent) => {
+                let parent_proposal = self
+                    .proposals
+                    .lock()
+                    .expect("poisoned lock")
+                    .iter()
+                    .find(|p| p.is_parent_of(proposal))
+                    .cloned();
+                if let Some(parent) = parent_proposal {
+                    return Ok(ParentRevision::Proposed(parent));
+                }
+                parent.root_hash()
+            }
+        };
+        match parent_hash {
+            None => Ok(ParentRevision::Empty),
+            Some(hash) => Ok(ParentRevision::Committed(self.revision(hash)?)),
+        }
+    }
+
     pub fn revision(&self, root_hash: HashKey) -> Result<CommittedRevision, RevisionManagerError> {
         self.by_hash
             .read()
@@ -348,11 +1022,46 @@ impl RevisionManager {
             })
     }
 
+    /// Wait until every committed revision has been persisted.
+    ///
+    /// Returns the error from the first revision that could not be persisted, if any.
+    /// After such a failure, no later revision is persisted and this (and every commit)
+    /// returns [`RevisionManagerError::PersistFailed`].
+    pub fn flush(&self) -> Result<(), RevisionManagerError> {
+        self.persist_worker.flush()
+    }
+
+    /// Returns the most recent revision that has been persisted.
+    ///
+    /// Unlike [`RevisionManager::current_revision`], the header of this revision
+    /// matches what is on disk.
+    pub fn persisted_revision(&self) -> CommittedRevision {
+        self.persist_worker.persisted()
+    }
+
+    /// Runs `write`, which writes the nodes of `proposal` past the end of the
+    /// file, while no revision is being persisted.
+    ///
+    /// The end of the file `proposal` allocates from is moved past every area
+    /// allocated so far, and no revision persisted later allocates below the
+    /// end `write` leaves it at, whether or not `proposal` is committed.
+    pub fn write_at_end<T>(
+        &self,
+        proposal: &mut NodeStore<MutableProposal, FileBacked>,
+        write: impl FnOnce(&mut NodeStore<MutableProposal, FileBacked>) -> T,
+    ) -> T {
+        self.persist_worker.write_at_end(proposal, write)
+    }
+
     pub fn root_hash(&self) -> Result<Option<HashKey>, RevisionManagerError> {
         Ok(self.current_revision().root_hash())
     }
 
     pub fn current_revision(&self) -> CommittedRevision {
+        self.current().revision
+    }
+
+    fn current(&self) -> Retained {
         self.historical
             .read()
             .expect("poisoned lock")
@@ -366,19 +1075,376 @@ impl RevisionManager {
     /// # Panics
     ///
     /// Panics if the it cannot create a thread pool.
-    pub fn threadpool(&self) -> &ThreadPool {
+    pub fn threadpool(&self) -> &Arc<ThreadPool> {
         // Note that OnceLock currently doesn't support get_or_try_init (it is available in a
         // nightly release). The get_or_init should be replaced with get_or_try_init once it
         // is available to allow the error to be passed back to the caller.
         self.threadpool.get_or_init(|| {
-            ThreadPoolBuilder::new()
-                .num_threads(BranchNode::MAX_CHILDREN)
-                .build()
-                .expect("Error in creating threadpool")
+            Arc::new(
+                ThreadPoolBuilder::new()
+                    .num_threads(BranchNode::MAX_CHILDREN)
+                    .build()
+                    .expect("Error in creating threadpool"),
+            )
         })
     }
 }
 
+/// Fails with [`RevisionManagerError::RevisionInUse`] if any of `revisions`
+/// is referenced by anything but the manager, such as a [`Head`] based on it.
+fn ensure_unused<'a>(
+    revisions: impl IntoIterator<Item = &'a CommittedRevision>,
+    by_hash: &HashMap<TrieHash, CommittedRevision>,
+) -> Result<(), RevisionManagerError> {
+    for revision in revisions {
+        // one reference from the list holding it, and one from `by_hash` if
+        // that is where its hash leads
+        let hash = revision.root_hash().or_default_root_hash();
+        let indexed = hash
+            .as_ref()
+            .and_then(|hash| by_hash.get(hash))
+            .is_some_and(|indexed| Arc::ptr_eq(indexed, revision));
+        let owners = if indexed { 2 } else { 1 };
+        if Arc::strong_count(revision) > owners {
+            return Err(RevisionManagerError::RevisionInUse { provided: hash });
+        }
+    }
+    Ok(())
+}
+
+/// Removes `hash` from `by_hash` if it leads to `revision`, which may share
+/// its hash with a revision of another head.
+fn remove_hash(
+    by_hash: &mut HashMap<TrieHash, CommittedRevision>,
+    hash: &TrieHash,
+    revision: &CommittedRevision,
+) {
+    if by_hash
+        .get(hash)
+        .is_some_and(|indexed| Arc::ptr_eq(indexed, revision))
+    {
+        by_hash.remove(hash);
+    }
+}
+
+/// Makes sure every retained revision of the main head and the named heads
+/// can be found by its hash.
+fn reindex(
+    by_hash: &mut HashMap<TrieHash, CommittedRevision>,
+    historical: &VecDeque<Retained>,
+    heads: &HashMap<String, Head>,
+) {
+    let retained = historical
+        .iter()
+        .chain(heads.values().flat_map(|head| &head.revisions));
+    for retained in retained {
+        if let Some(hash) = retained.revision.root_hash().or_default_root_hash() {
+            by_hash
+                .entry(hash)
+                .or_insert_with(|| retained.revision.clone());
+        }
+    }
+}
+
+/// A committed revision waiting to be written by the [`PersistWorker`].
+#[derive(Debug)]
+struct PersistJob {
+    /// The proposal that was committed.
+    proposal: ProposedRevision,
+    /// Revisions that were dropped when this proposal was committed, oldest first.
+    /// The nodes only they used are returned to the free lists before this
+    /// revision is written.
+    reaped: Vec<Reap>,
+    /// Whether the proposal was committed to a named head, so the header
+    /// written keeps the root of the main head.
+    fork: bool,
+}
+
+/// A revision of the main head dropped by [`RevisionManager::reap`].
+#[derive(Debug)]
+enum Reap {
+    /// The oldest retained revision; the nodes of its parent it no longer
+    /// used are freed.
+    Oldest { pending: PendingDeletes },
+    /// A revision between two retained ones, `parent` and the one whose
+    /// pending deletes are `child`.
+    Between {
+        parent: Box<NodeStore<Committed, FileBacked>>,
+        revision: Box<NodeStore<Committed, FileBacked>>,
+        pending: PendingDeletes,
+        child: PendingDeletes,
+    },
+}
+
+#[derive(Debug, Default)]
+struct PersistState {
+    /// The number of jobs handed to the worker
+    submitted: u64,
+    /// The number of jobs the worker has finished with
+    completed: u64,
+    /// The error from the first job that failed, until it is reported
+    error: Option<RevisionManagerError>,
+    /// Whether any job has failed; once set, no more jobs are persisted
+    failed: bool,
+}
+
+impl PersistState {
+    fn take_error(&mut self) -> Result<(), RevisionManagerError> {
+        match self.error.take() {
+            Some(err) => Err(err),
+            None if self.failed => Err(RevisionManagerError::PersistFailed),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes committed revisions to disk on a background thread.
+///
+/// Revisions are persisted one at a time, in the order they were committed, so the
+/// free lists each revision allocates from are the ones left behind by its parent.
+/// Committed revisions are usable from memory before they are persisted: their nodes
+/// are shared with the copy being persisted, and are read from disk only once they
+/// have been written.
+#[derive(Debug)]
+struct PersistWorker {
+    sender: Option<SyncSender<PersistJob>>,
+    handle: Option<JoinHandle<()>>,
+    state: Arc<(Mutex<PersistState>, Condvar)>,
+    persisted: Arc<Mutex<CommittedRevision>>,
+    /// The end of the space written past the end of the file by
+    /// [`RevisionManager::write_at_end`]; nothing is allocated below it.
+    reserved: Arc<Mutex<u64>>,
+}
+
+impl PersistWorker {
+    fn new(
+        latest: CommittedRevision,
+        root_store: Arc<dyn RootStore + Send + Sync>,
+        max_pending: usize,
+    ) -> Result<Self, RevisionManagerError> {
+        // bound the number of revisions that can be waiting on disk, so commits
+        // block rather than run arbitrarily far ahead of persistence
+        let (sender, receiver) = sync_channel(max_pending.max(1));
+        let state = Arc::new((Mutex::new(PersistState::default()), Condvar::new()));
+        let persisted = Arc::new(Mutex::new(latest));
+        let reserved = Arc::new(Mutex::new(0));
+
+        let handle = std::thread::Builder::new()
+            .name("firewood-persist".to_string())
+            .spawn({
+                let state = state.clone();
+                let persisted = persisted.clone();
+                let reserved = reserved.clone();
+                move || {
+                    Self::run(
+                        &receiver,
+                        root_store.as_ref(),
+                        &state,
+                        &persisted,
+                        &reserved,
+                    );
+                }
+            })
+            .map_err(|e| FileIoError::from_generic_no_file(e, "spawn persist worker"))?;
+
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+            state,
+            persisted,
+            reserved,
+        })
+    }
+
+    fn run(
+        receiver: &Receiver<PersistJob>,
+        root_store: &(dyn RootStore + Send + Sync),
+        state: &(Mutex<PersistState>, Condvar),
+        persisted: &Mutex<CommittedRevision>,
+        reserved: &Mutex<u64>,
+    ) {
+        let (lock, cvar) = state;
+        // how long until the revisions persisted since the last sync must be
+        // synced, under a periodic durability policy
+        let mut sync_due: Option<Duration> = None;
+        loop {
+            let job = match sync_due {
+                None => match receiver.recv() {
+                    Ok(job) => job,
+                    Err(_) => break,
+                },
+                Some(timeout) => match receiver.recv_timeout(timeout) {
+                    Ok(job) => job,
+                    Err(RecvTimeoutError::Timeout) => {
+                        sync_due = None;
+                        let latest = persisted.lock().expect("poisoned lock").clone();
+                        if let Err(err) = latest.sync_if_due() {
+                            let mut state = lock.lock().expect("poisoned lock");
+                            Self::record_error(&mut state, err.into());
+                        }
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => break,
+                },
+            };
+
+            let failed = lock.lock().expect("poisoned lock").failed;
+            // held until `persisted` is updated, so that whatever writes past
+            // the end of the file sees every area this revision allocates
+            let reserved = reserved.lock().expect("poisoned lock");
+            let result = if failed {
+                // the free lists are no longer known, so nothing more can be written
+                Err(RevisionManagerError::PersistFailed)
+            } else {
+                let latest = persisted.lock().expect("poisoned lock").clone();
+                std::panic::catch_unwind(AssertUnwindSafe(|| {
+                    Self::persist(&latest, job, root_store, *reserved)
+                }))
+                .unwrap_or(Err(RevisionManagerError::PersistFailed))
+            };
+
+            let mut state = lock.lock().expect("poisoned lock");
+            match result {
+                Ok(revision) => {
+                    // nothing syncs this revision if no later commit arrives in time
+                    sync_due = revision.sync_if_due().unwrap_or_else(|err| {
+                        Self::record_error(&mut state, err.into());
+                        None
+                    });
+                    *persisted.lock().expect("poisoned lock") = revision;
+                }
+                Err(err) => Self::record_error(&mut state, err),
+            }
+            drop(reserved);
+            state.completed = state.completed.wrapping_add(1);
+            cvar.notify_all();
+        }
+    }
+
+    fn record_error(state: &mut PersistState, err: RevisionManagerError) {
+        if !state.failed {
+            warn!("Failed to persist revision: {err}");
+            state.error = Some(err);
+            state.failed = true;
+        }
+    }
+
+    fn persist(
+        latest: &NodeStore<Committed, FileBacked>,
+        job: PersistJob,
+        root_store: &(dyn RootStore + Send + Sync),
+        reserved: u64,
+    ) -> Result<CommittedRevision, RevisionManagerError> {
+        // allocate from the free lists of the last revision written to disk
+        let mut committed = job.proposal.as_committed(latest);
+        committed.reserve_to(reserved);
+        for reaped in job.reaped {
+            match reaped {
+                Reap::Oldest { pending } => {
+                    committed.reap_nodes(take(&mut *pending.lock().expect("poisoned lock")))?;
+                }
+                Reap::Between {
+                    parent,
+                    revision,
+                    pending,
+                    child,
+                } => {
+                    let deleted = take(&mut *pending.lock().expect("poisoned lock"));
+                    let mut child = child.lock().expect("poisoned lock");
+                    *child = revision.reap_between(
+                        &parent,
+                        deleted,
+                        take(&mut *child),
+                        &mut committed,
+                    )?;
+                }
+            }
+        }
+
+        let main = if job.fork {
+            Some(committed.persist_on_fork(latest)?)
+        } else {
+            committed.persist()?;
+            None
+        };
+
+        if let (Some(hash), Some(address)) = (committed.root_hash(), committed.root_address()) {
+            root_store.add_root(&hash, &address)?;
+        }
+
+        Ok(Arc::new(main.unwrap_or(committed)))
+    }
+
+    fn submit(&self, job: PersistJob) -> Result<(), RevisionManagerError> {
+        let (lock, _) = &*self.state;
+        let mut state = lock.lock().expect("poisoned lock");
+        state.submitted = state.submitted.wrapping_add(1);
+        drop(state);
+
+        self.sender
+            .as_ref()
+            .expect("sender is only taken on drop")
+            .send(job)
+            .map_err(|_| RevisionManagerError::PersistFailed)
+    }
+
+    fn take_error(&self) -> Result<(), RevisionManagerError> {
+        let (lock, _) = &*self.state;
+        lock.lock().expect("poisoned lock").take_error()
+    }
+
+    /// Stops any more revisions from being persisted, after the free lists
+    /// on disk were left in an unknown state outside the worker.
+    fn poison(&self) {
+        let (lock, _) = &*self.state;
+        lock.lock().expect("poisoned lock").failed = true;
+    }
+
+    /// Replaces the last revision written to disk, which later revisions
+    /// allocate from. The worker must be idle.
+    fn set_persisted(&self, revision: CommittedRevision) {
+        *self.persisted.lock().expect("poisoned lock") = revision;
+    }
+
+    fn flush(&self) -> Result<(), RevisionManagerError> {
+        let (lock, cvar) = &*self.state;
+        let mut state = cvar
+            .wait_while(lock.lock().expect("poisoned lock"), |state| {
+                state.completed != state.submitted
+            })
+            .expect("poisoned lock");
+        state.take_error()
+    }
+
+    fn persisted(&self) -> CommittedRevision {
+        self.persisted.lock().expect("poisoned lock").clone()
+    }
+
+    fn write_at_end<T>(
+        &self,
+        proposal: &mut NodeStore<MutableProposal, FileBacked>,
+        write: impl FnOnce(&mut NodeStore<MutableProposal, FileBacked>) -> T,
+    ) -> T {
+        let mut reserved = self.reserved.lock().expect("poisoned lock");
+        proposal.reserve_to(reserved.max(self.persisted().size()));
+        let result = write(proposal);
+        *reserved = proposal.size();
+        result
+    }
+}
+
+impl Drop for PersistWorker {
+    fn drop(&mut self) {
+        // closing the channel lets the worker drain the queue and exit
+        drop(self.sender.take());
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            warn!("persist worker panicked");
+        }
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used)]
 mod tests {
@@ -389,8 +1455,8 @@ mod tests {
     #[cfg(test)]
     impl RevisionManager {
         /// Extract the root store by consuming the revision manager instance.
-        pub fn into_root_store(self) -> Box<dyn RootStore + Send + Sync> {
-            self.root_store
+        pub fn into_root_store(self) -> Arc<dyn RootStore + Send + Sync> {
+            self.root_store.clone()
         }
     }
 
@@ -407,7 +1473,7 @@ mod tests {
 
         // First database instance should open successfully
         let first_manager =
-            RevisionManager::new(db_path.clone(), config.clone(), Box::new(NoOpStore {}));
+            RevisionManager::new(db_path.clone(), config.clone(), Arc::new(NoOpStore {}));
         assert!(
             first_manager.is_ok(),
             "First database should open successfully"
@@ -415,7 +1481,7 @@ mod tests {
 
         // Second database instance should fail to open due to file locking
         let second_manager =
-            RevisionManager::new(db_path.clone(), config.clone(), Box::new(NoOpStore {}));
+            RevisionManager::new(db_path.clone(), config.clone(), Arc::new(NoOpStore {}));
         assert!(
             second_manager.is_err(),
             "Second database should fail to open"
@@ -435,7 +1501,7 @@ mod tests {
         drop(first_manager.unwrap());
 
         // Now the second database should open successfully
-        let third_manager = RevisionManager::new(db_path, config, Box::new(NoOpStore {}));
+        let third_manager = RevisionManager::new(db_path, config, Arc::new(NoOpStore {}));
         assert!(
             third_manager.is_ok(),
             "Database should open after first instance is dropped"
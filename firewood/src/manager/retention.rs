@@ -0,0 +1,147 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! Policies deciding which committed revisions are retained.
+//!
+//! Each time a revision is committed to the main head, the retention policy
+//! is asked about every retained revision but the latest two. Those it does
+//! not retain, and that are not pinned, are dropped, and the nodes no
+//! retained revision uses any more are reused. A revision that is still
+//! referenced, for example by a view or by the base of a named head, cannot
+//! be dropped yet, and stays until a later commit.
+
+use std::fmt::Debug;
+use std::num::NonZeroU64;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::v2::api::HashKey;
+
+/// What a [`RetentionPolicy`] knows about a committed revision.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct RevisionInfo {
+    /// The number of revisions committed before this one since the database
+    /// was opened. The revision it was opened at is number zero.
+    pub number: u64,
+    /// When the revision was committed, or, for the revision the database
+    /// was opened at, when it was opened.
+    pub committed_at: Instant,
+    /// The root hash of the revision, or `None` if its trie is empty.
+    pub root_hash: Option<HashKey>,
+}
+
+/// Decides which committed revisions are retained.
+///
+/// The retention policy of a database is set with
+/// [`DbConfig::retention`](crate::db::DbConfig::retention). Without one,
+/// the latest [`max_revisions`](super::RevisionManagerConfig) revisions are
+/// retained, as by [`KeepLast`].
+pub trait RetentionPolicy: Debug + Send + Sync {
+    /// Whether `revision` should still be retained, now that `latest` was
+    /// committed.
+    fn retain(&self, revision: &RevisionInfo, latest: &RevisionInfo) -> bool;
+}
+
+/// Retains the latest `n` revisions, counting the latest one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeepLast(pub usize);
+
+impl RetentionPolicy for KeepLast {
+    fn retain(&self, revision: &RevisionInfo, latest: &RevisionInfo) -> bool {
+        let n = u64::try_from(self.0).unwrap_or(u64::MAX);
+        latest.number.saturating_sub(revision.number) < n
+    }
+}
+
+/// Retains the revisions committed at most this long before the latest one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct KeepFor(pub Duration);
+
+impl RetentionPolicy for KeepFor {
+    fn retain(&self, revision: &RevisionInfo, latest: &RevisionInfo) -> bool {
+        latest
+            .committed_at
+            .saturating_duration_since(revision.committed_at)
+            <= self.0
+    }
+}
+
+/// Retains every revision whose number is a multiple of `every`, among the
+/// latest `every * count` revisions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Checkpoints {
+    /// How many revisions apart checkpoints are.
+    pub every: NonZeroU64,
+    /// How many checkpoints are retained.
+    pub count: u64,
+}
+
+impl RetentionPolicy for Checkpoints {
+    fn retain(&self, revision: &RevisionInfo, latest: &RevisionInfo) -> bool {
+        let age = latest.number.saturating_sub(revision.number);
+        revision.number % self.every == 0 && age / self.every < self.count
+    }
+}
+
+/// Retains every revision, so that nothing is ever reaped.
+///
+/// The file only grows, and every revision stays in memory until the
+/// database is closed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Archival;
+
+impl RetentionPolicy for Archival {
+    fn retain(&self, _revision: &RevisionInfo, _latest: &RevisionInfo) -> bool {
+        true
+    }
+}
+
+/// Retains the revisions any of the policies retains.
+#[derive(Clone, Debug, Default)]
+pub struct AnyOf(pub Vec<Arc<dyn RetentionPolicy>>);
+
+impl RetentionPolicy for AnyOf {
+    fn retain(&self, revision: &RevisionInfo, latest: &RevisionInfo) -> bool {
+        self.0.iter().any(|policy| policy.retain(revision, latest))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn info(number: u64, committed_at: Instant) -> RevisionInfo {
+        RevisionInfo {
+            number,
+            committed_at,
+            root_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_policies() {
+        let start = Instant::now();
+        let latest = info(20, start + Duration::from_secs(20));
+        let retained = |policy: &dyn RetentionPolicy| {
+            (0..=20)
+                .filter(|&n| policy.retain(&info(n, start + Duration::from_secs(n)), &latest))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(retained(&KeepLast(3)), [18, 19, 20]);
+        assert_eq!(retained(&KeepFor(Duration::from_secs(2))), [18, 19, 20]);
+        let checkpoints = Checkpoints {
+            every: NonZeroU64::new(5).unwrap(),
+            count: 2,
+        };
+        assert_eq!(retained(&checkpoints), [15, 20]);
+        assert_eq!(retained(&Archival).len(), 21);
+        assert_eq!(
+            retained(&AnyOf(vec![Arc::new(KeepLast(2)), Arc::new(checkpoints)])),
+            [15, 19, 20]
+        );
+        assert!(retained(&AnyOf::default()).is_empty());
+    }
+}
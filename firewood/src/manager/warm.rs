@@ -0,0 +1,245 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! Warming the node cache when a database is opened.
+//!
+//! The top levels of the latest revision are read into the cache, optionally
+//! followed by the addresses that were hot when the database was last closed.
+//! Those addresses are kept in a file next to the database, named after it
+//! with a `.hot` suffix.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+
+use firewood_storage::logger::{info, warn};
+use firewood_storage::{Committed, FileBacked, FileIoError, LinearAddress, NodeStore};
+use typed_builder::TypedBuilder;
+
+use super::CommittedRevision;
+
+/// Identifies a hot node file, and its version
+const HOT_NODES_MAGIC: &[u8; 8] = b"fwdhot\0\x01";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, TypedBuilder)]
+/// Cache warming configuration
+pub struct CacheWarmingConfig {
+    /// The number of levels of the latest revision's trie to read into the
+    /// node cache when the database is opened. Zero disables this.
+    #[builder(default = 0)]
+    pub levels: usize,
+
+    /// Whether the cache is warmed on a background thread. If not set,
+    /// opening the database returns once the cache is warm.
+    #[builder(default = false)]
+    pub background: bool,
+
+    /// Whether the addresses of the cached nodes are saved when the database
+    /// is closed, and read back into the cache when it is next opened.
+    #[builder(default = false)]
+    pub hot_nodes: bool,
+}
+
+/// Warms the node cache and saves its hot addresses when dropped.
+#[derive(Debug)]
+pub(super) struct CacheWarmer {
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+    /// Where to save the hot node addresses on drop, and the storage whose
+    /// cache they are taken from
+    hot_nodes: Option<(PathBuf, Arc<FileBacked>)>,
+}
+
+impl CacheWarmer {
+    /// Start warming the cache of `latest`, the revision just opened from `db_path`.
+    ///
+    /// Any saved hot node addresses are consumed, so that a stale list is never
+    /// reused after a crash. If `truncate` is set they are discarded.
+    pub(super) fn start(
+        config: CacheWarmingConfig,
+        db_path: &Path,
+        storage: &Arc<FileBacked>,
+        latest: &CommittedRevision,
+        truncate: bool,
+    ) -> Result<Self, FileIoError> {
+        let hot_path = hot_nodes_path(db_path);
+        let hot_addresses = if config.hot_nodes && !truncate {
+            take_hot_nodes(&hot_path)
+        } else {
+            remove_hot_nodes(&hot_path);
+            Vec::new()
+        };
+
+        let mut warmer = Self {
+            cancel: Arc::new(AtomicBool::new(false)),
+            handle: None,
+            hot_nodes: config.hot_nodes.then(|| (hot_path, Arc::clone(storage))),
+        };
+        if config.levels == 0 && hot_addresses.is_empty() {
+            return Ok(warmer);
+        }
+
+        if !config.background {
+            warm(latest, config.levels, hot_addresses, &warmer.cancel)?;
+            return Ok(warmer);
+        }
+
+        // warm a copy of the revision, so that the thread does not keep
+        // `latest` from being reaped while it runs
+        let latest = NodeStore::open(Arc::clone(storage))?;
+        let cancel = Arc::clone(&warmer.cancel);
+        let handle = std::thread::Builder::new()
+            .name("firewood-warm".to_string())
+            .spawn(move || {
+                if let Err(err) = warm(&latest, config.levels, hot_addresses, &cancel) {
+                    warn!("Failed to warm the node cache: {err}");
+                }
+            })
+            .map_err(|e| FileIoError::from_generic_no_file(e, "spawn cache warmer"))?;
+        warmer.handle = Some(handle);
+        Ok(warmer)
+    }
+}
+
+impl Drop for CacheWarmer {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take()
+            && handle.join().is_err()
+        {
+            warn!("cache warmer panicked");
+        }
+
+        if let Some((path, storage)) = self.hot_nodes.take()
+            && let Err(err) = write_hot_nodes(&path, &storage.hot_addresses())
+        {
+            warn!(
+                "Failed to save hot node addresses to {}: {err}",
+                path.display()
+            );
+        }
+    }
+}
+
+fn warm(
+    latest: &NodeStore<Committed, FileBacked>,
+    levels: usize,
+    hot_addresses: Vec<LinearAddress>,
+    cancel: &AtomicBool,
+) -> Result<(), FileIoError> {
+    let from_levels = latest.warm_levels(levels, cancel)?;
+    let from_hot_nodes = latest.warm_addresses(hot_addresses, cancel);
+    info!(
+        "Warmed the node cache with {from_levels} nodes from the top levels and {from_hot_nodes} hot nodes"
+    );
+    Ok(())
+}
+
+fn hot_nodes_path(db_path: &Path) -> PathBuf {
+    let mut path = db_path.as_os_str().to_owned();
+    path.push(".hot");
+    PathBuf::from(path)
+}
+
+/// Read and remove the saved hot node addresses, if any
+fn take_hot_nodes(path: &Path) -> Vec<LinearAddress> {
+    let addresses = match read_hot_nodes(path) {
+        Ok(addresses) => addresses,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Vec::new(),
+        Err(err) => {
+            warn!(
+                "Ignoring unreadable hot node file {}: {err}",
+                path.display()
+            );
+            Vec::new()
+        }
+    };
+    remove_hot_nodes(path);
+    addresses
+}
+
+fn remove_hot_nodes(path: &Path) {
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => {
+            warn!("Failed to remove hot node file {}: {err}", path.display());
+        }
+        _ => {}
+    }
+}
+
+fn read_hot_nodes(path: &Path) -> std::io::Result<Vec<LinearAddress>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != HOT_NODES_MAGIC {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidData,
+            "not a hot node file",
+        ));
+    }
+
+    let mut addresses = Vec::new();
+    let mut buf = [0u8; 8];
+    loop {
+        match reader.read_exact(&mut buf) {
+            Ok(()) => addresses.extend(LinearAddress::new(u64::from_le_bytes(buf))),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(addresses),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn write_hot_nodes(path: &Path, addresses: &[LinearAddress]) -> std::io::Result<()> {
+    // write a temporary file first, so a crash never leaves a truncated list
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    writer.write_all(HOT_NODES_MAGIC)?;
+    for addr in addresses {
+        writer.write_all(&addr.get().to_le_bytes())?;
+    }
+    writer
+        .into_inner()
+        .map_err(std::io::IntoInnerError::into_error)?
+        .sync_all()?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hot_nodes_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = hot_nodes_path(&dir.path().join("db"));
+        assert_eq!(path, dir.path().join("db.hot"));
+
+        let addresses: Vec<_> = [16u64, 4096, 32]
+            .into_iter()
+            .map(|a| LinearAddress::new(a).unwrap())
+            .collect();
+        write_hot_nodes(&path, &addresses).unwrap();
+        assert_eq!(take_hot_nodes(&path), addresses);
+
+        // the list is consumed when read
+        assert!(!path.exists());
+        assert!(take_hot_nodes(&path).is_empty());
+    }
+
+    #[test]
+    fn test_hot_nodes_rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("db.hot");
+        std::fs::write(&path, b"not hot nodes").unwrap();
+
+        assert!(read_hot_nodes(&path).is_err());
+        assert!(take_hot_nodes(&path).is_empty());
+    }
+}
@@ -0,0 +1,318 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! Building a trie bottom-up from key/value pairs in ascending key order.
+//!
+//! Inserting keys one at a time walks down from the root for every key and
+//! restructures the nodes along the way. When the keys arrive sorted, each
+//! node is complete as soon as a key outside of its subtrie is seen, so the
+//! trie can instead be assembled from the leaves up, and every node is built
+//! exactly once, in the post-order the persist path writes them.
+//!
+//! A node is hashed and written to the end of the file as soon as it is
+//! complete, and its parent only keeps its address and hash. Only the nodes
+//! along the path of the last key are ever held in memory.
+
+use firewood_storage::{
+    BranchNode, Child, Children, FileIoError, LeafNode, MutableProposal, NibblesIterator, Node,
+    NodeStore, Path, PathComponent, WritableStorage,
+};
+
+use crate::merkle::{Key, Value};
+use crate::v2::api;
+
+/// A node whose subtrie may still receive keys.
+///
+/// The open nodes form a chain from the root: the path of each one is a
+/// prefix of the path of the next.
+#[derive(Debug)]
+struct OpenNode {
+    /// The nibbles from the root to this node.
+    path: Vec<u8>,
+    value: Option<Value>,
+    children: Children<Option<Child>>,
+}
+
+impl OpenNode {
+    /// Closes this node below a node whose path is `parent_len` nibbles long,
+    /// returning the index of the child slot it belongs in, the path from the
+    /// root to that slot and the node.
+    fn close(mut self, parent_len: usize) -> (PathComponent, Path, Node) {
+        let index = self
+            .path
+            .get(parent_len)
+            .copied()
+            .and_then(PathComponent::try_new)
+            .expect("a child is longer than its parent");
+        let partial_path = self.path.split_off(parent_len.saturating_add(1));
+        (
+            index,
+            Path::from_nibbles_iterator(self.path.into_iter()),
+            Self::into_node(
+                Path::from_nibbles_iterator(partial_path.into_iter()),
+                self.value,
+                self.children,
+            ),
+        )
+    }
+
+    fn into_node(
+        partial_path: Path,
+        value: Option<Value>,
+        children: Children<Option<Child>>,
+    ) -> Node {
+        match value {
+            Some(value) if children.count() == 0 => Node::Leaf(LeafNode {
+                partial_path,
+                value,
+            }),
+            value => Node::Branch(Box::new(BranchNode {
+                partial_path,
+                value,
+                children,
+            })),
+        }
+    }
+}
+
+/// Builds a trie from key/value pairs pushed in strictly ascending key order,
+/// writing every node but the root to the end of the file of `nodestore`.
+#[derive(Debug)]
+pub(crate) struct BulkBuilder<'a, S> {
+    nodestore: &'a mut NodeStore<MutableProposal, S>,
+    stack: Vec<OpenNode>,
+    previous: Option<Key>,
+}
+
+impl<'a, S: WritableStorage> BulkBuilder<'a, S> {
+    pub(crate) const fn new(nodestore: &'a mut NodeStore<MutableProposal, S>) -> Self {
+        Self {
+            nodestore,
+            stack: Vec::new(),
+            previous: None,
+        }
+    }
+
+    /// Adds the next key/value pair.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::UnsortedBulkLoad`] if `key` is not greater than
+    /// the previous key, or [`api::Error::FileIO`] if a node cannot be
+    /// written.
+    pub(crate) fn push(&mut self, key: &[u8], value: Value) -> Result<(), api::Error> {
+        if let Some(previous) = self.previous.take()
+            && *key <= *previous
+        {
+            return Err(api::Error::UnsortedBulkLoad {
+                previous,
+                key: key.into(),
+            });
+        }
+        self.previous = Some(key.into());
+
+        let path: Vec<u8> = NibblesIterator::new(key).collect();
+        while let Some(top) = self.stack.last() {
+            if path.starts_with(&top.path) {
+                break;
+            }
+            let common = top
+                .path
+                .iter()
+                .zip(&path)
+                .take_while(|(a, b)| a == b)
+                .count();
+            let node = self.stack.pop().expect("checked above");
+            let parent_len = self.stack.last().map(|parent| parent.path.len());
+            if parent_len.is_none_or(|parent_len| parent_len < common) {
+                // the new key diverges from the closed node below its
+                // parent, so they share a new branch
+                self.stack.push(OpenNode {
+                    path: path.get(..common).expect("common prefix").to_vec(),
+                    value: None,
+                    children: Children::new(),
+                });
+            }
+            self.write_child(node).map_err(api::Error::FileIO)?;
+        }
+
+        self.stack.push(OpenNode {
+            path,
+            value: Some(value),
+            children: Children::new(),
+        });
+        Ok(())
+    }
+
+    /// Closes the remaining open nodes, returning the root of the trie, or
+    /// `None` if no pairs were pushed. The root is the only node not written
+    /// yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`api::Error::FileIO`] if a node cannot be written.
+    pub(crate) fn finish(mut self) -> Result<Option<Node>, api::Error> {
+        while self.stack.len() > 1 {
+            let node = self.stack.pop().expect("length checked");
+            self.write_child(node).map_err(api::Error::FileIO)?;
+        }
+        Ok(self.stack.pop().map(|root| {
+            OpenNode::into_node(
+                Path::from_nibbles_iterator(root.path.into_iter()),
+                root.value,
+                root.children,
+            )
+        }))
+    }
+
+    /// Hashes and writes `node`, whose subtrie is complete, and adds it to
+    /// the open node now on top of the stack by its address and hash.
+    fn write_child(&mut self, node: OpenNode) -> Result<(), FileIoError> {
+        let parent = self.stack.last_mut().expect("a child has a parent");
+        let (index, path_prefix, node) = node.close(parent.path.len());
+
+        #[cfg(feature = "ethhash")]
+        let (node, hash) = self.nodestore.hash_helper(node, path_prefix)?;
+        #[cfg(not(feature = "ethhash"))]
+        let (node, hash) = NodeStore::<MutableProposal, S>::hash_helper(node, path_prefix)?;
+        let node = node.as_shared_node(&*self.nodestore)?;
+        let address = self.nodestore.write_node_at_end(&node)?;
+
+        let parent = self.stack.last_mut().expect("checked above");
+        parent.children[index] = Some(Child::AddressWithHash(address, hash));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::unwrap_used)]
+
+    use firewood_storage::{HashedNodeReader, ImmutableProposal, MemStore, NodeStore};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::merkle::Merkle;
+    use crate::merkle::tests::create_in_memory_merkle;
+
+    fn bulk_root_hash(pairs: &[(&[u8], &[u8])]) -> Option<firewood_storage::TrieHash> {
+        let mut merkle = create_in_memory_merkle();
+        let mut builder = BulkBuilder::new(&mut merkle.nodestore);
+        for (key, value) in pairs {
+            builder.push(key, Box::from(*value)).unwrap();
+        }
+        let root = builder.finish().unwrap();
+        *merkle.nodestore.root_mut() = root;
+        let merkle: Merkle<NodeStore<Arc<ImmutableProposal>, MemStore>> =
+            merkle.try_into().unwrap();
+        merkle.nodestore.root_hash()
+    }
+
+    fn inserted_root_hash(pairs: &[(&[u8], &[u8])]) -> Option<firewood_storage::TrieHash> {
+        let mut merkle = create_in_memory_merkle();
+        for (key, value) in pairs {
+            merkle.insert(key, Box::from(*value)).unwrap();
+        }
+        let merkle: Merkle<NodeStore<Arc<ImmutableProposal>, MemStore>> =
+            merkle.try_into().unwrap();
+        merkle.nodestore.root_hash()
+    }
+
+    #[test]
+    fn matches_inserting_each_key() {
+        let cases: &[&[(&[u8], &[u8])]] = &[
+            &[],
+            &[(b"", b"empty")],
+            &[(b"a", b"1")],
+            &[(b"", b"empty"), (b"a", b"1"), (b"ab", b"2")],
+            &[(b"a", b"1"), (b"ab", b"2"), (b"abc", b"3"), (b"b", b"4")],
+            &[
+                (b"\x00", b"1"),
+                (b"\x01", b"2"),
+                (b"\x10", b"3"),
+                (b"\xff", b"4"),
+            ],
+            &[(b"abc", b"1"), (b"abd", b"2"), (b"b", b"3"), (b"ba", b"4")],
+        ];
+        for pairs in cases {
+            assert_eq!(
+                bulk_root_hash(pairs),
+                inserted_root_hash(pairs),
+                "{pairs:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_inserting_random_keys() {
+        let rng = firewood_storage::SeededRng::from_env_or_random();
+        let mut keys: Vec<Vec<u8>> = (0..500)
+            .map(|_| {
+                let len = rng.random_range(0..8);
+                (0..len).map(|_| rng.random_range(0..4)).collect()
+            })
+            .collect();
+        keys.sort();
+        keys.dedup();
+        let pairs: Vec<(&[u8], &[u8])> = keys.iter().map(|key| (&key[..], &key[..])).collect();
+        assert_eq!(bulk_root_hash(&pairs), inserted_root_hash(&pairs));
+    }
+
+    #[test]
+    fn holds_only_the_path_of_the_last_key() {
+        let mut merkle = create_in_memory_merkle();
+        let mut builder = BulkBuilder::new(&mut merkle.nodestore);
+        for n in 0u32..5_000 {
+            let key = n.to_be_bytes();
+            builder.push(&key, Box::from(&key[..])).unwrap();
+
+            // at most one open node per nibble of the key, plus the root,
+            // whose children have all been written
+            assert!(builder.stack.len() <= 9);
+            assert!(builder.stack.iter().all(|open| {
+                open.children
+                    .iter()
+                    .all(|(_, child)| !matches!(child, Some(Child::Node(_))))
+            }));
+        }
+    }
+
+    #[test]
+    fn matches_inserting_accounts_and_storage() {
+        // under ethhash, the only storage slot of an account is hashed as
+        // if it were a root, once its sibling count is known
+        let account = |n: u8| [n; 32].to_vec();
+        let slot = |n: u8, slot: u8| [[n; 32], [slot; 32]].concat();
+        let keys = [
+            account(1),
+            slot(1, 7),
+            account(2),
+            slot(2, 3),
+            slot(2, 9),
+            account(3),
+        ];
+        let pairs: Vec<(&[u8], &[u8])> = keys
+            .iter()
+            .map(|key| (&key[..], key.get(..8).unwrap()))
+            .collect();
+        assert_eq!(bulk_root_hash(&pairs), inserted_root_hash(&pairs));
+    }
+
+    #[test]
+    fn rejects_unsorted_keys() {
+        let mut merkle = create_in_memory_merkle();
+        let mut builder = BulkBuilder::new(&mut merkle.nodestore);
+        builder.push(b"b", Box::from(&b"1"[..])).unwrap();
+        assert!(matches!(
+            builder.push(b"a", Box::from(&b"2"[..])),
+            Err(api::Error::UnsortedBulkLoad { .. })
+        ));
+
+        let mut builder = BulkBuilder::new(&mut merkle.nodestore);
+        builder.push(b"a", Box::from(&b"1"[..])).unwrap();
+        assert!(matches!(
+            builder.push(b"a", Box::from(&b"2"[..])),
+            Err(api::Error::UnsortedBulkLoad { .. })
+        ));
+    }
+}
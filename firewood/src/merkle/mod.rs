@@ -7,6 +7,10 @@ pub(crate) mod tests;
 /// Parallel merkle
 pub mod parallel;
 
+mod multi_get;
+
+pub(crate) mod bulk;
+
 use crate::iter::{MerkleKeyValueIter, PathIterator, TryExtend};
 use crate::proof::{Proof, ProofCollection, ProofError, ProofNode};
 use crate::range_proof::RangeProof;
@@ -441,12 +445,30 @@ impl<T: TrieReader> Merkle<T> {
         Ok(node.value().map(|v| v.to_vec().into_boxed_slice()))
     }
 
+    /// Get the value of `key` when `key` is a `NibblesIterator`
+    pub(crate) fn get_value_from_iter(
+        &self,
+        key: NibblesIterator<'_>,
+    ) -> Result<Option<Value>, FileIoError> {
+        let Some(node) = self.get_node_from_iter(key)? else {
+            return Ok(None);
+        };
+        Ok(node.value().map(|v| v.to_vec().into_boxed_slice()))
+    }
+
     pub(crate) fn get_node(&self, key: &[u8]) -> Result<Option<SharedNode>, FileIoError> {
+        self.get_node_from_iter(NibblesIterator::new(key))
+    }
+
+    fn get_node_from_iter(
+        &self,
+        key: NibblesIterator<'_>,
+    ) -> Result<Option<SharedNode>, FileIoError> {
         let Some(root) = self.root() else {
             return Ok(None);
         };
 
-        let key = Path::from_nibbles_iterator(NibblesIterator::new(key));
+        let key = Path::from_nibbles_iterator(key);
         get_helper(&self.nodestore, &root, &key)
     }
 }
@@ -623,15 +645,63 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
         Ok(())
     }
 
+    /// Map `key` to the value returned by `make_value`, which is given the
+    /// current value of `key`, if any.
+    /// Each element of `key` is 2 nibbles.
+    pub fn insert_with(
+        &mut self,
+        key: &[u8],
+        make_value: impl FnOnce(Option<&[u8]>) -> Value,
+    ) -> Result<(), FileIoError> {
+        self.insert_with_from_iter(NibblesIterator::new(key), make_value)
+    }
+
+    /// Map `key` to the value returned by `make_value`, which is given the
+    /// current value of `key`, if any. The key is found and updated in a
+    /// single pass over the trie.
+    pub fn insert_with_from_iter(
+        &mut self,
+        key: NibblesIterator<'_>,
+        make_value: impl FnOnce(Option<&[u8]>) -> Value,
+    ) -> Result<(), FileIoError> {
+        let key = Path::from_nibbles_iterator(key);
+        let root = self.nodestore.root_mut();
+        let Some(root_node) = std::mem::take(root) else {
+            // The trie is empty. Create a new leaf node and set it as the root.
+            let root_node = Node::Leaf(LeafNode {
+                partial_path: key,
+                value: make_value(None),
+            });
+            *root = root_node.into();
+            return Ok(());
+        };
+
+        let root_node = self.insert_with_helper(root_node, key.as_ref(), make_value)?;
+        *self.nodestore.root_mut() = root_node.into();
+        Ok(())
+    }
+
     /// Map `key` to `value` into the subtrie rooted at `node`.
     /// Each element of `key` is 1 nibble.
     /// Returns the new root of the subtrie.
-    #[expect(clippy::missing_panics_doc)]
     pub fn insert_helper(
         &mut self,
-        mut node: Node,
+        node: Node,
         key: &[u8],
         value: Value,
+    ) -> Result<Node, FileIoError> {
+        self.insert_with_helper(node, key, |_| value)
+    }
+
+    /// Map `key` to the value returned by `make_value` into the subtrie rooted
+    /// at `node`. `make_value` is given the current value of `key`, if any.
+    /// Each element of `key` is 1 nibble.
+    /// Returns the new root of the subtrie.
+    fn insert_with_helper(
+        &mut self,
+        mut node: Node,
+        key: &[u8],
+        make_value: impl FnOnce(Option<&[u8]>) -> Value,
     ) -> Result<Node, FileIoError> {
         // 4 possibilities for the position of the `key` relative to `node`:
         // 1. The node is at `key`
@@ -653,6 +723,7 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
         ) {
             (None, None) => {
                 // 1. The node is at `key`
+                let value = make_value(node.value());
                 node.update_value(value);
                 counter!("firewood.insert", "merkle" => "update").increment(1);
                 Ok(node)
@@ -668,7 +739,7 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
                 //                       node
                 let mut branch = BranchNode {
                     partial_path: path_overlap.shared.into(),
-                    value: Some(value),
+                    value: Some(make_value(None)),
                     children: Children::new(),
                 };
 
@@ -693,7 +764,7 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
                             // There is no child at this index.
                             // Create a new leaf and put it here.
                             let new_leaf = Node::Leaf(LeafNode {
-                                value,
+                                value: make_value(None),
                                 partial_path,
                             });
                             branch.children[child_index] = Some(Child::Node(new_leaf));
@@ -701,7 +772,8 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
                             return Ok(node);
                         };
                         let child = self.read_for_update(child)?;
-                        let child = self.insert_helper(child, partial_path.as_ref(), value)?;
+                        let child =
+                            self.insert_with_helper(child, partial_path.as_ref(), make_value)?;
                         branch.children[child_index] = Some(Child::Node(child));
                         Ok(node)
                     }
@@ -714,7 +786,7 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
                         };
 
                         let new_leaf = Node::Leaf(LeafNode {
-                            value,
+                            value: make_value(None),
                             partial_path,
                         });
 
@@ -745,7 +817,7 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
                 branch.children[node_index] = Some(Child::Node(node));
 
                 let new_leaf = Node::Leaf(LeafNode {
-                    value,
+                    value: make_value(None),
                     partial_path: key_partial_path,
                 });
                 branch.children[key_index] = Some(Child::Node(new_leaf));
@@ -954,6 +1026,112 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
         }
     }
 
+    /// Removes any key-value pairs with keys in the half-open range
+    /// `[start, end)`, or from `start` onwards if `end` is `None`.
+    /// Returns the number of key-value pairs removed.
+    ///
+    /// Subtries that lie entirely within the range are removed whole, and
+    /// subtries that lie entirely outside of it are never read.
+    pub fn remove_range(&mut self, start: &[u8], end: Option<&[u8]>) -> Result<usize, FileIoError> {
+        self.remove_range_from_iter(
+            &[],
+            NibblesIterator::new(start),
+            end.map(NibblesIterator::new),
+        )
+    }
+
+    /// Removes any key-value pairs with keys in the half-open range
+    /// `[start, end)` from a trie whose root is at `root_path`, which is
+    /// empty unless this trie is a subtrie of a larger one. `root_path`,
+    /// `start` and `end` are full paths in nibbles.
+    pub fn remove_range_from_iter(
+        &mut self,
+        root_path: &[u8],
+        start: NibblesIterator<'_>,
+        end: Option<NibblesIterator<'_>>,
+    ) -> Result<usize, FileIoError> {
+        let start = Path::from_nibbles_iterator(start);
+        let end = end.map(Path::from_nibbles_iterator);
+        let root = self.nodestore.root_mut();
+        let Some(root_node) = std::mem::take(root) else {
+            // The trie is empty. There is nothing to remove.
+            counter!("firewood.remove", "prefix" => "range", "result" => "nonexistent")
+                .increment(1);
+            return Ok(0);
+        };
+
+        let mut deleted = 0;
+        let root_node = self.remove_range_helper(
+            root_node,
+            root_path,
+            start.as_ref(),
+            end.as_deref(),
+            &mut deleted,
+        )?;
+        counter!("firewood.remove", "prefix" => "range", "result" => "success")
+            .increment(deleted as u64);
+        *self.nodestore.root_mut() = root_node;
+        Ok(deleted)
+    }
+
+    /// Removes the keys in `[start, end)` from the subtrie rooted at `node`,
+    /// whose parent is at `parent_path`. All paths are in nibbles.
+    fn remove_range_helper(
+        &mut self,
+        node: Node,
+        parent_path: &[u8],
+        start: &[u8],
+        end: Option<&[u8]>,
+        deleted: &mut usize,
+    ) -> Result<Option<Node>, FileIoError> {
+        let path: Vec<u8> = parent_path
+            .iter()
+            .chain(node.partial_path().iter())
+            .copied()
+            .collect();
+
+        let mut branch = match node {
+            Node::Leaf(_) => {
+                return if range_contains(start, end, &path) {
+                    *deleted = deleted.saturating_add(1);
+                    Ok(None)
+                } else {
+                    Ok(Some(node))
+                };
+            }
+            Node::Branch(branch) => branch,
+        };
+
+        if subtrie_within_range(start, end, &path) {
+            self.delete_children(branch, deleted)?;
+            return Ok(None);
+        }
+
+        if branch.value.is_some() && range_contains(start, end, &path) {
+            branch.value = None;
+            *deleted = deleted.saturating_add(1);
+        }
+
+        for (child_index, child_slot) in &mut branch.children {
+            let child_path: Vec<u8> = path
+                .iter()
+                .copied()
+                .chain(once(child_index.as_u8()))
+                .collect();
+            if !subtrie_overlaps_range(start, end, &child_path) {
+                continue;
+            }
+            let Some(child) = child_slot.take() else {
+                continue;
+            };
+            let child = self.read_for_update(child)?;
+            let child = self.remove_range_helper(child, &child_path, start, end, deleted)?;
+            *child_slot = child.map(Child::Node);
+        }
+
+        self.flatten_branch(branch)
+    }
+
     /// Recursively deletes all children of a branch node.
     fn delete_children(
         &mut self,
@@ -1045,6 +1223,21 @@ impl<S: ReadableStorage> Merkle<NodeStore<MutableProposal, S>> {
     }
 }
 
+/// Returns true if the key at `path` is within `[start, end)`.
+fn range_contains(start: &[u8], end: Option<&[u8]>, path: &[u8]) -> bool {
+    path >= start && end.is_none_or(|end| path < end)
+}
+
+/// Returns true if every key starting with `path` is within `[start, end)`.
+fn subtrie_within_range(start: &[u8], end: Option<&[u8]>, path: &[u8]) -> bool {
+    path >= start && end.is_none_or(|end| path < end && !end.starts_with(path))
+}
+
+/// Returns true if some key starting with `path` may be within `[start, end)`.
+fn subtrie_overlaps_range(start: &[u8], end: Option<&[u8]>, path: &[u8]) -> bool {
+    (path >= start || start.starts_with(path)) && end.is_none_or(|end| path < end)
+}
+
 /// Returns an iterator where each element is the result of combining
 /// 2 nibbles of `nibbles`. If `nibbles` is odd length, panics in
 /// debug mode and drops the final nibble in release mode.
@@ -0,0 +1,222 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! Looking up many keys in one walk of the trie.
+//!
+//! The keys are sorted, so that the keys below any node are contiguous. Each
+//! node on a path shared by several keys is then read once, rather than once
+//! per key.
+
+use super::{Merkle, Value};
+use firewood_storage::{
+    Child, Children, FileIoError, NibblesIterator, Node, Path, PathComponent, TrieReader,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// A key, as nibbles, along with its position in the caller's list of keys
+type IndexedKey<'a> = (usize, &'a [u8]);
+
+/// The values found so far, along with the positions of their keys
+type Found = Vec<(usize, Value)>;
+
+/// The number of keys above which the children of the root are searched in parallel
+const PARALLEL_THRESHOLD: usize = 64;
+
+impl<T: TrieReader + Sync> Merkle<T> {
+    /// Returns the value of each key in `keys`, in the same order.
+    ///
+    /// Once there are enough keys, the subtries below the root are searched
+    /// in parallel on the current rayon thread pool. Run this within
+    /// [`rayon::ThreadPool::install`] to use a specific pool.
+    pub(crate) fn get_values<K: AsRef<[u8]>>(
+        &self,
+        keys: &[K],
+    ) -> Result<Vec<Option<Value>>, FileIoError> {
+        let mut values = vec![None; keys.len()];
+        let Some(root) = self.root() else {
+            return Ok(values);
+        };
+
+        let nibbles: Vec<Path> = keys
+            .iter()
+            .map(|key| Path::from_nibbles_iterator(NibblesIterator::new(key.as_ref())))
+            .collect();
+        let mut sorted: Vec<IndexedKey<'_>> = nibbles
+            .iter()
+            .enumerate()
+            .map(|(index, key)| (index, &**key))
+            .collect();
+        sorted.sort_unstable_by(|a, b| a.1.cmp(b.1));
+
+        let mut found = Found::new();
+        let (below, child_depth) = visit_node(&root, &sorted, 0, &mut found);
+        if let Node::Branch(branch) = &*root {
+            let groups: Vec<&[IndexedKey<'_>]> = below
+                .chunk_by(|a, b| a.1.get(child_depth) == b.1.get(child_depth))
+                .collect();
+            let search = |group: &[IndexedKey<'_>]| -> Result<Found, FileIoError> {
+                let mut found = Found::new();
+                search_child(
+                    &self.nodestore,
+                    &branch.children,
+                    group,
+                    child_depth,
+                    &mut found,
+                )?;
+                Ok(found)
+            };
+
+            if sorted.len() >= PARALLEL_THRESHOLD {
+                for group_found in groups
+                    .into_par_iter()
+                    .map(search)
+                    .collect::<Result<Vec<_>, _>>()?
+                {
+                    found.extend(group_found);
+                }
+            } else {
+                for group in groups {
+                    found.extend(search(group)?);
+                }
+            }
+        }
+
+        for (index, value) in found {
+            if let Some(slot) = values.get_mut(index) {
+                *slot = Some(value);
+            }
+        }
+        Ok(values)
+    }
+}
+
+/// Records the value of the keys in `keys` that end at `node`, which starts
+/// at nibble `depth` of each key.
+///
+/// Returns the keys that continue below `node`, and the depth of the nibble
+/// that selects their child.
+fn visit_node<'k, 'a>(
+    node: &Node,
+    keys: &'k [IndexedKey<'a>],
+    depth: usize,
+    found: &mut Found,
+) -> (&'k [IndexedKey<'a>], usize) {
+    let partial_path: &[u8] = node.partial_path();
+    let child_depth = depth.saturating_add(partial_path.len());
+    let at_or_below = |key: &[u8]| {
+        key.get(depth..)
+            .is_some_and(|rest| rest.starts_with(partial_path))
+    };
+
+    // the keys are sorted, so the ones sharing this node's path are contiguous
+    let Some(start) = keys.iter().position(|(_, key)| at_or_below(key)) else {
+        return (&[], child_depth);
+    };
+    let matching = keys.get(start..).unwrap_or_default();
+    let len = matching
+        .iter()
+        .take_while(|(_, key)| at_or_below(key))
+        .count();
+    let matching = matching.get(..len).unwrap_or_default();
+
+    // keys that end at this node sort first
+    let ending = matching
+        .iter()
+        .take_while(|(_, key)| key.len() == child_depth)
+        .count();
+    if let Some(value) = node.value() {
+        for (index, _) in matching.get(..ending).unwrap_or_default() {
+            found.push((*index, value.into()));
+        }
+    }
+
+    (matching.get(ending..).unwrap_or_default(), child_depth)
+}
+
+/// Searches the child selected by nibble `depth` of `keys`, which all share it.
+fn search_child<T: TrieReader>(
+    nodestore: &T,
+    children: &Children<Option<Child>>,
+    keys: &[IndexedKey<'_>],
+    depth: usize,
+    found: &mut Found,
+) -> Result<(), FileIoError> {
+    let Some(nibble) = keys.first().and_then(|(_, key)| key.get(depth)) else {
+        return Ok(());
+    };
+    let index = PathComponent::try_new(*nibble).expect("index is in bounds");
+    let depth = depth.saturating_add(1);
+    match children[index].as_ref() {
+        None => Ok(()),
+        Some(Child::Node(child)) => search(nodestore, child, keys, depth, found),
+        Some(Child::AddressWithHash(addr, _)) => {
+            let child = nodestore.read_node(*addr)?;
+            search(nodestore, &child, keys, depth, found)
+        }
+        Some(Child::MaybePersisted(maybe_persisted, _)) => {
+            let child = maybe_persisted.as_shared_node(nodestore)?;
+            search(nodestore, &child, keys, depth, found)
+        }
+    }
+}
+
+/// Records the value of each key in `keys` found in the subtrie rooted at
+/// `node`, which starts at nibble `depth` of each key.
+fn search<T: TrieReader>(
+    nodestore: &T,
+    node: &Node,
+    keys: &[IndexedKey<'_>],
+    depth: usize,
+    found: &mut Found,
+) -> Result<(), FileIoError> {
+    let (below, child_depth) = visit_node(node, keys, depth, found);
+    let Node::Branch(branch) = node else {
+        return Ok(());
+    };
+    for group in below.chunk_by(|a, b| a.1.get(child_depth) == b.1.get(child_depth)) {
+        search_child(nodestore, &branch.children, group, child_depth, found)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use crate::merkle::tests::create_in_memory_merkle;
+
+    #[test]
+    fn test_get_values_in_input_order() {
+        let mut merkle = create_in_memory_merkle();
+        let keys: Vec<Vec<u8>> = (0u16..500)
+            .map(|i| (i * 7).to_be_bytes().to_vec())
+            .collect();
+        for key in &keys {
+            merkle.insert(key, key.clone().into_boxed_slice()).unwrap();
+        }
+        // a key that is a prefix of others, with a value of its own
+        merkle.insert(&[0x01], Box::new([0xAA])).unwrap();
+
+        let merkle = merkle.hash();
+        let mut lookups: Vec<Vec<u8>> = keys.iter().rev().step_by(3).cloned().collect();
+        lookups.push(vec![0x01]);
+        lookups.push(vec![0x01, 0x02, 0x03]); // below a leaf
+        lookups.push(vec![0xFF, 0xFF]); // missing
+        lookups.push(vec![]); // the root, which has no value
+        lookups.push(lookups[0].clone()); // duplicate
+
+        for lookups in [&lookups[..], &lookups[..10]] {
+            let values = merkle.get_values(lookups).unwrap();
+            assert_eq!(values.len(), lookups.len());
+            for (key, value) in lookups.iter().zip(values) {
+                assert_eq!(value, merkle.get_value(key).unwrap(), "key {key:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_values_empty() {
+        let merkle = create_in_memory_merkle().hash();
+        assert_eq!(merkle.get_values(&[b"a", b"b"]).unwrap(), vec![None, None]);
+        assert!(merkle.get_values::<&[u8]>(&[]).unwrap().is_empty());
+    }
+}
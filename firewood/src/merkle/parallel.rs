@@ -2,8 +2,8 @@
 // See the file LICENSE.md for licensing terms.
 
 use crate::db::BatchOp;
-use crate::merkle::{Key, Merkle, Value};
-use crate::v2::api::KeyValuePairIter;
+use crate::merkle::{Key, Merkle, Value, range_contains, subtrie_overlaps_range};
+use crate::v2::api::{ConditionFailed, KeyValuePairIter, MergeOperator};
 use firewood_storage::logger::error;
 use firewood_storage::{
     BranchNode, Child, Children, FileBacked, FileIoError, ImmutableProposal, LeafNode,
@@ -41,6 +41,8 @@ pub enum CreateProposalError {
     FileIoError(FileIoError),
     SendError,
     InvalidConversionToPathComponent,
+    ConditionFailed(ConditionFailed),
+    NoMergeOperator,
 }
 
 impl From<FileIoError> for CreateProposalError {
@@ -49,6 +51,12 @@ impl From<FileIoError> for CreateProposalError {
     }
 }
 
+impl From<ConditionFailed> for CreateProposalError {
+    fn from(err: ConditionFailed) -> Self {
+        CreateProposalError::ConditionFailed(err)
+    }
+}
+
 impl From<SendError<BatchOp<Key, Value>>> for CreateProposalError {
     fn from(_err: SendError<BatchOp<Key, Value>>) -> Self {
         CreateProposalError::SendError
@@ -61,9 +69,20 @@ impl From<SendError<BatchOp<Key, Value>>> for CreateProposalError {
 #[derive(Debug, Default)]
 pub struct ParallelMerkle {
     workers: Children<Option<WorkerSender>>,
+    merge_operator: Option<Arc<dyn MergeOperator>>,
 }
 
 impl ParallelMerkle {
+    /// Creates a `ParallelMerkle` whose workers apply merges with
+    /// `merge_operator`.
+    #[must_use]
+    pub fn new(merge_operator: Option<Arc<dyn MergeOperator>>) -> Self {
+        Self {
+            workers: Children::default(),
+            merge_operator,
+        }
+    }
+
     /// Force the root (if necessary) into a branch with no partial path to allow the clean
     /// separation of the trie into an array of subtries that can be operated on independently
     /// by the worker threads.
@@ -168,30 +187,16 @@ impl ParallelMerkle {
     fn worker_event_loop(
         mut merkle: Merkle<NodeStore<MutableProposal, FileBacked>>,
         first_path_component: PathComponent,
+        merge_operator: Option<&dyn MergeOperator>,
         child_receiver: Receiver<BatchOp<Key, Value>>,
-        response_sender: Sender<Result<Response, FileIoError>>,
-    ) -> Result<(), Box<SendError<Result<Response, FileIoError>>>> {
+        response_sender: Sender<Result<Response, CreateProposalError>>,
+    ) -> Result<(), Box<SendError<Result<Response, CreateProposalError>>>> {
         // Wait for a message on the receiver child channel. Break out of loop when the sender has
         // closed the child sender.
         while let Ok(request) = child_receiver.recv() {
-            if let Err(err) = match request {
-                // insert a key-value pair into the subtrie
-                BatchOp::Put { key, value } => {
-                    let mut nibbles_iter = NibblesIterator::new(&key);
-                    nibbles_iter.next(); // Skip the first nibble
-                    merkle.insert_from_iter(nibbles_iter, value)
-                }
-                BatchOp::Delete { key } => {
-                    let mut nibbles_iter = NibblesIterator::new(&key);
-                    nibbles_iter.next(); // Skip the first nibble
-                    merkle.remove_from_iter(nibbles_iter).map(|_| ())
-                }
-                BatchOp::DeleteRange { prefix } => {
-                    let mut nibbles_iter = NibblesIterator::new(&prefix);
-                    nibbles_iter.next(); // Skip the first nibble
-                    merkle.remove_prefix_from_iter(nibbles_iter).map(|_| ())
-                }
-            } {
+            if let Err(err) =
+                ParallelMerkle::apply(&mut merkle, first_path_component, merge_operator, request)
+            {
                 response_sender.send(Err(err))?;
                 break; // Stop handling additional requests
             }
@@ -223,6 +228,56 @@ impl ParallelMerkle {
         Ok(())
     }
 
+    /// Applies one operation to a worker's subtrie, checking its condition
+    /// first if it has one. The first nibble of the key is skipped, as it
+    /// selects the subtrie.
+    fn apply(
+        merkle: &mut Merkle<NodeStore<MutableProposal, FileBacked>>,
+        first_path_component: PathComponent,
+        merge_operator: Option<&dyn MergeOperator>,
+        request: BatchOp<Key, Value>,
+    ) -> Result<(), CreateProposalError> {
+        fn subtrie_key(key: &[u8]) -> NibblesIterator<'_> {
+            let mut nibbles_iter = NibblesIterator::new(key);
+            nibbles_iter.next(); // Skip the first nibble
+            nibbles_iter
+        }
+        if request.is_conditional() {
+            let current = merkle.get_value_from_iter(subtrie_key(request.key()))?;
+            request.check_condition(current.as_deref())?;
+        }
+        match request {
+            // insert a key-value pair into the subtrie
+            BatchOp::Put { key, value }
+            | BatchOp::PutIfAbsent { key, value }
+            | BatchOp::CompareAndSwap { key, value, .. } => {
+                merkle.insert_from_iter(subtrie_key(&key), value)?;
+            }
+            BatchOp::Delete { key } | BatchOp::DeleteIfEquals { key, .. } => {
+                merkle.remove_from_iter(subtrie_key(&key))?;
+            }
+            BatchOp::DeleteRange { prefix } => {
+                merkle.remove_prefix_from_iter(subtrie_key(&prefix))?;
+            }
+            BatchOp::DeleteKeyRange { start, end } => {
+                // The range bounds are full keys, so the subtrie is given its
+                // own path instead of skipping the first nibble.
+                merkle.remove_range_from_iter(
+                    &[first_path_component.as_u8()],
+                    NibblesIterator::new(&start),
+                    end.as_deref().map(NibblesIterator::new),
+                )?;
+            }
+            BatchOp::Merge { key, operand } => {
+                let merge_operator = merge_operator.ok_or(CreateProposalError::NoMergeOperator)?;
+                merkle.insert_with_from_iter(subtrie_key(&key), |existing| {
+                    merge_operator.merge(&key, existing, &operand)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
     /// Creates a worker for performing operations on a subtrie, with the subtrie being determined
     /// by the value of the `first_path_component`.
     fn create_worker(
@@ -230,7 +285,8 @@ impl ParallelMerkle {
         proposal: &NodeStore<MutableProposal, FileBacked>,
         root_branch: &mut BranchNode,
         first_path_component: PathComponent,
-        response_sender: Sender<Result<Response, FileIoError>>,
+        merge_operator: Option<Arc<dyn MergeOperator>>,
+        response_sender: Sender<Result<Response, CreateProposalError>>,
     ) -> Result<WorkerSender, FileIoError> {
         // Create a channel for the coordinator (main thread) to send messages to this worker.
         let (child_sender, child_receiver) = mpsc::channel();
@@ -260,6 +316,7 @@ impl ParallelMerkle {
             if let Err(err) = ParallelMerkle::worker_event_loop(
                 Merkle::from(worker_nodestore),
                 first_path_component,
+                merge_operator.as_deref(),
                 child_receiver,
                 response_sender,
             ) {
@@ -273,10 +330,10 @@ impl ParallelMerkle {
     // root node of the main trie.
     fn merge_children(
         &mut self,
-        response_channel: Receiver<Result<Response, FileIoError>>,
+        response_channel: Receiver<Result<Response, CreateProposalError>>,
         proposal: &mut NodeStore<MutableProposal, FileBacked>,
         root_branch: &mut BranchNode,
-    ) -> Result<(), FileIoError> {
+    ) -> Result<(), CreateProposalError> {
         while let Ok(response) = response_channel.recv() {
             match response {
                 Ok(response) => {
@@ -302,7 +359,7 @@ impl ParallelMerkle {
         proposal: &NodeStore<MutableProposal, FileBacked>,
         root_branch: &mut BranchNode,
         first_path_component: PathComponent,
-        response_sender: Sender<Result<Response, FileIoError>>,
+        response_sender: Sender<Result<Response, CreateProposalError>>,
     ) -> Result<&mut WorkerSender, FileIoError> {
         // Find the worker's state corresponding to the first nibble which is stored in an array.
         let worker_option = self.workers.get_mut(first_path_component);
@@ -316,6 +373,7 @@ impl ParallelMerkle {
                 proposal,
                 root_branch,
                 first_path_component,
+                self.merge_operator.clone(),
                 response_sender,
             )?)),
         }
@@ -341,18 +399,68 @@ impl ParallelMerkle {
         Ok(())
     }
 
+    /// Removes the keys in the half-open range `[start, end)` of a
+    /// [`BatchOp::DeleteKeyRange`]. The main thread removes the value of the
+    /// root if it is in the range, and the operation is sent to every worker
+    /// whose subtrie overlaps the range.
+    fn split_key_range(
+        &mut self,
+        pool: &ThreadPool,
+        proposal: &NodeStore<MutableProposal, FileBacked>,
+        root_branch: &mut BranchNode,
+        (start, end): (&[u8], Option<&[u8]>),
+        response_sender: &Sender<Result<Response, CreateProposalError>>,
+        response_receiver: &Receiver<Result<Response, CreateProposalError>>,
+    ) -> Result<(), CreateProposalError> {
+        let start_path = Path::from_nibbles_iterator(NibblesIterator::new(start));
+        let end_path = end.map(|end| Path::from_nibbles_iterator(NibblesIterator::new(end)));
+        if range_contains(&start_path, end_path.as_deref(), &[]) {
+            root_branch.value = None;
+        }
+        for first_path_component in PathComponent::ALL {
+            let overlaps = subtrie_overlaps_range(
+                &start_path,
+                end_path.as_deref(),
+                &[first_path_component.as_u8()],
+            );
+            // A subtrie without a worker or a child is empty.
+            let is_empty = self.workers.get(first_path_component).is_none()
+                && root_branch.children.get(first_path_component).is_none();
+            if !overlaps || is_empty {
+                continue;
+            }
+            let worker = self.worker(
+                pool,
+                proposal,
+                root_branch,
+                first_path_component,
+                response_sender.clone(),
+            )?;
+            let op = BatchOp::DeleteKeyRange {
+                start: start.into(),
+                end: end.map(Into::into),
+            };
+            if let Err(err) = worker.send(op) {
+                // See `find_worker_error` for why a send can fail.
+                ParallelMerkle::find_worker_error(response_receiver)?;
+                return Err(err.into());
+            }
+        }
+        Ok(())
+    }
+
     /// The parent thread may receive a `SendError` if the worker that it is sending to has
-    /// returned to the threadpool after encountering a `FileIoError`. This function should
-    /// be called after receiving a `SendError` to find and propagate the `FileIoError`.
-    fn find_fileio_error(
-        response_receiver: &Receiver<Result<Response, FileIoError>>,
-    ) -> Result<(), FileIoError> {
+    /// returned to the threadpool after encountering an error, such as a `FileIoError` or a
+    /// failed condition. This function should be called after receiving a `SendError` to find
+    /// and propagate that error.
+    fn find_worker_error(
+        response_receiver: &Receiver<Result<Response, CreateProposalError>>,
+    ) -> Result<(), CreateProposalError> {
         // Go through the messages in the response channel without blocking to see if we can
-        // find the FileIoError that caused the worker to close the channel, resulting in a
-        // send error. If we can find it, then we propagate the FileIoError. Note that
-        // successful responses can be in the response channel ahead of the FileIoError.
-        // These are sent from workers that completed their requests without encountering
-        // a FileIoError.
+        // find the error that caused the worker to close the channel, resulting in a
+        // send error. If we can find it, then we propagate it. Note that successful
+        // responses can be in the response channel ahead of the error. These are sent
+        // from workers that completed their requests without encountering an error.
         for result in response_receiver.try_iter() {
             let _ = result?; // explicitly ignore the successful Response
         }
@@ -405,22 +513,51 @@ impl ParallelMerkle {
             //         For a remove prefix, we would need to remove everything. We do this by sending
             //         a remove prefix with an empty prefix to all of the children, then removing the
             //         value of the root node.
+            //
+            // Key ranges are handled first, as a range can span many subtries.
+            if let BatchOp::DeleteKeyRange { start, end } = op.as_ref() {
+                self.split_key_range(
+                    pool,
+                    &mutable_nodestore,
+                    &mut root_branch,
+                    (start, end),
+                    &response_sender,
+                    &response_receiver,
+                )?;
+                continue;
+            }
             let mut key_nibbles = NibblesIterator::new(op.key().as_ref());
             let Some(first_path_component) = key_nibbles.next() else {
+                op.check_condition(root_branch.value.as_deref())?;
                 match &op {
-                    BatchOp::Put { key: _, value } => {
+                    BatchOp::Put { key: _, value }
+                    | BatchOp::PutIfAbsent { key: _, value }
+                    | BatchOp::CompareAndSwap { key: _, value, .. } => {
                         root_branch.value = Some(value.as_ref().into());
                     }
-                    BatchOp::Delete { key: _ } => {
+                    BatchOp::Delete { key: _ } | BatchOp::DeleteIfEquals { key: _, .. } => {
                         root_branch.value = None;
                     }
+                    BatchOp::DeleteKeyRange { .. } => unreachable!("handled above"),
+                    BatchOp::Merge { key, operand } => {
+                        let merge_operator = self
+                            .merge_operator
+                            .as_deref()
+                            .ok_or(CreateProposalError::NoMergeOperator)?;
+                        let value = merge_operator.merge(
+                            key.as_ref(),
+                            root_branch.value.as_deref(),
+                            operand.as_ref(),
+                        );
+                        root_branch.value = Some(value);
+                    }
                     BatchOp::DeleteRange { prefix: _ } => {
                         // Calling remove prefix with an empty prefix is equivalent to a remove all.
                         if let Err(err) = self.remove_all_entries(&mut root_branch) {
                             // A send error is most likely due to a worker returning to the thread pool
-                            // after it encountered a FileIoError. Try to find the FileIoError in the
-                            // response channel and return that instead.
-                            ParallelMerkle::find_fileio_error(&response_receiver)?;
+                            // after it encountered an error. Try to find that error in the
+                            // response channel and return it instead.
+                            ParallelMerkle::find_worker_error(&response_receiver)?;
                             return Err(err.into());
                         }
                     }
@@ -447,22 +584,11 @@ impl ParallelMerkle {
             //       to the worker. It may be possible to send a nibble iterator instead of a
             //       Box<[u8]> to the worker if we use rayon scoped threads. This change would
             //       eliminate a memory copy but may require some code refactoring.
-            if let Err(err) = match &op {
-                BatchOp::Put { key: _, value } => worker.send(BatchOp::Put {
-                    key: op.key().as_ref().into(),
-                    value: value.as_ref().into(),
-                }),
-                BatchOp::Delete { key: _ } => worker.send(BatchOp::Delete {
-                    key: op.key().as_ref().into(),
-                }),
-                BatchOp::DeleteRange { prefix: _ } => worker.send(BatchOp::DeleteRange {
-                    prefix: op.key().as_ref().into(),
-                }),
-            } {
+            if let Err(err) = worker.send(op.to_boxed()) {
                 // A send error is most likely due to a worker returning to the thread pool
-                // after it encountered a FileIoError. Try to find the FileIoError in the
-                // response channel and return that instead.
-                ParallelMerkle::find_fileio_error(&response_receiver)?;
+                // after it encountered an error. Try to find that error in the
+                // response channel and return it instead.
+                ParallelMerkle::find_worker_error(&response_receiver)?;
                 return Err(err.into());
             }
         }
@@ -194,7 +194,7 @@ fn insert_one() {
     merkle.insert(b"abc", Box::new([])).unwrap();
 }
 
-fn create_in_memory_merkle() -> Merkle<NodeStore<MutableProposal, MemStore>> {
+pub(crate) fn create_in_memory_merkle() -> Merkle<NodeStore<MutableProposal, MemStore>> {
     let memstore = MemStore::new(vec![]);
 
     let nodestore = NodeStore::new_empty_proposal(memstore.into());
@@ -312,6 +312,25 @@ fn remove_root() {
     assert!(merkle.nodestore.root_node().is_none());
 }
 
+#[test]
+fn insert_with_existing_value() {
+    let mut merkle = create_in_memory_merkle();
+    merkle.insert(b"abc", Box::from(b"1".as_slice())).unwrap();
+
+    for key in [&b"abc"[..], b"ab", b"abcd", b"abd", b"x"] {
+        merkle
+            .insert_with(key, |existing| {
+                [existing.unwrap_or_default(), b"2"].concat().into()
+            })
+            .unwrap();
+    }
+
+    assert_eq!(&*merkle.get_value(b"abc").unwrap().unwrap(), b"12");
+    for key in [&b"ab"[..], b"abcd", b"abd", b"x"] {
+        assert_eq!(&*merkle.get_value(key).unwrap().unwrap(), b"2");
+    }
+}
+
 #[test]
 fn remove_prefix_exact() {
     let mut merkle = two_byte_all_keys();
@@ -418,6 +437,71 @@ fn remove_prefix() {
     }
 }
 
+#[test]
+fn remove_range_bounded() {
+    let mut merkle = two_byte_all_keys();
+    let got = merkle.remove_range(&[0x10, 0x10], Some(&[0x20])).unwrap();
+    assert_eq!(got, 16);
+    for key_val in u8::MIN..=u8::MAX {
+        let key = [key_val, key_val];
+        let got = merkle.get_value(&key).unwrap();
+        assert_eq!(got.is_none(), (0x10..0x20).contains(&key_val));
+    }
+
+    // an empty range removes nothing
+    assert_eq!(merkle.remove_range(&[0x30], Some(&[0x30])).unwrap(), 0);
+}
+
+#[test]
+fn remove_range_unbounded() {
+    let mut merkle = two_byte_all_keys();
+    assert_eq!(merkle.remove_range(&[0x80, 0x81], None).unwrap(), 127);
+    assert_eq!(merkle.remove_range(&[], None).unwrap(), 129);
+    assert!(merkle.nodestore.root_node().is_none());
+}
+
+#[test]
+fn remove_range_matches_removing_each_key() {
+    let rng = firewood_storage::SeededRng::from_env_or_random();
+    let keygen = || -> Vec<u8> {
+        let len = rng.random_range(0..=4);
+        (0..len).map(|_| rng.random_range(0..4)).collect()
+    };
+
+    for _ in 0..50 {
+        let mut items: Vec<(Key, Value)> = (0..50)
+            .map(|_| (keygen().into(), Box::from(b"value".as_slice())))
+            .collect();
+        items.sort_unstable();
+        items.dedup_by_key(|(k, _)| k.clone());
+
+        let mut bounds = [keygen(), keygen()];
+        bounds.sort_unstable();
+        let [start, end] = bounds;
+        let end = (rng.random_range(0..5) > 0).then_some(end);
+        let in_range = |key: &[u8]| {
+            key >= start.as_slice() && end.as_ref().is_none_or(|end| key < end.as_slice())
+        };
+
+        let mut merkle = create_in_memory_merkle();
+        let mut expected = create_in_memory_merkle();
+        for (key, value) in &items {
+            merkle.insert(key, value.clone()).unwrap();
+            if !in_range(key) {
+                expected.insert(key, value.clone()).unwrap();
+            }
+        }
+
+        let removed = merkle.remove_range(&start, end.as_deref()).unwrap();
+        assert_eq!(removed, items.iter().filter(|(k, _)| in_range(k)).count());
+        assert_eq!(
+            merkle.hash().nodestore.root_hash(),
+            expected.hash().nodestore.root_hash(),
+            "start: {start:?}, end: {end:?}, items: {items:?}",
+        );
+    }
+}
+
 #[test]
 fn get_empty_proof() {
     let merkle = create_in_memory_merkle().hash();
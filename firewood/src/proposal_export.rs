@@ -0,0 +1,259 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use integer_encoding::VarInt;
+
+use crate::merkle::{Key, Value};
+use crate::v2::api::{BatchOp, HashKey};
+
+const MAGIC: &[u8; 8] = b"fwdprpsl";
+const VERSION: u8 = 0;
+
+const PUT: u8 = 0;
+const DELETE: u8 = 1;
+
+/// An error reading an exported proposal.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ImportError {
+    /// The data does not start with the magic bytes of an exported proposal
+    #[error("not an exported proposal")]
+    InvalidMagic,
+
+    /// The data was written by an unsupported version of the format
+    #[error("unsupported proposal export version {found}")]
+    UnsupportedVersion {
+        /// The version found in the data
+        found: u8,
+    },
+
+    /// The data ended in the middle of an item
+    #[error("exported proposal is truncated")]
+    Truncated,
+
+    /// An operation has an unknown tag
+    #[error("invalid operation tag {found}")]
+    InvalidTag {
+        /// The tag found in the data
+        found: u8,
+    },
+
+    /// There is data after the last operation
+    #[error("exported proposal has {count} trailing bytes")]
+    TrailingBytes {
+        /// The number of bytes after the last operation
+        count: usize,
+    },
+}
+
+/// A proposal, as the root hashes of its parent and of itself and the puts
+/// and deletes that turn the parent into the proposal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ProposalExport {
+    pub(crate) parent: Option<HashKey>,
+    pub(crate) root: Option<HashKey>,
+    pub(crate) ops: Vec<BatchOp<Key, Value>>,
+}
+
+impl ProposalExport {
+    /// Serializes the proposal.
+    ///
+    /// # Format
+    ///
+    /// - 8 bytes: the magic value `b"fwdprpsl"`.
+    /// - 1 byte: the version of the format, currently `0`.
+    /// - The parent root hash, then the proposal root hash, each as a single
+    ///   byte `0` for the empty trie, or `1` followed by the 32-byte hash.
+    /// - The number of operations, followed by each operation as:
+    ///   - a single byte `0` for a put or `1` for a delete,
+    ///   - the key, as a length-prefixed sequence of bytes,
+    ///   - for a put, the value, as a length-prefixed sequence of bytes.
+    ///
+    /// Lengths and counts are unsigned LEB128 variable-length integers.
+    pub(crate) fn to_vec(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.push(VERSION);
+        write_hash(&mut out, self.parent.as_ref());
+        write_hash(&mut out, self.root.as_ref());
+        write_var_int(&mut out, self.ops.len());
+        for op in &self.ops {
+            match op {
+                BatchOp::Delete { key } => {
+                    out.push(DELETE);
+                    write_bytes(&mut out, key);
+                }
+                op => {
+                    out.push(PUT);
+                    write_bytes(&mut out, op.key());
+                    write_bytes(&mut out, op.value().map_or(&[][..], AsRef::as_ref));
+                }
+            }
+        }
+        out
+    }
+
+    /// Reads a proposal serialized by [`ProposalExport::to_vec`].
+    pub(crate) fn from_slice(data: &[u8]) -> Result<Self, ImportError> {
+        let mut reader = Reader(data);
+        if reader.read_slice(MAGIC.len())? != MAGIC {
+            return Err(ImportError::InvalidMagic);
+        }
+        let version = reader.read_byte()?;
+        if version != VERSION {
+            return Err(ImportError::UnsupportedVersion { found: version });
+        }
+        let parent = reader.read_hash()?;
+        let root = reader.read_hash()?;
+
+        let count = reader.read_var_int()?;
+        // Every operation takes at least 2 bytes, so a count that cannot fit
+        // is truncated data rather than a reason to allocate.
+        let mut ops = Vec::with_capacity(count.min(data.len() / 2));
+        for _ in 0..count {
+            let op = match reader.read_byte()? {
+                PUT => BatchOp::Put {
+                    key: reader.read_bytes()?.into(),
+                    value: reader.read_bytes()?.into(),
+                },
+                DELETE => BatchOp::Delete {
+                    key: reader.read_bytes()?.into(),
+                },
+                found => return Err(ImportError::InvalidTag { found }),
+            };
+            ops.push(op);
+        }
+
+        if !reader.0.is_empty() {
+            return Err(ImportError::TrailingBytes {
+                count: reader.0.len(),
+            });
+        }
+        Ok(Self { parent, root, ops })
+    }
+}
+
+fn write_var_int(out: &mut Vec<u8>, v: usize) {
+    let mut buf = [0u8; 10];
+    let n = v.encode_var(&mut buf);
+    out.extend_from_slice(buf.get(..n).unwrap_or_default());
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_var_int(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+fn write_hash(out: &mut Vec<u8>, hash: Option<&HashKey>) {
+    if let Some(hash) = hash {
+        out.push(1);
+        out.extend_from_slice(hash.as_ref());
+    } else {
+        out.push(0);
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn read_slice(&mut self, n: usize) -> Result<&'a [u8], ImportError> {
+        let (head, rest) = self.0.split_at_checked(n).ok_or(ImportError::Truncated)?;
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn read_byte(&mut self) -> Result<u8, ImportError> {
+        let (&byte, rest) = self.0.split_first().ok_or(ImportError::Truncated)?;
+        self.0 = rest;
+        Ok(byte)
+    }
+
+    fn read_var_int(&mut self) -> Result<usize, ImportError> {
+        let (v, n) = usize::decode_var(self.0).ok_or(ImportError::Truncated)?;
+        self.read_slice(n)?;
+        Ok(v)
+    }
+
+    fn read_bytes(&mut self) -> Result<&'a [u8], ImportError> {
+        let len = self.read_var_int()?;
+        self.read_slice(len)
+    }
+
+    fn read_hash(&mut self) -> Result<Option<HashKey>, ImportError> {
+        match self.read_byte()? {
+            0 => Ok(None),
+            1 => {
+                let hash = self.read_slice(32)?;
+                HashKey::try_from(hash)
+                    .map(Some)
+                    .map_err(|_| ImportError::Truncated)
+            }
+            found => Err(ImportError::InvalidTag { found }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn export() -> ProposalExport {
+        ProposalExport {
+            parent: Some(HashKey::try_from(&[7u8; 32][..]).unwrap()),
+            root: None,
+            ops: vec![
+                BatchOp::Put {
+                    key: Box::from(&b"a"[..]),
+                    value: Box::from(&b"1"[..]),
+                },
+                BatchOp::Delete {
+                    key: Box::from(&b"b"[..]),
+                },
+                BatchOp::Put {
+                    key: Box::from(&b"c"[..]),
+                    value: Box::default(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let export = export();
+        assert_eq!(ProposalExport::from_slice(&export.to_vec()), Ok(export));
+    }
+
+    #[test]
+    fn invalid_data() {
+        let data = export().to_vec();
+        for len in 0..data.len() {
+            assert!(
+                ProposalExport::from_slice(data.get(..len).unwrap()).is_err(),
+                "{len}"
+            );
+        }
+
+        let mut trailing = data.clone();
+        trailing.push(0);
+        assert_eq!(
+            ProposalExport::from_slice(&trailing),
+            Err(ImportError::TrailingBytes { count: 1 })
+        );
+
+        let mut magic = data.clone();
+        *magic.first_mut().unwrap() = b'x';
+        assert_eq!(
+            ProposalExport::from_slice(&magic),
+            Err(ImportError::InvalidMagic)
+        );
+
+        let mut version = data;
+        *version.get_mut(MAGIC.len()).unwrap() = 1;
+        assert_eq!(
+            ProposalExport::from_slice(&version),
+            Err(ImportError::UnsupportedVersion { found: 1 })
+        );
+    }
+}
@@ -0,0 +1,325 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! A streaming binary container for the key/value pairs of a revision.
+//!
+//! A snapshot holds the keys of a revision in ascending order, so it can be
+//! loaded with [`Db::bulk_load`](crate::db::Db::bulk_load), along with the
+//! root hash the loaded trie must have. Nothing in it depends on how nodes
+//! are stored, so a snapshot can move state between machines and between
+//! versions of firewood.
+//!
+//! # Format
+//!
+//! - 8 bytes: the magic value `b"fwdsnap\0"`.
+//! - 1 byte: the version of the format, currently `0`.
+//! - The root hash, as a single byte `0` for the empty trie, or `1` followed
+//!   by the 32-byte hash.
+//! - Each pair as a single byte `1`, followed by the key and then the value,
+//!   each as a length-prefixed sequence of bytes. Lengths are unsigned LEB128
+//!   variable-length integers.
+//! - A single byte `0` marking the end of the pairs.
+//! - 32 bytes: the SHA-256 digest of everything before it.
+
+use std::io::{self, Read, Write};
+
+use integer_encoding::{VarIntReader, VarIntWriter};
+use sha2::{Digest, Sha256};
+
+use crate::merkle::{Key, Value};
+use crate::v2::api::HashKey;
+
+const MAGIC: &[u8; 8] = b"fwdsnap\0";
+const VERSION: u8 = 0;
+
+const END: u8 = 0;
+const PAIR: u8 = 1;
+
+/// An error reading a snapshot.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SnapshotError {
+    /// The snapshot could not be read
+    #[error("reading snapshot: {0}")]
+    Io(#[from] io::Error),
+
+    /// The data does not start with the magic bytes of a snapshot
+    #[error("not a firewood snapshot")]
+    InvalidMagic,
+
+    /// The snapshot was written by an unsupported version of the format
+    #[error("unsupported snapshot version {found}")]
+    UnsupportedVersion {
+        /// The version found in the snapshot
+        found: u8,
+    },
+
+    /// An item has an unknown tag
+    #[error("invalid snapshot tag {found}")]
+    InvalidTag {
+        /// The tag found in the snapshot
+        found: u8,
+    },
+
+    /// The trailing checksum does not match the contents
+    #[error("snapshot checksum mismatch")]
+    ChecksumMismatch,
+}
+
+/// A [`Write`] that hashes everything written through it.
+#[derive(Debug)]
+pub(crate) struct HashingWriter<W> {
+    pub(crate) inner: W,
+    pub(crate) hasher: Sha256,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(buf.get(..n).unwrap_or_default());
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A [`Read`] that hashes everything read through it.
+#[derive(Debug)]
+pub(crate) struct HashingReader<R> {
+    pub(crate) inner: R,
+    pub(crate) hasher: Sha256,
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(buf.get(..n).unwrap_or_default());
+        Ok(n)
+    }
+}
+
+/// Writes a snapshot, one pair at a time.
+///
+/// The pairs must be written in ascending key order for the snapshot to be
+/// loadable. [`SnapshotWriter::finish`] must be called to complete it.
+#[derive(Debug)]
+pub struct SnapshotWriter<W: Write> {
+    writer: HashingWriter<W>,
+}
+
+impl<W: Write> SnapshotWriter<W> {
+    /// Starts a snapshot of a revision with the given root hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be written.
+    pub fn new(writer: W, root_hash: Option<&HashKey>) -> io::Result<Self> {
+        let mut writer = HashingWriter {
+            inner: writer,
+            hasher: Sha256::new(),
+        };
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        if let Some(root_hash) = root_hash {
+            writer.write_all(&[1])?;
+            writer.write_all(root_hash.as_ref())?;
+        } else {
+            writer.write_all(&[0])?;
+        }
+        Ok(Self { writer })
+    }
+
+    /// Writes the next pair.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pair cannot be written.
+    pub fn write(&mut self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.writer.write_all(&[PAIR])?;
+        for bytes in [key, value] {
+            self.writer.write_varint(bytes.len())?;
+            self.writer.write_all(bytes)?;
+        }
+        Ok(())
+    }
+
+    /// Completes the snapshot, returning the underlying writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the end of the snapshot cannot be written.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[END])?;
+        let HashingWriter { mut inner, hasher } = self.writer;
+        inner.write_all(&hasher.finalize())?;
+        inner.flush()?;
+        Ok(inner)
+    }
+}
+
+/// Reads a snapshot written by [`SnapshotWriter`], yielding its pairs.
+///
+/// The checksum is verified when the last pair has been read, so a pair may
+/// be yielded before an error that shows the snapshot to be corrupt.
+#[derive(Debug)]
+pub struct SnapshotReader<R: Read> {
+    reader: HashingReader<R>,
+    root_hash: Option<HashKey>,
+    done: bool,
+}
+
+impl<R: Read> SnapshotReader<R> {
+    /// Reads the header of a snapshot.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the header cannot be read or is not the header of
+    /// a supported snapshot.
+    pub fn new(reader: R) -> Result<Self, SnapshotError> {
+        let mut reader = HashingReader {
+            inner: reader,
+            hasher: Sha256::new(),
+        };
+        let mut magic = [0u8; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if magic != *MAGIC {
+            return Err(SnapshotError::InvalidMagic);
+        }
+        let version = read_byte(&mut reader)?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion { found: version });
+        }
+        let root_hash = match read_byte(&mut reader)? {
+            0 => None,
+            1 => {
+                let mut hash = [0u8; 32];
+                reader.read_exact(&mut hash)?;
+                Some(HashKey::from(hash))
+            }
+            found => return Err(SnapshotError::InvalidTag { found }),
+        };
+        Ok(Self {
+            reader,
+            root_hash,
+            done: false,
+        })
+    }
+
+    /// The root hash of the revision in the snapshot.
+    #[must_use]
+    pub const fn root_hash(&self) -> Option<&HashKey> {
+        self.root_hash.as_ref()
+    }
+
+    fn read_pair(&mut self) -> Result<Option<(Key, Value)>, SnapshotError> {
+        match read_byte(&mut self.reader)? {
+            PAIR => {
+                let key = read_bytes(&mut self.reader)?;
+                let value = read_bytes(&mut self.reader)?;
+                Ok(Some((key, value)))
+            }
+            END => {
+                let expected = self.reader.hasher.finalize_reset();
+                let mut checksum = [0u8; 32];
+                self.reader.inner.read_exact(&mut checksum)?;
+                if checksum != *expected {
+                    return Err(SnapshotError::ChecksumMismatch);
+                }
+                Ok(None)
+            }
+            found => Err(SnapshotError::InvalidTag { found }),
+        }
+    }
+}
+
+impl<R: Read> Iterator for SnapshotReader<R> {
+    type Item = Result<(Key, Value), SnapshotError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let pair = self.read_pair();
+        self.done = !matches!(pair, Ok(Some(_)));
+        pair.transpose()
+    }
+}
+
+pub(crate) fn read_byte(reader: &mut impl Read) -> io::Result<u8> {
+    let mut byte = 0;
+    reader.read_exact(std::slice::from_mut(&mut byte))?;
+    Ok(byte)
+}
+
+pub(crate) fn read_bytes(reader: &mut impl Read) -> io::Result<Box<[u8]>> {
+    let len: usize = reader.read_varint()?;
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes.into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    #![expect(clippy::unwrap_used)]
+
+    use super::*;
+
+    fn snapshot(root_hash: Option<&HashKey>) -> Vec<u8> {
+        let mut writer = SnapshotWriter::new(Vec::new(), root_hash).unwrap();
+        writer.write(b"a", b"1").unwrap();
+        writer.write(b"b", b"").unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let root_hash = HashKey::from([7u8; 32]);
+        for root_hash in [None, Some(&root_hash)] {
+            let data = snapshot(root_hash);
+            let reader = SnapshotReader::new(&data[..]).unwrap();
+            assert_eq!(reader.root_hash(), root_hash);
+            let pairs: Vec<_> = reader.collect::<Result<_, _>>().unwrap();
+            assert_eq!(
+                pairs,
+                [
+                    (Box::from(&b"a"[..]), Box::from(&b"1"[..])),
+                    (Box::from(&b"b"[..]), Box::default())
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_data() {
+        let data = snapshot(None);
+        for len in 0..data.len() {
+            let truncated = data.get(..len).unwrap();
+            assert!(
+                SnapshotReader::new(truncated)
+                    .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+                    .is_err(),
+                "{len}"
+            );
+        }
+
+        let mut corrupt = data.clone();
+        *corrupt.get_mut(MAGIC.len() + 6).unwrap() = b'x';
+        let reader = SnapshotReader::new(&corrupt[..]).unwrap();
+        assert!(matches!(
+            reader.collect::<Result<Vec<_>, _>>(),
+            Err(SnapshotError::ChecksumMismatch)
+        ));
+
+        let mut magic = data;
+        *magic.first_mut().unwrap() = b'x';
+        assert!(matches!(
+            SnapshotReader::new(&magic[..]),
+            Err(SnapshotError::InvalidMagic)
+        ));
+    }
+}
@@ -11,8 +11,14 @@ use std::fmt::Debug;
 use std::num::NonZeroUsize;
 use std::sync::Arc;
 
+pub use crate::backup::BackupError;
+pub use crate::diff::KeyChange;
+pub use crate::proposal_export::ImportError;
 pub use crate::range_proof::RangeProof;
-pub use crate::v2::batch_op::{BatchOp, KeyValuePair, KeyValuePairIter, MapIntoBatch};
+pub use crate::snapshot::SnapshotError;
+pub use crate::v2::batch_op::{
+    BatchOp, ConditionFailed, KeyValuePair, KeyValuePairIter, MapIntoBatch, MergeOperator,
+};
 
 /// A `KeyType` is something that can be xcast to a u8 reference,
 /// and can be sent and shared across threads. References with
@@ -135,6 +141,31 @@ pub enum Error {
     /// A `RootStore` error occurred
     RootStoreError(#[from] RootStoreError),
 
+    /// A previous revision could not be persisted
+    #[error("a previous revision could not be persisted; the database must be reopened")]
+    PersistFailed,
+
+    /// A revision that would be discarded is still referenced
+    #[error("revision {provided:?} is still in use")]
+    RevisionInUse {
+        /// The root hash of the revision or proposal still in use
+        provided: Option<HashKey>,
+    },
+
+    /// There is no named head with this name
+    #[error("head {name:?} not found")]
+    HeadNotFound {
+        /// The name of the head
+        name: String,
+    },
+
+    /// A named head with this name already exists
+    #[error("head {name:?} already exists")]
+    HeadExists {
+        /// The name of the head
+        name: String,
+    },
+
     /// Cannot commit a committed proposal
     #[error("Cannot commit a committed proposal")]
     AlreadyCommitted,
@@ -174,12 +205,68 @@ pub enum Error {
     // Error converting a u8 index into a path component
     #[error("error converting a u8 index into a path component")]
     InvalidConversionToPathComponent,
+
+    /// The condition of a conditional [`BatchOp`] did not hold
+    #[error(transparent)]
+    ConditionFailed(#[from] ConditionFailed),
+
+    /// A batch contains a merge, but the database has no merge operator
+    #[error("a merge requires a merge operator, but none is configured")]
+    NoMergeOperator,
+
+    /// An exported proposal could not be read
+    #[error("invalid exported proposal: {0}")]
+    ProposalImport(#[from] ImportError),
+
+    /// An imported proposal does not have the root hash it was exported with
+    #[error("imported proposal has root hash {found:?}, expected {expected:?}")]
+    ImportRootMismatch {
+        /// The root hash the proposal was exported with
+        expected: Option<HashKey>,
+        /// The root hash of the imported proposal
+        found: Option<HashKey>,
+    },
+
+    /// A bulk load was given a key that is not greater than the key before it
+    #[error("bulk load keys must be strictly ascending, but {key:?} follows {previous:?}")]
+    UnsortedBulkLoad {
+        /// The key before the out of order key
+        previous: Key,
+        /// The out of order key
+        key: Key,
+    },
+
+    /// A snapshot could not be read
+    #[error("invalid snapshot: {0}")]
+    Snapshot(#[from] SnapshotError),
+
+    /// An incremental backup could not be read or restored
+    #[error("incremental backup: {0}")]
+    Backup(#[from] BackupError),
+
+    /// A bulk load was started on a database that already has data
+    #[error("bulk loading requires an empty database")]
+    BulkLoadNotEmpty,
+
+    /// A rebased proposal changes keys that were also changed between its
+    /// parent and the new parent
+    #[error("rebase conflicts on {} keys", keys.len())]
+    RebaseConflict {
+        /// The keys changed on both sides, in ascending order
+        keys: Vec<Key>,
+    },
+
+    /// A proposal built on top of another uncommitted proposal was rebased,
+    /// which would drop the changes of that proposal
+    #[error("cannot rebase a proposal whose parent is an uncommitted proposal")]
+    RebaseUncommittedParent,
 }
 
 impl From<RevisionManagerError> for Error {
     fn from(err: RevisionManagerError) -> Self {
         use RevisionManagerError::{
-            FileIoError, NotLatest, RevisionNotFound, RevisionWithoutAddress, RootStoreError,
+            FileIoError, HeadExists, HeadNotFound, NotLatest, PersistFailed, RevisionInUse,
+            RevisionNotFound, RevisionWithoutAddress, RootStoreError,
         };
         match err {
             NotLatest { provided, expected } => Self::ParentNotLatest { provided, expected },
@@ -189,6 +276,10 @@ impl From<RevisionManagerError> for Error {
             RevisionWithoutAddress { provided } => Self::RevisionWithoutAddress { provided },
             FileIoError(io_err) => Self::FileIO(io_err),
             RootStoreError(err) => Self::RootStoreError(err),
+            PersistFailed => Self::PersistFailed,
+            RevisionInUse { provided } => Self::RevisionInUse { provided },
+            HeadNotFound { name } => Self::HeadNotFound { name },
+            HeadExists { name } => Self::HeadExists { name },
         }
     }
 }
@@ -209,6 +300,8 @@ impl From<CreateProposalError> for Error {
             CreateProposalError::InvalidConversionToPathComponent => {
                 Error::InvalidConversionToPathComponent
             }
+            CreateProposalError::ConditionFailed(err) => Error::ConditionFailed(err),
+            CreateProposalError::NoMergeOperator => Error::NoMergeOperator,
         }
     }
 }
@@ -273,7 +366,7 @@ pub trait Db {
 /// 3. From [`Proposal::propose`] which is a view on top of another proposal.
 pub trait DbView {
     /// The type of a stream of key/value pairs
-    type Iter<'view>: Iterator<Item = Result<(Key, Value), Error>>
+    type Iter<'view>: KeyValueIter
     where
         Self: 'view;
 
@@ -290,6 +383,15 @@ pub trait DbView {
     #[expect(clippy::missing_errors_doc)]
     fn val<K: KeyType>(&self, key: K) -> Result<Option<Value>, Error>;
 
+    /// Get the values of many keys, in the same order as `keys`
+    ///
+    /// Implementations backed by a trie read each node shared by several keys
+    /// only once, and may search independent subtries in parallel.
+    #[expect(clippy::missing_errors_doc)]
+    fn val_many<K: KeyType>(&self, keys: &[K]) -> Result<Vec<Option<Value>>, Error> {
+        keys.iter().map(|key| self.val(key)).collect()
+    }
+
     /// Obtain a proof for a single key
     #[expect(clippy::missing_errors_doc)]
     fn single_key_proof<K: KeyType>(&self, key: K) -> Result<FrozenProof, Error>;
@@ -334,10 +436,112 @@ pub trait DbView {
     fn iter_from<K: KeyType>(&self, first_key: K) -> Result<Self::Iter<'_>, Error> {
         self.iter_option(Some(first_key))
     }
+
+    /// Obtain a stream over the keys/values of this view with keys in the
+    /// range [`start`, `end`)
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - If None, start at the lowest key
+    /// * `end` - If None, continue to the end of the database. Otherwise, stop
+    ///   before the first key greater than or equal to `end`
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_range<K: KeyType>(
+        &self,
+        start: Option<K>,
+        end: Option<K>,
+    ) -> Result<Self::Iter<'_>, Error>;
+
+    /// Obtain a stream over the keys/values of this view whose keys start with `prefix`
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_prefix<K: KeyType>(&self, prefix: K) -> Result<Self::Iter<'_>, Error> {
+        let end = prefix_end(prefix.as_ref());
+        self.iter_range(Some(prefix.as_ref()), end.as_deref())
+    }
+
+    /// Obtain a stream over the keys/values of this view in descending order,
+    /// using an optional starting point
+    ///
+    /// # Arguments
+    ///
+    /// * `last_key` - If None, start at the highest key. Otherwise, start at
+    ///   the highest key less than or equal to `last_key`
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_rev_option<K: KeyType>(&self, last_key: Option<K>) -> Result<Self::Iter<'_>, Error>;
+
+    /// Obtain a stream over the keys/values of this view in descending order,
+    /// starting from the end
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_rev(&self) -> Result<Self::Iter<'_>, Error> {
+        self.iter_rev_option(Option::<Key>::None)
+    }
+
+    /// Obtain a stream over the key/values in descending order, starting at a specific key
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_rev_from<K: KeyType>(&self, last_key: K) -> Result<Self::Iter<'_>, Error> {
+        self.iter_rev_option(Some(last_key))
+    }
+}
+
+/// A stream of key/value pairs whose [`IterationMode`] can be changed.
+///
+/// Every iterator returned by a [`DbView`] or [`DynDbView`] implements this,
+/// so any of them can yield keys only or value digests, for example
+/// `view.iter_prefix(prefix)?.with_mode(IterationMode::KeysOnly)`.
+pub trait KeyValueIter: Iterator<Item = Result<(Key, Value), Error>> {
+    /// Yield what `mode` asks for alongside each remaining key, instead of its value
+    fn set_mode(&mut self, mode: IterationMode);
+
+    /// Like [`KeyValueIter::set_mode`], but takes and returns the iterator
+    #[must_use]
+    fn with_mode(mut self, mode: IterationMode) -> Self
+    where
+        Self: Sized,
+    {
+        self.set_mode(mode);
+        self
+    }
+}
+
+impl<I: KeyValueIter + ?Sized> KeyValueIter for Box<I> {
+    fn set_mode(&mut self, mode: IterationMode) {
+        (**self).set_mode(mode);
+    }
 }
 
 /// A boxed iterator over key/value pairs.
-pub type BoxKeyValueIter<'view> = Box<dyn Iterator<Item = Result<(Key, Value), Error>> + 'view>;
+pub type BoxKeyValueIter<'view> = Box<dyn KeyValueIter + 'view>;
+
+/// A boxed iterator over the changes between two revisions, in ascending key order.
+pub type BoxKeyChangeIter<'a> = Box<dyn Iterator<Item = Result<KeyChange, Error>> + 'a>;
+
+/// What an iterator yields alongside each key.
+///
+/// Set it with [`KeyValueIter::set_mode`] or [`KeyValueIter::with_mode`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum IterationMode {
+    /// The key's value.
+    #[default]
+    KeyValue,
+    /// An empty value. Values are never copied out of the trie.
+    KeysOnly,
+    /// The SHA-256 digest of the key's value.
+    ValueDigests,
+}
+
+impl IterationMode {
+    /// Returns what this mode yields for a key whose value is `value`.
+    #[must_use]
+    pub fn value_of(self, value: &[u8]) -> Value {
+        use sha2::{Digest, Sha256};
+
+        match self {
+            Self::KeyValue => value.into(),
+            Self::KeysOnly => Box::default(),
+            Self::ValueDigests => Sha256::digest(value).to_vec().into_boxed_slice(),
+        }
+    }
+}
 
 /// A dynamic dyspatch version of [`DbView`] that can be shared.
 pub type ArcDynDbView = Arc<dyn DynDbView>;
@@ -357,6 +561,10 @@ pub trait DynDbView: Debug + Send + Sync + 'static {
     #[expect(clippy::missing_errors_doc)]
     fn val(&self, key: &[u8]) -> Result<Option<Value>, Error>;
 
+    /// Get the values of many keys, in the same order as `keys`
+    #[expect(clippy::missing_errors_doc)]
+    fn val_many(&self, keys: &[&[u8]]) -> Result<Vec<Option<Value>>, Error>;
+
     /// Obtain a proof for a single key
     #[expect(clippy::missing_errors_doc)]
     fn single_key_proof(&self, key: &[u8]) -> Result<FrozenProof, Error>;
@@ -401,6 +609,51 @@ pub trait DynDbView: Debug + Send + Sync + 'static {
     fn iter_from(&self, first_key: &[u8]) -> Result<BoxKeyValueIter<'_>, Error> {
         self.iter_option(Some(first_key))
     }
+
+    /// Obtain a stream over the keys/values of this view with keys in the
+    /// range [`start`, `end`)
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - If None, start at the lowest key
+    /// * `end` - If None, continue to the end of the database. Otherwise, stop
+    ///   before the first key greater than or equal to `end`
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_range(
+        &self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> Result<BoxKeyValueIter<'_>, Error>;
+
+    /// Obtain a stream over the keys/values of this view whose keys start with `prefix`
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_prefix(&self, prefix: &[u8]) -> Result<BoxKeyValueIter<'_>, Error> {
+        let end = prefix_end(prefix);
+        self.iter_range(Some(prefix), end.as_deref())
+    }
+
+    /// Obtain a stream over the keys/values of this view in descending order,
+    /// using an optional starting point
+    ///
+    /// # Arguments
+    ///
+    /// * `last_key` - If None, start at the highest key. Otherwise, start at
+    ///   the highest key less than or equal to `last_key`
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_rev_option(&self, last_key: Option<&[u8]>) -> Result<BoxKeyValueIter<'_>, Error>;
+
+    /// Obtain a stream over the keys/values of this view in descending order,
+    /// starting from the end
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_rev(&self) -> Result<BoxKeyValueIter<'_>, Error> {
+        self.iter_rev_option(None)
+    }
+
+    /// Obtain a stream over the key/values in descending order, starting at a specific key
+    #[expect(clippy::missing_errors_doc)]
+    fn iter_rev_from(&self, last_key: &[u8]) -> Result<BoxKeyValueIter<'_>, Error> {
+        self.iter_rev_option(Some(last_key))
+    }
 }
 
 impl<T: Debug + DbView + Send + Sync + 'static> DynDbView for T
@@ -415,6 +668,10 @@ where
         DbView::val(self, key)
     }
 
+    fn val_many(&self, keys: &[&[u8]]) -> Result<Vec<Option<Value>>, Error> {
+        DbView::val_many(self, keys)
+    }
+
     fn single_key_proof(&self, key: &[u8]) -> Result<FrozenProof, Error> {
         DbView::single_key_proof(self, key)
     }
@@ -436,6 +693,37 @@ where
             Err(e) => Err(e),
         }
     }
+
+    fn iter_rev_option(&self, last_key: Option<&[u8]>) -> Result<BoxKeyValueIter<'_>, Error> {
+        match DbView::iter_rev_option(self, last_key) {
+            Ok(iter) => Ok(Box::new(iter)),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn iter_range(
+        &self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+    ) -> Result<BoxKeyValueIter<'_>, Error> {
+        match DbView::iter_range(self, start, end) {
+            Ok(iter) => Ok(Box::new(iter)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Returns the smallest key that is greater than every key starting with
+/// `prefix`, or `None` if there is no such key (the prefix is empty or all
+/// `0xff` bytes).
+#[must_use]
+pub fn prefix_end(prefix: &[u8]) -> Option<Key> {
+    let last = prefix.iter().rposition(|&b| b != u8::MAX)?;
+    let mut end = prefix.get(..=last)?.to_vec();
+    if let Some(b) = end.last_mut() {
+        *b = b.wrapping_add(1);
+    }
+    Some(end.into_boxed_slice())
 }
 
 /// A proposal for a new revision of the database.
@@ -497,4 +785,12 @@ mod tests {
     fn test_firewood_default_root_hash_equals_none() {
         assert_eq!(TrieHash::default_root_hash(), None);
     }
+
+    #[test]
+    fn test_prefix_end() {
+        assert_eq!(prefix_end(b""), None);
+        assert_eq!(prefix_end(&[0xff, 0xff]), None);
+        assert_eq!(prefix_end(b"abc").as_deref(), Some(&b"abd"[..]));
+        assert_eq!(prefix_end(&[0x01, 0xff]).as_deref(), Some(&[0x02][..]));
+    }
 }
@@ -1,11 +1,18 @@
 // Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
 // See the file LICENSE.md for licensing terms.
 
+use crate::merkle::{Key, Value};
 use crate::v2::api::{KeyType, ValueType};
+use std::fmt::Debug;
 
 /// A key/value pair operation.
 ///
-/// Put (upsert), Delete (single key), or Prefix Delete (range) are supported.
+/// Put (upsert), Delete (single key), Prefix Delete and Key Range Delete are
+/// supported, as well as conditional puts and deletes and merges. The condition of a conditional
+/// operation is checked against the value the key holds when the operation is
+/// applied, which includes the effect of earlier operations in the same batch.
+/// If it does not hold, the whole proposal fails with
+/// [`api::Error::ConditionFailed`](crate::v2::api::Error::ConditionFailed).
 #[derive(Debug, Clone, Copy)]
 #[non_exhaustive]
 pub enum BatchOp<K: KeyType, V: ValueType> {
@@ -29,6 +36,81 @@ pub enum BatchOp<K: KeyType, V: ValueType> {
         /// The prefix of the keys to delete
         prefix: K,
     },
+
+    /// Delete the keys in the half-open range `[start, end)`
+    DeleteKeyRange {
+        /// The first key to delete
+        start: K,
+
+        /// The first key after the range, or `None` to delete every key
+        /// from `start` onwards
+        end: Option<K>,
+    },
+
+    /// Insert a key/value pair, if the key does not have a value yet
+    PutIfAbsent {
+        /// the key
+        key: K,
+
+        /// the value
+        value: V,
+    },
+
+    /// Replace the value of a key, if it currently has the expected value
+    CompareAndSwap {
+        /// the key
+        key: K,
+
+        /// the value the key must have
+        expected: V,
+
+        /// the new value
+        value: V,
+    },
+
+    /// Delete a key, if it currently has the expected value
+    DeleteIfEquals {
+        /// the key
+        key: K,
+
+        /// the value the key must have
+        expected: V,
+    },
+
+    /// Combine the value of a key with an operand, using the
+    /// [`MergeOperator`] of the database
+    Merge {
+        /// the key
+        key: K,
+
+        /// the operand passed to the merge operator
+        operand: V,
+    },
+}
+
+/// Combines the value of a key with the operand of a [`BatchOp::Merge`].
+///
+/// The merge operator of a database is set with
+/// [`DbConfig::merge_operator`](crate::db::DbConfig::merge_operator). It is
+/// called while the proposal is built, with the value the key has at that
+/// point of the batch, so read-modify-write updates such as counters do not
+/// need to read the value first.
+pub trait MergeOperator: Debug + Send + Sync {
+    /// Returns the new value of `key` given its current value, if any, and
+    /// the operand of the merge.
+    fn merge(&self, key: &[u8], existing: Option<&[u8]>, operand: &[u8]) -> Value;
+}
+
+/// The condition of a conditional [`BatchOp`] did not hold.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("condition failed for key {key:?}: expected {expected:?}, found {found:?}")]
+pub struct ConditionFailed {
+    /// The key of the operation
+    pub key: Key,
+    /// The value the operation required, or `None` if it required no value
+    pub expected: Option<Value>,
+    /// The value the key had
+    pub found: Option<Value>,
 }
 
 impl<K: KeyType, V: ValueType> BatchOp<K, V> {
@@ -38,19 +120,82 @@ impl<K: KeyType, V: ValueType> BatchOp<K, V> {
         match self {
             BatchOp::Put { key, .. }
             | BatchOp::Delete { key }
-            | BatchOp::DeleteRange { prefix: key } => key,
+            | BatchOp::DeleteRange { prefix: key }
+            | BatchOp::DeleteKeyRange { start: key, .. }
+            | BatchOp::PutIfAbsent { key, .. }
+            | BatchOp::CompareAndSwap { key, .. }
+            | BatchOp::DeleteIfEquals { key, .. }
+            | BatchOp::Merge { key, .. } => key,
         }
     }
 
-    /// Get the value of this operation
+    /// Get the value of this operation, which is the operand of a merge
     #[must_use]
     pub const fn value(&self) -> Option<&V> {
         match self {
-            BatchOp::Put { value, .. } => Some(value),
+            BatchOp::Put { value, .. }
+            | BatchOp::PutIfAbsent { value, .. }
+            | BatchOp::CompareAndSwap { value, .. }
+            | BatchOp::Merge { operand: value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Get the end of the range of a [`BatchOp::DeleteKeyRange`], if it has one
+    #[must_use]
+    pub const fn range_end(&self) -> Option<&K> {
+        match self {
+            BatchOp::DeleteKeyRange { end: Some(end), .. } => Some(end),
+            _ => None,
+        }
+    }
+
+    /// Get the value a conditional operation expects the key to have
+    #[must_use]
+    pub const fn expected(&self) -> Option<&V> {
+        match self {
+            BatchOp::CompareAndSwap { expected, .. } | BatchOp::DeleteIfEquals { expected, .. } => {
+                Some(expected)
+            }
             _ => None,
         }
     }
 
+    /// Returns true if this operation only applies when its condition holds
+    #[must_use]
+    pub const fn is_conditional(&self) -> bool {
+        matches!(
+            self,
+            BatchOp::PutIfAbsent { .. }
+                | BatchOp::CompareAndSwap { .. }
+                | BatchOp::DeleteIfEquals { .. }
+        )
+    }
+
+    /// Checks the condition of this operation against `current`, the value
+    /// the key has when the operation is applied. Unconditional operations
+    /// always pass.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConditionFailed`], naming the key, if the condition does not
+    /// hold.
+    pub fn check_condition(&self, current: Option<&[u8]>) -> Result<(), ConditionFailed> {
+        if !self.is_conditional() {
+            return Ok(());
+        }
+        let expected = self.expected().map(AsRef::as_ref);
+        if current == expected {
+            Ok(())
+        } else {
+            Err(ConditionFailed {
+                key: self.key().as_ref().into(),
+                expected: expected.map(Into::into),
+                found: current.map(Into::into),
+            })
+        }
+    }
+
     /// Convert this operation into a borrowed version, where the key and value
     /// are references to the original data.
     #[must_use]
@@ -59,6 +204,22 @@ impl<K: KeyType, V: ValueType> BatchOp<K, V> {
             BatchOp::Put { key, value } => BatchOp::Put { key, value },
             BatchOp::Delete { key } => BatchOp::Delete { key },
             BatchOp::DeleteRange { prefix } => BatchOp::DeleteRange { prefix },
+            BatchOp::DeleteKeyRange { start, end } => BatchOp::DeleteKeyRange {
+                start,
+                end: end.as_ref(),
+            },
+            BatchOp::PutIfAbsent { key, value } => BatchOp::PutIfAbsent { key, value },
+            BatchOp::CompareAndSwap {
+                key,
+                expected,
+                value,
+            } => BatchOp::CompareAndSwap {
+                key,
+                expected,
+                value,
+            },
+            BatchOp::DeleteIfEquals { key, expected } => BatchOp::DeleteIfEquals { key, expected },
+            BatchOp::Merge { key, operand } => BatchOp::Merge { key, operand },
         }
     }
 
@@ -76,6 +237,74 @@ impl<K: KeyType, V: ValueType> BatchOp<K, V> {
             BatchOp::DeleteRange { prefix } => BatchOp::DeleteRange {
                 prefix: prefix.as_ref(),
             },
+            BatchOp::DeleteKeyRange { start, end } => BatchOp::DeleteKeyRange {
+                start: start.as_ref(),
+                end: end.as_ref().map(AsRef::as_ref),
+            },
+            BatchOp::PutIfAbsent { key, value } => BatchOp::PutIfAbsent {
+                key: key.as_ref(),
+                value: value.as_ref(),
+            },
+            BatchOp::CompareAndSwap {
+                key,
+                expected,
+                value,
+            } => BatchOp::CompareAndSwap {
+                key: key.as_ref(),
+                expected: expected.as_ref(),
+                value: value.as_ref(),
+            },
+            BatchOp::DeleteIfEquals { key, expected } => BatchOp::DeleteIfEquals {
+                key: key.as_ref(),
+                expected: expected.as_ref(),
+            },
+            BatchOp::Merge { key, operand } => BatchOp::Merge {
+                key: key.as_ref(),
+                operand: operand.as_ref(),
+            },
+        }
+    }
+}
+
+impl<K: KeyType, V: ValueType> BatchOp<K, V> {
+    /// Copies the key and values of this operation into an operation that
+    /// owns them.
+    #[must_use]
+    pub fn to_boxed(&self) -> BatchOp<Key, Value> {
+        match self.as_ref() {
+            BatchOp::Put { key, value } => BatchOp::Put {
+                key: key.into(),
+                value: value.into(),
+            },
+            BatchOp::Delete { key } => BatchOp::Delete { key: key.into() },
+            BatchOp::DeleteRange { prefix } => BatchOp::DeleteRange {
+                prefix: prefix.into(),
+            },
+            BatchOp::DeleteKeyRange { start, end } => BatchOp::DeleteKeyRange {
+                start: start.into(),
+                end: end.map(Into::into),
+            },
+            BatchOp::PutIfAbsent { key, value } => BatchOp::PutIfAbsent {
+                key: key.into(),
+                value: value.into(),
+            },
+            BatchOp::CompareAndSwap {
+                key,
+                expected,
+                value,
+            } => BatchOp::CompareAndSwap {
+                key: key.into(),
+                expected: expected.into(),
+                value: value.into(),
+            },
+            BatchOp::DeleteIfEquals { key, expected } => BatchOp::DeleteIfEquals {
+                key: key.into(),
+                expected: expected.into(),
+            },
+            BatchOp::Merge { key, operand } => BatchOp::Merge {
+                key: key.into(),
+                operand: operand.into(),
+            },
         }
     }
 }
@@ -85,6 +314,8 @@ impl BatchOp<&[u8], &[u8]> {
         std::mem::discriminant(&self) == std::mem::discriminant(&other)
             && self.key() == other.key()
             && self.value() == other.value()
+            && self.expected() == other.expected()
+            && self.range_end() == other.range_end()
     }
 
     fn hash_impl<H: std::hash::Hasher>(self, state: &mut H) {
@@ -94,6 +325,12 @@ impl BatchOp<&[u8], &[u8]> {
         if let Some(value) = self.value() {
             value.hash(state);
         }
+        if let Some(expected) = self.expected() {
+            expected.hash(state);
+        }
+        if let Some(end) = self.range_end() {
+            end.hash(state);
+        }
     }
 }
 
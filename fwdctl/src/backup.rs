@@ -0,0 +1,94 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use std::path::PathBuf;
+
+use clap::Args;
+use firewood::db::{Db, DbConfig};
+use firewood::v2::api;
+use indicatif::{ProgressBar, ProgressStyle};
+
+use crate::DatabasePath;
+use crate::diff::{Root, resolve_root, root_parser};
+
+#[derive(Debug, Args)]
+pub struct Options {
+    #[command(flatten)]
+    pub database: DatabasePath,
+
+    /// The file to write the backup to
+    #[arg(
+        required = true,
+        value_name = "BACKUP_FILE",
+        help = "New database file to write the backup to"
+    )]
+    pub output: PathBuf,
+
+    /// The revision to back up.
+    /// Defaults to "latest"
+    #[arg(
+        long,
+        value_name = "ROOT",
+        value_parser = root_parser,
+        default_value = "latest",
+        help = "Root hash, in hex, of the revision to back up, or \"latest\""
+    )]
+    pub root: Root,
+
+    /// Write an incremental backup instead of a compact database file
+    #[arg(
+        long,
+        help = "Write an incremental backup that `restore` can replay, keeping nodes at their addresses"
+    )]
+    pub incremental: bool,
+
+    /// The revision an incremental backup is based on
+    #[arg(
+        long,
+        value_name = "BASE",
+        value_parser = root_parser,
+        requires = "incremental",
+        help = "Root hash, in hex, of the revision backed up before, or \"latest\". It must still be retained by the database. Without it, an incremental backup starts a new chain and holds every node"
+    )]
+    pub base: Option<Root>,
+}
+
+pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
+    log::debug!("backing up database {opts:?}");
+    let cfg = DbConfig::builder().create_if_missing(false).truncate(false);
+    let db = Db::new(opts.database.dbpath.clone(), cfg.build())?;
+
+    let root = resolve_root(&db, &opts.root)?;
+    if opts.incremental {
+        let base = opts
+            .base
+            .as_ref()
+            .map(|base| resolve_root(&db, base))
+            .transpose()?;
+        let written = db.incremental_backup_to(&opts.output, base.clone(), root.clone())?;
+        println!(
+            "Backed up revision {root:?} on top of {base:?} to {}: {} nodes, {} bytes",
+            opts.output.display(),
+            written.nodes,
+            written.bytes
+        );
+        return Ok(());
+    }
+
+    let progress_bar = ProgressBar::new_spinner().with_style(
+        ProgressStyle::with_template("{spinner} {bytes} written [{elapsed}]")
+            .expect("valid template"),
+    );
+    let copied = db.backup_to_with_progress(&opts.output, root.clone(), |progress| {
+        progress_bar.set_position(progress.bytes);
+    })?;
+    progress_bar.finish_and_clear();
+
+    println!(
+        "Backed up revision {root:?} to {}: {} nodes, {} bytes",
+        opts.output.display(),
+        copied.nodes,
+        copied.bytes
+    );
+    Ok(())
+}
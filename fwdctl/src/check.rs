@@ -8,7 +8,9 @@ use std::sync::Arc;
 use askama::Template;
 use clap::Args;
 use firewood::v2::api;
-use firewood_storage::{CacheReadStrategy, CheckOpt, DBStats, FileBacked, NodeStore};
+use firewood_storage::{
+    CacheReadStrategy, CheckOpt, DBStats, FileBacked, NodeCacheBudget, NodeStore, StorageConfig,
+};
 use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
 use nonzero_ext::nonzero;
 use num_format::{Locale, ToFormattedString};
@@ -42,16 +44,21 @@ pub struct Options {
 
 pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
     let db_path = PathBuf::from(&opts.database.dbpath);
-    let node_cache_size = nonzero!(1usize);
+    // we scan the database once - no need to cache anything
+    let node_cache = NodeCacheBudget {
+        branch_bytes: 0,
+        leaf_bytes: 0,
+    };
     let free_list_cache_size = nonzero!(1usize);
 
     let fb = FileBacked::new(
         db_path,
-        node_cache_size,
+        node_cache,
         free_list_cache_size,
         false,
         false,                         // don't create if missing
         CacheReadStrategy::WritesOnly, // we scan the database once - no need to cache anything
+        StorageConfig::default(),
     )?;
     let storage = Arc::new(fb);
 
@@ -79,7 +86,7 @@ pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
             println!("\t{error}");
         }
         println!();
-        println!("Unfixable Errors ({}):", report.unfixable.len(),);
+        println!("Unfixable Errors ({}):", report.unfixable.len());
         for (error, io_error) in report.unfixable {
             println!("\t{error}");
             if let Some(io_error) = io_error {
@@ -0,0 +1,206 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use clap::Args;
+use firewood::db::{Db, DbConfig};
+use firewood::merkle::Key;
+use firewood::v2::api::{self, Db as _, HashKey};
+use std::ops::Bound;
+use std::path::PathBuf;
+
+use crate::DatabasePath;
+use crate::dump::{
+    KeyFromStream, OutputFormat, handle_next_key, key_count_exceeded, key_parser, key_parser_hex,
+    record_output_handler,
+};
+
+/// A revision to diff, given on the command line as a root hash in hex or as
+/// "latest".
+#[derive(Debug, Clone, PartialEq)]
+pub enum Root {
+    Latest,
+    Hash(HashKey),
+}
+
+#[derive(Debug, Args)]
+pub struct Options {
+    #[command(flatten)]
+    pub database: DatabasePath,
+
+    /// The revision to diff from.
+    #[arg(
+        required = true,
+        value_name = "FROM_ROOT",
+        value_parser = root_parser,
+        help = "Root hash, in hex, of the revision to diff from, or \"latest\""
+    )]
+    pub from_root: Root,
+
+    /// The revision to diff to.
+    #[arg(
+        required = true,
+        value_name = "TO_ROOT",
+        value_parser = root_parser,
+        help = "Root hash, in hex, of the revision to diff to, or \"latest\""
+    )]
+    pub to_root: Root,
+
+    /// The key to start the diff from (if no key is provided, start from the beginning).
+    /// Defaults to None.
+    #[arg(
+        short = 's',
+        long,
+        required = false,
+        value_name = "START_KEY",
+        value_parser = key_parser,
+        help = "Start the diff from this key (inclusive)."
+    )]
+    pub start_key: Option<Key>,
+
+    /// The key to stop the diff at (if no key is provided, stop at the end).
+    /// Defaults to None.
+    #[arg(
+        short = 'S',
+        long,
+        required = false,
+        value_name = "STOP_KEY",
+        value_parser = key_parser,
+        help = "Stop the diff at this key (inclusive)."
+    )]
+    pub stop_key: Option<Key>,
+
+    /// The key to start the diff from in hex format.
+    /// Defaults to None.
+    #[arg(
+        long,
+        required = false,
+        conflicts_with = "start_key",
+        value_name = "START_KEY_HEX",
+        value_parser = key_parser_hex,
+        help = "Start the diff from this key (inclusive) in hex format. Conflicts with start_key"
+    )]
+    pub start_key_hex: Option<Key>,
+
+    /// The key to stop the diff at in hex format.
+    /// Defaults to None.
+    #[arg(
+        long,
+        required = false,
+        conflicts_with = "stop_key",
+        value_name = "STOP_KEY_HEX",
+        value_parser = key_parser_hex,
+        help = "Stop the diff at this key (inclusive) in hex format. Conflicts with stop_key"
+    )]
+    pub stop_key_hex: Option<Key>,
+
+    /// The max number of changed keys to print.
+    /// Defaults to None.
+    #[arg(
+        short = 'm',
+        long,
+        required = false,
+        value_name = "MAX_KEY_COUNT",
+        help = "Maximum number of changed keys to print."
+    )]
+    pub max_key_count: Option<u32>,
+
+    /// The output format of the diff.
+    /// Defaults to "stdout"
+    #[arg(
+        short = 'o',
+        long,
+        required = false,
+        value_name = "OUTPUT_FORMAT",
+        value_enum,
+        default_value_t = OutputFormat::Stdout,
+        help = "Output format of the diff, default to stdout. CSV and JSON formats are available."
+    )]
+    pub output_format: OutputFormat,
+
+    /// The output file name of the diff.
+    /// Output format must be set when the file name is set.
+    #[arg(
+        short = 'f',
+        long,
+        requires = "output_format",
+        value_name = "OUTPUT_FILE_NAME",
+        default_value = "diff",
+        help = "Output file name of the diff, default to diff. Output format must be set when the file name is set."
+    )]
+    pub output_file_name: PathBuf,
+
+    #[arg(short = 'x', long, help = "Print the keys and values in hex format.")]
+    pub hex: bool,
+}
+
+pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
+    log::debug!("diff database {opts:?}");
+
+    if opts.output_format == OutputFormat::Dot {
+        return Err(api::Error::InternalError(Box::new(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Dot format is not supported for diffs",
+        ))));
+    }
+
+    let cfg = DbConfig::builder().create_if_missing(false).truncate(false);
+    let db = Db::new(opts.database.dbpath.clone(), cfg.build())?;
+    let from_root = resolve_root(&db, &opts.from_root)?;
+    let to_root = resolve_root(&db, &opts.to_root)?;
+
+    let start_key = opts.start_key.clone().or(opts.start_key_hex.clone());
+    let stop_key = opts.stop_key.clone().or(opts.stop_key_hex.clone());
+    let range = (
+        start_key.map_or(Bound::Unbounded, Bound::Included),
+        stop_key.map_or(Bound::Unbounded, Bound::Included),
+    );
+    let mut iter = db.diff(from_root, to_root, range)?;
+
+    let Some(mut output_handler) =
+        record_output_handler(&opts.output_format, opts.output_file_name.clone(), opts.hex)
+            .expect("Error creating output handler")
+    else {
+        return Ok(());
+    };
+
+    let mut key_count: u32 = 0;
+    while let Some(change) = iter.next() {
+        output_handler.handle_change(&change?)?;
+
+        key_count = key_count.saturating_add(1);
+        if key_count_exceeded(opts.max_key_count, key_count) {
+            let next_key: KeyFromStream = iter
+                .next()
+                .map(|next| next.map(|change| (change.key().into(), Box::default())));
+            handle_next_key(next_key);
+            break;
+        }
+    }
+    output_handler.flush()?;
+
+    Ok(())
+}
+
+/// The root hash of `root`, which must not be the empty trie.
+pub(crate) fn resolve_root(db: &Db, root: &Root) -> Result<HashKey, api::Error> {
+    match root {
+        Root::Hash(hash) => Ok(hash.clone()),
+        Root::Latest => db.root_hash()?.ok_or_else(|| {
+            api::Error::InternalError(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Database is empty",
+            )))
+        }),
+    }
+}
+
+pub(crate) fn root_parser(s: &str) -> Result<Root, std::io::Error> {
+    if s == "latest" {
+        return Ok(Root::Latest);
+    }
+    let bytes =
+        hex::decode(s).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    HashKey::try_from(bytes.as_slice())
+        .map(Root::Hash)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
+}
@@ -4,7 +4,7 @@ use clap::Args;
 use firewood::db::{Db, DbConfig};
 use firewood::iter::MerkleKeyValueIter;
 use firewood::merkle::{Key, Value};
-use firewood::v2::api::{self, Db as _};
+use firewood::v2::api::{self, Db as _, KeyChange};
 use std::borrow::Cow;
 use std::error::Error;
 use std::fs::File;
@@ -13,7 +13,7 @@ use std::path::PathBuf;
 
 use crate::DatabasePath;
 
-type KeyFromStream = Option<Result<(Key, Value), api::Error>>;
+pub(crate) type KeyFromStream = Option<Result<(Key, Value), api::Error>>;
 
 #[derive(Debug, clap::ValueEnum, Clone, PartialEq)]
 pub enum OutputFormat {
@@ -189,7 +189,7 @@ pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
     Ok(())
 }
 
-fn key_count_exceeded(max: Option<u32>, key_count: u32) -> bool {
+pub(crate) fn key_count_exceeded(max: Option<u32>, key_count: u32) -> bool {
     max.is_some_and(|max| key_count >= max)
 }
 
@@ -197,11 +197,11 @@ fn u8_to_string(data: &[u8]) -> Cow<'_, str> {
     String::from_utf8_lossy(data)
 }
 
-fn key_parser(s: &str) -> Result<Box<[u8]>, std::io::Error> {
+pub(crate) fn key_parser(s: &str) -> Result<Box<[u8]>, std::io::Error> {
     Ok(Box::from(s.as_bytes()))
 }
 
-fn key_parser_hex(s: &str) -> Result<Box<[u8]>, std::io::Error> {
+pub(crate) fn key_parser_hex(s: &str) -> Result<Box<[u8]>, std::io::Error> {
     hex::decode(s)
         .map(Vec::into_boxed_slice)
         .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))
@@ -209,20 +209,27 @@ fn key_parser_hex(s: &str) -> Result<Box<[u8]>, std::io::Error> {
 
 // Helper function to convert key and value to a string
 fn key_value_to_string(key: &[u8], value: &[u8], hex: bool) -> (String, String) {
-    let key_str = if hex {
-        hex::encode(key)
-    } else {
-        u8_to_string(key).to_string()
-    };
-    let value_str = if hex {
-        hex::encode(value)
+    (bytes_to_string(key, hex), bytes_to_string(value, hex))
+}
+
+fn bytes_to_string(bytes: &[u8], hex: bool) -> String {
+    if hex {
+        hex::encode(bytes)
     } else {
-        u8_to_string(value).to_string()
-    };
-    (key_str, value_str)
+        u8_to_string(bytes).to_string()
+    }
 }
 
-fn handle_next_key(next_key: KeyFromStream) {
+/// The name of the kind of `change`, as printed in CSV and JSON output
+const fn change_kind(change: &KeyChange) -> &'static str {
+    match change {
+        KeyChange::Inserted { .. } => "inserted",
+        KeyChange::Updated { .. } => "updated",
+        KeyChange::Deleted { .. } => "deleted",
+    }
+}
+
+pub(crate) fn handle_next_key(next_key: KeyFromStream) {
     match next_key {
         Some(Ok((key, _))) => {
             println!(
@@ -240,8 +247,9 @@ fn handle_next_key(next_key: KeyFromStream) {
     }
 }
 
-trait OutputHandler {
+pub(crate) trait OutputHandler {
     fn handle_record(&mut self, key: &[u8], value: &[u8]) -> Result<(), std::io::Error>;
+    fn handle_change(&mut self, change: &KeyChange) -> Result<(), std::io::Error>;
     fn flush(&mut self) -> Result<(), std::io::Error>;
 }
 
@@ -257,6 +265,21 @@ impl OutputHandler for CsvOutputHandler {
         Ok(())
     }
 
+    fn handle_change(&mut self, change: &KeyChange) -> Result<(), std::io::Error> {
+        let value_str = |value: Option<&[u8]>| {
+            value
+                .map(|value| bytes_to_string(value, self.hex))
+                .unwrap_or_default()
+        };
+        self.writer.write_record(&[
+            change_kind(change).to_string(),
+            bytes_to_string(change.key(), self.hex),
+            value_str(change.old_value()),
+            value_str(change.new_value()),
+        ])?;
+        Ok(())
+    }
+
     fn flush(&mut self) -> Result<(), std::io::Error> {
         self.writer.flush()?;
         Ok(())
@@ -283,7 +306,36 @@ impl OutputHandler for JsonOutputHandler {
         Ok(())
     }
 
+    fn handle_change(&mut self, change: &KeyChange) -> Result<(), std::io::Error> {
+        let key_str = bytes_to_string(change.key(), self.hex);
+        let value_str = |value: Option<&[u8]>| {
+            value.map_or_else(
+                || "null".to_string(),
+                |value| format!(r#""{}""#, bytes_to_string(value, self.hex)),
+            )
+        };
+        if self.is_first {
+            self.writer.write_all(b"{\n")?;
+            self.is_first = false;
+        } else {
+            self.writer.write_all(b",\n")?;
+        }
+
+        write!(
+            self.writer,
+            r#"  "{key_str}": {{"change": "{}", "old": {}, "new": {}}}"#,
+            change_kind(change),
+            value_str(change.old_value()),
+            value_str(change.new_value()),
+        )?;
+        Ok(())
+    }
+
     fn flush(&mut self) -> Result<(), std::io::Error> {
+        if self.is_first {
+            // nothing was written, so the object was never opened
+            self.writer.write_all(b"{")?;
+        }
         let _ = self.writer.write(b"\n}\n");
         self.writer.flush()?;
         Ok(())
@@ -300,6 +352,21 @@ impl OutputHandler for StdoutOutputHandler {
         println!("'{key_str}': '{value_str}'");
         Ok(())
     }
+
+    fn handle_change(&mut self, change: &KeyChange) -> Result<(), std::io::Error> {
+        let key_str = bytes_to_string(change.key(), self.hex);
+        match (change.old_value(), change.new_value()) {
+            (None, Some(new)) => println!("+ '{key_str}': '{}'", bytes_to_string(new, self.hex)),
+            (Some(old), None) => println!("- '{key_str}': '{}'", bytes_to_string(old, self.hex)),
+            (Some(old), Some(new)) => println!(
+                "~ '{key_str}': '{}' -> '{}'",
+                bytes_to_string(old, self.hex),
+                bytes_to_string(new, self.hex)
+            ),
+            (None, None) => {}
+        }
+        Ok(())
+    }
     fn flush(&mut self) -> Result<(), std::io::Error> {
         Ok(())
     }
@@ -309,16 +376,36 @@ fn create_output_handler(
     opts: &Options,
     db: &Db,
 ) -> Result<Option<Box<dyn OutputHandler + Send + Sync>>, Box<dyn Error>> {
-    let hex = opts.hex;
-    let mut file_name = opts.output_file_name.clone();
-    let extension = match opts.output_format {
+    if opts.output_format == OutputFormat::Dot {
+        let mut file_name = opts.output_file_name.clone();
+        file_name.set_extension("dot");
+        println!("Dumping to {}", file_name.display());
+        let file = File::create(file_name)?;
+        let mut writer = BufWriter::new(file);
+        // For dot format, we generate the output immediately since it doesn't use streaming
+        db.dump(&mut writer)?;
+        return Ok(None);
+    }
+    record_output_handler(&opts.output_format, opts.output_file_name.clone(), opts.hex)
+}
+
+/// Creates the handler that streams records in `format`, writing to
+/// `file_name` with the extension of the format.
+///
+/// Returns `None` for [`OutputFormat::Dot`], which is not streamed.
+pub(crate) fn record_output_handler(
+    format: &OutputFormat,
+    mut file_name: PathBuf,
+    hex: bool,
+) -> Result<Option<Box<dyn OutputHandler + Send + Sync>>, Box<dyn Error>> {
+    let extension = match format {
         OutputFormat::Csv => "csv",
         OutputFormat::Json => "json",
         OutputFormat::Stdout => "txt",
         OutputFormat::Dot => "dot",
     };
     file_name.set_extension(extension);
-    match opts.output_format {
+    match format {
         OutputFormat::Csv => {
             println!("Dumping to {}", file_name.display());
             let file = File::create(file_name)?;
@@ -337,13 +424,6 @@ fn create_output_handler(
             })))
         }
         OutputFormat::Stdout => Ok(Some(Box::new(StdoutOutputHandler { hex }))),
-        OutputFormat::Dot => {
-            println!("Dumping to {}", file_name.display());
-            let file = File::create(file_name)?;
-            let mut writer = BufWriter::new(file);
-            // For dot format, we generate the output immediately since it doesn't use streaming
-            db.dump(&mut writer)?;
-            Ok(None)
-        }
+        OutputFormat::Dot => Ok(None),
     }
 }
@@ -0,0 +1,100 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use firewood::db::{Db, DbConfig};
+use firewood::iter::MerkleKeyValueIter;
+use firewood::snapshot::SnapshotWriter;
+use firewood::v2::api::{self, Db as _};
+
+use crate::DatabasePath;
+
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq)]
+pub enum SnapshotFormat {
+    Binary,
+    Ndjson,
+}
+
+#[derive(Debug, Args)]
+pub struct Options {
+    #[command(flatten)]
+    pub database: DatabasePath,
+
+    /// The format of the snapshot.
+    /// Defaults to "binary"
+    #[arg(
+        short = 'o',
+        long,
+        value_enum,
+        default_value_t = SnapshotFormat::Binary,
+        help = "Format of the snapshot, default to binary. NDJSON is readable, binary is compact and checksummed."
+    )]
+    pub output_format: SnapshotFormat,
+
+    /// The file name of the snapshot, without the extension of the format.
+    #[arg(
+        short = 'f',
+        long,
+        value_name = "OUTPUT_FILE_NAME",
+        default_value = "snapshot",
+        help = "Output file name of the snapshot, default to snapshot."
+    )]
+    pub output_file_name: PathBuf,
+}
+
+pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
+    log::debug!("exporting database {opts:?}");
+    let cfg = DbConfig::builder().create_if_missing(false).truncate(false);
+    let db = Db::new(opts.database.dbpath.clone(), cfg.build())?;
+
+    let root_hash = db.root_hash()?;
+    let mut file_name = opts.output_file_name.clone();
+    file_name.set_extension(match opts.output_format {
+        SnapshotFormat::Binary => "fwdsnap",
+        SnapshotFormat::Ndjson => "ndjson",
+    });
+    println!("Exporting to {}", file_name.display());
+    let file = BufWriter::new(File::create(&file_name)?);
+
+    let revision = root_hash
+        .clone()
+        .map(|hash| db.revision(hash))
+        .transpose()?;
+    let pairs = revision
+        .iter()
+        .flat_map(|revision| MerkleKeyValueIter::from(&**revision));
+    let mut count = 0u64;
+    match opts.output_format {
+        SnapshotFormat::Binary => {
+            let mut writer = SnapshotWriter::new(file, root_hash.as_ref())?;
+            for pair in pairs {
+                let (key, value) = pair?;
+                writer.write(&key, &value)?;
+                count = count.saturating_add(1);
+            }
+            writer.finish()?;
+        }
+        SnapshotFormat::Ndjson => {
+            let mut writer = file;
+            let header = serde_json::json!({ "root_hash": root_hash.as_ref().map(hex::encode) });
+            writeln!(writer, "{header}")?;
+            for pair in pairs {
+                let (key, value) = pair?;
+                let line = serde_json::json!({
+                    "key": hex::encode(key),
+                    "value": hex::encode(value),
+                });
+                writeln!(writer, "{line}")?;
+                count = count.saturating_add(1);
+            }
+            writer.flush()?;
+        }
+    }
+
+    println!("Exported {count} keys, root hash {root_hash:?}");
+    Ok(())
+}
@@ -0,0 +1,165 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+
+use clap::Args;
+use firewood::db::{Db, DbConfig, Proposal};
+use firewood::merkle::{Key, Value};
+use firewood::snapshot::SnapshotReader;
+use firewood::v2::api::{self, Db as _, DbView as _, HashKey, Proposal as _};
+
+use crate::DatabasePath;
+
+#[derive(Debug, clap::ValueEnum, Clone, PartialEq)]
+pub enum InputFormat {
+    Csv,
+    Ndjson,
+    Binary,
+}
+
+#[derive(Debug, Args)]
+pub struct Options {
+    #[command(flatten)]
+    pub database: DatabasePath,
+
+    /// The file of key/value pairs to load
+    #[arg(
+        required = true,
+        value_name = "INPUT_FILE",
+        help = "File of key/value pairs in ascending key order, as written by `dump --output-format csv` or `export`"
+    )]
+    pub input: PathBuf,
+
+    /// The format of the input file.
+    /// Defaults to "csv"
+    #[arg(
+        short = 'i',
+        long,
+        value_enum,
+        default_value_t = InputFormat::Csv,
+        help = "Format of the input file. The root hash of NDJSON and binary snapshots is verified before committing."
+    )]
+    pub input_format: InputFormat,
+
+    #[arg(
+        short = 'x',
+        long,
+        help = "The keys and values of a CSV file are in hex format."
+    )]
+    pub hex: bool,
+}
+
+pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
+    log::debug!("importing {opts:?}");
+    let cfg = DbConfig::builder().create_if_missing(false).truncate(false);
+    let db = Db::new(opts.database.dbpath.clone(), cfg.build())?;
+
+    let (proposal, count) = match opts.input_format {
+        InputFormat::Csv => import_csv(&db, opts)?,
+        InputFormat::Ndjson => import_ndjson(&db, opts)?,
+        InputFormat::Binary => {
+            let reader = SnapshotReader::new(BufReader::new(File::open(&opts.input)?))?;
+            let expected = reader.root_hash().cloned();
+            let loaded = load(&db, reader.map(|pair| pair.map_err(api::Error::from)))?;
+            verify_root(&loaded.0, expected)?;
+            loaded
+        }
+    };
+    proposal.commit()?;
+
+    println!("Imported {count} keys, root hash {:?}", db.root_hash()?);
+    Ok(())
+}
+
+/// Bulk loads `pairs`, returning the proposal and the number of keys loaded.
+fn load(
+    db: &Db,
+    pairs: impl Iterator<Item = Result<(Key, Value), api::Error>>,
+) -> Result<(Proposal<'_>, u64), api::Error> {
+    let mut count = 0u64;
+    let proposal = db.bulk_load(pairs.inspect(|pair| {
+        if pair.is_ok() {
+            count = count.saturating_add(1);
+        }
+    }))?;
+    Ok((proposal, count))
+}
+
+/// Checks that a loaded snapshot has the root hash it was exported with.
+fn verify_root(proposal: &Proposal<'_>, expected: Option<HashKey>) -> Result<(), api::Error> {
+    let found = proposal.root_hash()?;
+    if found != expected {
+        return Err(api::Error::ImportRootMismatch { expected, found });
+    }
+    Ok(())
+}
+
+fn import_csv<'db>(db: &'db Db, opts: &Options) -> Result<(Proposal<'db>, u64), api::Error> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(&opts.input)
+        .map_err(csv_error)?;
+    let pairs = reader.byte_records().map(|record| {
+        let record = record.map_err(csv_error)?;
+        let (Some(key), Some(value), 2) = (record.get(0), record.get(1), record.len()) else {
+            return Err(invalid_input(format!(
+                "expected a key and a value, found {} fields in record {}",
+                record.len(),
+                record.position().map_or(0, csv::Position::record)
+            )));
+        };
+        Ok((decode(key, opts.hex)?, decode(value, opts.hex)?))
+    });
+    load(db, pairs)
+}
+
+/// Imports a file written by `export --output-format ndjson`: a header line
+/// with the root hash, followed by a line for each pair.
+fn import_ndjson<'db>(db: &'db Db, opts: &Options) -> Result<(Proposal<'db>, u64), api::Error> {
+    let mut lines = BufReader::new(File::open(&opts.input)?).lines();
+    let header = parse_line(lines.next().transpose()?.as_deref().unwrap_or_default())?;
+    let expected = match header.get("root_hash") {
+        Some(serde_json::Value::Null) => None,
+        Some(serde_json::Value::String(hash)) => {
+            Some(HashKey::try_from(&*decode(hash.as_bytes(), true)?)?)
+        }
+        _ => return Err(invalid_input("missing root_hash header".to_string())),
+    };
+
+    let pairs = lines.map(|line| {
+        let line = parse_line(&line?)?;
+        let field = |name| match line.get(name) {
+            Some(serde_json::Value::String(hex)) => decode(hex.as_bytes(), true),
+            _ => Err(invalid_input(format!("missing {name} in {line}"))),
+        };
+        Ok((field("key")?, field("value")?))
+    });
+    let loaded = load(db, pairs)?;
+    verify_root(&loaded.0, expected)?;
+    Ok(loaded)
+}
+
+fn parse_line(line: &str) -> Result<serde_json::Value, api::Error> {
+    serde_json::from_str(line).map_err(|e| api::Error::IO(e.into()))
+}
+
+fn decode(field: &[u8], hex: bool) -> Result<Box<[u8]>, api::Error> {
+    if hex {
+        hex::decode(field)
+            .map(Vec::into_boxed_slice)
+            .map_err(|e| invalid_input(e.to_string()))
+    } else {
+        Ok(field.into())
+    }
+}
+
+fn invalid_input(msg: String) -> api::Error {
+    api::Error::IO(std::io::Error::new(std::io::ErrorKind::InvalidInput, msg))
+}
+
+fn csv_error(e: csv::Error) -> api::Error {
+    api::Error::IO(e.into())
+}
@@ -8,13 +8,19 @@ use std::path::PathBuf;
 use clap::{Parser, Subcommand};
 use firewood::v2::api;
 
+pub mod backup;
 pub mod check;
 pub mod create;
 pub mod delete;
+pub mod diff;
 pub mod dump;
+pub mod export;
 pub mod get;
 pub mod graph;
+pub mod import;
 pub mod insert;
+pub mod restore;
+pub mod rollback;
 pub mod root;
 
 #[derive(Clone, Debug, Parser)]
@@ -65,10 +71,22 @@ enum Commands {
     Root(root::Options),
     /// Dump contents of key/value store
     Dump(dump::Options),
+    /// Print the keys that changed between two revisions
+    Diff(diff::Options),
     /// Produce a dot file of the database
     Graph(graph::Options),
     /// Runs the checker on the database
     Check(check::Options),
+    /// Load sorted key/value pairs into an empty database
+    Import(import::Options),
+    /// Write the latest revision to a snapshot that `import` can load
+    Export(export::Options),
+    /// Copy a revision into a new, compact database file
+    Backup(backup::Options),
+    /// Replay a chain of incremental backups into a new database file
+    Restore(restore::Options),
+    /// Make a retained revision the latest one, discarding newer revisions
+    Rollback(rollback::Options),
 }
 
 fn main() -> Result<(), api::Error> {
@@ -85,8 +103,14 @@ fn main() -> Result<(), api::Error> {
         Commands::Delete(opts) => delete::run(opts),
         Commands::Root(opts) => root::run(opts),
         Commands::Dump(opts) => dump::run(opts),
+        Commands::Diff(opts) => diff::run(opts),
         Commands::Graph(opts) => graph::run(opts),
         Commands::Check(opts) => check::run(opts),
+        Commands::Import(opts) => import::run(opts),
+        Commands::Export(opts) => export::run(opts),
+        Commands::Backup(opts) => backup::run(opts),
+        Commands::Restore(opts) => restore::run(opts),
+        Commands::Rollback(opts) => rollback::run(opts),
     }
 }
 
@@ -0,0 +1,36 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use std::path::PathBuf;
+
+use clap::Args;
+use firewood::backup;
+use firewood::v2::api;
+
+use crate::DatabasePath;
+
+#[derive(Debug, Args)]
+pub struct Options {
+    #[command(flatten)]
+    pub database: DatabasePath,
+
+    /// The incremental backups to replay
+    #[arg(
+        required = true,
+        value_name = "BACKUP_FILE",
+        num_args = 1..,
+        help = "Incremental backups written by `backup --incremental`, oldest first"
+    )]
+    pub backups: Vec<PathBuf>,
+}
+
+pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
+    log::debug!("restoring {opts:?}");
+    let root = backup::restore(&opts.database.dbpath, &opts.backups)?;
+    println!(
+        "Restored {} backups to {}, root hash {root:?}",
+        opts.backups.len(),
+        opts.database.dbpath.display()
+    );
+    Ok(())
+}
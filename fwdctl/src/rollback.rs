@@ -0,0 +1,35 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+use clap::Args;
+use firewood::db::{Db, DbConfig};
+use firewood::v2::api;
+
+use crate::DatabasePath;
+use crate::diff::{Root, resolve_root, root_parser};
+
+#[derive(Debug, Args)]
+pub struct Options {
+    #[command(flatten)]
+    pub database: DatabasePath,
+
+    /// The revision to roll back to
+    #[arg(
+        required = true,
+        value_name = "ROOT",
+        value_parser = root_parser,
+        help = "Root hash, in hex, of a revision the database still retains, or \"latest\""
+    )]
+    pub root: Root,
+}
+
+pub(super) fn run(opts: &Options) -> Result<(), api::Error> {
+    log::debug!("rolling back database {opts:?}");
+    let cfg = DbConfig::builder().create_if_missing(false).truncate(false);
+    let db = Db::new(opts.database.dbpath.clone(), cfg.build())?;
+
+    let root = resolve_root(&db, &opts.root)?;
+    db.rollback_to(root.clone())?;
+    println!("Rolled back to revision {root:?}");
+    Ok(())
+}
@@ -548,6 +548,62 @@ fn fwdctl_dump_with_hex() -> Result<()> {
     fwdctl_delete_db()
 }
 
+#[test]
+#[serial]
+fn fwdctl_diff() -> Result<()> {
+    cargo_bin_cmd!()
+        .arg("create")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+
+    cargo_bin_cmd!()
+        .arg("insert")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .args(["a"])
+        .args(["1"])
+        .assert()
+        .success();
+
+    // the latest revision is the same as itself
+    cargo_bin_cmd!()
+        .arg("diff")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .args(["latest", "latest"])
+        .args(["--output-format", "json"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Dumping to diff.json"));
+
+    let contents = fs::read_to_string("diff.json").expect("Should read diff.json file");
+    assert_eq!(contents, "{\n}\n");
+    fs::remove_file("diff.json").expect("Should remove diff.json file");
+
+    // a root that is not retained
+    cargo_bin_cmd!()
+        .arg("diff")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .args(["latest", &"00".repeat(32)])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("RevisionNotFound"));
+
+    // a root that is not a hash
+    cargo_bin_cmd!()
+        .arg("diff")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .args(["latest", "abcd"])
+        .assert()
+        .failure();
+
+    fwdctl_delete_db()
+}
+
 #[test]
 #[serial]
 fn fwdctl_check_empty_db() -> Result<()> {
@@ -582,27 +638,388 @@ fn fwdctl_check_db_with_data() -> Result<()> {
         .assert()
         .success();
 
-    // TODO: bulk loading data instead of inserting one by one
-    for _ in 0..4 {
-        let key = sample_iter.by_ref().take(64).collect::<String>();
-        let value = sample_iter.by_ref().take(10).collect::<String>();
+    let mut pairs: Vec<(String, String)> = (0..1000)
+        .map(|_| {
+            let key = sample_iter.by_ref().take(64).collect::<String>();
+            let value = sample_iter.by_ref().take(10).collect::<String>();
+            (key, value)
+        })
+        .collect();
+    pairs.sort();
+    let input = tmpdb::path().with_extension("csv");
+    let csv = pairs
+        .iter()
+        .map(|(key, value)| [key.as_str(), ",", value, "\n"].concat())
+        .collect::<String>();
+    fs::write(&input, csv)?;
+
+    cargo_bin_cmd!()
+        .arg("import")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 1000 keys"));
+    fs::remove_file(&input)?;
+
+    cargo_bin_cmd!()
+        .arg("check")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+
+    fwdctl_delete_db()
+}
+
+#[test]
+#[serial]
+fn fwdctl_import() -> Result<()> {
+    cargo_bin_cmd!()
+        .arg("create")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+
+    let input = tmpdb::path().with_extension("csv");
+    fs::write(&input, "b,2\na,1\n")?;
+    cargo_bin_cmd!()
+        .arg("import")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("UnsortedBulkLoad"));
+
+    fs::write(&input, "61,31\n62,32\n")?;
+    cargo_bin_cmd!()
+        .arg("import")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg("--hex")
+        .arg(&input)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 2 keys"));
+
+    cargo_bin_cmd!()
+        .arg("get")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .args(["b"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2"));
+
+    // the database is no longer empty
+    cargo_bin_cmd!()
+        .arg("import")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg("--hex")
+        .arg(&input)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("BulkLoadNotEmpty"));
+    fs::remove_file(&input)?;
+
+    fwdctl_delete_db()
+}
+
+#[test]
+#[serial]
+fn fwdctl_export_import() -> Result<()> {
+    cargo_bin_cmd!()
+        .arg("create")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+
+    for (key, value) in [("a", "1"), ("b", "2"), ("c", "3")] {
         cargo_bin_cmd!()
             .arg("insert")
             .arg("--db")
             .arg(tmpdb::path())
-            .args([key])
-            .args([value])
+            .args([key, value])
             .assert()
             .success();
     }
+    let root = cargo_bin_cmd!()
+        .arg("root")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+    let root = String::from_utf8(root.get_output().stdout.clone())?;
+
+    let copy = tmpdb::path().with_extension("copy");
+    let snapshot = tmpdb::path().with_extension("snapshot");
+    for (format, extension) in [("binary", "fwdsnap"), ("ndjson", "ndjson")] {
+        cargo_bin_cmd!()
+            .arg("export")
+            .arg("--db")
+            .arg(tmpdb::path())
+            .args(["--output-format", format])
+            .arg("--output-file-name")
+            .arg(&snapshot)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Exported 3 keys"));
+        let file = snapshot.with_extension(extension);
+
+        cargo_bin_cmd!()
+            .arg("create")
+            .arg("--db")
+            .arg(&copy)
+            .assert()
+            .success();
+        cargo_bin_cmd!()
+            .arg("import")
+            .arg("--db")
+            .arg(&copy)
+            .args(["--input-format", format])
+            .arg(&file)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Imported 3 keys"));
+        cargo_bin_cmd!()
+            .arg("root")
+            .arg("--db")
+            .arg(&copy)
+            .assert()
+            .success()
+            .stdout(predicate::str::diff(root.clone()));
+        fs::remove_file(&copy)?;
+
+        if format == "ndjson" {
+            // a snapshot whose pairs do not match its root hash is rejected
+            let contents = fs::read_to_string(&file)?;
+            fs::write(&file, contents.replace("\"33\"", "\"34\""))?;
+            cargo_bin_cmd!()
+                .arg("create")
+                .arg("--db")
+                .arg(&copy)
+                .assert()
+                .success();
+            cargo_bin_cmd!()
+                .arg("import")
+                .arg("--db")
+                .arg(&copy)
+                .args(["--input-format", format])
+                .arg(&file)
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("ImportRootMismatch"));
+            fs::remove_file(&copy)?;
+        }
+        fs::remove_file(&file)?;
+    }
+
+    fwdctl_delete_db()
+}
+
+#[test]
+#[serial]
+fn fwdctl_backup() -> Result<()> {
+    cargo_bin_cmd!()
+        .arg("create")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+
+    for (key, value) in [("a", "1"), ("b", "2")] {
+        cargo_bin_cmd!()
+            .arg("insert")
+            .arg("--db")
+            .arg(tmpdb::path())
+            .args([key, value])
+            .assert()
+            .success();
+    }
+
+    let backup = tmpdb::path().with_extension("backup");
+    cargo_bin_cmd!()
+        .arg("backup")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg(&backup)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Backed up revision"));
+
+    // the destination is never overwritten
+    cargo_bin_cmd!()
+        .arg("backup")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg(&backup)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already exists"));
 
+    cargo_bin_cmd!()
+        .arg("get")
+        .arg("--db")
+        .arg(&backup)
+        .args(["b"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2"));
     cargo_bin_cmd!()
         .arg("check")
         .arg("--db")
+        .arg(&backup)
+        .assert()
+        .success();
+    fs::remove_file(&backup)?;
+
+    fwdctl_delete_db()
+}
+
+#[test]
+#[serial]
+fn fwdctl_incremental_backup() -> Result<()> {
+    let full = tmpdb::path().with_extension("full.fwdincr");
+    let next = tmpdb::path().with_extension("next.fwdincr");
+    let missing = tmpdb::path().with_extension("missing.fwdincr");
+    let restored = tmpdb::path().with_extension("restored");
+    // left behind by a failed run
+    for file in [&full, &next, &missing, &restored] {
+        let _ = fs::remove_file(file);
+    }
+
+    cargo_bin_cmd!()
+        .arg("create")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+
+    // keys are 32 bytes long so that the restore check accepts them with ethhash
+    let (a, b) = ("a".repeat(32), "b".repeat(32));
+    for (key, value) in [(a.as_str(), "1"), (b.as_str(), "2")] {
+        cargo_bin_cmd!()
+            .arg("insert")
+            .arg("--db")
+            .arg(tmpdb::path())
+            .args([key, value])
+            .assert()
+            .success();
+    }
+
+    // a full backup starts the chain, and nothing has changed since
+    for (backup, base) in [(&full, None), (&next, Some("latest"))] {
+        cargo_bin_cmd!()
+            .arg("backup")
+            .arg("--db")
+            .arg(tmpdb::path())
+            .arg("--incremental")
+            .args(base.iter().flat_map(|base| ["--base", base]))
+            .arg(backup)
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Backed up revision"));
+    }
+
+    // only retained revisions can be a base
+    cargo_bin_cmd!()
+        .arg("backup")
+        .arg("--db")
         .arg(tmpdb::path())
+        .arg("--incremental")
+        .args(["--base", &"00".repeat(32)])
+        .arg(&missing)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("RevisionNotFound"));
+
+    // the chain is replayed in order
+    cargo_bin_cmd!()
+        .arg("restore")
+        .arg("--db")
+        .arg(&restored)
+        .arg(&next)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("BaseMismatch"));
+    fs::remove_file(&restored)?;
+
+    cargo_bin_cmd!()
+        .arg("restore")
+        .arg("--db")
+        .arg(&restored)
+        .args([&full, &next])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Restored 2 backups"));
+
+    cargo_bin_cmd!()
+        .arg("get")
+        .arg("--db")
+        .arg(&restored)
+        .arg(&b)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("2"));
+    for file in [&restored, &full, &next] {
+        fs::remove_file(file)?;
+    }
+
+    fwdctl_delete_db()
+}
+
+#[test]
+#[serial]
+fn fwdctl_rollback() -> Result<()> {
+    cargo_bin_cmd!()
+        .arg("create")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .assert()
+        .success();
+
+    cargo_bin_cmd!()
+        .arg("insert")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .args(["a", "1"])
         .assert()
         .success();
 
+    // the latest revision is already current
+    cargo_bin_cmd!()
+        .arg("rollback")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg("latest")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Rolled back to revision"));
+
+    // only retained revisions can be rolled back to
+    cargo_bin_cmd!()
+        .arg("rollback")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .arg("00".repeat(32))
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("RevisionNotFound"));
+
+    cargo_bin_cmd!()
+        .arg("get")
+        .arg("--db")
+        .arg(tmpdb::path())
+        .args(["a"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1"));
+
     fwdctl_delete_db()
 }
 
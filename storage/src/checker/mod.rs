@@ -606,6 +606,39 @@ impl<S: WritableStorage> NodeStore<Committed, S> {
     }
 }
 
+impl<S: WritableStorage> NodeStore<Committed, S> {
+    /// Adds every leaked area, one that neither the trie of this revision
+    /// nor the free lists use, to the free lists, and writes the header.
+    /// Returns the number of bytes reclaimed.
+    ///
+    /// This must only be called when no other revision is retained, since
+    /// the nodes of any other revision are leaked from this one's point of
+    /// view.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if the check finds anything wrong besides
+    /// leaked areas, in which case nothing is changed, or if an area or the
+    /// header cannot be written.
+    pub fn reclaim_leaked_areas(&mut self) -> Result<u64, FileIoError> {
+        let report = self.check(CheckOpt {
+            hash_check: false,
+            progress_bar: None,
+        });
+        let mut leaked = Vec::new();
+        for error in report.errors {
+            let CheckerError::AreaLeaks(ranges) = error else {
+                return Err(FileIoError::from_generic_no_file(
+                    error,
+                    "reclaiming leaked areas",
+                ));
+            };
+            leaked.extend(self.split_all_leaked_ranges(&ranges, None));
+        }
+        self.free_areas(leaked)
+    }
+}
+
 impl<S: WritableStorage> NodeStore<MutableProposal, S> {
     fn fix(&mut self, check_report: CheckerReport) -> FixReport {
         let mut fixed = Vec::new();
@@ -53,9 +53,10 @@ pub use node::{
     BranchNode, Child, Children, ChildrenSlots, LeafNode, Node, PathIterItem,
     branch::{HashType, IntoHashType},
 };
+pub use nodestore::backup::CopyProgress;
 pub use nodestore::{
     AreaIndex, Committed, HashedNodeReader, ImmutableProposal, LinearAddress, MutableProposal,
-    NodeReader, NodeStore, Parentable, RootReader, TrieReader,
+    NodeReader, NodeStore, NodeStoreParent, Parentable, RootReader, TrieReader,
 };
 pub use path::{
     ComponentIter, IntoSplitPath, JoinedPath, PartialPath, PathBuf, PathCommonPrefix,
@@ -103,6 +104,143 @@ impl Display for CacheReadStrategy {
     }
 }
 
+/// The memory budgets of the node cache, in bytes.
+///
+/// Branches and leaves are evicted independently, each in least recently used
+/// order, once the approximate memory held by nodes of that kind exceeds its
+/// budget. A budget of zero disables caching for that kind of node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeCacheBudget {
+    /// The number of bytes that cached branch nodes may use.
+    pub branch_bytes: usize,
+
+    /// The number of bytes that cached leaf nodes may use.
+    pub leaf_bytes: usize,
+}
+
+impl NodeCacheBudget {
+    /// The default branch budget, 512 MiB.
+    pub const DEFAULT_BRANCH_BYTES: usize = 512 << 20;
+
+    /// The default leaf budget, 256 MiB.
+    pub const DEFAULT_LEAF_BYTES: usize = 256 << 20;
+}
+
+impl Default for NodeCacheBudget {
+    fn default() -> Self {
+        Self {
+            branch_bytes: Self::DEFAULT_BRANCH_BYTES,
+            leaf_bytes: Self::DEFAULT_LEAF_BYTES,
+        }
+    }
+}
+
+/// The durability policy applied when a revision is persisted.
+///
+/// Persisting a revision writes the new nodes (advancing the on-disk free
+/// lists as space is allocated) and then overwrites the header so that it
+/// points at the new root. Until the data is flushed with `fsync`, the
+/// operating system may write those pages back in any order, so a crash can
+/// leave a header on disk that references nodes that never made it.
+///
+/// Each policy trades commit latency against the guarantees below.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Durability {
+    /// Sync once per commit, after both the nodes and the header are written.
+    ///
+    /// A revision is durable once its commit returns. A crash *during* the
+    /// commit may persist the header before the nodes it references.
+    SyncOnCommit,
+
+    /// Sync the nodes, then write the header, then sync again.
+    ///
+    /// A revision is durable once its commit returns, and the header on disk
+    /// only ever references nodes that are already durable. This costs two
+    /// syncs per commit.
+    ///
+    /// This is the default. Commits used to never sync, as with
+    /// [`Durability::NoSync`], so the two syncs are new latency for every
+    /// commit, often the largest part of it on slow disks; choose
+    /// [`Durability::Periodic`] or [`Durability::NoSync`] to avoid them.
+    #[default]
+    OrderedSync,
+
+    /// Behave like [`Durability::OrderedSync`] for commits that land at least
+    /// this long after the previous sync; other commits are not synced.
+    ///
+    /// Commits that were not synced are synced by a timer once this interval
+    /// has passed since the last sync, even if no further commits arrive.
+    /// A crash may lose any commit made since the last sync, and because
+    /// unsynced headers can be written back before their nodes, the header on
+    /// disk is only guaranteed to be consistent as of the last sync.
+    Periodic(std::time::Duration),
+
+    /// Never sync; durability is left entirely to the operating system.
+    ///
+    /// Intended for tests and benchmarks. A crash may lose any number of
+    /// commits and may leave the database unreadable.
+    NoSync,
+}
+
+impl Display for Durability {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Tunables for how a [`FileBacked`] storage writes to disk.
+///
+/// The io-uring settings are only used when the `io-uring` feature is
+/// enabled; without it, nodes are written one at a time with `pwrite`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct StorageConfig {
+    /// The durability policy applied when a revision is persisted.
+    pub durability: Durability,
+
+    /// The number of submission queue entries in the io-uring. This bounds
+    /// the number of writes that can be in flight at once. The completion
+    /// queue is twice this size.
+    pub ring_size: u32,
+
+    /// How long the kernel submission queue polling thread may sit idle
+    /// before it goes to sleep, in milliseconds.
+    pub sqpoll_idle_ms: u32,
+
+    /// The number of serialized bytes accumulated before a batch of writes is
+    /// handed to the io-uring. Larger batches mean fewer, fuller rings at the
+    /// cost of memory.
+    pub batch_bytes: usize,
+
+    /// Whether nodes allocated at adjacent addresses within a batch are
+    /// merged into a single write. This trades a copy of the serialized
+    /// nodes for fewer submissions, which tends to help on fast SSDs.
+    pub coalesce_writes: bool,
+}
+
+impl StorageConfig {
+    /// The default number of io-uring submission queue entries.
+    pub const DEFAULT_RING_SIZE: u32 = 32;
+
+    /// The default io-uring submission queue polling idle time.
+    pub const DEFAULT_SQPOLL_IDLE_MS: u32 = 1000;
+
+    /// The default batch size, large enough to hold the largest node.
+    pub const DEFAULT_BATCH_BYTES: usize = nodestore::INITIAL_BUMP_SIZE;
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            durability: Durability::default(),
+            ring_size: Self::DEFAULT_RING_SIZE,
+            sqpoll_idle_ms: Self::DEFAULT_SQPOLL_IDLE_MS,
+            batch_bytes: Self::DEFAULT_BATCH_BYTES,
+            coalesce_writes: false,
+        }
+    }
+}
+
 /// This enum encapsulates what points to the stored area.
 #[derive(Debug, Clone, PartialEq, Eq, Copy)]
 pub enum StoredAreaParent {
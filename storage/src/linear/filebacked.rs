@@ -34,23 +34,32 @@ use std::os::unix::fs::FileExt;
 use std::os::windows::fs::FileExt;
 use std::path::PathBuf;
 use std::sync::Mutex;
+use std::time::Instant;
 
 use lru::LruCache;
-use metrics::counter;
+use metrics::{counter, gauge};
 
-use crate::{CacheReadStrategy, LinearAddress, MaybePersistedNode, SharedNode};
+use crate::{
+    CacheReadStrategy, Durability, LinearAddress, MaybePersistedNode, Node, NodeCacheBudget,
+    SharedNode, StorageConfig, firewood_counter,
+};
 
+use super::node_cache::{NodeCache, NodeKind};
 use super::{FileIoError, OffsetReader, ReadableStorage, WritableStorage};
 
 /// A [`ReadableStorage`] and [`WritableStorage`] backed by a file
 pub struct FileBacked {
     fd: File,
     filename: PathBuf,
-    cache: Mutex<LruCache<LinearAddress, SharedNode>>,
+    cache: Mutex<NodeCache>,
     free_list_cache: Mutex<LruCache<LinearAddress, Option<LinearAddress>>>,
     cache_read_strategy: CacheReadStrategy,
+    config: StorageConfig,
+    last_sync: Mutex<Option<Instant>>,
     #[cfg(feature = "io-uring")]
     pub(crate) ring: Mutex<ManuallyDrop<io_uring::IoUring>>,
+    #[cfg(feature = "io-uring")]
+    pub(crate) ring_id: String,
 }
 
 impl Drop for FileBacked {
@@ -86,6 +95,7 @@ impl std::fmt::Debug for FileBacked {
             .field("cache", &self.cache)
             .field("free_list_cache", &self.free_list_cache)
             .field("cache_read_strategy", &self.cache_read_strategy)
+            .field("config", &self.config)
             .finish()
     }
 }
@@ -119,20 +129,21 @@ impl FileBacked {
         )
     }
 
-    #[cfg(feature = "io-uring")]
-    // The size of the kernel ring buffer. This buffer will control how many writes we do with
-    // a single system call.
-    // TODO: make this configurable
-    pub(crate) const RINGSIZE: u32 = 32;
+    /// Return the storage configuration this file was opened with
+    #[must_use]
+    pub const fn config(&self) -> &StorageConfig {
+        &self.config
+    }
 
     /// Create or open a file at a given path
     pub fn new(
         path: PathBuf,
-        node_cache_size: NonZero<usize>,
+        node_cache: NodeCacheBudget,
         free_list_cache_size: NonZero<usize>,
         truncate: bool,
         create: bool,
         cache_read_strategy: CacheReadStrategy,
+        config: StorageConfig,
     ) -> Result<Self, FileIoError> {
         let fd = OpenOptions::new()
             .read(true)
@@ -149,18 +160,15 @@ impl FileBacked {
 
         #[cfg(feature = "io-uring")]
         let ring = {
-            // The kernel will stop the worker thread in this many ms if there is no work to do
-            const IDLETIME_MS: u32 = 1000;
-
             io_uring::IoUring::builder()
                 // we promise not to fork and we are the only issuer of writes to this ring
                 .dontfork()
                 .setup_single_issuer()
                 // completion queue should be larger than the request queue, we allocate double
-                .setup_cqsize(FileBacked::RINGSIZE * 2)
-                // start a kernel thread to do the IO
-                .setup_sqpoll(IDLETIME_MS)
-                .build(FileBacked::RINGSIZE)
+                .setup_cqsize(config.ring_size.saturating_mul(2))
+                // start a kernel thread to do the IO; it stops after this many idle ms
+                .setup_sqpoll(config.sqpoll_idle_ms)
+                .build(config.ring_size)
                 .map_err(|e| FileIoError {
                     inner: e,
                     filename: Some(path.clone()),
@@ -171,14 +179,70 @@ impl FileBacked {
 
         Ok(Self {
             fd,
-            cache: Mutex::new(LruCache::new(node_cache_size)),
+            cache: Mutex::new(NodeCache::new(node_cache)),
             free_list_cache: Mutex::new(LruCache::new(free_list_cache_size)),
             cache_read_strategy,
+            config,
+            last_sync: Mutex::new(None),
             filename: path,
             #[cfg(feature = "io-uring")]
             ring: Mutex::new(ManuallyDrop::new(ring)),
+            #[cfg(feature = "io-uring")]
+            ring_id: next_ring_id(),
         })
     }
+
+    /// Cache a node read while warming the cache.
+    ///
+    /// Warming may race with commits that free and reuse `addr`, so a node
+    /// already cached at `addr` (which is never older than the one read
+    /// here) is kept. Returns `true` if the node was cached.
+    pub(crate) fn warm_node(&self, addr: LinearAddress, node: SharedNode) -> bool {
+        let mut guard = self.cache.lock().expect("poisoned lock");
+        let Some((kind, evicted)) = guard.put_if_absent(addr, node) else {
+            return false;
+        };
+        Self::record_cache_put(&guard, kind, evicted);
+        true
+    }
+
+    /// Return the addresses of all cached nodes, least recently used first.
+    ///
+    /// These can be saved and passed back to [`NodeStore::warm_addresses`]
+    /// after a restart to rebuild the cache.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cache lock is poisoned.
+    ///
+    /// [`NodeStore::warm_addresses`]: crate::NodeStore::warm_addresses
+    #[must_use]
+    pub fn hot_addresses(&self) -> Vec<LinearAddress> {
+        self.cache.lock().expect("poisoned lock").addresses()
+    }
+
+    /// Insert a node into the node cache, reporting evictions and cache size
+    fn put_cached_node(cache: &mut NodeCache, addr: LinearAddress, node: SharedNode) {
+        let (kind, evicted) = cache.put(addr, node);
+        Self::record_cache_put(cache, kind, evicted);
+    }
+
+    fn record_cache_put(cache: &NodeCache, kind: NodeKind, evicted: u64) {
+        if evicted > 0 {
+            counter!("firewood.cache.node.evict", "kind" => kind.as_str()).increment(evicted);
+        }
+        #[expect(clippy::cast_precision_loss)]
+        gauge!("firewood.cache.node.bytes", "kind" => kind.as_str()).set(cache.bytes(kind) as f64);
+    }
+}
+
+/// Allocate a process-unique identifier for an io-uring, used to label its metrics
+#[cfg(feature = "io-uring")]
+fn next_ring_id() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT_RING_ID: AtomicU64 = AtomicU64::new(0);
+    NEXT_RING_ID.fetch_add(1, Ordering::Relaxed).to_string()
 }
 
 impl ReadableStorage for FileBacked {
@@ -197,10 +261,28 @@ impl ReadableStorage for FileBacked {
 
     fn read_cached_node(&self, addr: LinearAddress, mode: &'static str) -> Option<SharedNode> {
         let mut guard = self.cache.lock().expect("poisoned lock");
-        let cached = guard.get(&addr).cloned();
-        counter!("firewood.cache.node", "mode" => mode, "type" => if cached.is_some() { "hit" } else { "miss" })
-            .increment(1);
-        cached
+        // misses are recorded by `record_cache_miss`, once the node has been read
+        let (node, kind) = guard.get(addr)?;
+        firewood_counter!(
+            "firewood.cache.node",
+            "node cache lookups",
+            "mode" => mode,
+            "type" => "hit",
+            "kind" => kind.as_str()
+        )
+        .increment(1);
+        Some(node)
+    }
+
+    fn record_cache_miss(&self, node: &Node, mode: &'static str) {
+        firewood_counter!(
+            "firewood.cache.node",
+            "node cache lookups",
+            "mode" => mode,
+            "type" => "miss",
+            "kind" => NodeKind::of(node).as_str()
+        )
+        .increment(1);
     }
 
     fn free_list_cache(&self, addr: LinearAddress) -> Option<Option<LinearAddress>> {
@@ -221,12 +303,12 @@ impl ReadableStorage for FileBacked {
             }
             CacheReadStrategy::All => {
                 let mut guard = self.cache.lock().expect("poisoned lock");
-                guard.put(addr, node);
+                Self::put_cached_node(&mut guard, addr, node);
             }
             CacheReadStrategy::BranchReads => {
                 if !node.is_leaf() {
                     let mut guard = self.cache.lock().expect("poisoned lock");
-                    guard.put(addr, node);
+                    Self::put_cached_node(&mut guard, addr, node);
                 }
             }
         }
@@ -264,7 +346,7 @@ impl WritableStorage for FileBacked {
                 .allocated_info()
                 .expect("node should be allocated");
 
-            guard.put(addr, shared_node);
+            Self::put_cached_node(&mut guard, addr, shared_node);
             // The node can now be read from the general cache, so we can delete the local copy
             maybe_persisted_node.persist_at(addr);
         }
@@ -274,7 +356,7 @@ impl WritableStorage for FileBacked {
     fn invalidate_cached_nodes<'a>(&self, nodes: impl Iterator<Item = &'a MaybePersistedNode>) {
         let mut guard = self.cache.lock().expect("poisoned lock");
         for addr in nodes.filter_map(MaybePersistedNode::as_linear_address) {
-            guard.pop(&addr);
+            guard.pop(addr);
         }
     }
 
@@ -282,6 +364,25 @@ impl WritableStorage for FileBacked {
         let mut guard = self.free_list_cache.lock().expect("poisoned lock");
         guard.put(addr, next);
     }
+
+    fn durability(&self) -> Durability {
+        self.config.durability
+    }
+
+    fn sync(&self) -> Result<(), FileIoError> {
+        let sync_start = coarsetime::Instant::now();
+        self.fd
+            .sync_data()
+            .map_err(|e| self.file_io_error(e, 0, Some("sync".to_string())))?;
+        *self.last_sync.lock().expect("poisoned lock") = Some(Instant::now());
+        counter!("firewood.io.sync_ms").increment(sync_start.elapsed().as_millis());
+        counter!("firewood.io.sync").increment(1);
+        Ok(())
+    }
+
+    fn last_sync(&self) -> Option<Instant> {
+        *self.last_sync.lock().expect("poisoned lock")
+    }
 }
 
 const PREDICTIVE_READ_BUFFER_SIZE: usize = 1024;
@@ -369,11 +470,15 @@ mod test {
         // read the whole thing in
         let fb = FileBacked::new(
             path,
-            nonzero!(10usize),
+            NodeCacheBudget::default(),
             nonzero!(10usize),
             false,
             true,
             CacheReadStrategy::WritesOnly,
+            StorageConfig {
+                durability: Durability::NoSync,
+                ..StorageConfig::default()
+            },
         )
         .unwrap();
 
@@ -411,11 +516,15 @@ mod test {
 
         let fb = FileBacked::new(
             path,
-            nonzero!(10usize),
+            NodeCacheBudget::default(),
             nonzero!(10usize),
             false,
             true,
             CacheReadStrategy::WritesOnly,
+            StorageConfig {
+                durability: Durability::NoSync,
+                ..StorageConfig::default()
+            },
         )
         .unwrap();
 
@@ -23,10 +23,12 @@ use std::fmt::Debug;
 use std::io::{Cursor, Read};
 use std::ops::Deref;
 use std::path::PathBuf;
+use std::time::Instant;
 
-use crate::{CacheReadStrategy, LinearAddress, MaybePersistedNode, SharedNode};
+use crate::{CacheReadStrategy, Durability, LinearAddress, MaybePersistedNode, Node, SharedNode};
 pub(super) mod filebacked;
 pub mod memory;
+mod node_cache;
 
 /// An error that occurs when reading or writing to a [`ReadableStorage`] or [`WritableStorage`]
 ///
@@ -132,6 +134,9 @@ pub trait ReadableStorage: Debug + Sync + Send {
         None
     }
 
+    /// Record that `node` was read from storage because it was not cached
+    fn record_cache_miss(&self, _node: &Node, _mode: &'static str) {}
+
     /// Fetch the next pointer from the freelist cache
     fn free_list_cache(&self, _addr: LinearAddress) -> Option<Option<LinearAddress>> {
         None
@@ -197,6 +202,21 @@ pub trait WritableStorage: ReadableStorage {
 
     /// Add a new entry to the freelist cache
     fn add_to_free_list_cache(&self, _addr: LinearAddress, _next: Option<LinearAddress>) {}
+
+    /// Return the durability policy for this writable storage
+    fn durability(&self) -> Durability {
+        Durability::NoSync
+    }
+
+    /// Flush all previous writes to stable storage
+    fn sync(&self) -> Result<(), FileIoError> {
+        Ok(())
+    }
+
+    /// Return the time of the last successful [`WritableStorage::sync`], if any
+    fn last_sync(&self) -> Option<Instant> {
+        None
+    }
 }
 
 pub trait OffsetReader: Read {
@@ -0,0 +1,290 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! A node cache bounded by the memory its nodes use rather than their count.
+//!
+//! Branches and leaves are kept in separate LRU lists, each with its own byte
+//! budget, so a workload with large values cannot push the (much more
+//! valuable) branches out of the cache.
+
+use lru::LruCache;
+
+use crate::{LinearAddress, Node, NodeCacheBudget, SharedNode};
+
+/// The kind of node, used to pick a budget and to label metrics
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum NodeKind {
+    Branch,
+    Leaf,
+}
+
+impl NodeKind {
+    pub(super) const fn of(node: &Node) -> Self {
+        match node {
+            Node::Branch(_) => NodeKind::Branch,
+            Node::Leaf(_) => NodeKind::Leaf,
+        }
+    }
+
+    pub(super) const fn as_str(self) -> &'static str {
+        match self {
+            NodeKind::Branch => "branch",
+            NodeKind::Leaf => "leaf",
+        }
+    }
+}
+
+/// Approximate number of bytes a cached node keeps alive
+pub(super) fn node_weight(node: &Node) -> usize {
+    // each entry also costs a key and the list pointers in the LRU
+    const INLINE: usize = size_of::<Node>()
+        + size_of::<LinearAddress>()
+        + size_of::<SharedNode>()
+        + 2 * size_of::<usize>();
+
+    match node {
+        Node::Branch(branch) => INLINE
+            .saturating_add(size_of::<crate::BranchNode>())
+            .saturating_add(branch.partial_path.len())
+            .saturating_add(branch.value.as_ref().map_or(0, |v| v.len())),
+        Node::Leaf(leaf) => INLINE
+            .saturating_add(leaf.partial_path.len())
+            .saturating_add(leaf.value.len()),
+    }
+}
+
+/// One LRU list with a byte budget
+#[derive(Debug)]
+struct WeightedLru {
+    entries: LruCache<LinearAddress, (SharedNode, usize)>,
+    bytes: usize,
+    budget: usize,
+}
+
+impl WeightedLru {
+    fn new(budget: usize) -> Self {
+        Self {
+            entries: LruCache::unbounded(),
+            bytes: 0,
+            budget,
+        }
+    }
+
+    fn get(&mut self, addr: LinearAddress) -> Option<SharedNode> {
+        self.entries.get(&addr).map(|(node, _)| node.clone())
+    }
+
+    fn pop(&mut self, addr: LinearAddress) -> bool {
+        match self.entries.pop(&addr) {
+            Some((_, weight)) => {
+                self.bytes = self.bytes.saturating_sub(weight);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert a node, returning the number of entries evicted to make room
+    fn put(&mut self, addr: LinearAddress, node: SharedNode, weight: usize) -> u64 {
+        self.pop(addr);
+        if weight > self.budget {
+            // this node alone would exceed the budget
+            return 0;
+        }
+
+        let mut evicted = 0u64;
+        while self.bytes.saturating_add(weight) > self.budget {
+            let Some((_, (_, lru_weight))) = self.entries.pop_lru() else {
+                break;
+            };
+            self.bytes = self.bytes.saturating_sub(lru_weight);
+            evicted = evicted.saturating_add(1);
+        }
+        self.entries.put(addr, (node, weight));
+        self.bytes = self.bytes.saturating_add(weight);
+        evicted
+    }
+}
+
+/// A node cache with separate byte budgets for branches and leaves
+#[derive(Debug)]
+pub(super) struct NodeCache {
+    branches: WeightedLru,
+    leaves: WeightedLru,
+}
+
+impl NodeCache {
+    pub(super) fn new(budget: NodeCacheBudget) -> Self {
+        Self {
+            branches: WeightedLru::new(budget.branch_bytes),
+            leaves: WeightedLru::new(budget.leaf_bytes),
+        }
+    }
+
+    const fn lru(&mut self, kind: NodeKind) -> &mut WeightedLru {
+        match kind {
+            NodeKind::Branch => &mut self.branches,
+            NodeKind::Leaf => &mut self.leaves,
+        }
+    }
+
+    /// Look up a node, returning it along with its kind
+    pub(super) fn get(&mut self, addr: LinearAddress) -> Option<(SharedNode, NodeKind)> {
+        if let Some(node) = self.branches.get(addr) {
+            return Some((node, NodeKind::Branch));
+        }
+        self.leaves.get(addr).map(|node| (node, NodeKind::Leaf))
+    }
+
+    /// Cache a node, returning the number of nodes of the same kind evicted
+    pub(super) fn put(&mut self, addr: LinearAddress, node: SharedNode) -> (NodeKind, u64) {
+        let kind = NodeKind::of(&node);
+        // an address freed by one kind of node may be reused by the other
+        let other = match kind {
+            NodeKind::Branch => NodeKind::Leaf,
+            NodeKind::Leaf => NodeKind::Branch,
+        };
+        self.lru(other).pop(addr);
+
+        let weight = node_weight(&node);
+        (kind, self.lru(kind).put(addr, node, weight))
+    }
+
+    /// Cache a node unless a node is already cached at `addr`
+    pub(super) fn put_if_absent(
+        &mut self,
+        addr: LinearAddress,
+        node: SharedNode,
+    ) -> Option<(NodeKind, u64)> {
+        if self.branches.entries.contains(&addr) || self.leaves.entries.contains(&addr) {
+            return None;
+        }
+        Some(self.put(addr, node))
+    }
+
+    /// The addresses of all cached nodes, least recently used first
+    pub(super) fn addresses(&self) -> Vec<LinearAddress> {
+        self.branches
+            .entries
+            .iter()
+            .rev()
+            .chain(self.leaves.entries.iter().rev())
+            .map(|(addr, _)| *addr)
+            .collect()
+    }
+
+    pub(super) fn pop(&mut self, addr: LinearAddress) {
+        if !self.branches.pop(addr) {
+            self.leaves.pop(addr);
+        }
+    }
+
+    /// The number of bytes currently cached for the given kind
+    pub(super) const fn bytes(&self, kind: NodeKind) -> usize {
+        match kind {
+            NodeKind::Branch => self.branches.bytes,
+            NodeKind::Leaf => self.leaves.bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::{BranchNode, Children, LeafNode, Path};
+
+    fn leaf(value_len: usize) -> SharedNode {
+        SharedNode::new(Node::Leaf(LeafNode {
+            partial_path: Path::new(),
+            value: vec![0; value_len].into_boxed_slice(),
+        }))
+    }
+
+    fn branch() -> SharedNode {
+        SharedNode::new(Node::Branch(Box::new(BranchNode {
+            partial_path: Path::new(),
+            value: None,
+            children: Children::default(),
+        })))
+    }
+
+    fn addr(a: u64) -> LinearAddress {
+        LinearAddress::new(a).unwrap()
+    }
+
+    #[test]
+    fn test_leaves_evicted_by_bytes() {
+        let leaf_weight = node_weight(&leaf(100));
+        let mut cache = NodeCache::new(NodeCacheBudget {
+            branch_bytes: 1 << 20,
+            leaf_bytes: 2 * leaf_weight,
+        });
+
+        assert_eq!(cache.put(addr(1), leaf(100)), (NodeKind::Leaf, 0));
+        assert_eq!(cache.put(addr(2), leaf(100)), (NodeKind::Leaf, 0));
+        assert_eq!(cache.bytes(NodeKind::Leaf), 2 * leaf_weight);
+
+        // touch 1 so that 2 is the least recently used
+        assert!(cache.get(addr(1)).is_some());
+        assert_eq!(cache.put(addr(3), leaf(100)), (NodeKind::Leaf, 1));
+        assert!(cache.get(addr(2)).is_none());
+        assert!(cache.get(addr(1)).is_some());
+        assert!(cache.get(addr(3)).is_some());
+
+        // a single large value can evict several small ones
+        let big = leaf(100 + leaf_weight);
+        assert!(node_weight(&big) <= 2 * leaf_weight);
+        assert_eq!(cache.put(addr(4), big), (NodeKind::Leaf, 2));
+        assert_eq!(
+            cache.bytes(NodeKind::Leaf),
+            node_weight(&leaf(100 + leaf_weight))
+        );
+    }
+
+    #[test]
+    fn test_budgets_are_separate() {
+        let mut cache = NodeCache::new(NodeCacheBudget {
+            branch_bytes: 1 << 20,
+            leaf_bytes: 0,
+        });
+
+        // leaves never fit, and never push branches out
+        assert_eq!(cache.put(addr(1), branch()), (NodeKind::Branch, 0));
+        assert_eq!(cache.put(addr(2), leaf(10)), (NodeKind::Leaf, 0));
+        assert_eq!(cache.get(addr(1)).unwrap().1, NodeKind::Branch);
+        assert!(cache.get(addr(2)).is_none());
+        assert_eq!(cache.bytes(NodeKind::Leaf), 0);
+    }
+
+    #[test]
+    fn test_reused_address_changes_kind() {
+        let mut cache = NodeCache::new(NodeCacheBudget {
+            branch_bytes: 1 << 20,
+            leaf_bytes: 1 << 20,
+        });
+
+        cache.put(addr(1), branch());
+        cache.put(addr(1), leaf(10));
+        assert_eq!(cache.get(addr(1)).unwrap().1, NodeKind::Leaf);
+        assert_eq!(cache.bytes(NodeKind::Branch), 0);
+
+        cache.pop(addr(1));
+        assert!(cache.get(addr(1)).is_none());
+        assert_eq!(cache.bytes(NodeKind::Leaf), 0);
+    }
+
+    #[test]
+    fn test_put_if_absent_keeps_existing_node() {
+        let mut cache = NodeCache::new(NodeCacheBudget::default());
+
+        cache.put(addr(1), leaf(1));
+        assert!(cache.put_if_absent(addr(1), leaf(2)).is_none());
+        assert_eq!(cache.get(addr(1)).unwrap().0.value(), Some(&[0][..]));
+        assert!(cache.put_if_absent(addr(2), branch()).is_some());
+
+        // branches first, each kind least recently used first
+        cache.put(addr(3), leaf(1));
+        assert_eq!(cache.addresses(), vec![addr(2), addr(1), addr(3)]);
+    }
+}
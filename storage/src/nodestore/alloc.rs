@@ -275,7 +275,10 @@ impl<'a, S: ReadableStorage> NodeAllocator<'a, S> {
         Ok(None)
     }
 
-    fn allocate_from_end(&mut self, n: u64) -> Result<(LinearAddress, AreaIndex), FileIoError> {
+    pub(crate) fn allocate_from_end(
+        &mut self,
+        n: u64,
+    ) -> Result<(LinearAddress, AreaIndex), FileIoError> {
         let index = AreaIndex::from_size(n).map_err(|e| {
             self.storage
                 .file_io_error(e, 0, Some("allocate_from_end".to_string()))
@@ -321,7 +324,6 @@ impl<S: WritableStorage> NodeAllocator<'_, S> {
     /// # Errors
     ///
     /// Returns a [`FileIoError`] if the area cannot be read or written.
-    #[expect(clippy::indexing_slicing)]
     pub fn delete_node(&mut self, node: MaybePersistedNode) -> Result<(), FileIoError> {
         let Some(addr) = node.as_linear_address() else {
             return Ok(());
@@ -344,6 +346,21 @@ impl<S: WritableStorage> NodeAllocator<'_, S> {
         .increment(area_size_index.size());
 
         // The area that contained the node is now free.
+        self.free_area(addr, area_size_index)
+    }
+
+    /// Adds the area of size `area_size_index` at `addr`, which nothing
+    /// uses, to the head of its free list.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if the area cannot be written.
+    #[expect(clippy::indexing_slicing)]
+    pub fn free_area(
+        &mut self,
+        addr: LinearAddress,
+        area_size_index: AreaIndex,
+    ) -> Result<(), FileIoError> {
         let mut stored_area_bytes = Vec::new();
         FreeArea::new(self.header.free_lists()[area_size_index.as_usize()])
             .as_bytes(area_size_index, &mut stored_area_bytes);
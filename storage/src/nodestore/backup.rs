@@ -0,0 +1,350 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! # Backup Module
+//!
+//! Copies the nodes of a committed revision into another storage, leaving
+//! behind the nodes of every other revision and the free space between them.
+//!
+//! Incremental backups instead keep every node at its original address: the
+//! areas written since a base revision are read with
+//! [`NodeStore::new_areas`] and written back with
+//! [`NodeStore::restore_area`], on top of a restored copy of the base.
+
+use std::io::{Error, ErrorKind};
+use std::sync::Arc;
+
+use super::alloc::NodeAllocator;
+use super::header::NodeStoreHeader;
+use super::{Committed, HashedNodeReader, NodeReader, NodeStore, RootReader};
+use crate::linear::WritableStorage;
+use crate::node::branch::Child;
+use crate::{
+    AreaIndex, FileIoError, IntoHashType, LinearAddress, Node, PathComponent, ReadableStorage,
+    SharedNode,
+};
+
+/// How much of a revision has been copied so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CopyProgress {
+    /// The number of nodes written
+    pub nodes: u64,
+    /// The number of bytes allocated for the nodes written
+    pub bytes: u64,
+}
+
+impl<S: ReadableStorage> NodeStore<Committed, S> {
+    /// Copy the nodes reachable from this revision into `dest`, which must be
+    /// empty, and write a header making it the only revision there.
+    ///
+    /// Nodes are written children first, so they are packed one after the
+    /// other with no free areas between them. `progress` is called after each
+    /// node is written.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if a node cannot be read from this storage or
+    /// written to `dest`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a node of this revision has not been hashed, which cannot
+    /// happen for a committed revision.
+    pub fn copy_to<D: WritableStorage>(
+        &self,
+        dest: Arc<D>,
+        mut progress: impl FnMut(CopyProgress),
+    ) -> Result<NodeStore<Committed, D>, FileIoError> {
+        let mut copy = NodeStore::new_empty_committed(dest);
+        copy.flush_header_with_padding()?;
+
+        if let (Some(root), Some(root_hash)) = (self.root_node(), self.root_hash()) {
+            let mut header = copy.header;
+            let mut allocator = NodeAllocator::new(copy.storage.as_ref(), &mut header);
+            let mut copied = CopyProgress::default();
+            let root_address = self.copy_node(
+                &root,
+                &mut allocator,
+                copy.storage.as_ref(),
+                &mut copied,
+                &mut progress,
+            )?;
+            header.set_root_address(Some(root_address));
+            copy.header = header;
+            copy.kind.root = Some(Child::AddressWithHash(
+                root_address,
+                root_hash.into_hash_type(),
+            ));
+        }
+
+        copy.flush_header()?;
+        copy.storage.sync()?;
+        Ok(copy)
+    }
+
+    /// Copy `node` and everything below it, returning its new address.
+    fn copy_node<D: WritableStorage>(
+        &self,
+        node: &SharedNode,
+        allocator: &mut NodeAllocator<'_, D>,
+        dest: &D,
+        copied: &mut CopyProgress,
+        progress: &mut impl FnMut(CopyProgress),
+    ) -> Result<LinearAddress, FileIoError> {
+        let copied_branch;
+        let copy = if let Node::Branch(branch) = &**node {
+            let mut branch = branch.clone();
+            for (_, slot) in &mut branch.children {
+                let Some(child) = slot.take() else {
+                    continue;
+                };
+                let (child_node, hash) = match child {
+                    Child::AddressWithHash(addr, hash) => (self.read_node(addr)?, hash),
+                    Child::MaybePersisted(node, hash) => (node.as_shared_node(self)?, hash),
+                    Child::Node(_) => {
+                        unreachable!("the children of a committed revision are hashed")
+                    }
+                };
+                let addr = self.copy_node(&child_node, allocator, dest, copied, progress)?;
+                *slot = Some(Child::AddressWithHash(addr, hash));
+            }
+            copied_branch = Node::Branch(branch);
+            &copied_branch
+        } else {
+            &**node
+        };
+
+        let mut bytes = Vec::new();
+        copy.as_bytes(AreaIndex::MIN, &mut bytes);
+        let (addr, area_index) = allocator.allocate_node(&bytes)?;
+        *bytes.get_mut(0).expect("byte was reserved") = area_index.get();
+        dest.write(addr.get(), &bytes)?;
+
+        copied.nodes = copied.nodes.saturating_add(1);
+        copied.bytes = copied.bytes.saturating_add(area_index.size());
+        progress(*copied);
+        Ok(addr)
+    }
+}
+
+impl<S: ReadableStorage> NodeStore<Committed, S> {
+    /// Calls `visit` with the address and contents of each area of this
+    /// revision that is not an area of `base`.
+    ///
+    /// `base` must be alive in the same storage, so that an address used by
+    /// both revisions holds the same node in each. Each node is compared
+    /// with the node of `base` that starts at the same path, and subtrees
+    /// shared with `base` are skipped without being read, so the cost is
+    /// proportional to the nodes written since `base` rather than to the
+    /// size of the trie. Children are visited before their parents.
+    ///
+    /// The contents are the serialized node, preceded by its area index, and
+    /// may be shorter than the area.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if a node cannot be read, if a node of this
+    /// revision has not been written to storage yet, or the first error
+    /// returned by `visit`.
+    pub fn new_areas(
+        &self,
+        base: Option<&NodeStore<Committed, S>>,
+        mut visit: impl FnMut(LinearAddress, &[u8]) -> Result<(), FileIoError>,
+    ) -> Result<(), FileIoError> {
+        let Some(root) = self.kind.root.as_ref() else {
+            return Ok(());
+        };
+        let base = base.and_then(BaseCursor::at_root);
+        self.visit_new_nodes(
+            self.persisted_address(root)?,
+            &mut Vec::new(),
+            base,
+            &mut |addr, node| {
+                let (area_index, _) = self.area_index_and_size(addr)?;
+                let mut bytes = Vec::new();
+                node.as_bytes(area_index, &mut bytes);
+                visit(addr, &bytes)
+            },
+        )
+    }
+
+    /// Calls `visit` with the address of each node of this revision that is
+    /// not a node of `base`, as [`NodeStore::new_areas`] does.
+    pub(super) fn new_node_addresses(
+        &self,
+        base: &NodeStore<Committed, S>,
+        mut visit: impl FnMut(LinearAddress),
+    ) -> Result<(), FileIoError> {
+        let Some(root) = self.kind.root.as_ref() else {
+            return Ok(());
+        };
+        let base = BaseCursor::at_root(base);
+        self.visit_new_nodes(
+            self.persisted_address(root)?,
+            &mut Vec::new(),
+            base,
+            &mut |addr, _| {
+                visit(addr);
+                Ok(())
+            },
+        )
+    }
+
+    /// Visits the new nodes below the node at `addr`, which starts at `path`.
+    fn visit_new_nodes(
+        &self,
+        addr: LinearAddress,
+        path: &mut Vec<u8>,
+        base: Option<BaseCursor<'_, S>>,
+        visit: &mut impl FnMut(LinearAddress, &Node) -> Result<(), FileIoError>,
+    ) -> Result<(), FileIoError> {
+        let base = base.map(|base| base.seek(path)).transpose()?;
+        if base.is_some_and(|base| base.depth == path.len() && base.addr == addr) {
+            return Ok(());
+        }
+
+        let node = self.read_node(addr)?;
+        if let Node::Branch(branch) = &*node {
+            let depth = path.len();
+            path.extend_from_slice(&branch.partial_path);
+            for (pc, child) in &branch.children {
+                let Some(child) = child else {
+                    continue;
+                };
+                path.push(pc.as_u8());
+                self.visit_new_nodes(self.persisted_address(child)?, path, base, visit)?;
+                path.pop();
+            }
+            path.truncate(depth);
+        }
+        visit(addr, &node)
+    }
+
+    fn persisted_address(&self, child: &Child) -> Result<LinearAddress, FileIoError> {
+        child.persisted_address().ok_or_else(|| {
+            self.storage.file_io_error(
+                Error::other("node has not been written to storage"),
+                0,
+                Some("new_areas".to_string()),
+            )
+        })
+    }
+}
+
+/// A node of the base revision of [`NodeStore::new_areas`], starting at the
+/// first `depth` nibbles of the path being visited.
+struct BaseCursor<'a, S> {
+    store: &'a NodeStore<Committed, S>,
+    addr: LinearAddress,
+    depth: usize,
+}
+
+impl<S> Clone for BaseCursor<'_, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for BaseCursor<'_, S> {}
+
+impl<'a, S: ReadableStorage> BaseCursor<'a, S> {
+    fn at_root(store: &'a NodeStore<Committed, S>) -> Option<Self> {
+        let addr = store.kind.root.as_ref()?.persisted_address()?;
+        Some(Self {
+            store,
+            addr,
+            depth: 0,
+        })
+    }
+
+    /// Moves down towards the node that starts at `path`, stopping at that
+    /// node or at the deepest node on the way if there is none.
+    fn seek(mut self, path: &[u8]) -> Result<Self, FileIoError> {
+        while self.depth < path.len() {
+            let node = self.store.read_node(self.addr)?;
+            let Node::Branch(branch) = &*node else {
+                break;
+            };
+            let end = self.depth.saturating_add(branch.partial_path.len());
+            let (Some(partial), Some(&nibble)) = (path.get(self.depth..end), path.get(end)) else {
+                break;
+            };
+            if partial != &*branch.partial_path {
+                break;
+            }
+            let Some(child) = PathComponent::try_new(nibble)
+                .and_then(|pc| branch.children[pc].as_ref())
+                .and_then(Child::persisted_address)
+            else {
+                break;
+            };
+            self.addr = child;
+            self.depth = end.saturating_add(1);
+        }
+        Ok(self)
+    }
+}
+
+impl<S: WritableStorage> NodeStore<Committed, S> {
+    /// Writes an area visited by [`NodeStore::new_areas`] back at `addr`,
+    /// growing the store to hold it.
+    ///
+    /// The area is not reachable until it, or a node above it, is made the
+    /// root with [`NodeStore::restore_root`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if `addr` is not a valid area address, if
+    /// `bytes` does not fit in the area its first byte names, or if the area
+    /// cannot be written.
+    pub fn restore_area(&mut self, addr: LinearAddress, bytes: &[u8]) -> Result<(), FileIoError> {
+        let area_size = bytes
+            .first()
+            .copied()
+            .and_then(AreaIndex::new)
+            .map(AreaIndex::size)
+            .filter(|&size| bytes.len() as u64 <= size);
+        let (Some(area_size), true, true) = (
+            area_size,
+            addr.is_aligned(),
+            addr.get() >= NodeStoreHeader::SIZE,
+        ) else {
+            return Err(self.storage.file_io_error(
+                Error::new(ErrorKind::InvalidData, "invalid area"),
+                addr.get(),
+                Some("restore_area".to_string()),
+            ));
+        };
+
+        self.storage.write(addr.get(), bytes)?;
+        let end = addr.get().saturating_add(area_size);
+        if end > self.header.size() {
+            self.header.set_size(end);
+        }
+        Ok(())
+    }
+
+    /// Makes the node at `root` the root of the store and writes the header.
+    ///
+    /// The free lists are left empty, so areas between the restored ones are
+    /// leaked until [`NodeStore::reclaim_leaked_areas`] adds them to the free
+    /// lists once the last backup is restored.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if the root cannot be read or the header
+    /// cannot be written.
+    pub fn restore_root(&mut self, root: Option<LinearAddress>) -> Result<(), FileIoError> {
+        self.header.set_root_address(root);
+        self.kind.root = match root {
+            Some(addr) => {
+                let node = self.read_node_from_disk(addr, "restore")?;
+                let hash = crate::hash_node(&node, &crate::Path::default());
+                Some(Child::AddressWithHash(addr, hash))
+            }
+            None => None,
+        };
+        self.flush_header()?;
+        self.storage.sync()
+    }
+}
@@ -14,6 +14,7 @@
 //! - [`hash`] - Node hashing functionality, including specialized ethereum hash processing
 //! - [`header`] - `NodeStore` header structure and validation logic
 //! - [`persist`] - Persistence operations for writing nodes and metadata to storage
+//! - [`warm`] - Reading nodes into the node cache ahead of use
 //!
 //! ## Types
 //!
@@ -39,12 +40,14 @@
 //! - **`Parentable`** - Trait for nodestores that can have children
 
 pub(crate) mod alloc;
+pub(crate) mod backup;
 pub(crate) mod hash;
 pub(crate) mod header;
 pub(crate) mod persist;
 #[cfg(feature = "io-uring")]
 pub(crate) mod persist_io_uring;
 pub(crate) mod primitives;
+pub(crate) mod warm;
 
 use crate::linear::OffsetReader;
 use crate::logger::trace;
@@ -52,6 +55,7 @@ use crate::node::branch::ReadSerializable as _;
 use arc_swap::ArcSwap;
 use arc_swap::access::DynAccess;
 use smallvec::SmallVec;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::io::{Error, ErrorKind, Read};
 
@@ -99,7 +103,7 @@ use super::linear::WritableStorage;
 
 /// Initial size for the bump allocator used in node serialization batches.
 /// Set to the maximum area size to minimize allocations for large nodes.
-const INITIAL_BUMP_SIZE: usize = AreaIndex::MAX_AREA_SIZE as usize;
+pub(crate) const INITIAL_BUMP_SIZE: usize = AreaIndex::MAX_AREA_SIZE as usize;
 
 impl<S: ReadableStorage> NodeStore<Committed, S> {
     /// Open an existing [`NodeStore`]
@@ -143,6 +147,29 @@ impl<S: ReadableStorage> NodeStore<Committed, S> {
         Ok(nodestore)
     }
 
+    /// The nodes of the parent of this revision that it no longer uses.
+    #[must_use]
+    pub fn deleted_nodes(&self) -> &[MaybePersistedNode] {
+        &self.kind.deleted
+    }
+
+    /// A copy of this revision that reads the same trie, without its
+    /// deleted nodes.
+    ///
+    /// It keeps the nodes of the trie from being freed only as long as the
+    /// caller makes sure of it, unlike the revision itself.
+    #[must_use]
+    pub fn without_deleted(&self) -> Self {
+        NodeStore {
+            header: self.header,
+            kind: Committed {
+                deleted: Box::default(),
+                root: self.kind.root.clone(),
+            },
+            storage: self.storage.clone(),
+        }
+    }
+
     /// Create a new, empty, Committed [`NodeStore`] and clobber
     /// the underlying store with an empty freelist and no root node
     pub fn new_empty_committed(storage: Arc<S>) -> Self {
@@ -248,6 +275,21 @@ impl<S> NodeStore<Arc<ImmutableProposal>, S> {
             NodeStoreParent::Committed(_) => {}
         }
     }
+
+    /// Returns the revision this proposal was created on top of.
+    #[must_use]
+    pub fn parent(&self) -> NodeStoreParent {
+        NodeStoreParent::clone(&self.kind.parent.load())
+    }
+
+    /// Returns true if `other` was proposed on top of this proposal.
+    #[must_use]
+    pub fn is_parent_of(&self, other: &NodeStore<Arc<ImmutableProposal>, S>) -> bool {
+        match *other.kind.parent.load() {
+            NodeStoreParent::Proposed(ref parent) => Arc::ptr_eq(&self.kind, parent),
+            NodeStoreParent::Committed(_) => false,
+        }
+    }
 }
 
 impl Parentable for Committed {
@@ -376,6 +418,30 @@ impl<S: WritableStorage> NodeStore<MutableProposal, S> {
             storage,
         }
     }
+
+    /// Writes `node` to a new area at the end of the file and returns its
+    /// address, so that it no longer has to be kept in memory.
+    ///
+    /// The free lists are never used, so dropping the proposal cannot leave
+    /// one pointing at a node; the area is only lost, until leak reclamation
+    /// finds it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if the node cannot be written.
+    pub fn write_node_at_end(&mut self, node: &Node) -> Result<LinearAddress, FileIoError> {
+        let mut bytes = Vec::new();
+        node.as_bytes(AreaIndex::MIN, &mut bytes);
+        let (address, area_size_index) =
+            NodeAllocator::new(self.storage.as_ref(), &mut self.header)
+                .allocate_from_end(bytes.len() as u64)?;
+        // the first byte was reserved for the area size index
+        if let Some(first) = bytes.first_mut() {
+            *first = area_size_index.get();
+        }
+        self.storage.write(address.get(), &bytes)?;
+        Ok(address)
+    }
 }
 
 /// Reads from an immutable (i.e. already hashed) merkle trie.
@@ -445,9 +511,12 @@ pub struct Committed {
     root: Option<Child>,
 }
 
+/// The revision a proposal was created on top of.
 #[derive(Clone, Debug)]
 pub enum NodeStoreParent {
+    /// Another proposal, which has not been committed yet
     Proposed(Arc<ImmutableProposal>),
+    /// A committed revision with the given root hash, or the empty trie
     Committed(Option<TrieHash>),
 }
 
@@ -513,6 +582,21 @@ pub struct NodeStore<T, S> {
 }
 
 impl<T, S> NodeStore<T, S> {
+    /// Returns the offset past every area allocated in this node store.
+    #[must_use]
+    pub const fn size(&self) -> u64 {
+        self.header.size()
+    }
+
+    /// Makes sure nothing is allocated from the end of the file below
+    /// `size`, because space up to there was written outside of this node
+    /// store.
+    pub const fn reserve_to(&mut self, size: u64) {
+        if size > self.header.size() {
+            self.header.set_size(size);
+        }
+    }
+
     pub(crate) const fn freelists(&self) -> &alloc::FreeLists {
         self.header.free_lists()
     }
@@ -694,7 +778,10 @@ where
     S: ReadableStorage,
 {
     fn root_address(&self) -> Option<LinearAddress> {
-        self.header.root_address()
+        // The root only has an address once it has been allocated on disk. The
+        // header is not used here because a committed revision can be published
+        // before its header is written.
+        self.kind.root().and_then(|root| root.as_linear_address())
     }
 
     fn root_hash(&self) -> Option<TrieHash> {
@@ -746,6 +833,7 @@ impl<T, S: ReadableStorage> NodeStore<T, S> {
         }
 
         let (node, _) = self.read_node_with_num_bytes_from_disk(addr)?;
+        self.storage.record_cache_miss(&node, mode);
 
         match self.storage.cache_read_strategy() {
             CacheReadStrategy::All => {
@@ -832,15 +920,156 @@ impl<S: WritableStorage> NodeStore<Committed, S> {
         mut self,
         proposal: &mut NodeStore<Committed, S>,
     ) -> Result<(), FileIoError> {
-        self.storage
-            .invalidate_cached_nodes(self.kind.deleted.iter());
-        trace!("There are {} nodes to reap", self.kind.deleted.len());
-        let mut allocator = NodeAllocator::new(self.storage.as_ref(), &mut proposal.header);
-        for node in take(&mut self.kind.deleted) {
+        proposal.reap_nodes(take(&mut self.kind.deleted).into_vec())
+    }
+
+    /// Returns `nodes`, which no retained revision uses any more, to the
+    /// free lists of this proposal.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if a node cannot be deleted.
+    pub fn reap_nodes(&mut self, nodes: Vec<MaybePersistedNode>) -> Result<(), FileIoError> {
+        self.storage.invalidate_cached_nodes(nodes.iter());
+        trace!("There are {} nodes to reap", nodes.len());
+        let mut allocator = NodeAllocator::new(self.storage.as_ref(), &mut self.header);
+        for node in nodes {
             allocator.delete_node(node)?;
         }
         Ok(())
     }
+
+    /// Drops this revision from between `parent` and its child, while both
+    /// are retained.
+    ///
+    /// `deleted` are the nodes of `parent` this revision no longer uses, and
+    /// `child_deleted` the nodes of this revision its child no longer uses.
+    /// Those of the latter that this revision wrote are used by nothing else,
+    /// and are returned to the free lists of `proposal`. The others are nodes
+    /// of `parent`, and are returned together with `deleted` as the nodes of
+    /// `parent` the child no longer uses.
+    ///
+    /// This revision and `parent` must have been written to storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if a node cannot be read or deleted, or if
+    /// a revision has not been written to storage.
+    pub fn reap_between(
+        &self,
+        parent: &NodeStore<Committed, S>,
+        deleted: Vec<MaybePersistedNode>,
+        child_deleted: Vec<MaybePersistedNode>,
+        proposal: &mut NodeStore<Committed, S>,
+    ) -> Result<Vec<MaybePersistedNode>, FileIoError> {
+        let mut written = HashSet::new();
+        self.new_node_addresses(parent, |addr| {
+            written.insert(addr);
+        })?;
+        let (freed, kept): (Vec<_>, Vec<_>) = child_deleted.into_iter().partition(|node| {
+            node.as_linear_address()
+                .is_some_and(|addr| written.contains(&addr))
+        });
+        proposal.reap_nodes(freed)?;
+        Ok(deleted.into_iter().chain(kept).collect())
+    }
+
+    /// Adds `areas`, which nothing uses, to the free lists, then writes and
+    /// syncs the header. Returns the number of bytes freed.
+    pub(crate) fn free_areas(
+        &mut self,
+        areas: impl IntoIterator<Item = (LinearAddress, AreaIndex)>,
+    ) -> Result<u64, FileIoError> {
+        let mut freed = 0u64;
+        let mut allocator = NodeAllocator::new(self.storage.as_ref(), &mut self.header);
+        for (addr, area_index) in areas {
+            allocator.free_area(addr, area_index)?;
+            freed = freed.saturating_add(area_index.size());
+        }
+        self.flush_header()?;
+        self.storage.sync()?;
+        Ok(freed)
+    }
+
+    /// Makes `target` the latest revision on disk again, discarding `newer`,
+    /// the revisions committed after it, oldest first.
+    ///
+    /// `self` must be the last revision written to storage, which supplies
+    /// the free lists. See [`NodeStore::discard`] for which nodes are freed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if a node cannot be read, if a revision has
+    /// not been written to storage, or if the free lists or the header
+    /// cannot be written.
+    pub fn rollback(
+        &self,
+        target: &NodeStore<Committed, S>,
+        newer: &[&NodeStore<Committed, S>],
+    ) -> Result<NodeStore<Committed, S>, FileIoError> {
+        self.discard(target, newer, target)
+    }
+
+    /// Frees the nodes of `discarded`, a chain of revisions committed on top
+    /// of `base`, oldest first, and makes `head` the latest revision on disk.
+    ///
+    /// `self` must be the last revision written to storage, which supplies
+    /// the free lists. Every node written by the revisions in `discarded` is
+    /// returned to them; the nodes those revisions deleted are left alone,
+    /// since they belong to `base` or were written by `discarded` itself.
+    /// The returned revision keeps the deleted nodes of `head`, to be freed
+    /// when it is reaped.
+    ///
+    /// Nothing may read the revisions in `discarded` once this returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if a node cannot be read, if a revision has
+    /// not been written to storage, or if the free lists or the header
+    /// cannot be written.
+    pub fn discard(
+        &self,
+        base: &NodeStore<Committed, S>,
+        discarded: &[&NodeStore<Committed, S>],
+        head: &NodeStore<Committed, S>,
+    ) -> Result<NodeStore<Committed, S>, FileIoError> {
+        let mut written = Vec::new();
+        let mut parent = base;
+        for &revision in discarded {
+            revision.new_node_addresses(parent, |addr| {
+                written.push(MaybePersistedNode::from(addr));
+            })?;
+            parent = revision;
+        }
+        trace!("Discarding {} nodes", written.len());
+
+        let mut latest = NodeStore {
+            header: self.header,
+            kind: Committed {
+                deleted: head.kind.deleted.clone(),
+                root: head.kind.root.clone(),
+            },
+            storage: self.storage.clone(),
+        };
+        self.storage.invalidate_cached_nodes(written.iter());
+        let root_address = latest.kind.root.as_ref().and_then(Child::persisted_address);
+        latest.header.set_root_address(root_address);
+
+        // Switch the root before overwriting the discarded nodes with free
+        // areas, so a crash in between only leaks them.
+        latest.flush_header()?;
+        self.storage.sync()?;
+
+        let mut allocator = NodeAllocator::new(self.storage.as_ref(), &mut latest.header);
+        for node in written {
+            allocator.delete_node(node)?;
+        }
+        // the freed areas must reach the disk before the header listing them
+        self.storage.sync()?;
+        latest.flush_header()?;
+        self.storage.sync()?;
+        Ok(latest)
+    }
 }
 
 // Helper functions for the checker
@@ -848,10 +1077,6 @@ impl<T, S: ReadableStorage> NodeStore<T, S>
 where
     NodeStore<T, S>: NodeReader,
 {
-    pub(crate) const fn size(&self) -> u64 {
-        self.header.size()
-    }
-
     // Find the area index and size of the stored area at the given address if the area is valid.
     // TODO: there should be a way to read stored area directly instead of try reading as a free area then as a node
     pub(crate) fn read_leaked_area(
@@ -945,6 +1170,85 @@ mod tests {
         }
     }
 
+    /// Persist a revision with a single leaf root and return its address
+    fn persist_leaf<S: WritableStorage + 'static>(storage: Arc<S>) -> LinearAddress {
+        let base = NodeStore::new_empty_committed(storage);
+        let mut proposal = NodeStore::new(&base).unwrap();
+        proposal.root_mut().replace(Node::Leaf(LeafNode {
+            partial_path: Path::from([1, 2]),
+            value: b"value".to_vec().into_boxed_slice(),
+        }));
+        let proposal: NodeStore<Arc<ImmutableProposal>, _> = proposal.try_into().unwrap();
+        let mut committed = proposal.as_committed(&base);
+        committed.persist().unwrap();
+        committed.root_address().unwrap()
+    }
+
+    /// Sum the node cache misses recorded while running `f`
+    fn cache_misses(f: impl FnOnce()) -> u64 {
+        use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, f);
+        snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .filter(|(key, ..)| {
+                key.key().name() == "firewood.cache.node"
+                    && key
+                        .key()
+                        .labels()
+                        .any(|l| l.key() == "type" && l.value() == "miss")
+            })
+            .map(|(.., value)| match value {
+                DebugValue::Counter(count) => count,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn cache_misses_only_counted_for_cached_storage() {
+        let memstore = Arc::new(MemStore::new(vec![]));
+        let addr = persist_leaf(memstore.clone());
+        let store = NodeStore::open(memstore).unwrap();
+        assert_eq!(
+            cache_misses(|| {
+                store.read_node_from_disk(addr, "read").unwrap();
+            }),
+            0
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let open = |truncate| {
+            Arc::new(
+                crate::FileBacked::new(
+                    dir.path().join("db"),
+                    crate::NodeCacheBudget::default(),
+                    std::num::NonZero::new(10).unwrap(),
+                    truncate,
+                    true,
+                    CacheReadStrategy::WritesOnly,
+                    crate::StorageConfig {
+                        durability: crate::Durability::NoSync,
+                        ..crate::StorageConfig::default()
+                    },
+                )
+                .unwrap(),
+            )
+        };
+        let addr = persist_leaf(open(true));
+        let store = NodeStore::open(open(false)).unwrap();
+        assert_eq!(
+            cache_misses(|| {
+                store.read_node_from_disk(addr, "read").unwrap();
+            }),
+            1
+        );
+    }
+
     #[test]
     #[ignore = "https://github.com/ava-labs/firewood/issues/1054"]
     #[should_panic(expected = "Node size 16777225 is too large")]
@@ -31,7 +31,7 @@ use std::iter::FusedIterator;
 
 use crate::linear::FileIoError;
 use crate::nodestore::AreaIndex;
-use crate::{Child, firewood_counter};
+use crate::{Child, Durability, LinearAddress, firewood_counter};
 use coarsetime::Instant;
 
 use crate::{MaybePersistedNode, NodeReader, WritableStorage};
@@ -206,16 +206,20 @@ fn serialize_node_to_bump<'a>(
     bump: &'a bumpalo::Bump,
     shared_node: &crate::SharedNode,
     node_allocator: &mut NodeAllocator<'_, impl WritableStorage>,
-) -> Result<(&'a [u8], crate::LinearAddress, usize), FileIoError> {
+) -> Result<(&'a [u8], crate::LinearAddress, u64), FileIoError> {
     let mut bytes = bumpalo::collections::Vec::new_in(bump);
     shared_node.as_bytes(AreaIndex::MIN, &mut bytes);
     let (persisted_address, area_size_index) = node_allocator.allocate_node(bytes.as_slice())?;
     *bytes.get_mut(0).expect("byte was reserved") = area_size_index.get();
     bytes.shrink_to_fit();
     let slice = bytes.into_bump_slice();
-    Ok((slice, persisted_address, area_size_index.size() as usize))
+    Ok((slice, persisted_address, area_size_index.size()))
 }
 
+/// A serialized node, the address it was allocated at, the size of the area
+/// allocated for it, and the node itself
+pub(super) type AllocatedNode<'a> = (&'a [u8], crate::LinearAddress, u64, MaybePersistedNode);
+
 /// Helper function to process unpersisted nodes with batching and overflow detection
 ///
 /// This function iterates through all unpersisted nodes, serializes them into a bump allocator,
@@ -234,9 +238,11 @@ pub(super) fn process_unpersisted_nodes<N, S, F>(
 where
     N: NodeReader + RootReader,
     S: WritableStorage,
-    F: FnMut(Vec<(&[u8], crate::LinearAddress, MaybePersistedNode)>) -> Result<(), FileIoError>,
+    F: FnMut(Vec<AllocatedNode<'_>>) -> Result<(), FileIoError>,
 {
     let mut allocated_objects = Vec::new();
+    // the bump's chunks are sized up front, so count the bytes actually used
+    let mut batch_bytes = 0usize;
 
     // Process each unpersisted node directly from the iterator
     for node in UnPersistedNodeIterator::new(node_store) {
@@ -245,20 +251,26 @@ where
             .expect("in memory, so no IO");
 
         // Serialize the node into the bump allocator
-        let (slice, persisted_address, idx_size) =
+        let (slice, persisted_address, area_size) =
             serialize_node_to_bump(bump, &shared_node, node_allocator)?;
 
-        allocated_objects.push((slice, persisted_address, node));
+        // record the address now, so parents serialized in the same batch can
+        // refer to it; readers keep using the in-memory node until it is cached
+        node.allocate_at(persisted_address);
+        batch_bytes = batch_bytes.saturating_add(slice.len());
+        allocated_objects.push((slice, persisted_address, area_size, node));
 
         // we pause if we can't allocate another node of the same size as the last one
         // This isn't a guarantee that we won't exceed bump_size_limit
         // but it's a good enough approximation
-        let might_overflow = bump.allocated_bytes() > bump_size_limit.saturating_sub(idx_size);
+        let might_overflow = batch_bytes
+            > bump_size_limit.saturating_sub(usize::try_from(area_size).unwrap_or(usize::MAX));
         if might_overflow {
             // must persist freelist before writing anything
             node_allocator.flush_freelist()?;
             write_fn(allocated_objects)?;
             allocated_objects = Vec::new();
+            batch_bytes = 0;
             bump.reset();
         }
     }
@@ -335,16 +347,13 @@ impl<S: WritableStorage + 'static> NodeStore<Committed, S> {
     /// Returns a [`FileIoError`] if any node cannot be written to storage.
     fn write_nodes_generic(
         &self,
-        allocated_objects: Vec<(&[u8], crate::LinearAddress, MaybePersistedNode)>,
+        allocated_objects: Vec<AllocatedNode<'_>>,
     ) -> Result<(), FileIoError> {
         // Collect addresses and nodes for caching
         let mut cached_nodes = Vec::new();
 
-        for (serialized, persisted_address, node) in allocated_objects {
+        for (serialized, persisted_address, _, node) in allocated_objects {
             self.storage.write(persisted_address.get(), serialized)?;
-
-            // Allocate the node to store the address, then collect for caching and persistence
-            node.allocate_at(persisted_address);
             cached_nodes.push(node);
         }
 
@@ -359,35 +368,125 @@ impl<S: WritableStorage + 'static> NodeStore<Committed, S> {
     ///
     /// This method performs a complete persistence operation by:
     /// 1. Flushing all nodes to storage
-    /// 2. Setting the root address in the header
-    /// 3. Flushing the header to storage
+    /// 2. Syncing the nodes, if the storage's [`Durability`] orders them before the header
+    /// 3. Setting the root address in the header
+    /// 4. Flushing the header to storage
+    /// 5. Syncing the header, if the storage's [`Durability`] requires it
     ///
     /// # Errors
     ///
     /// Returns a [`FileIoError`] if any of the persistence operations fail.
     #[fastrace::trace(short_name = true)]
     pub fn persist(&mut self) -> Result<(), FileIoError> {
+        self.persist_with_root(|persisted| {
+            persisted
+                .kind
+                .root
+                .as_ref()
+                .and_then(Child::persisted_address)
+        })
+    }
+
+    /// Persist the nodes of a revision committed on a different head than
+    /// `main`, the last revision persisted on the main head.
+    ///
+    /// The header written keeps the root of `main`, so reopening the database
+    /// finds the main head. Returns `main` with the header written, which
+    /// holds the free lists left behind by this revision.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if any of the persistence operations fail.
+    #[fastrace::trace(short_name = true)]
+    pub fn persist_on_fork(
+        &mut self,
+        main: &NodeStore<Committed, S>,
+    ) -> Result<NodeStore<Committed, S>, FileIoError> {
+        let root_address = main.header.root_address();
+        self.persist_with_root(|_| root_address)?;
+        Ok(NodeStore {
+            header: self.header,
+            kind: Committed {
+                deleted: main.kind.deleted.clone(),
+                root: main.kind.root.clone(),
+            },
+            storage: self.storage.clone(),
+        })
+    }
+
+    /// Persists the nodes, then writes the header with the root address
+    /// returned by `root_address` once the nodes have their addresses.
+    fn persist_with_root(
+        &mut self,
+        root_address: impl FnOnce(&Self) -> Option<LinearAddress>,
+    ) -> Result<(), FileIoError> {
+        let durability = self.storage.durability();
+        let sync = match durability {
+            Durability::SyncOnCommit | Durability::OrderedSync => true,
+            Durability::Periodic(interval) => self
+                .storage
+                .last_sync()
+                .is_none_or(|last| last.elapsed() >= interval),
+            Durability::NoSync => false,
+        };
+
         // First persist all the nodes
         self.header = self.flush_nodes()?;
 
-        // Set the root address in the header based on the persisted root
-        let root_address = self.kind.root.as_ref().and_then(Child::persisted_address);
+        // The header must not reach the disk before the nodes it references
+        if sync && durability != Durability::SyncOnCommit {
+            self.storage.sync()?;
+        }
+
+        let root_address = root_address(self);
         self.header.set_root_address(root_address);
 
         // Finally persist the header
         self.flush_header()?;
 
+        if sync {
+            self.storage.sync()?;
+        }
+
         Ok(())
     }
 }
 
+impl<S: WritableStorage> NodeStore<Committed, S> {
+    /// Sync the storage if its durability is [`Durability::Periodic`] and the
+    /// interval has elapsed since the last sync.
+    ///
+    /// Commits only sync when they land after the interval has elapsed, so
+    /// this lets the last commits of a burst become durable once the database
+    /// goes quiet.
+    ///
+    /// Returns how long until the next sync is due, or `None` if the
+    /// durability is not periodic.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if the sync fails.
+    pub fn sync_if_due(&self) -> Result<Option<std::time::Duration>, FileIoError> {
+        let Durability::Periodic(interval) = self.storage.durability() else {
+            return Ok(None);
+        };
+        match self.storage.last_sync().map(|last| last.elapsed()) {
+            Some(elapsed) if elapsed < interval => Ok(Some(interval.saturating_sub(elapsed))),
+            _ => {
+                self.storage.sync()?;
+                Ok(Some(interval))
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 #[expect(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
     use super::*;
     use crate::{
         Child, Children, HashType, ImmutableProposal, LinearAddress, NodeStore, Path,
-        PathComponent, SharedNode,
+        PathComponent, ReadableStorage, SharedNode,
         linear::memory::MemStore,
         node::{BranchNode, LeafNode, Node},
         nodestore::MutableProposal,
@@ -443,6 +542,277 @@ mod tests {
         Node::Branch(Box::new(branch))
     }
 
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum IoOp {
+        Write { offset: u64 },
+        Sync,
+    }
+
+    /// A [`WritableStorage`] that models a volatile page cache in front of
+    /// stable storage. Writes only become durable once synced, and any write
+    /// or sync can be made to fail to simulate a crash at that point.
+    #[derive(Debug)]
+    struct FaultyStore {
+        durable: std::sync::Mutex<Vec<u8>>,
+        pending: std::sync::Mutex<Vec<(u64, Vec<u8>)>>,
+        ops: std::sync::Mutex<Vec<IoOp>>,
+        last_sync: std::sync::Mutex<Option<std::time::Instant>>,
+        fail_after: std::sync::Mutex<Option<usize>>,
+        durability: Durability,
+    }
+
+    impl FaultyStore {
+        fn new(durability: Durability) -> Self {
+            Self {
+                durable: std::sync::Mutex::default(),
+                pending: std::sync::Mutex::default(),
+                ops: std::sync::Mutex::default(),
+                last_sync: std::sync::Mutex::default(),
+                fail_after: std::sync::Mutex::default(),
+                durability,
+            }
+        }
+
+        /// The bytes a reader sees: durable bytes plus every pending write.
+        fn volatile(&self) -> MemStore {
+            let store = MemStore::new(self.durable.lock().unwrap().clone());
+            for (offset, bytes) in self.pending.lock().unwrap().iter() {
+                store.write(*offset, bytes).unwrap();
+            }
+            store
+        }
+
+        /// The bytes left after a crash where the OS wrote back nothing
+        /// since the last sync.
+        fn durable(&self) -> MemStore {
+            MemStore::new(self.durable.lock().unwrap().clone())
+        }
+
+        /// The bytes left after a crash where the OS wrote back only the
+        /// pending header writes, the worst case for an unordered page cache.
+        fn crash(&self) -> MemStore {
+            let store = MemStore::new(self.durable.lock().unwrap().clone());
+            for (offset, bytes) in self.pending.lock().unwrap().iter() {
+                if *offset == 0 {
+                    store.write(*offset, bytes).unwrap();
+                }
+            }
+            store
+        }
+
+        fn inject_fault(&self) -> Result<(), FileIoError> {
+            let mut fail_after = self.fail_after.lock().unwrap();
+            match *fail_after {
+                Some(0) => Err(FileIoError::new(
+                    std::io::Error::other("injected fault"),
+                    None,
+                    0,
+                    None,
+                )),
+                Some(ref mut remaining) => {
+                    *remaining = remaining.saturating_sub(1);
+                    Ok(())
+                }
+                None => Ok(()),
+            }
+        }
+    }
+
+    impl ReadableStorage for FaultyStore {
+        fn stream_from(&self, addr: u64) -> Result<impl crate::linear::OffsetReader, FileIoError> {
+            let volatile = self.volatile();
+            let mut bytes = Vec::new();
+            std::io::Read::read_to_end(&mut volatile.stream_from(addr)?, &mut bytes).unwrap();
+            Ok(std::io::Cursor::new(bytes))
+        }
+
+        fn size(&self) -> Result<u64, FileIoError> {
+            self.volatile().size()
+        }
+    }
+
+    impl WritableStorage for FaultyStore {
+        fn write(&self, offset: u64, object: &[u8]) -> Result<usize, FileIoError> {
+            self.inject_fault()?;
+            self.pending.lock().unwrap().push((offset, object.to_vec()));
+            self.ops.lock().unwrap().push(IoOp::Write { offset });
+            Ok(object.len())
+        }
+
+        fn durability(&self) -> Durability {
+            self.durability
+        }
+
+        fn sync(&self) -> Result<(), FileIoError> {
+            self.inject_fault()?;
+            let volatile = self.volatile();
+            let mut durable = self.durable.lock().unwrap();
+            durable.clear();
+            durable.resize(volatile.size().unwrap() as usize, 0);
+            std::io::Read::read_exact(&mut volatile.stream_from(0).unwrap(), &mut durable).unwrap();
+            self.pending.lock().unwrap().clear();
+            self.ops.lock().unwrap().push(IoOp::Sync);
+            *self.last_sync.lock().unwrap() = Some(std::time::Instant::now());
+            Ok(())
+        }
+
+        fn last_sync(&self) -> Option<std::time::Instant> {
+            *self.last_sync.lock().unwrap()
+        }
+    }
+
+    /// Create an empty, durable database on a [`FaultyStore`]
+    fn create_faulty_store(
+        durability: Durability,
+    ) -> (Arc<FaultyStore>, NodeStore<Committed, FaultyStore>) {
+        let store = Arc::new(FaultyStore::new(durability));
+        let base = NodeStore::new_empty_committed(store.clone());
+        base.flush_header_with_padding().unwrap();
+        store.sync().unwrap();
+        store.ops.lock().unwrap().clear();
+        (store, base)
+    }
+
+    /// Commit a small trie whose root holds `value` on top of `base`
+    fn commit_value(
+        base: &NodeStore<Committed, FaultyStore>,
+        value: &[u8],
+    ) -> Result<NodeStore<Committed, FaultyStore>, FileIoError> {
+        let mut proposal = NodeStore::new(base).unwrap();
+        proposal.root_mut().replace(create_branch(
+            &[0],
+            Some(value),
+            vec![
+                (PathComponent::ALL[1], create_leaf(&[1, 2, 3], b"value1")),
+                (PathComponent::ALL[2], create_leaf(&[4, 5, 6], b"value2")),
+            ],
+        ));
+        let proposal: NodeStore<Arc<ImmutableProposal>, _> = proposal.try_into().unwrap();
+        let mut committed = proposal.as_committed(base);
+        committed.persist()?;
+        Ok(committed)
+    }
+
+    /// Reopen a crashed image and return the value stored at its root
+    fn recovered_root_value(store: MemStore) -> Option<Box<[u8]>> {
+        let ns = NodeStore::open(Arc::new(store)).unwrap();
+        ns.root_node().map(|root| root.value().unwrap().into())
+    }
+
+    #[test]
+    fn test_ordered_sync_orders_header_after_nodes() {
+        let (store, base) = create_faulty_store(Durability::OrderedSync);
+        commit_value(&base, b"root").unwrap();
+
+        let ops = store.ops.lock().unwrap().clone();
+        let header = ops
+            .iter()
+            .rposition(|op| *op == IoOp::Write { offset: 0 })
+            .unwrap();
+        assert_eq!(ops[header - 1], IoOp::Sync);
+        assert_eq!(ops[header + 1..], [IoOp::Sync]);
+        assert!(ops[..header - 1].iter().all(|op| *op != IoOp::Sync));
+        assert_eq!(
+            recovered_root_value(store.crash()).as_deref(),
+            Some(&b"root"[..])
+        );
+    }
+
+    #[test]
+    fn test_ordered_sync_survives_crash_at_every_operation() {
+        let (store, base) = create_faulty_store(Durability::OrderedSync);
+        commit_value(&base, b"root").unwrap();
+        let total_ops = store.ops.lock().unwrap().len();
+
+        for fail_after in 0..total_ops {
+            let (store, base) = create_faulty_store(Durability::OrderedSync);
+            *store.fail_after.lock().unwrap() = Some(fail_after);
+            assert!(commit_value(&base, b"root").is_err());
+
+            // the header on disk either still points at the empty trie or
+            // at the new root, and in both cases every node it references
+            // is durable
+            let recovered = recovered_root_value(store.crash());
+            assert!(
+                recovered.is_none() || recovered.as_deref() == Some(&b"root"[..]),
+                "inconsistent recovery after {fail_after} operations"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sync_on_commit_syncs_once() {
+        let (store, base) = create_faulty_store(Durability::SyncOnCommit);
+        commit_value(&base, b"root").unwrap();
+
+        let ops = store.ops.lock().unwrap().clone();
+        assert_eq!(ops.iter().filter(|op| **op == IoOp::Sync).count(), 1);
+        assert_eq!(ops.last(), Some(&IoOp::Sync));
+        assert_eq!(
+            recovered_root_value(store.crash()).as_deref(),
+            Some(&b"root"[..])
+        );
+    }
+
+    #[test]
+    fn test_periodic_sync_skips_recent_commits() {
+        let (store, base) =
+            create_faulty_store(Durability::Periodic(std::time::Duration::from_hours(1)));
+        let committed = commit_value(&base, b"first").unwrap();
+        assert!(!store.ops.lock().unwrap().contains(&IoOp::Sync));
+
+        // the unsynced commit is lost, but the previously synced state is intact
+        assert!(recovered_root_value(store.durable()).is_none());
+
+        // once the interval has elapsed the next commit is synced in order
+        *store.last_sync.lock().unwrap() = None;
+        commit_value(&committed, b"second").unwrap();
+        let ops = store.ops.lock().unwrap().clone();
+        assert_eq!(ops.iter().filter(|op| **op == IoOp::Sync).count(), 2);
+        assert_eq!(
+            recovered_root_value(store.crash()).as_deref(),
+            Some(&b"second"[..])
+        );
+    }
+
+    #[test]
+    fn test_sync_if_due_syncs_pending_periodic_commits() {
+        let interval = std::time::Duration::from_hours(1);
+        let (store, base) = create_faulty_store(Durability::Periodic(interval));
+        let committed = commit_value(&base, b"first").unwrap();
+
+        // not due yet, so nothing is synced
+        let next = committed.sync_if_due().unwrap().unwrap();
+        assert!(next <= interval);
+        assert!(!store.ops.lock().unwrap().contains(&IoOp::Sync));
+
+        // once due, the pending commit is made durable without another commit
+        *store.last_sync.lock().unwrap() = None;
+        assert_eq!(committed.sync_if_due().unwrap(), Some(interval));
+        assert_eq!(store.ops.lock().unwrap().last(), Some(&IoOp::Sync));
+        assert_eq!(
+            recovered_root_value(store.durable()).as_deref(),
+            Some(&b"first"[..])
+        );
+
+        // other policies never sync on a timer
+        let (store, base) = create_faulty_store(Durability::OrderedSync);
+        assert_eq!(base.sync_if_due().unwrap(), None);
+        assert!(store.ops.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_no_sync_never_syncs() {
+        let (store, base) = create_faulty_store(Durability::NoSync);
+        commit_value(&base, b"root").unwrap();
+
+        assert!(!store.ops.lock().unwrap().contains(&IoOp::Sync));
+        assert_eq!(
+            recovered_root_value(store.volatile()).as_deref(),
+            Some(&b"root"[..])
+        );
+    }
+
     #[test]
     fn test_empty_nodestore() {
         let mem_store = MemStore::new(vec![]).into();
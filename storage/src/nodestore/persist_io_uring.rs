@@ -6,9 +6,13 @@
 //! This module contains io-uring-specific implementations for batch node persistence.
 //! It is only compiled when the `io-uring` feature is enabled.
 
+use std::borrow::Cow;
+
+use smallvec::{SmallVec, smallvec};
+
 use super::alloc::NodeAllocator;
 use super::header::NodeStoreHeader;
-use super::persist::process_unpersisted_nodes;
+use super::persist::{AllocatedNode, process_unpersisted_nodes};
 use super::{Committed, NodeStore};
 use crate::LinearAddress;
 use crate::linear::{FileIoError, ReadableStorage, WritableStorage};
@@ -16,11 +20,72 @@ use crate::logger::trace;
 use crate::{FileBacked, MaybePersistedNode, firewood_counter};
 
 /// Entry in the pinned buffer array tracking in-flight io-uring operations
+///
+/// When writes are coalesced, a single entry covers several nodes whose areas
+/// were allocated back to back; the buffer then owns a copy of their serialized
+/// bytes, with the unused tail of each area but the last filled with zeros.
 #[derive(Clone, Debug)]
 struct BufferEntry<'a> {
-    buffer: &'a [u8],
+    buffer: Cow<'a, [u8]>,
     address: LinearAddress,
-    node: MaybePersistedNode,
+    /// The end of the area allocated for the last node in this write
+    area_end: Option<u64>,
+    nodes: SmallVec<[MaybePersistedNode; 1]>,
+}
+
+impl<'a> BufferEntry<'a> {
+    fn new(
+        buffer: &'a [u8],
+        address: LinearAddress,
+        area_size: u64,
+        node: MaybePersistedNode,
+    ) -> Self {
+        Self {
+            buffer: Cow::Borrowed(buffer),
+            address,
+            area_end: address.get().checked_add(area_size),
+            nodes: smallvec![node],
+        }
+    }
+
+    /// Append a node to this write if its area starts exactly where the area
+    /// of the last node in this write ends
+    fn try_coalesce(
+        &mut self,
+        buffer: &[u8],
+        address: LinearAddress,
+        area_size: u64,
+        node: &MaybePersistedNode,
+    ) -> bool {
+        if self.area_end != Some(address.get()) {
+            return false;
+        }
+        let Ok(offset) = usize::try_from(address.get().saturating_sub(self.address.get())) else {
+            return false;
+        };
+        let owned = self.buffer.to_mut();
+        owned.resize(offset, 0);
+        owned.extend_from_slice(buffer);
+        self.area_end = address.get().checked_add(area_size);
+        self.nodes.push(node.clone());
+        true
+    }
+}
+
+/// Group serialized nodes into writes, merging nodes in adjacent areas if
+/// `coalesce` is set
+fn group_writes(allocated_objects: Vec<AllocatedNode<'_>>, coalesce: bool) -> Vec<BufferEntry<'_>> {
+    let mut writes: Vec<BufferEntry<'_>> = Vec::with_capacity(allocated_objects.len());
+    for (serialized, address, area_size, node) in allocated_objects {
+        if coalesce
+            && let Some(last) = writes.last_mut()
+            && last.try_coalesce(serialized, address, area_size, &node)
+        {
+            continue;
+        }
+        writes.push(BufferEntry::new(serialized, address, area_size, node));
+    }
+    writes
 }
 
 /// Helper function to retry `submit_and_wait` on EINTR
@@ -84,9 +149,8 @@ fn handle_completion_queue(
                 Some("write failure".to_string()),
             ));
         }
-        // I/O completed successfully - mark node as persisted and cache it
-        pbe_entry.node.allocate_at(pbe_entry.address);
-        cached_nodes.push(pbe_entry.node);
+        // I/O completed successfully - cache the nodes
+        cached_nodes.extend(pbe_entry.nodes);
     }
     Ok(())
 }
@@ -101,34 +165,46 @@ impl NodeStore<Committed, FileBacked> {
     pub(super) fn flush_nodes_io_uring(&mut self) -> Result<NodeStoreHeader, FileIoError> {
         use bumpalo::Bump;
 
+        let batch_bytes = self.storage.config().batch_bytes;
         let mut header = self.header;
         let mut node_allocator = NodeAllocator::new(self.storage.as_ref(), &mut header);
-        let mut bump = Bump::with_capacity(super::INITIAL_BUMP_SIZE);
+        let mut bump = Bump::with_capacity(batch_bytes);
 
         process_unpersisted_nodes(
             &mut bump,
             &mut node_allocator,
             self,
-            super::INITIAL_BUMP_SIZE,
+            batch_bytes,
             |allocated_objects| self.ring_writes(allocated_objects),
         )?;
 
         Ok(header)
     }
 
-    fn ring_writes(
-        &self,
-        allocated_objects: Vec<(&[u8], LinearAddress, MaybePersistedNode)>,
-    ) -> Result<(), FileIoError> {
+    fn ring_writes(&self, allocated_objects: Vec<AllocatedNode<'_>>) -> Result<(), FileIoError> {
+        let config = self.storage.config();
+        let ring_id = self.storage.ring_id.clone();
         let mut ring = self.storage.ring.lock().expect("poisoned lock");
 
         let mut saved_pinned_buffers =
-            vec![Option::<BufferEntry<'_>>::None; FileBacked::RINGSIZE as usize];
+            vec![Option::<BufferEntry<'_>>::None; config.ring_size as usize];
 
         // Collect addresses and nodes for caching
         let mut cached_nodes = Vec::new();
 
-        for (serialized, persisted_address, node) in allocated_objects {
+        let node_count = allocated_objects.len();
+        let writes = group_writes(allocated_objects, config.coalesce_writes);
+        firewood_counter!(
+            "ring.coalesced",
+            "nodes merged into a preceding write",
+            "ring" => ring_id.clone()
+        )
+        .increment(node_count.saturating_sub(writes.len()) as u64);
+
+        for write in writes {
+            let persisted_address = write.address;
+            let write_len = write.buffer.len() as u64;
+            let mut write = Some(write);
             loop {
                 // Find the first available write buffer, enumerate to get the position for marking it completed
                 if let Some((pos, pbe)) = saved_pinned_buffers
@@ -136,15 +212,11 @@ impl NodeStore<Committed, FileBacked> {
                     .enumerate()
                     .find(|(_, pbe)| pbe.is_none())
                 {
-                    *pbe = Some(BufferEntry {
-                        buffer: serialized,
-                        address: persisted_address,
-                        node: node.clone(),
-                    });
+                    let pinned = pbe.insert(write.take().expect("each write is queued once"));
 
                     let submission_queue_entry = self
                         .storage
-                        .make_op(serialized)
+                        .make_op(&pinned.buffer)
                         .offset(persisted_address.get())
                         .build()
                         .user_data(pos as u64);
@@ -152,7 +224,8 @@ impl NodeStore<Committed, FileBacked> {
                     #[expect(unsafe_code)]
                     // SAFETY: the submission_queue_entry's found buffer must not move or go out of scope
                     // until the operation has been completed. This is ensured by having a Some(offset)
-                    // and not marking it None until the kernel has said it's done below.
+                    // and not marking it None until the kernel has said it's done below. An owned
+                    // (coalesced) buffer is heap allocated, so moving the entry does not move it.
                     while unsafe { ring.submission().push(&submission_queue_entry) }.is_err() {
                         ring.submitter().squeue_wait().map_err(|e| {
                             self.storage.file_io_error(
@@ -162,13 +235,19 @@ impl NodeStore<Committed, FileBacked> {
                             )
                         })?;
                         trace!("submission queue is full");
-                        firewood_counter!("ring.full", "amount of full ring").increment(1);
+                        firewood_counter!("ring.full", "amount of full ring", "ring" => ring_id.clone())
+                            .increment(1);
                     }
+                    firewood_counter!("ring.writes", "writes submitted to the ring", "ring" => ring_id.clone())
+                        .increment(1);
+                    firewood_counter!("ring.bytes", "bytes written through the ring", "ring" => ring_id.clone())
+                        .increment(write_len);
                     break;
                 }
                 // if we get here, that means we couldn't find a place to queue the request, so wait for at least one operation
                 // to complete, then handle the completion queue
-                firewood_counter!("ring.full", "amount of full ring").increment(1);
+                firewood_counter!("ring.full", "amount of full ring", "ring" => ring_id.clone())
+                    .increment(1);
                 submit_and_wait_with_retry(&mut ring, 1, &self.storage, "submit_and_wait")?;
                 let completion_queue = ring.completion();
                 trace!("competion queue length: {}", completion_queue.len());
@@ -206,6 +285,12 @@ impl NodeStore<Committed, FileBacked> {
             saved_pinned_buffers.iter().find(|pbe| pbe.is_some())
         );
 
+        firewood_counter!(
+            "ring.cached_nodes",
+            "nodes added to the node cache after their write completed",
+            "ring" => ring_id
+        )
+        .increment(cached_nodes.len() as u64);
         self.storage.write_cached_nodes(cached_nodes)?;
         debug_assert!(ring.completion().is_empty());
 
@@ -213,3 +298,132 @@ impl NodeStore<Committed, FileBacked> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use std::num::NonZero;
+    use std::sync::Arc;
+
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+
+    use super::*;
+    use crate::nodestore::{ImmutableProposal, NodeReader, RootReader};
+    use crate::{
+        BranchNode, CacheReadStrategy, Child, Children, Durability, LeafNode, Node,
+        NodeCacheBudget, Path, PathComponent, StorageConfig,
+    };
+
+    fn node() -> MaybePersistedNode {
+        MaybePersistedNode::from(crate::SharedNode::new(Node::Leaf(LeafNode {
+            partial_path: Path::new(),
+            value: Box::default(),
+        })))
+    }
+
+    #[test]
+    fn test_group_writes_coalesces_adjacent_areas() {
+        let bytes = [1u8, 2, 3, 4, 5, 6];
+        let addr = |a: u64| LinearAddress::new(a).expect("non-zero");
+        // each node fills only part of its 4 byte area
+        let objects = || {
+            vec![
+                (&bytes[0..2], addr(100), 4, node()),
+                (&bytes[2..4], addr(104), 4, node()),
+                (&bytes[4..6], addr(200), 4, node()),
+            ]
+        };
+
+        let writes = group_writes(objects(), false);
+        assert_eq!(writes.len(), 3);
+        assert!(writes.iter().all(|w| matches!(w.buffer, Cow::Borrowed(_))));
+
+        let writes = group_writes(objects(), true);
+        assert_eq!(writes.len(), 2);
+        assert_eq!(writes[0].address, addr(100));
+        assert_eq!(&*writes[0].buffer, &[1, 2, 0, 0, 3, 4]);
+        assert_eq!(writes[0].nodes.len(), 2);
+        assert_eq!(writes[1].address, addr(200));
+        assert_eq!(&*writes[1].buffer, &bytes[4..6]);
+    }
+
+    #[test]
+    fn test_coalesced_trie_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("coalesce.db");
+        let open = |truncate| {
+            Arc::new(
+                FileBacked::new(
+                    path.clone(),
+                    NodeCacheBudget::default(),
+                    NonZero::new(10).unwrap(),
+                    truncate,
+                    true,
+                    CacheReadStrategy::WritesOnly,
+                    StorageConfig {
+                        durability: Durability::NoSync,
+                        coalesce_writes: true,
+                        ..StorageConfig::default()
+                    },
+                )
+                .unwrap(),
+            )
+        };
+
+        // a root branch with a leaf under every nibble, all allocated from the
+        // end of a fresh file and so back to back
+        let leaf = |i: u8| {
+            Child::Node(Node::Leaf(LeafNode {
+                partial_path: Path::from(&[i, i]),
+                value: vec![i; usize::from(i) + 1].into(),
+            }))
+        };
+        let mut children = Children::new();
+        for (i, nibble) in (0u8..).zip(PathComponent::ALL) {
+            children[nibble] = Some(leaf(i));
+        }
+        let base = NodeStore::new_empty_committed(open(true));
+        base.flush_header_with_padding().unwrap();
+        let mut proposal = NodeStore::new(&base).unwrap();
+        proposal
+            .root_mut()
+            .replace(Node::Branch(Box::new(BranchNode {
+                partial_path: Path::new(),
+                value: Some(b"root".to_vec().into()),
+                children,
+            })));
+        let proposal: NodeStore<Arc<ImmutableProposal>, _> = proposal.try_into().unwrap();
+        let mut committed = proposal.as_committed(&base);
+
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || committed.persist()).unwrap();
+        let coalesced: u64 = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .filter(|(key, ..)| key.key().name() == "ring.coalesced")
+            .map(|(.., value)| match value {
+                DebugValue::Counter(count) => count,
+                _ => 0,
+            })
+            .sum();
+        assert!(coalesced > 0, "no writes were coalesced");
+
+        // every node reads back from a fresh handle on the file
+        drop(committed);
+        let reopened = NodeStore::open(open(false)).unwrap();
+        let root = reopened.root_node().unwrap();
+        assert_eq!(root.value(), Some(&b"root"[..]));
+        let Node::Branch(branch) = &*root else {
+            panic!("root is a branch");
+        };
+        for (i, nibble) in (0u8..).zip(PathComponent::ALL) {
+            let Some(Child::AddressWithHash(address, _)) = &branch.children[nibble] else {
+                panic!("child {i} is persisted");
+            };
+            let child = reopened.read_node(*address).unwrap();
+            assert_eq!(child.value(), Some(&vec![i; usize::from(i) + 1][..]));
+        }
+    }
+}
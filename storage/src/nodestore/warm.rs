@@ -0,0 +1,215 @@
+// Copyright (C) 2025, Ava Labs, Inc. All rights reserved.
+// See the file LICENSE.md for licensing terms.
+
+//! # Cache Warming Module
+//!
+//! Reads nodes of a committed revision into the node cache of a
+//! [`FileBacked`] storage, so that the first reads after opening a database
+//! do not all go to disk.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::{Committed, HashedNodeReader, NodeStore};
+use crate::logger::trace;
+use crate::{FileBacked, FileIoError, LinearAddress, Node, firewood_counter};
+
+impl NodeStore<Committed, FileBacked> {
+    /// Read the top `levels` levels of this revision's trie into the node
+    /// cache, breadth first, regardless of the [`CacheReadStrategy`].
+    ///
+    /// Stops early, without error, once `cancel` is set. Returns the number
+    /// of nodes added to the cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`FileIoError`] if a node cannot be read.
+    ///
+    /// [`CacheReadStrategy`]: crate::CacheReadStrategy
+    pub fn warm_levels(&self, levels: usize, cancel: &AtomicBool) -> Result<usize, FileIoError> {
+        let mut warmed = 0usize;
+        let mut queue: VecDeque<(LinearAddress, usize)> = self
+            .root_address()
+            .map(|root| (root, 0))
+            .into_iter()
+            .collect();
+
+        while let Some((addr, depth)) = queue.pop_front() {
+            if depth >= levels || cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            let (node, _) = self.read_node_with_num_bytes_from_disk(addr)?;
+            if let Node::Branch(branch) = &*node {
+                let next = depth.saturating_add(1);
+                queue.extend(
+                    branch
+                        .children_addresses()
+                        .into_iter()
+                        .filter_map(|(_, child)| child.map(|child| (child, next))),
+                );
+            }
+            if self.storage.warm_node(addr, node) {
+                warmed = warmed.saturating_add(1);
+            }
+        }
+
+        firewood_counter!(
+            "firewood.cache.warm",
+            "nodes read into the cache while warming"
+        )
+        .increment(warmed as u64);
+        Ok(warmed)
+    }
+
+    /// Read the nodes at `addresses` into the node cache, typically a list
+    /// previously returned by [`FileBacked::hot_addresses`].
+    ///
+    /// Addresses that no longer hold a node (for example, because the node
+    /// was freed since the list was saved) are skipped. Stops early once
+    /// `cancel` is set. Returns the number of nodes added to the cache.
+    pub fn warm_addresses(
+        &self,
+        addresses: impl IntoIterator<Item = LinearAddress>,
+        cancel: &AtomicBool,
+    ) -> usize {
+        let mut warmed = 0usize;
+        for addr in addresses {
+            if cancel.load(Ordering::Relaxed) {
+                break;
+            }
+            match self.read_node_with_num_bytes_from_disk(addr) {
+                Ok((node, _)) => {
+                    if self.storage.warm_node(addr, node) {
+                        warmed = warmed.saturating_add(1);
+                    }
+                }
+                Err(e) => trace!("skipping hot node at {addr:?}: {e}"),
+            }
+        }
+
+        firewood_counter!(
+            "firewood.cache.warm",
+            "nodes read into the cache while warming"
+        )
+        .increment(warmed as u64);
+        warmed
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used)]
+mod tests {
+    use std::num::NonZero;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::nodestore::{ImmutableProposal, NodeReader};
+    use crate::{
+        BranchNode, CacheReadStrategy, Child, Children, Durability, LeafNode, NodeCacheBudget,
+        Path, PathComponent, ReadableStorage, StorageConfig,
+    };
+
+    fn open(path: &std::path::Path, truncate: bool) -> Arc<FileBacked> {
+        let storage = FileBacked::new(
+            path.to_path_buf(),
+            NodeCacheBudget::default(),
+            NonZero::new(10).unwrap(),
+            truncate,
+            true,
+            CacheReadStrategy::WritesOnly,
+            StorageConfig {
+                durability: Durability::NoSync,
+                ..StorageConfig::default()
+            },
+        )
+        .unwrap();
+        Arc::new(storage)
+    }
+
+    /// Build a revision with a root branch, a nested branch and two leaves
+    fn create_revision(path: &std::path::Path) {
+        let leaf = |value: &[u8]| {
+            Child::Node(Node::Leaf(LeafNode {
+                partial_path: Path::from(&[1, 2]),
+                value: value.into(),
+            }))
+        };
+        let mut inner = Children::new();
+        inner[PathComponent::ALL[1]] = Some(leaf(b"deep1"));
+        inner[PathComponent::ALL[2]] = Some(leaf(b"deep2"));
+        let mut children = Children::new();
+        children[PathComponent::ALL[3]] = Some(Child::Node(Node::Branch(Box::new(BranchNode {
+            partial_path: Path::from(&[4]),
+            value: None,
+            children: inner,
+        }))));
+
+        let base = NodeStore::new_empty_committed(open(path, true));
+        base.flush_header_with_padding().unwrap();
+        let mut proposal = NodeStore::new(&base).unwrap();
+        proposal
+            .root_mut()
+            .replace(Node::Branch(Box::new(BranchNode {
+                partial_path: Path::new(),
+                value: Some(b"root".to_vec().into()),
+                children,
+            })));
+        let proposal: NodeStore<Arc<ImmutableProposal>, _> = proposal.try_into().unwrap();
+        let mut committed = proposal.as_committed(&base);
+        committed.persist().unwrap();
+    }
+
+    fn cached(ns: &NodeStore<Committed, FileBacked>) -> usize {
+        ns.storage.hot_addresses().len()
+    }
+
+    #[test]
+    fn test_warm_levels() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warm.db");
+        create_revision(&path);
+
+        let ns = NodeStore::open(open(&path, false)).unwrap();
+        let cancel = AtomicBool::new(false);
+        assert_eq!(cached(&ns), 0);
+        assert_eq!(ns.warm_levels(0, &cancel).unwrap(), 0);
+        assert_eq!(ns.warm_levels(2, &cancel).unwrap(), 2);
+        // levels that are already cached are not counted again
+        assert_eq!(ns.warm_levels(10, &cancel).unwrap(), 2);
+        assert_eq!(cached(&ns), 4);
+
+        let root = ns.root_address().unwrap();
+        assert!(ns.storage.read_cached_node(root, "test").is_some());
+        assert!(ns.read_node(root).unwrap().is_branch());
+    }
+
+    #[test]
+    fn test_warm_levels_cancelled() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warm.db");
+        create_revision(&path);
+
+        let ns = NodeStore::open(open(&path, false)).unwrap();
+        assert_eq!(ns.warm_levels(10, &AtomicBool::new(true)).unwrap(), 0);
+        assert_eq!(cached(&ns), 0);
+    }
+
+    #[test]
+    fn test_warm_hot_addresses_after_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("warm.db");
+        create_revision(&path);
+
+        let ns = NodeStore::open(open(&path, false)).unwrap();
+        ns.warm_levels(10, &AtomicBool::new(false)).unwrap();
+        let mut hot = ns.storage.hot_addresses();
+        drop(ns);
+
+        // an address that does not hold a node is skipped
+        hot.push(LinearAddress::new(1 << 40).unwrap());
+
+        let ns = NodeStore::open(open(&path, false)).unwrap();
+        assert_eq!(ns.warm_addresses(hot, &AtomicBool::new(false)), 4);
+        assert_eq!(cached(&ns), 4);
+    }
+}